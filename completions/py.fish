@@ -6,22 +6,60 @@ complete -c py --wraps python
 
 # Statically-known completions.
 complete -c py --long-option list --no-files -d "List all known interpreters"
+complete -c py --condition "__fish_seen_argument --long list" --long-option columns --require-parameter --no-files -d "Columns to list"
+complete -c py --condition "__fish_seen_argument --long list" --long-option sort --exclusive --arguments "version implementation architecture path source" -d "Column to sort by"
+complete -c py --condition "__fish_seen_argument --long list" --long-option json --no-files -d "List interpreters as JSON"
+complete -c py --condition "__fish_seen_argument --long list" --long-option format --exclusive --arguments "table json tsv nul versions" -d "Format to list in"
+complete -c py --long-option pre -d "Allow pre-release interpreters"
+complete -c py --long-option windows -d "Launch a Windows interpreter (WSL)"
+complete -c py --long-option no-venv -d "Ignore virtual environments"
+complete -c py --long-option pick -d "Pick from a menu of matching interpreters"
+complete -c py --long-option dry-run -d "Print the command instead of running it"
+complete -c py --long-option env --require-parameter --no-files -d "Set an environment variable for the interpreter"
+complete -c py --long-option clean-env -d "Run the interpreter in a clean environment"
+complete -c py --long-option windowed -d "Run the windowed pythonw"
+complete -c py --long-option chdir --exclusive --arguments "(__fish_complete_directories)" -d "Change the working directory first"
+complete -c py --long-option config --require-parameter --force-files -d "Load a specific configuration file"
+complete -c py --long-option no-config -d "Do not load any configuration"
+complete -c py --long-option color --exclusive --arguments "auto always never" -d "When to color output"
+complete -c py --long-option duplicates --no-files -d "List interpreters shadowed by others"
+complete -c py --long-option available --no-files -d "List versions which can be installed"
+complete -c py --long-option which --no-files -d "Print the path to the interpreter"
+complete -c py --long-option resolve --no-files -d "Print the interpreter as JSON"
+complete -c py --long-option clear-cache --no-files -d "Remove the cache of interpreters"
+complete -c py --long-option refresh-cache --no-files -d "Search for interpreters again"
+complete -c py --long-option serve --no-files -d "Answer requests from editors over stdio"
+complete -c py --long-option configuration --no-files -d "Print the configuration and where it came from"
+complete -c py --long-option default --exclusive -d "Set the default version in the configuration file"
+complete -c py --condition "__fish_is_first_arg" --arguments pin --no-files -d "Write the version to .python-version"
+complete -c py --condition "__fish_seen_subcommand_from pin" --long-option closest --no-files -d "Update the closest .python-version instead"
+complete -c py --condition "__fish_is_first_arg" --arguments install --no-files -d "Download and install a version"
+complete -c py --condition "__fish_is_first_arg" --arguments uninstall --no-files -d "Remove an installed version"
+complete -c py --condition "__fish_is_first_arg" --arguments installed --no-files -d "List installed versions"
+complete -c py --condition "__fish_is_first_arg" --arguments self --no-files -d "Manage the launcher itself"
+complete -c py --condition "__fish_seen_subcommand_from self" --arguments update --no-files -d "Update the launcher to its latest release"
+complete -c py --long-option activate --exclusive --arguments "bash zsh sh fish pwsh" -d "Print a snippet to activate the virtual environment"
+complete -c py --old-option 0 --no-files -d "List interpreters like the Windows launcher"
+complete -c py --old-option 0p --no-files -d "List interpreter paths like the Windows launcher"
+complete -c py --old-option stable --no-files -d "Launch the newest stable interpreter"
+complete -c py --old-option latest --no-files -d "Launch the newest interpreter"
 complete -c py --short-option h --long-option help --no-files -d "Display help and exit"
 
-# Dynamic/system-specific completions.
-set -l seen_major_versions
-py --list | while read -d " │ " -l padded_version padded_path
-    # Complete on the `major.minor` version.
-    set -l full_version (string trim $padded_version)
-    set -l executable_path (string trim $padded_path)
-    complete -c py --old-option $full_version -d "Launch $executable_path"
-    # Complete on the major version.
-    # Assume that `py --list` emits a sorted list of versions, so the
-    # first instance of any major version is the one that will be used.
-    set -l major_version (string split --fields 1 . $full_version)
-    if not contains $major_version $seen_major_versions
-        # Must use `--old-option` in case the major version ever goes multi-digit.
-        complete -c py --old-option $major_version -d "Launch $executable_path"
-        set --append seen_major_versions $major_version
+# Dynamic/system-specific completions, found whenever completing so that they
+# match what is installed at the time.
+function __fish_py_version_flags
+    set -l seen_major_versions
+    # `py` emits versions from newest to oldest, so the first instance of any
+    # major version is the one that will be used.
+    for full_version in (py --list --format=versions 2>/dev/null)
+        # Complete on the `major.minor` version.
+        printf -- "-%s\tLaunch Python %s\n" $full_version $full_version
+        # Complete on the major version.
+        set -l major_version (string split --fields 1 . $full_version)
+        if not contains $major_version $seen_major_versions
+            printf -- "-%s\tLaunch Python %s\n" $major_version $full_version
+            set --append seen_major_versions $major_version
+        end
     end
 end
+complete -c py --condition "__fish_is_first_arg" --arguments "(__fish_py_version_flags)" --no-files
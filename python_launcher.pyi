@@ -0,0 +1,31 @@
+from typing import Dict, Optional, Union
+
+class ExactVersion:
+    def __init__(self, version: str) -> None: ...
+    @staticmethod
+    def from_path(path: str) -> ExactVersion: ...
+    @property
+    def major(self) -> int: ...
+    @property
+    def minor(self) -> int: ...
+    @property
+    def micro(self) -> Optional[int]: ...
+    @property
+    def free_threaded(self) -> bool: ...
+    @property
+    def implementation(self) -> str: ...
+    def __lt__(self, other: ExactVersion) -> bool: ...
+    def __le__(self, other: ExactVersion) -> bool: ...
+    def __gt__(self, other: ExactVersion) -> bool: ...
+    def __ge__(self, other: ExactVersion) -> bool: ...
+    def __hash__(self) -> int: ...
+
+class RequestedVersion:
+    def __init__(self, version: str = "") -> None: ...
+    def matches(self, version: ExactVersion) -> bool: ...
+    def __hash__(self) -> int: ...
+
+def find_executable(
+    version: Union[str, RequestedVersion, None] = None
+) -> Optional[str]: ...
+def all_executables() -> Dict[ExactVersion, str]: ...
@@ -1,3 +1,4 @@
+import hashlib
 import pathlib
 import tarfile
 
@@ -5,7 +6,7 @@ from . import version
 
 
 def main(args):
-    """Create a tarball and print the path to it."""
+    """Create a tarball and its SHA256 checksum file and print the path to the tarball."""
     semver = version.get_version()
     repo_path = pathlib.Path(__file__).parent.parent
     sub_dir = f"python_launcher-{semver}"
@@ -34,4 +35,9 @@ def main(args):
         tar_file.add(man_path, man_tar)
         tar_file.add(fish_path, fish_tar)
 
+    # Read by `py self update` to verify the tarball.
+    checksum = hashlib.sha256(tar_path.read_bytes()).hexdigest()
+    checksum_path = tar_path.with_name(f"{tar_path.name}.sha256")
+    checksum_path.write_text(f"{checksum}  {tar_path.name}\n", encoding="utf-8")
+
     print(tar_path)
@@ -0,0 +1,239 @@
+//! Searching without blocking
+//!
+//! With the `tokio` feature, the functions here are asynchronous variants of
+//! those at the top of the crate, for callers like language servers and GUIs
+//! which can't block while directories are read and interpreters are run. The
+//! work is done on Tokio's [blocking thread pool](task::spawn_blocking), with
+//! every source (or directory) searched and interpreters introspected
+//! concurrently (up to [`MAX_CONCURRENT_INTROSPECTIONS`] at a time), so a slow
+//! network mount or interpreter doesn't hold up the rest. A Tokio runtime must
+//! be running.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    panic,
+    path::PathBuf,
+};
+
+use tokio::task::{self, JoinHandle};
+
+use crate::{
+    env_disabled_sources,
+    env_provider::ProcessEnv,
+    find_executable_in_hashmap, find_preferred_executable_in_hashmap,
+    interpreter::{Interpreter, Metadata, MetadataLevel, MAX_CONCURRENT_INTROSPECTIONS},
+    source_enabled, ExactVersion, InterpreterSource, Result, Search, VersionPreferenceList,
+    VersionSpecifier,
+};
+
+/// Waits for work on the blocking thread pool, resuming any panic it had.
+async fn join<T>(handle: JoinHandle<T>) -> T {
+    match handle.await {
+        Ok(result) => result,
+        Err(error) => panic::resume_unwind(error.into_panic()),
+    }
+}
+
+/// The [built-in sources](crate::default_sources), which can be searched on
+/// other threads.
+pub async fn default_sources() -> Vec<Box<dyn InterpreterSource + Send>> {
+    join(task::spawn_blocking(|| {
+        Search::new(&ProcessEnv, &crate::load_config()).send_sources()
+    }))
+    .await
+}
+
+/// Finds all possible Python executables from the specified sources, searching
+/// them concurrently (see [`crate::all_executables_from`]).
+pub async fn all_executables_from(
+    sources: Vec<Box<dyn InterpreterSource + Send>>,
+) -> HashMap<ExactVersion, PathBuf> {
+    let disabled = join(task::spawn_blocking(|| env_disabled_sources(&ProcessEnv))).await;
+    executables_from(sources, disabled).await
+}
+
+/// [`all_executables_from`] with the specified sources disabled.
+async fn executables_from(
+    sources: Vec<Box<dyn InterpreterSource + Send>>,
+    disabled: Vec<String>,
+) -> HashMap<ExactVersion, PathBuf> {
+    let searches = sources
+        .into_iter()
+        .filter(|source| source_enabled(source.name(), &disabled))
+        .map(|source| task::spawn_blocking(move || source.executables()))
+        .collect::<Vec<JoinHandle<HashMap<ExactVersion, PathBuf>>>>();
+
+    // Merging in the order of the sources keeps the earliest source's
+    // executable for each version, however long each search took.
+    let mut executables = HashMap::new();
+    for search in searches {
+        for (version, path) in join(search).await {
+            executables.entry(version).or_insert(path);
+        }
+    }
+    executables
+}
+
+/// Finds all possible Python executables from the built-in sources (see
+/// [`crate::all_executables`]).
+pub async fn all_executables() -> HashMap<ExactVersion, PathBuf> {
+    // The configuration is only loaded once for both the sources and which of
+    // them are disabled.
+    let (sources, disabled) = join(task::spawn_blocking(|| {
+        let config = crate::load_config();
+        let search = Search::new(&ProcessEnv, &config);
+        (search.send_sources(), search.disabled_sources())
+    }))
+    .await;
+    executables_from(sources, disabled).await
+}
+
+/// Finds the Python executables in the specified directories, reading them
+/// concurrently (see [`crate::executables_in_directories`]).
+pub async fn executables_in_directories(
+    directories: Vec<PathBuf>,
+) -> HashMap<ExactVersion, PathBuf> {
+    let searches = directories
+        .into_iter()
+        .map(|directory| {
+            task::spawn_blocking(move || crate::executables_in_directories([directory]))
+        })
+        .collect::<Vec<JoinHandle<HashMap<ExactVersion, PathBuf>>>>();
+    let mut executables = HashMap::new();
+    for search in searches {
+        for (version, path) in join(search).await {
+            executables.entry(version).or_insert(path);
+        }
+    }
+    executables
+}
+
+/// Attempts to find the newest executable that satisfies a specified
+/// [`crate::RequestedVersion`] or [`VersionSpecifier`] (see
+/// [`crate::find_executable`]).
+pub async fn find_executable(requested: impl Into<VersionSpecifier>) -> Option<PathBuf> {
+    let requested = requested.into();
+    let found_executables = all_executables().await;
+    // Checking for a pre-release may run the executable.
+    join(task::spawn_blocking(move || {
+        find_executable_in_hashmap(&requested, &found_executables)
+    }))
+    .await
+}
+
+/// Attempts to find an executable for each requested version of a
+/// [`VersionPreferenceList`] in order (see
+/// [`crate::find_preferred_executable`]).
+pub async fn find_preferred_executable(preferences: VersionPreferenceList) -> Option<PathBuf> {
+    let found_executables = all_executables().await;
+    join(task::spawn_blocking(move || {
+        find_preferred_executable_in_hashmap(&preferences, &found_executables)
+    }))
+    .await
+}
+
+/// Learns about every interpreter concurrently at the specified level (see
+/// [`Interpreter::metadata`]), in the order of `interpreters`.
+///
+/// Like [`crate::interpreter::metadata_of`], no more than
+/// [`MAX_CONCURRENT_INTROSPECTIONS`] interpreters are learned about at once.
+pub async fn metadata(
+    interpreters: Vec<Interpreter>,
+    level: MetadataLevel,
+) -> Vec<Result<Metadata>> {
+    let mut introspections = VecDeque::<JoinHandle<Result<Metadata>>>::new();
+    let mut metadata = Vec::with_capacity(interpreters.len());
+    for interpreter in interpreters {
+        // The oldest is waited for first to keep the order of `interpreters`.
+        if introspections.len() == MAX_CONCURRENT_INTROSPECTIONS {
+            if let Some(introspection) = introspections.pop_front() {
+                metadata.push(join(introspection).await);
+            }
+        }
+        introspections.push_back(task::spawn_blocking(move || interpreter.metadata(level)));
+    }
+    for introspection in introspections {
+        metadata.push(join(introspection).await);
+    }
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{touch_file, DirectorySource, Error, ExecutionError, RequestedVersion};
+
+    #[tokio::test]
+    async fn all_executables_from_test() {
+        let first_dir = tempfile::tempdir().unwrap();
+        let second_dir = tempfile::tempdir().unwrap();
+        let first_python311 = touch_file(first_dir.path().join("python3.11"));
+        touch_file(second_dir.path().join("python3.11"));
+        let python312 = touch_file(second_dir.path().join("python3.12"));
+        let sources: Vec<Box<dyn InterpreterSource + Send>> = vec![
+            Box::new(DirectorySource::new(
+                "first",
+                vec![first_dir.path().to_path_buf()],
+            )),
+            Box::new(DirectorySource::new(
+                "second",
+                vec![second_dir.path().to_path_buf()],
+            )),
+        ];
+
+        let executables = all_executables_from(sources).await;
+        assert_eq!(executables.len(), 2);
+        assert_eq!(
+            executables.get(&ExactVersion::new(3, 11)),
+            Some(&first_python311)
+        );
+        assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+    }
+
+    #[tokio::test]
+    async fn executables_in_directories_test() {
+        let first_dir = tempfile::tempdir().unwrap();
+        let second_dir = tempfile::tempdir().unwrap();
+        touch_file(first_dir.path().join("python3.10"));
+        let second_python310 = touch_file(second_dir.path().join("python3.10"));
+
+        let executables = executables_in_directories(vec![
+            second_dir.path().to_path_buf(),
+            first_dir.path().to_path_buf(),
+        ])
+        .await;
+        assert_eq!(
+            executables,
+            HashMap::from([(ExactVersion::new(3, 10), second_python310)])
+        );
+    }
+
+    #[tokio::test]
+    async fn metadata_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let interpreters = vec![
+            Interpreter::from_path(touch_file(dir.path().join("python3.9"))).unwrap(),
+            Interpreter::from_path(touch_file(dir.path().join("pypy3.10"))).unwrap(),
+        ];
+
+        let learned = metadata(interpreters.clone(), MetadataLevel::FileName).await;
+        let versions = learned
+            .into_iter()
+            .map(|metadata| metadata.unwrap().version)
+            .collect::<Vec<ExactVersion>>();
+        assert_eq!(versions, [interpreters[0].version, interpreters[1].version]);
+
+        // Neither can be run.
+        let learned = metadata(interpreters, MetadataLevel::Introspection).await;
+        assert!(learned.iter().all(|result| matches!(
+            result,
+            Err(Error::Execution(ExecutionError::IntrospectionFailed(..)))
+        )));
+    }
+
+    #[tokio::test]
+    async fn find_executable_test() {
+        assert_eq!(find_executable(RequestedVersion::MajorOnly(42)).await, None);
+    }
+}
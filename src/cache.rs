@@ -0,0 +1,351 @@
+//! Caching the executables found by searching
+//!
+//! Reading every directory of every source adds up when `py` is run over and
+//! over (e.g. by a shell prompt or a Makefile), so the CLI searches via
+//! [`all_executables`], which keeps what it found in the
+//! [cache file](cache_path) and reuses it for as long as nothing it was found
+//! in has changed. [`crate::all_executables`] and the rest of the library never
+//! read or write the cache. The cache is keyed by the
+//! [directories](InterpreterSource::directories) of the sources along with
+//! when each was last modified, so changing which directories are searched
+//! (e.g. `PATH`) or adding, removing, or renaming an executable in one of them
+//! invalidates it.
+//!
+//! A directory which was modified within the last [`SETTLE_TIME`] may be
+//! modified again without its modification time changing, so nothing is
+//! cached until every directory has settled.
+//!
+//! Caching is turned off by `disable-cache` in the
+//! [configuration](crate::config::Config::disable_cache), while [`clear`] and
+//! [`refresh`] (i.e. `py --clear-cache` and `py --refresh-cache`) are for when
+//! the cache is out of date without a directory having been modified, e.g.
+//! when a symlink is pointed at another interpreter.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::Config,
+    env_provider::{EnvProvider, ProcessEnv},
+    filesystem::FileSystem,
+    Error, ExactVersion, InterpreterSource, SearchError,
+};
+
+/// How long ago every searched directory must have been modified for the
+/// executables found in them to be cached.
+pub const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Where the executables found by searching are cached, whether the file
+/// exists or not.
+///
+/// This is `$XDG_CACHE_HOME/python-launcher/executables.toml` or
+/// `~/.cache/python-launcher/executables.toml`; `None` is returned if neither
+/// `XDG_CACHE_HOME` nor `HOME` is set.
+pub fn cache_path() -> Option<PathBuf> {
+    cache_path_in(&ProcessEnv)
+}
+
+/// [`cache_path`] for the environment `env`.
+pub fn cache_path_in(env: &dyn EnvProvider) -> Option<PathBuf> {
+    env.non_empty_path("XDG_CACHE_HOME")
+        .or_else(|| env.non_empty_path("HOME").map(|home| home.join(".cache")))
+        .map(|cache_home| cache_home.join("python-launcher").join("executables.toml"))
+}
+
+/// The state of the directories the sources search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fingerprint {
+    /// The SHA-256 hash of the launcher's version, the disabled sources, and
+    /// the name and directories of each source along with when each directory
+    /// was last modified.
+    key: String,
+    /// When the most recently modified directory was modified.
+    newest: Option<SystemTime>,
+}
+
+impl Fingerprint {
+    /// The fingerprint of the sources, with the directories' modification
+    /// times from `file_system`, unless one of them doesn't say which
+    /// directories it searches.
+    fn of(
+        file_system: &dyn FileSystem,
+        sources: &[Box<dyn InterpreterSource>],
+        disabled: &[String],
+    ) -> Option<Self> {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(format!("\0{disabled:?}"));
+        let mut newest = None;
+        for source in sources {
+            hasher.update(format!("\0{}", source.name()));
+            for directory in source.directories()? {
+                let modified = file_system
+                    .metadata(&directory)
+                    .ok()
+                    .and_then(|metadata| metadata.modified);
+                let since_epoch = modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+                hasher.update(format!("\0{}\0{since_epoch:?}", directory.display()));
+                newest = newest.max(modified);
+            }
+        }
+        Some(Self {
+            key: format!("{:x}", hasher.finalize()),
+            newest,
+        })
+    }
+
+    /// Whether every directory was modified at least [`SETTLE_TIME`] ago.
+    fn settled(&self) -> bool {
+        self.newest.map_or(true, |newest| {
+            SystemTime::now()
+                .duration_since(newest)
+                .map_or(false, |age| age >= SETTLE_TIME)
+        })
+    }
+}
+
+/// The executables in the contents of a cache file, if it was written for
+/// `key`.
+fn parse(contents: &str, key: &str) -> Option<HashMap<ExactVersion, PathBuf>> {
+    let cache = contents.parse::<toml::Value>().ok()?;
+    if cache.get("key")?.as_str()? != key {
+        return None;
+    }
+    cache
+        .get("executables")?
+        .as_table()?
+        .iter()
+        .map(|(version, path)| {
+            Some((
+                ExactVersion::from_str(version).ok()?,
+                PathBuf::from(path.as_str()?),
+            ))
+        })
+        .collect()
+}
+
+/// The contents of a cache file for the executables found under `key`, unless
+/// a path isn't valid Unicode.
+fn format(key: &str, executables: &HashMap<ExactVersion, PathBuf>) -> Option<String> {
+    let mut table = toml::value::Table::new();
+    for (version, path) in executables {
+        table.insert(
+            version.to_string(),
+            toml::Value::String(path.to_str()?.to_string()),
+        );
+    }
+    let mut cache = toml::value::Table::new();
+    cache.insert("key".to_string(), toml::Value::String(key.to_string()));
+    cache.insert("executables".to_string(), toml::Value::Table(table));
+    toml::to_string(&toml::Value::Table(cache)).ok()
+}
+
+/// Replaces the cache file with `contents`, so that it's never seen half
+/// written.
+fn save(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temporary_path = path.with_extension(format!("toml.{}", process::id()));
+    fs::write(&temporary_path, contents)?;
+    fs::rename(&temporary_path, path).map_err(|error| {
+        let _ = fs::remove_file(&temporary_path);
+        error
+    })
+}
+
+/// Removes the cache file, returning its path if there was one.
+///
+/// # Errors
+///
+/// If the cache file exists but can't be removed, [`SearchError::CacheFailed`]
+/// is returned.
+pub fn clear() -> crate::Result<Option<PathBuf>> {
+    let path = match cache_path() {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(Some(path)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(Error::Search(SearchError::CacheFailed(path, error.into()))),
+    }
+}
+
+/// Finds all possible Python executables like [`crate::all_executables`] with
+/// the configuration, reusing those in the cache file if the directories they
+/// were found in haven't changed since and caching them otherwise.
+pub fn all_executables(config: &Config) -> HashMap<ExactVersion, PathBuf> {
+    crate::Search::new(&ProcessEnv, config)
+        .with_cache(true)
+        .all_executables()
+}
+
+/// Searches for executables again whether or not the cache is up to date,
+/// caching what is found (see [`all_executables`]) with the configuration.
+///
+/// # Errors
+///
+/// If the out-of-date cache file can't be removed, [`SearchError::CacheFailed`]
+/// is returned.
+pub fn refresh(config: &Config) -> crate::Result<HashMap<ExactVersion, PathBuf>> {
+    clear()?;
+    Ok(all_executables(config))
+}
+
+/// The executables found by `search` for the sources, reusing those in the
+/// cache file of `env` if the sources' directories in `file_system` haven't
+/// changed since they were found and caching them otherwise.
+pub(crate) fn cached_executables(
+    env: &dyn EnvProvider,
+    file_system: &dyn FileSystem,
+    sources: &[Box<dyn InterpreterSource>],
+    disabled: &[String],
+    config: &Config,
+    search: impl FnOnce() -> HashMap<ExactVersion, PathBuf>,
+) -> HashMap<ExactVersion, PathBuf> {
+    if config.disable_cache {
+        log::info!("Not caching executables as `disable-cache` is set");
+        return search();
+    }
+    let (path, fingerprint) = match (
+        cache_path_in(env),
+        Fingerprint::of(file_system, sources, disabled),
+    ) {
+        (Some(path), Some(fingerprint)) => (path, fingerprint),
+        _ => return search(),
+    };
+    if let Some(executables) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| parse(&contents, &fingerprint.key))
+    {
+        log::info!("Using the executables cached in {}", path.display());
+        return executables;
+    }
+
+    let executables = search();
+    if !fingerprint.settled() {
+        log::debug!("Not caching the executables found as a directory was just modified");
+    } else if let Some(contents) = format(&fingerprint.key, &executables) {
+        match save(&path, &contents) {
+            Ok(()) => log::debug!("Cached the executables found in {}", path.display()),
+            Err(error) => log::debug!("Can't cache executables in {}: {error}", path.display()),
+        }
+    }
+    executables
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use crate::{
+        env_provider::MapEnv,
+        filesystem::{MemoryFileSystem, OsFileSystem},
+        DirectorySource,
+    };
+
+    use super::*;
+
+    #[test_case(MapEnv::new().with_var("XDG_CACHE_HOME", "/cache").with_var("HOME", "/home/user") => Some(PathBuf::from("/cache/python-launcher/executables.toml")) ; "XDG_CACHE_HOME")]
+    #[test_case(MapEnv::new().with_var("XDG_CACHE_HOME", "").with_var("HOME", "/home/user") => Some(PathBuf::from("/home/user/.cache/python-launcher/executables.toml")) ; "HOME")]
+    #[test_case(MapEnv::new() => None ; "neither")]
+    fn cache_path_in_tests(env: MapEnv) -> Option<PathBuf> {
+        cache_path_in(&env)
+    }
+
+    fn executables() -> HashMap<ExactVersion, PathBuf> {
+        HashMap::from([
+            (
+                ExactVersion::new(3, 12),
+                PathBuf::from("/usr/bin/python3.12"),
+            ),
+            (
+                ExactVersion::from_str("3.13t").unwrap(),
+                PathBuf::from("/usr/bin/python3.13t"),
+            ),
+            (
+                ExactVersion::from_str("pypy3.10").unwrap(),
+                PathBuf::from("/opt/pypy/bin/pypy3.10"),
+            ),
+        ])
+    }
+
+    #[test]
+    fn round_trip() {
+        let contents = format("abc", &executables()).unwrap();
+        assert_eq!(parse(&contents, "abc"), Some(executables()));
+        assert_eq!(parse(&contents, "def"), None);
+        assert_eq!(
+            parse("key = 'abc'\n[executables]\nthree = '/python'\n", "abc"),
+            None
+        );
+        assert_eq!(parse("not TOML", "abc"), None);
+    }
+
+    fn sources(directories: &[&Path]) -> Vec<Box<dyn InterpreterSource>> {
+        vec![Box::new(DirectorySource::new(
+            "path",
+            directories.iter().map(|dir| dir.to_path_buf()).collect(),
+        ))]
+    }
+
+    #[test]
+    fn fingerprint() {
+        let first_dir = tempfile::tempdir().unwrap();
+        let second_dir = tempfile::tempdir().unwrap();
+        let fingerprint =
+            Fingerprint::of(&OsFileSystem, &sources(&[first_dir.path()]), &[]).unwrap();
+
+        assert_eq!(
+            Fingerprint::of(&OsFileSystem, &sources(&[first_dir.path()]), &[]),
+            Some(fingerprint.clone())
+        );
+        assert!(!fingerprint.settled());
+        for (sources, disabled) in [
+            (sources(&[first_dir.path(), second_dir.path()]), Vec::new()),
+            (sources(&[second_dir.path()]), Vec::new()),
+            (sources(&[first_dir.path()]), vec!["snap".to_string()]),
+        ] {
+            assert_ne!(
+                Fingerprint::of(&OsFileSystem, &sources, &disabled)
+                    .unwrap()
+                    .key,
+                fingerprint.key
+            );
+        }
+
+        // A missing directory has no modification time.
+        let missing = first_dir.path().join("missing");
+        let missing_fingerprint =
+            Fingerprint::of(&OsFileSystem, &sources(&[&missing]), &[]).unwrap();
+        assert_eq!(missing_fingerprint.newest, None);
+        assert!(missing_fingerprint.settled());
+
+        // The modification times come from the file system of the search,
+        // which a `MemoryFileSystem` doesn't keep.
+        let file_system = MemoryFileSystem::new().dir(first_dir.path());
+        let memory_fingerprint =
+            Fingerprint::of(&file_system, &sources(&[first_dir.path()]), &[]).unwrap();
+        assert_eq!(memory_fingerprint.newest, None);
+        assert_ne!(memory_fingerprint.key, fingerprint.key);
+    }
+
+    #[test]
+    fn save_replaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("python-launcher").join("executables.toml");
+        save(&path, "first").unwrap();
+        save(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+}
@@ -1,25 +1,36 @@
 //! Parsing of CLI flags
 //!
 //! The [`Action`] enum represents what action to perform based on the
-//! command-line arguments passed to the program.
+//! command-line arguments passed to the program. Nothing is printed here:
+//! what an action prints is written to the writer the caller passes to
+//! [`Action::write_output`], so other binaries can reuse the CLI.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     fmt::Write,
-    fs::File,
-    io::{BufRead, BufReader, Read},
+    fs::{self, File},
+    io,
+    os::unix::io::RawFd,
     path::{Path, PathBuf},
     str::FromStr,
     string::ToString,
 };
 
-use comfy_table::{Table, TableComponent};
+use comfy_table::{Attribute, Cell, Color, Table, TableComponent};
+use serde_json::{json, Value};
 
-use crate::{ExactVersion, RequestedVersion};
+use crate::config::{Config, ConfigFiles, ListColumn, SettingSource};
+use crate::run::{Argv0, RunMode};
+use crate::shebang::{ArgumentSplitting, Shebang, ShebangCommand};
 
-/// The expected directory name for virtual environments.
-pub static DEFAULT_VENV_DIR: &str = ".venv";
+use crate::{
+    env_provider::{EnvProvider, ProcessEnv, WithCurrentDir},
+    ExactVersion, Implementation, RequestedVersion, Search, VenvDetection, VenvInfo,
+    VersionPreferenceList, VersionSpecifier,
+};
+
+pub use crate::DEFAULT_VENV_DIR;
 
 /// Represents the possible outcomes based on CLI arguments.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -34,6 +45,55 @@ pub enum Action {
     ///
     /// The string is formatted to be human-readable.
     List(String),
+    /// A report of every setting, the value in effect, and where it came
+    /// from.
+    ///
+    /// The string is formatted to be human-readable.
+    Configuration(String),
+    /// A shell snippet which activates a virtual environment.
+    ///
+    /// The snippet is meant to be evaluated by the shell, e.g.
+    /// `eval "$(py --activate)"`.
+    Activate(String),
+    /// The path to the Python executable which would be executed, to be
+    /// printed instead of executing it.
+    Which(PathBuf),
+    /// A JSON object describing the Python executable which would be
+    /// executed, to be printed instead of executing it (see
+    /// [`resolution_value`]).
+    Resolve(String),
+    /// The command which would be executed, i.e. the environment variables
+    /// set for the executable followed by it and its arguments, to be
+    /// printed instead of executing it.
+    ///
+    /// The command is quoted for a POSIX shell.
+    DryRun(String),
+    /// The version of Python to download and install (see
+    /// [`crate::install::install`]).
+    Install(RequestedVersion),
+    /// The version of Python to uninstall every install of (see
+    /// [`crate::install::uninstall`]).
+    Uninstall(RequestedVersion),
+    /// Update the launcher itself to its latest release (see
+    /// [`crate::update::self_update`]).
+    SelfUpdate,
+    /// Remove the cache of the executables found by searching (see
+    /// [`crate::cache::clear`]).
+    ClearCache,
+    /// Search for executables again and cache them (see
+    /// [`crate::cache::refresh`]).
+    RefreshCache,
+    /// Answer requests over standard input and output until it's closed (see
+    /// [`crate::server`]).
+    Serve,
+    /// Contents to write to a file, e.g. to persist the default version.
+    Write {
+        /// The file to write, which (along with its directory) is created if
+        /// necessary.
+        path: PathBuf,
+        /// The new contents of the file.
+        contents: String,
+    },
     /// Details for executing a Python executable.
     Execute {
         /// The Python Launcher used to find the Python executable.
@@ -42,6 +102,17 @@ pub enum Action {
         executable: PathBuf,
         /// Arguments to the executable.
         args: Vec<String>,
+        /// Environment variables to set for the executable, in order (see
+        /// [`Config::environment`] and `--env`).
+        env_vars: Vec<(String, String)>,
+        /// Environment variables to unset for the executable (see
+        /// [`Config::clean_env`]).
+        unset_env_vars: Vec<String>,
+        /// How to run the executable (see [`Config::run_mode`]).
+        run_mode: RunMode,
+        /// What the executable sees as `argv[0]` instead of its path, if
+        /// anything (see [`Config::argv0`]).
+        argv0: Option<String>,
     },
 }
 
@@ -67,57 +138,482 @@ impl Action {
     ///
     /// Returns [`Action::List`].
     ///
-    /// The list of executable is gathered via [`crate::all_executables`].
+    /// The list of executable is gathered via [`crate::all_executables`], along
+    /// with [`crate::windows_executables`] marked as being Windows-native.
+    ///
+    /// With `--list --all`, every executable is listed along with its source:
+    /// every environment from [`crate::all_environments`] followed by every
+    /// executable from [`crate::all_executables_by_source`], even if an
+    /// earlier source found the same version.
+    ///
+    /// With `--json` (e.g. `--list --json` or `--list --all --json`), the
+    /// same executables are listed as a JSON array of objects with `version`,
+    /// `path`, and `source` keys instead (see [`list_value`]).
+    ///
+    /// ## `--duplicates`
+    ///
+    /// Returns [`Action::List`].
+    ///
+    /// Every occurrence of an executable for each version is listed (see
+    /// [`crate::all_executable_occurrences`]), marking the one which is used
+    /// and those which are shadowed by it.
+    ///
+    /// ## `--available`
+    ///
+    /// Returns [`Action::List`].
+    ///
+    /// Every version which can be installed on this platform is listed (see
+    /// [`crate::install::available`]), marking those which are already
+    /// installed by the launcher or otherwise found.
+    ///
+    /// ## `-0` and `-0p`
+    ///
+    /// Returns [`Action::List`].
+    ///
+    /// The same executables as `--list` are listed in the format of the
+    /// Windows launcher, along with any activated environment, marking the
+    /// one which would be used when no version is requested with `*`. `-0`
+    /// describes each executable while `-0p` gives its path.
+    ///
+    /// ## `--activate`
+    ///
+    /// Returns [`Action::Activate`].
+    ///
+    /// The virtual environment is found via [`crate::find_environment`] and
+    /// the snippet is for the shell named by the optional argument following
+    /// the flag (e.g. `--activate fish`), else the `SHELL` environment
+    /// variable (see [`Shell`]).
+    ///
+    /// ## `--which`
+    ///
+    /// Returns [`Action::Which`] with the executable that would be executed
+    /// (see [`crate::find_executable`]), either on its own or following a
+    /// version flag, e.g. `py -3.11 --which`.
+    ///
+    /// ## `--resolve`
+    ///
+    /// Returns [`Action::Resolve`] describing the executable `--which` would
+    /// return, i.e. its `path`, `version`, the `source` which found it, and
+    /// the `venv` it belongs to (see [`resolution_value`]). Like `--which`, it
+    /// may follow a version flag, e.g. `py -3.11 --resolve`.
+    ///
+    /// ## `--clear-cache` and `--refresh-cache`
+    ///
+    /// Returns [`Action::ClearCache`] and [`Action::RefreshCache`],
+    /// respectively, for when the [cache](crate::cache) of the executables
+    /// found by searching is out of date. Either flag must be on its own.
+    ///
+    /// ## `--serve`
+    ///
+    /// Returns [`Action::Serve`], for editors and other tools to ask what
+    /// `--list --json` and `--resolve` would print without running `py` each
+    /// time (see [`crate::server`]). The flag must be on its own.
+    ///
+    /// ## `--configuration`
+    ///
+    /// Returns [`Action::Configuration`].
+    ///
+    /// Every setting of the configuration (see
+    /// [`crate::config::Config::load_with_sources`]) is reported along with
+    /// where its value came from, with any environment variables and launcher
+    /// flags which take precedence over it applied.
+    ///
+    /// ## `--pre`
+    ///
+    /// Allows pre-release versions of Python to be selected (which is also
+    /// the case when the `PYLAUNCH_ALLOW_PRERELEASE` environment variable is
+    /// set or the configuration file sets `allow-prereleases`). The flag may
+    /// be followed by any other launcher argument.
+    ///
+    /// ## `--windows`
+    ///
+    /// Under WSL, searches for a Windows-native interpreter via
+    /// [`crate::find_preferred_windows_executable`] instead of the usual
+    /// search. Like `--pre`, the flag may be followed by any other launcher
+    /// argument.
+    ///
+    /// ## `--no-venv`
+    ///
+    /// Ignores all virtual environments, activated or not, so that only
+    /// [`crate::find_executable`] is used (i.e. [`crate::VenvDetection::Disabled`]).
+    /// Like `--pre`, the flag may be followed by any other launcher argument.
+    ///
+    /// ## `--pick`
+    ///
+    /// When stdin and stdout are terminals and several interpreters satisfy
+    /// the request, shows a menu to pick one from (see
+    /// [`crate::picker::pick`]) instead of using the newest. The configuration
+    /// file setting `pick` does the same, except that the interpreter picked
+    /// before for the project is used without asking. Like `--pre`, the flag
+    /// may be followed by any other launcher argument.
+    ///
+    /// ## `--dry-run`
+    ///
+    /// Returns [`Action::DryRun`] with the command that the
+    /// [`Action::Execute`] instance which would otherwise be returned runs
+    /// (see [`dry_run_command`]). Like `--pre`, the flag may be followed by any
+    /// other launcher argument, as long as an interpreter is then executed.
+    ///
+    /// ## `--windowed`
+    ///
+    /// Runs the windowed counterpart of the interpreter, e.g. `pythonw3.12` for
+    /// `python3.12` (see [`windowed_executable`]), as the Windows launcher's
+    /// `pyw` does, returning [`crate::SearchError::NoWindowedExecutable`] if
+    /// there is none. This is also the case when the launcher is run as `pyw`
+    /// (e.g. via a symbolic link). A script ending in `.pyw` is run by the
+    /// windowed counterpart too, falling back to the interpreter itself if there
+    /// is none. Like `--pre`, the flag may be followed by any other launcher
+    /// argument.
+    ///
+    /// ## `--env`
+    ///
+    /// `--env NAME=VALUE` sets an environment variable for the interpreter,
+    /// e.g. `py --env PYTHONFAULTHANDLER=1 script.py`. Unlike the
+    /// `environment` table of the configuration, the variable is set even if
+    /// it already is, after the configuration's variables so it takes
+    /// precedence over them. The flag may be repeated, with the last value for
+    /// a variable winning. Like `--pre`, the flag may be followed by any other
+    /// launcher argument, as long as an interpreter is then executed.
+    ///
+    /// ## `--clean-env`
+    ///
+    /// Runs the interpreter in a clean environment (which is also the case
+    /// when the configuration file sets `clean-env`): every `PYTHON*`
+    /// environment variable is unset for it, except those set by the
+    /// `environment` table of the configuration or `--env`, and `-s` is
+    /// passed so the user's site-packages directory isn't used. `-E` and `-I`
+    /// aren't passed as they would also ignore the variables set on purpose and
+    /// leave the script's directory out of `sys.path`, respectively. Like
+    /// `--pre`, the flag may be followed by any other launcher argument.
+    ///
+    /// ## `--chdir`
+    ///
+    /// `--chdir DIR` changes the working directory to `DIR` before anything
+    /// else happens, so that virtual environments, `.python-version` files,
+    /// and a project's configuration are found relative to `DIR` and the
+    /// interpreter runs in it (like `git -C`). Relative paths that follow,
+    /// including the script, are relative to `DIR` as well. Like `--pre`, the
+    /// flag may be followed by any other launcher argument.
+    ///
+    /// ## `--config` and `--no-config`
+    ///
+    /// `--config PATH` loads the configuration file at `PATH` instead of the
+    /// system-wide and user's configuration files, while `--no-config` skips
+    /// loading any configuration (see [`crate::config::ConfigFiles`]). The
+    /// interpreter which is run has the `PYLAUNCH_CONFIG` or
+    /// `PYLAUNCH_NO_CONFIG` environment variable set, respectively, so any use
+    /// of the launcher by it does the same. Like `--pre`, the flags may be
+    /// followed by any other launcher argument.
+    ///
+    /// ## `--color`
+    ///
+    /// `--color WHEN` or `--color=WHEN` chooses when `--list` is colored (see
+    /// [`ColorChoice`]). Like `--pre`, the flag may be followed by any other
+    /// launcher argument.
+    ///
+    /// ## `--default`
+    ///
+    /// Returns an [`Action::Write`] instance which sets the `default-version`
+    /// of the configuration file (see [`crate::config::ConfigFiles::writable_path`])
+    /// to the version following the
+    /// flag, e.g. `py --default 3.11`, after checking that an interpreter for
+    /// the version can be found.
+    ///
+    /// ## `pin`
+    ///
+    /// Returns an [`Action::Write`] instance which writes the version
+    /// following the subcommand to the `.python-version` file in the current
+    /// directory, e.g. `py pin 3.12`. With `--closest` (e.g.
+    /// `py pin --closest 3.12`), the closest existing `.python-version` file
+    /// is written to instead (see [`crate::find_python_version_file`]). The
+    /// version must be one pyenv understands and have an interpreter which
+    /// can be found.
+    ///
+    /// ## `install`
+    ///
+    /// Returns [`Action::Install`] with the version following the subcommand,
+    /// e.g. `py install 3.12`.
+    ///
+    /// ## `uninstall`
+    ///
+    /// Returns [`Action::Uninstall`] with the version following the
+    /// subcommand, e.g. `py uninstall 3.12`.
+    ///
+    /// ## `installed`
+    ///
+    /// Returns [`Action::List`] with every interpreter installed by
+    /// [`crate::install::install`] (see [`crate::install::installed`]).
+    ///
+    /// ## `self update`
+    ///
+    /// Returns [`Action::SelfUpdate`].
+    ///
+    /// ## `venv create`
+    ///
+    /// Returns an [`Action::Execute`] instance which runs `-m venv` to create
+    /// a virtual environment, e.g. `py venv create 3.11 .venv --upgrade-pip`.
+    /// The version is optional, as is the directory (defaulting to
+    /// [`DEFAULT_VENV_DIR`]); `--upgrade-pip` becomes `--upgrade-deps` and
+    /// any other arguments are passed on to `venv`. Virtual environments are
+    /// never used to create the new virtual environment.
     ///
     /// ## Version Restriction
     ///
     /// Returns the appropriate [`Action::Execute`] instance for the requested
-    /// Python version.
+    /// Python version. The version may be followed by comma-separated
+    /// exclusions, e.g. `-3,!3.8` for any Python 3 except Python 3.8.
     ///
-    /// [`crate::find_executable`] is used to perform the search.
+    /// [`crate::find_preferred_executable`] is used to perform the search.
     ///
     /// ## No Arguments for the Launcher
     ///
     /// Returns an [`Action::Execute`] instance.
     ///
-    /// As a first step, a check is done for an activated virtual environment
-    /// via the `VIRTUAL_ENV` environment variable. If none is set, look for a
-    /// virtual environment in a directory named by [`DEFAULT_VENV_DIR`] in the
-    /// current or any parent directories.
+    /// As a first step, a virtual environment is searched for via
+    /// [`crate::find_environment`]: an activated virtual environment via the
+    /// `VIRTUAL_ENV` environment variable, followed by an activated conda
+    /// environment via `CONDA_PREFIX`. If neither is set, look for a virtual
+    /// environment in a directory named by any of the [`crate::VENV_DIRS`] in
+    /// the current or any parent directories (unless the
+    /// `PYLAUNCH_NO_VENV_SEARCH` environment variable is set or the
+    /// configuration file sets `venv-search` to `false`), followed by the
+    /// virtual environment of any Poetry project via
+    /// [`crate::find_poetry_env`] or Pipenv project via
+    /// [`crate::find_pipenv_env`].
     ///
     /// If no virtual environment is found, a shebang line is searched for in
     /// the first argument to the Python interpreter. If one is found then it
-    /// is used to (potentially) restrict the requested version searched for.
+    /// is used to (potentially) restrict the requested version searched for
+    /// (see [`crate::shebang`]). Any arguments to the interpreter in the
+    /// shebang line are put ahead of the script (even if a version was
+    /// specified or a virtual environment is used), split according to the
+    /// `shebang-arguments` setting of the configuration file. Custom commands
+    /// for shebang lines come from its `shebang-commands` table.
+    ///
+    /// If no version is requested by a shebang or `PY_PYTHON`, the default
+    /// version is requested (see [`crate::default_version`]).
+    ///
+    /// The arguments in the `PY_FLAGS` environment variable, split like a
+    /// POSIX shell would (see [`split_flags`]), are put ahead of all other
+    /// arguments to the interpreter, e.g. `PY_FLAGS="-X dev -W error"` (this
+    /// applies to every [`Action::Execute`]).
+    ///
+    /// Any environment variables from the `environment` table of the
+    /// configuration which aren't already set are included for the
+    /// interpreter (this applies to every [`Action::Execute`]).
     ///
     /// The search for an interpreter proceeds using [`crate::find_executable`].
     ///
     /// # Errors
     ///
+    /// Any error parsing the launcher flags (see [`Options::parse`]) is
+    /// returned. If `PY_FLAGS` has an unterminated quote,
+    /// [`crate::CliError::InvalidPyFlags`] is returned.
+    ///
     /// If `-h`, `--help`, or `--list` are specified as the first argument but
-    /// there are other arguments, [`crate::Error::IllegalArgument`] is returned.
+    /// there are other arguments, [`crate::CliError::IllegalArgument`] is
+    /// returned. The same goes for any of them and other launcher arguments
+    /// which don't execute an interpreter when preceded by `--dry-run` or
+    /// `--env`.
     ///
     /// If no executable could be found for [`Action::Help`] or
-    /// [`Action::List`], [`crate::Error::NoExecutableFound`] is returned.
+    /// [`Action::List`], [`crate::SearchError::NoExecutableFound`] is returned.
+    ///
+    /// For [`Action::Activate`], [`crate::SearchError::NoVirtualEnvFound`] is
+    /// returned if there is no virtual environment,
+    /// [`crate::CliError::UnsupportedShell`] if the shell is unknown, and
+    /// [`crate::SearchError::NoActivationScript`] if the virtual environment
+    /// lacks an activation script for the shell.
     ///
     /// # Panics
     ///
     /// - If a [`writeln!`] call fails.
-    /// - If the current directory cannot be accessed.
     pub fn from_main(argv: &[String]) -> crate::Result<Self> {
-        let launcher_path = PathBuf::from(&argv[0]); // Strip the path to this executable.
+        Self::from_options(&Options::parse(argv)?)
+    }
+
+    /// Determines the action for the [launcher options](Options) which were
+    /// already parsed, as [`Action::from_main`] does.
+    ///
+    /// # Errors
+    ///
+    /// See [`Action::from_main`].
+    pub fn from_options(options: &Options) -> crate::Result<Self> {
+        let Options {
+            launcher_path,
+            mut allow_prereleases,
+            windows,
+            venv_detection,
+            picking,
+            dry_run,
+            env_overrides,
+            mut clean_env,
+            windowed,
+            color,
+            config_files: _,
+            working_dir: _,
+            launcher_flags,
+            args,
+        } = options.clone();
+        let launcher_args = &args[..];
+        let config_files = options.config_files();
+        let env = options.env();
+
+        // Report a broken configuration file instead of silently ignoring it
+        // while searching for executables.
+        let (config, settings) = Config::load_files(&*env, &config_files)?;
+        allow_prereleases |= config.allow_prereleases;
+        clean_env |= config.clean_env;
+        let picking = picking.unwrap_or(if config.pick {
+            Picking::Remembered
+        } else {
+            Picking::Disabled
+        });
+        let venv_detection =
+            venv_detection.unwrap_or_else(|| default_venv_detection(&*env, &config));
+        // Searches reuse the configuration rather than loading it again, and
+        // the executables found across runs of `py`.
+        let search = Search::new(&*env, &config).with_cache(true);
+
+        let action = match launcher_args.first() {
+            Some(flag) if flag == "--list" => {
+                let mut all = false;
+                let mut format = None;
+                let mut columns = config.list_columns.clone();
+                let mut sort = config.list_sort;
+                let mut options = launcher_args[1..].iter();
+                while let Some(option) = options.next() {
+                    // Options with a value accept both `--option value` and
+                    // `--option=value`.
+                    let (name, inline_value) = match option.split_once('=') {
+                        Some((name, value)) => (name, Some(value)),
+                        None => (option.as_str(), None),
+                    };
+                    match name {
+                        "--all" if !all && inline_value.is_none() => all = true,
+                        "--json" if format.is_none() && inline_value.is_none() => {
+                            format = Some(ListFormat::Json);
+                        }
+                        "--columns" | "--sort" | "--format" => {
+                            let value = match inline_value {
+                                Some(value) => value,
+                                None => options.next().ok_or_else(|| {
+                                    crate::Error::Cli(crate::CliError::MissingArgument(
+                                        name.to_string(),
+                                    ))
+                                })?,
+                            };
+                            match name {
+                                "--columns" => columns = Some(ListColumn::from_names(value)?),
+                                "--sort" => {
+                                    sort = Some(ListColumn::from_name(value).ok_or_else(|| {
+                                        crate::Error::Cli(crate::CliError::UnknownListColumn(
+                                            value.to_string(),
+                                        ))
+                                    })?);
+                                }
+                                _ => {
+                                    format =
+                                        Some(ListFormat::from_name(value).ok_or_else(|| {
+                                            crate::Error::Cli(crate::CliError::UnknownListFormat(
+                                                value.to_string(),
+                                            ))
+                                        })?);
+                                }
+                            }
+                        }
+                        _ => {
+                            return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                                launcher_path,
+                                flag.to_string(),
+                            )))
+                        }
+                    }
+                }
+
+                // What `py` on its own would launch: the first environment
+                // found, else whatever version is preferred by default.
+                let environments = search.all_environments(venv_detection);
+                let default_executable = match environments.first() {
+                    Some((kind, venv)) => Some((venv.executable.clone(), *kind)),
+                    None => find_executable(
+                        VersionPreferenceList::from(RequestedVersion::Any),
+                        None,
+                        allow_prereleases,
+                        windows,
+                        VenvDetection::Disabled,
+                        Picking::Disabled,
+                        &search,
+                    )
+                    .ok()
+                    .map(|path| (path, default_reason(&search))),
+                };
 
-        match argv.get(1) {
-            Some(flag) if flag == "-h" || flag == "--help" || flag == "--list" => {
-                if argv.len() > 2 {
-                    Err(crate::Error::IllegalArgument(
+                // Only the environment and the executable which would be used
+                // for each version are listed unless `--all` is specified.
+                let mut listed = if all {
+                    listed_executables(
+                        &environments,
+                        search.all_executables_by_source(),
+                        &search.windows_executables(),
+                    )
+                } else {
+                    listed_executables(
+                        &environments[..environments.len().min(1)],
+                        preferred_executables(search.all_executables_by_source()),
+                        &search.windows_executables(),
+                    )
+                };
+                if let Some(column) = sort {
+                    sort_listed(&mut listed, column);
+                }
+                let default = default_executable.and_then(|(default_path, reason)| {
+                    listed
+                        .iter()
+                        .position(|(_, path, _)| *path == default_path)
+                        .map(|index| (index, reason))
+                });
+                let columns = columns.unwrap_or_else(|| {
+                    let mut columns = vec![
+                        ListColumn::Version,
+                        ListColumn::Implementation,
+                        ListColumn::Architecture,
+                        ListColumn::Path,
+                    ];
+                    if all {
+                        columns.push(ListColumn::Source);
+                    }
+                    columns
+                });
+                Ok(Action::List(match format.unwrap_or(ListFormat::Table) {
+                    ListFormat::Table => list_table(
+                        &listed,
+                        &columns,
+                        default,
+                        color.enabled(nix::libc::STDOUT_FILENO),
+                    )?,
+                    ListFormat::Json => list_json(&listed, default),
+                    ListFormat::Tsv => list_delimited(&listed, &columns, false)?,
+                    ListFormat::Nul => list_delimited(&listed, &columns, true)?,
+                    ListFormat::Versions => list_versions(&listed, &environments),
+                }))
+            }
+            Some(flag) if flag == "-h" || flag == "--help" => {
+                if launcher_args.len() > 1 {
+                    Err(crate::Error::Cli(crate::CliError::IllegalArgument(
                         launcher_path,
                         flag.to_string(),
-                    ))
-                } else if flag == "--list" {
-                    Ok(Action::List(list_executables(&crate::all_executables())?))
+                    )))
                 } else {
-                    crate::find_executable(RequestedVersion::Any)
-                        .ok_or(crate::Error::NoExecutableFound(RequestedVersion::Any))
+                    let specifier = VersionSpecifier {
+                        prereleases: allow_prereleases,
+                        ..VersionSpecifier::default()
+                    };
+                    search
+                        .find_executable(specifier)
+                        .ok_or(crate::Error::Search(crate::SearchError::NoExecutableFound(
+                            RequestedVersion::Any,
+                        )))
                         .map(|executable_path| {
                             Action::Help(
                                 help_message(&launcher_path, &executable_path),
@@ -126,232 +622,2100 @@ impl Action {
                         })
                 }
             }
-            Some(version) if version_from_flag(version).is_some() => {
+            // Checked ahead of version flags, which `-0` would otherwise be.
+            Some(flag) if flag == "-0" || flag == "-0p" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                let active_env = search
+                    .all_environments(venv_detection)
+                    .into_iter()
+                    .map(|(_, venv)| venv)
+                    .find(|venv| venv.activated);
+                let default_executable = match &active_env {
+                    Some(venv) => Some(venv.executable.clone()),
+                    None => find_executable(
+                        VersionPreferenceList::from(RequestedVersion::Any),
+                        None,
+                        allow_prereleases,
+                        false,
+                        VenvDetection::Disabled,
+                        Picking::Disabled,
+                        &search,
+                    )
+                    .ok(),
+                };
+                Ok(Action::List(list_tags(
+                    &search.all_executables(),
+                    active_env.as_ref(),
+                    default_executable.as_deref(),
+                    flag == "-0p",
+                )?))
+            }
+            Some(flag) if flag == "--duplicates" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                Ok(Action::List(list_duplicates(
+                    search.all_executable_occurrences(),
+                )?))
+            }
+            Some(flag) if flag == "--available" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                let found = search
+                    .all_executables()
+                    .into_keys()
+                    .collect::<Vec<ExactVersion>>();
+                Ok(Action::List(list_available(
+                    &crate::install::available()?,
+                    // Having nowhere to install to means nothing is installed.
+                    &crate::install::installed().unwrap_or_default(),
+                    &found,
+                )))
+            }
+            Some(flag) if flag == "--configuration" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                Ok(Action::Configuration(configuration_report(
+                    &*env,
+                    settings,
+                    &launcher_flags,
+                    allow_prereleases,
+                    venv_detection,
+                )))
+            }
+            Some(flag) if flag == "--activate" => {
+                let shell = match launcher_args {
+                    [_] => Shell::detect(),
+                    [_, shell_name] => Shell::from_name(shell_name).ok_or_else(|| {
+                        crate::Error::Cli(crate::CliError::UnsupportedShell(shell_name.to_string()))
+                    })?,
+                    _ => {
+                        return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                            launcher_path,
+                            flag.to_string(),
+                        )))
+                    }
+                };
+                let venv = search
+                    .find_environment(venv_detection)?
+                    .ok_or(crate::Error::Search(crate::SearchError::NoVirtualEnvFound))?;
+                Ok(Action::Activate(activation_snippet(&venv.root, shell)?))
+            }
+            Some(flag) if flag == "--default" => {
+                let version = match launcher_args {
+                    [_, version] => version.strip_prefix('-').unwrap_or(version),
+                    [_] => {
+                        return Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                            flag.to_string(),
+                        )))
+                    }
+                    _ => {
+                        return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                            launcher_path,
+                            flag.to_string(),
+                        )))
+                    }
+                };
+                let mut preferences = VersionPreferenceList::from_str(version)?;
+                preferences.prereleases = allow_prereleases;
+                if search.find_preferred_executable(&preferences).is_none() {
+                    return Err(no_executable_found(&preferences));
+                }
+                let path = config_files
+                    .writable_path()
+                    .ok_or(crate::Error::Config(crate::ConfigError::NoUserConfig))?;
+                let contents = match fs::read_to_string(&path) {
+                    Ok(contents) => contents,
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+                    Err(error) => {
+                        return Err(crate::Error::Config(crate::ConfigError::ReadFailed(
+                            path,
+                            error.into(),
+                        )))
+                    }
+                };
+                Ok(Action::Write {
+                    contents: crate::config::set_default_version(&contents, version),
+                    path,
+                })
+            }
+            Some(subcommand) if subcommand == "pin" => {
+                let (closest, version) = match launcher_args {
+                    [_, flag, version] if flag == "--closest" => (true, version),
+                    [_, version] if version != "--closest" => (false, version),
+                    [_] | [_, _] => {
+                        return Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                            subcommand.to_string(),
+                        )))
+                    }
+                    _ => {
+                        return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                            launcher_path,
+                            subcommand.to_string(),
+                        )))
+                    }
+                };
+                let version = version.strip_prefix('-').unwrap_or(version);
+                // The version must be understood when reading the file back.
+                let requested = crate::pyenv_requested_version(version).ok_or_else(|| {
+                    crate::Error::Version(crate::VersionError::InvalidVersionSpecifier(
+                        version.to_string(),
+                    ))
+                })?;
+                let mut specifier = VersionSpecifier::from(requested);
+                specifier.prereleases |= allow_prereleases;
+                if search.find_executable(specifier).is_none() {
+                    return Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+                        requested,
+                    )));
+                }
+                let cwd = search.env.current_dir().map_err(|error| {
+                    crate::Error::Cli(crate::CliError::InvalidWorkingDir(
+                        PathBuf::from("."),
+                        error.into(),
+                    ))
+                })?;
+                // Like `pyenv local`, the file is written to the current
+                // directory unless the closest existing one is asked for.
+                let path = closest
+                    .then(|| crate::find_python_version_file(&cwd))
+                    .flatten()
+                    .unwrap_or_else(|| cwd.join(".python-version"));
+                Ok(Action::Write {
+                    path,
+                    contents: format!("{version}\n"),
+                })
+            }
+            Some(subcommand) if subcommand == "install" || subcommand == "uninstall" => {
+                let version = match launcher_args {
+                    [_, version] => version.strip_prefix('-').unwrap_or(version),
+                    [_] => {
+                        return Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                            subcommand.to_string(),
+                        )))
+                    }
+                    _ => {
+                        return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                            launcher_path,
+                            subcommand.to_string(),
+                        )))
+                    }
+                };
+                let requested = RequestedVersion::from_str(version)?;
+                Ok(if subcommand == "install" {
+                    Action::Install(requested)
+                } else {
+                    Action::Uninstall(requested)
+                })
+            }
+            Some(subcommand) if subcommand == "self" => match launcher_args {
+                [_, command] if command == "update" => Ok(Action::SelfUpdate),
+                [_] => Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                    subcommand.to_string(),
+                ))),
+                _ => Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                    launcher_path,
+                    subcommand.to_string(),
+                ))),
+            },
+            Some(subcommand) if subcommand == "installed" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        subcommand.to_string(),
+                    )));
+                }
+                Ok(Action::List(list_installed(&crate::install::installed()?)))
+            }
+            Some(subcommand)
+                if subcommand == "venv"
+                    && launcher_args.get(1).map(String::as_str) == Some("create") =>
+            {
+                let (preferences, args) = venv_create_args(&launcher_args[2..]);
+                Ok(Action::Execute {
+                    launcher_path,
+                    // Base the new virtual environment on an installed
+                    // interpreter, not some other virtual environment.
+                    executable: find_executable(
+                        preferences,
+                        None,
+                        allow_prereleases,
+                        windows,
+                        VenvDetection::Disabled,
+                        picking,
+                        &search,
+                    )?,
+                    args,
+                    env_vars: child_env_vars(&config, clean_env),
+                    unset_env_vars: Vec::new(),
+                    run_mode: config.run_mode,
+                    argv0: None,
+                })
+            }
+            Some(flag) if flag == "--which" || flag == "--resolve" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                let executable = find_executable(
+                    VersionPreferenceList::from(RequestedVersion::Any),
+                    None,
+                    allow_prereleases,
+                    windows,
+                    venv_detection,
+                    picking,
+                    &search,
+                )?;
+                Ok(report_executable(flag, executable, venv_detection, &search))
+            }
+            Some(flag) if flag == "--serve" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                Ok(Action::Serve)
+            }
+            Some(flag) if flag == "--clear-cache" || flag == "--refresh-cache" => {
+                if launcher_args.len() > 1 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                Ok(if flag == "--clear-cache" {
+                    Action::ClearCache
+                } else {
+                    Action::RefreshCache
+                })
+            }
+            Some(version)
+                if preferences_from_flag(version).is_some()
+                    && matches!(
+                        launcher_args.get(1).map(String::as_str),
+                        Some("--which" | "--resolve")
+                    ) =>
+            {
+                let flag = &launcher_args[1];
+                if launcher_args.len() > 2 {
+                    return Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                        launcher_path,
+                        flag.to_string(),
+                    )));
+                }
+                let executable = find_executable(
+                    preferences_from_flag(version).unwrap(),
+                    None,
+                    allow_prereleases,
+                    windows,
+                    venv_detection,
+                    picking,
+                    &search,
+                )?;
+                Ok(report_executable(flag, executable, venv_detection, &search))
+            }
+            Some(version) if preferences_from_flag(version).is_some() => {
+                // Make sure to skip the version specification.
+                let args = &launcher_args[1..];
+                let shebang = script_shebang(args, &search);
                 Ok(Action::Execute {
                     launcher_path,
-                    // Make sure to skip the app path and version specification.
-                    executable: find_executable(version_from_flag(version).unwrap(), &argv[2..])?,
-                    args: argv[2..].to_vec(),
+                    executable: find_executable(
+                        preferences_from_flag(version).unwrap(),
+                        shebang.as_ref(),
+                        allow_prereleases,
+                        windows,
+                        venv_detection,
+                        picking,
+                        &search,
+                    )?,
+                    args: with_shebang_arguments(shebang.as_ref(), args, config.shebang_arguments),
+                    env_vars: child_env_vars(&config, clean_env),
+                    unset_env_vars: Vec::new(),
+                    run_mode: config.run_mode,
+                    argv0: None,
                 })
             }
-            Some(_) | None => Ok(Action::Execute {
+            Some(_) | None => {
+                let shebang = script_shebang(launcher_args, &search);
+                Ok(Action::Execute {
+                    launcher_path,
+                    executable: find_executable(
+                        VersionPreferenceList::from(RequestedVersion::Any),
+                        shebang.as_ref(),
+                        allow_prereleases,
+                        windows,
+                        venv_detection,
+                        picking,
+                        &search,
+                    )?,
+                    args: with_shebang_arguments(
+                        shebang.as_ref(),
+                        launcher_args,
+                        config.shebang_arguments,
+                    ),
+                    env_vars: child_env_vars(&config, clean_env),
+                    unset_env_vars: Vec::new(),
+                    run_mode: config.run_mode,
+                    argv0: None,
+                })
+            }
+        }?;
+
+        match action {
+            Action::Execute {
                 launcher_path,
-                // Make sure to skip the app path.
-                executable: find_executable(RequestedVersion::Any, &argv[1..])?,
-                args: argv[1..].to_vec(),
-            }),
+                mut executable,
+                mut args,
+                mut env_vars,
+                mut unset_env_vars,
+                run_mode,
+                argv0: _,
+            } => {
+                // Carry `--config` and `--no-config` over to any use of the
+                // launcher by the interpreter.
+                match &options.config_files {
+                    Some(ConfigFiles::File(path)) => {
+                        env_vars.push((
+                            "PYLAUNCH_CONFIG".to_string(),
+                            path.to_string_lossy().to_string(),
+                        ));
+                        unset_env_vars.push("PYLAUNCH_NO_CONFIG".to_string());
+                    }
+                    Some(ConfigFiles::None) => {
+                        env_vars.push(("PYLAUNCH_NO_CONFIG".to_string(), "1".to_string()));
+                    }
+                    Some(ConfigFiles::Default) | None => (),
+                }
+                // Set last, so they win over the configuration.
+                for (name, value) in env_overrides {
+                    env_vars.retain(|(set_name, _)| *set_name != name);
+                    env_vars.push((name, value));
+                }
+                let gui_script = script(&args).map_or(false, |script| script.ends_with(".pyw"));
+                if windowed || gui_script {
+                    match windowed_executable(&executable) {
+                        Some(windowed_executable) => executable = windowed_executable,
+                        // A GUI script still runs without one, as is usual
+                        // on Linux.
+                        None if !windowed => {
+                            log::info!("No windowed interpreter for {}", executable.display())
+                        }
+                        None => {
+                            return Err(crate::Error::Search(
+                                crate::SearchError::NoWindowedExecutable(executable),
+                            ))
+                        }
+                    }
+                }
+                if let Some(flags) = env.var("PY_FLAGS").filter(|flags| !flags.is_empty()) {
+                    let mut flagged_args = split_flags(&flags).ok_or_else(|| {
+                        crate::Error::Cli(crate::CliError::InvalidPyFlags(flags.to_string()))
+                    })?;
+                    flagged_args.append(&mut args);
+                    args = flagged_args;
+                }
+                if clean_env {
+                    unset_env_vars.extend(
+                        inherited_python_env_vars()
+                            .into_iter()
+                            .filter(|name| env_vars.iter().all(|(set_name, _)| set_name != name)),
+                    );
+                    args.insert(0, "-s".to_string());
+                }
+                let argv0 = match config.argv0 {
+                    Argv0::Path => None,
+                    Argv0::Name => executable
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string()),
+                    Argv0::Launcher => Some(launcher_path.to_string_lossy().to_string()),
+                };
+                Ok(if dry_run {
+                    Action::DryRun(dry_run_command(
+                        &executable,
+                        argv0.as_deref(),
+                        &args,
+                        &env_vars,
+                        &unset_env_vars,
+                    ))
+                } else {
+                    Action::Execute {
+                        launcher_path,
+                        executable,
+                        args,
+                        env_vars,
+                        unset_env_vars,
+                        run_mode,
+                        argv0,
+                    }
+                })
+            }
+            // Whatever was asked for instead must be on its own.
+            _ if dry_run || !options.env_overrides.is_empty() => {
+                Err(crate::Error::Cli(crate::CliError::IllegalArgument(
+                    options.launcher_path.clone(),
+                    args[0].to_string(),
+                )))
+            }
+            action => Ok(action),
         }
     }
-}
 
-fn help_message(launcher_path: &Path, executable_path: &Path) -> String {
-    let mut message = String::new();
-    writeln!(
-        message,
-        include_str!("HELP.txt"),
-        env!("CARGO_PKG_VERSION"),
-        launcher_path.to_string_lossy(),
-        executable_path.to_string_lossy()
-    )
-    .unwrap();
-    message
+    /// Writes what the action prints to `output` (standard output for `py`),
+    /// returning whether there was anything to print.
+    ///
+    /// Only [`Action::Help`] (which is followed by Python's own help),
+    /// [`Action::List`], [`Action::Configuration`], [`Action::Activate`],
+    /// [`Action::Which`], [`Action::Resolve`], and [`Action::DryRun`] print
+    /// anything; every other
+    /// action is carried out by the caller. Errors are returned by
+    /// [`Action::from_main`] for the caller to report.
+    ///
+    /// # Errors
+    ///
+    /// Any error from writing to `output`.
+    pub fn write_output(&self, output: &mut dyn io::Write) -> io::Result<bool> {
+        match self {
+            Self::Help(text, _)
+            | Self::List(text)
+            | Self::Configuration(text)
+            | Self::Activate(text)
+            | Self::Resolve(text)
+            | Self::DryRun(text) => write!(output, "{text}")?,
+            Self::Which(executable) => writeln!(output, "{}", executable.display())?,
+            _ => return Ok(false),
+        }
+        // Anything run next (e.g. Python for `--help`) must print after this.
+        output.flush()?;
+        Ok(true)
+    }
 }
 
-/// Attempts to find a version specifier from a CLI argument.
+/// The launcher flags which lead the arguments to `py` (e.g. `--pre` or
+/// `--config PATH`), along with the arguments which follow them.
 ///
-/// It is assumed that the flag from the command-line is passed as-is
-/// (i.e. the flag starts with `-`).
-fn version_from_flag(arg: &str) -> Option<RequestedVersion> {
-    if !arg.starts_with('-') {
-        None
-    } else {
-        RequestedVersion::from_str(&arg[1..]).ok()
-    }
+/// Parsing the flags up front lets the caller act on them (e.g. on `--color`)
+/// before the [`Action`] is determined via [`Action::from_options`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    launcher_path: PathBuf,
+    allow_prereleases: bool,
+    windows: bool,
+    venv_detection: Option<VenvDetection>,
+    picking: Option<Picking>,
+    dry_run: bool,
+    env_overrides: Vec<(String, String)>,
+    clean_env: bool,
+    windowed: bool,
+    color: ColorChoice,
+    /// The configuration files chosen by `--config` or `--no-config`, if
+    /// either was specified.
+    config_files: Option<ConfigFiles>,
+    /// The canonical path of the directory chosen by `--chdir`.
+    working_dir: Option<PathBuf>,
+    launcher_flags: Vec<String>,
+    args: Vec<String>,
 }
 
-fn list_executables(executables: &HashMap<ExactVersion, PathBuf>) -> crate::Result<String> {
-    if executables.is_empty() {
-        return Err(crate::Error::NoExecutableFound(RequestedVersion::Any));
-    }
+impl Options {
+    /// Parses the launcher flags which lead `argv` (see [`Action::from_main`]).
+    ///
+    /// # Errors
+    ///
+    /// If a flag is missing its argument, [`crate::CliError::MissingArgument`]
+    /// is returned. If the argument to `--env` isn't `NAME=VALUE`,
+    /// [`crate::CliError::InvalidEnvVar`] is returned, and if the argument to
+    /// `--color` is unknown, [`crate::CliError::UnknownColorChoice`] is
+    /// returned.
+    ///
+    /// If the directory of `--chdir` doesn't exist or isn't a directory,
+    /// [`crate::CliError::InvalidWorkingDir`] is returned.
+    pub fn parse(argv: &[String]) -> crate::Result<Self> {
+        let launcher_path = PathBuf::from(&argv[0]); // Strip the path to this executable.
 
-    let mut executable_pairs = Vec::from_iter(executables);
-    executable_pairs.sort_unstable();
-    executable_pairs.reverse();
+        let mut allow_prereleases = env::var_os("PYLAUNCH_ALLOW_PRERELEASE").is_some();
+        let mut windows = false;
+        let mut venv_detection = None;
+        let mut picking = None;
+        let mut dry_run = false;
+        let mut env_overrides = Vec::new();
+        let mut clean_env = false;
+        let mut windowed = Path::new(&argv[0])
+            .file_stem()
+            .map_or(false, |stem| stem == "pyw");
+        let mut color = ColorChoice::Auto;
+        let mut config_files = None;
+        let mut working_dir = None;
+        let color_choice = |name: &str| {
+            ColorChoice::from_name(name).ok_or_else(|| {
+                crate::Error::Cli(crate::CliError::UnknownColorChoice(name.to_string()))
+            })
+        };
+        let mut launcher_args = &argv[1..];
+        while let Some(flag) = launcher_args.first() {
+            match flag.as_str() {
+                "--pre" => allow_prereleases = true,
+                // Virtual environments are never Windows-native.
+                "--windows" => {
+                    windows = true;
+                    venv_detection = Some(VenvDetection::Disabled);
+                }
+                "--no-venv" => venv_detection = Some(VenvDetection::Disabled),
+                "--pick" => picking = Some(Picking::Ask),
+                "--dry-run" => dry_run = true,
+                "--clean-env" => clean_env = true,
+                "--windowed" => windowed = true,
+                "--env" => match launcher_args.get(1) {
+                    Some(assignment) => {
+                        env_overrides.push(
+                            assignment
+                                .split_once('=')
+                                .filter(|(name, _)| !name.is_empty())
+                                .map(|(name, value)| (name.to_string(), value.to_string()))
+                                .ok_or_else(|| {
+                                    crate::Error::Cli(crate::CliError::InvalidEnvVar(
+                                        assignment.to_string(),
+                                    ))
+                                })?,
+                        );
+                        launcher_args = &launcher_args[1..];
+                    }
+                    None => {
+                        return Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                            flag.to_string(),
+                        )))
+                    }
+                },
+                "--chdir" => match launcher_args.get(1) {
+                    Some(dir) => {
+                        working_dir = Some(
+                            resolve_working_dir(working_dir.as_deref(), Path::new(dir)).map_err(
+                                |error| {
+                                    crate::Error::Cli(crate::CliError::InvalidWorkingDir(
+                                        PathBuf::from(dir),
+                                        error.into(),
+                                    ))
+                                },
+                            )?,
+                        );
+                        launcher_args = &launcher_args[1..];
+                    }
+                    None => {
+                        return Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                            flag.to_string(),
+                        )))
+                    }
+                },
+                "--config" => match launcher_args.get(1) {
+                    Some(path) => {
+                        // Absolute, as the interpreter may run elsewhere.
+                        let base = working_dir.clone().or_else(|| env::current_dir().ok());
+                        config_files = Some(ConfigFiles::File(match base {
+                            Some(base) => base.join(path),
+                            None => PathBuf::from(path),
+                        }));
+                        launcher_args = &launcher_args[1..];
+                    }
+                    None => {
+                        return Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                            flag.to_string(),
+                        )))
+                    }
+                },
+                "--no-config" => config_files = Some(ConfigFiles::None),
+                "--color" => match launcher_args.get(1) {
+                    Some(name) => {
+                        color = color_choice(name)?;
+                        launcher_args = &launcher_args[1..];
+                    }
+                    None => {
+                        return Err(crate::Error::Cli(crate::CliError::MissingArgument(
+                            flag.to_string(),
+                        )))
+                    }
+                },
+                _ => match flag.strip_prefix("--color=") {
+                    Some(name) => color = color_choice(name)?,
+                    None => break,
+                },
+            }
+            launcher_args = &launcher_args[1..];
+        }
 
-    let mut table = Table::new();
-    table.load_preset(comfy_table::presets::NOTHING);
-    // Using U+2502/"Box Drawings Light Vertical" over
-    // U+007C/"Vertical Line"/pipe simply because it looks better.
-    // Leaving out a header and other decorations to make it easier
-    // parse the output.
-    table.set_style(TableComponent::VerticalLines, '│');
+        Ok(Self {
+            launcher_path,
+            allow_prereleases,
+            windows,
+            venv_detection,
+            picking,
+            dry_run,
+            env_overrides,
+            clean_env,
+            windowed,
+            color,
+            config_files,
+            working_dir,
+            launcher_flags: argv[1..argv.len() - launcher_args.len()].to_vec(),
+            args: launcher_args.to_vec(),
+        })
+    }
 
-    for (version, path) in executable_pairs {
-        table.add_row(vec![version.to_string(), path.display().to_string()]);
+    /// The configuration files to load: those chosen by `--config` or
+    /// `--no-config`, else by the `PYLAUNCH_CONFIG` and `PYLAUNCH_NO_CONFIG`
+    /// environment variables (see [`ConfigFiles::from_env`]).
+    pub fn config_files(&self) -> ConfigFiles {
+        self.config_files
+            .clone()
+            .unwrap_or_else(|| ConfigFiles::from_env(&ProcessEnv))
     }
 
-    Ok(table.to_string() + "\n")
-}
+    /// When to color output, as chosen by `--color`.
+    pub fn color(&self) -> ColorChoice {
+        self.color
+    }
 
-fn relative_venv_path(add_default: bool) -> PathBuf {
-    let mut path = PathBuf::new();
-    if add_default {
-        path.push(DEFAULT_VENV_DIR);
+    /// The directory chosen by `--chdir`, which the interpreter is to be run
+    /// in.
+    pub fn working_dir(&self) -> Option<&Path> {
+        self.working_dir.as_deref()
     }
-    path.push("bin");
-    path.push("python");
-    path
-}
 
-/// Returns the path to the activated virtual environment's executable.
-///
-/// A virtual environment is determined to be activated based on the
-/// existence of the `VIRTUAL_ENV` environment variable.
-fn venv_executable_path(venv_root: &str) -> PathBuf {
-    PathBuf::from(venv_root).join(relative_venv_path(false))
-}
+    /// The environment of the process, with the directory chosen by
+    /// `--chdir` as the current directory.
+    pub fn env(&self) -> Box<dyn EnvProvider> {
+        match &self.working_dir {
+            Some(dir) => Box::new(WithCurrentDir::new(ProcessEnv, dir)),
+            None => Box::new(ProcessEnv),
+        }
+    }
 
-fn activated_venv() -> Option<PathBuf> {
-    log::info!("Checking for VIRTUAL_ENV environment variable");
-    env::var_os("VIRTUAL_ENV").map(|venv_root| {
-        log::debug!("VIRTUAL_ENV set to {venv_root:?}");
-        venv_executable_path(&venv_root.to_string_lossy())
-    })
+    /// The version requested by a version flag (e.g. `3.12` for `-3.12`) when
+    /// the interpreter is to be run, i.e. not for `--which`, `--resolve`, or
+    /// `--dry-run`.
+    pub fn requested_version(&self) -> Option<RequestedVersion> {
+        let (flag, rest) = self.args.split_first()?;
+        let reported = matches!(
+            rest.first().map(String::as_str),
+            Some("--which" | "--resolve")
+        );
+        if self.dry_run || reported {
+            return None;
+        }
+        preferences_from_flag(flag)?.versions.first().copied()
+    }
 }
 
-fn venv_path_search() -> Option<PathBuf> {
-    if env::current_dir().is_err() {
-        log::warn!("current working directory is invalid");
-        None
+/// The canonical path of `dir` as a working directory, where a relative `dir`
+/// is relative to `base` (else the current directory).
+pub(crate) fn resolve_working_dir(base: Option<&Path>, dir: &Path) -> io::Result<PathBuf> {
+    let dir = match base {
+        Some(base) => base.join(dir),
+        None => dir.to_path_buf(),
+    }
+    .canonicalize()?;
+    if dir.is_dir() {
+        Ok(dir)
     } else {
-        let cwd = env::current_dir().unwrap();
-        let printable_cwd = cwd.display();
-        log::info!("Searching for a venv in {printable_cwd} and parent directories");
-        cwd.ancestors().find_map(|path| {
-            let venv_path = path.join(relative_venv_path(true));
-            let printable_venv_path = venv_path.display();
-            log::info!("Checking {printable_venv_path}");
-            // bool::then_some() makes more sense, but still experimental.
-            venv_path.is_file().then(|| venv_path)
-        })
+        Err(io::Error::new(io::ErrorKind::Other, "Not a directory"))
     }
 }
 
-fn venv_executable() -> Option<PathBuf> {
-    activated_venv().or_else(venv_path_search)
+/// Whether an interpreter is picked interactively when several satisfy a
+/// request (see [`crate::picker`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Picking {
+    /// The newest interpreter is used.
+    Disabled,
+    /// The interpreter remembered for the project is used, else one is
+    /// picked (`pick = true`).
+    Remembered,
+    /// An interpreter is always picked (`--pick`).
+    Ask,
 }
 
-// https://en.m.wikipedia.org/wiki/Shebang_(Unix)
-fn parse_python_shebang(reader: &mut impl Read) -> Option<RequestedVersion> {
-    let mut shebang_buffer = [0; 2];
-    log::info!("Looking for a Python-related shebang");
-    if reader.read(&mut shebang_buffer).is_err() || shebang_buffer != [0x23, 0x21] {
-        // Doesn't start w/ `#!` in ASCII/UTF-8.
-        log::debug!("No '#!' at the start of the first line of the file");
-        return None;
-    }
-
-    let mut buffered_reader = BufReader::new(reader);
-    let mut first_line = String::new();
-
-    if buffered_reader.read_line(&mut first_line).is_err() {
-        log::debug!("Can't read first line of the file");
-        return None;
-    };
-
-    // Whitespace between `#!` and the path is allowed.
-    let line = first_line.trim();
-
-    let accepted_paths = [
-        "python",
-        "/usr/bin/python",
-        "/usr/local/bin/python",
-        "/usr/bin/env python",
-    ];
+/// When to color output, as chosen by `--color`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    /// Color output to a terminal, unless the `NO_COLOR` environment variable
+    /// is set to anything (see <https://no-color.org/>).
+    Auto,
+    /// Always color output, even if `NO_COLOR` is set.
+    Always,
+    /// Never color output.
+    Never,
+}
 
-    for acceptable_path in &accepted_paths {
-        if !line.starts_with(acceptable_path) {
-            continue;
+impl ColorChoice {
+    /// Returns the choice with the specified name, i.e. `auto`, `always`, or
+    /// `never`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
         }
-
-        log::debug!("Found shebang: {acceptable_path}");
-        let version = line[acceptable_path.len()..].to_string();
-        log::debug!("Found version: {version}");
-        return RequestedVersion::from_str(&version).ok();
     }
 
-    None
+    /// Whether output written to the file descriptor (e.g. stdout) is
+    /// colored.
+    pub fn enabled(self, fd: RawFd) -> bool {
+        match self {
+            Self::Auto => {
+                env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+                    && nix::unistd::isatty(fd).unwrap_or(false)
+            }
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
 }
 
-fn find_executable(version: RequestedVersion, args: &[String]) -> crate::Result<PathBuf> {
-    let mut requested_version = version;
-    let mut chosen_path: Option<PathBuf> = None;
+/// A shell which a virtual environment can be activated in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shell {
+    /// A POSIX-compatible shell, e.g. bash or zsh.
+    Posix,
+    /// [fish](https://fishshell.com/).
+    Fish,
+    /// [PowerShell](https://learn.microsoft.com/powershell/).
+    PowerShell,
+}
 
-    if requested_version == RequestedVersion::Any {
-        if let Some(venv_path) = venv_executable() {
-            chosen_path = Some(venv_path);
-        } else if !args.is_empty() {
-            // Using the first argument because it's the simplest and sanest.
-            // We can't use the last argument because that could actually be an argument
-            // to the Python module being executed. This is the same reason we can't go
-            // searching for the first/last file path that we find. The only safe way to
-            // get the file path regardless of its position is to replicate Python's arg
-            // parsing and that's a **lot** of work for little gain. Hence we only care
-            // about the first argument.
-            let possible_file = &args[0];
-            log::info!("Checking {possible_file:?} for a shebang");
-            if let Ok(mut open_file) = File::open(possible_file) {
-                if let Some(shebang_version) = parse_python_shebang(&mut open_file) {
-                    requested_version = shebang_version;
-                }
-            }
+impl Shell {
+    /// Returns the shell for the name (or path) of its executable, e.g.
+    /// `bash` or `/usr/bin/fish`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match Path::new(name).file_name()?.to_str()? {
+            "sh" | "bash" | "zsh" | "dash" | "ksh" => Some(Self::Posix),
+            "fish" => Some(Self::Fish),
+            "pwsh" | "powershell" => Some(Self::PowerShell),
+            _ => None,
         }
     }
 
-    if chosen_path.is_none() {
-        if let Some(env_var) = requested_version.env_var() {
-            log::info!("Checking the {env_var} environment variable");
-            if let Ok(env_var_value) = env::var(&env_var) {
-                if !env_var_value.is_empty() {
-                    log::debug!("{env_var} = '{env_var_value}'");
-                    let env_requested_version = RequestedVersion::from_str(&env_var_value)?;
-                    requested_version = env_requested_version;
-                }
-            } else {
-                log::info!("{env_var} not set");
-            };
-        }
+    /// Returns the shell named by the `SHELL` environment variable, defaulting
+    /// to [`Shell::Posix`].
+    pub fn detect() -> Self {
+        env::var("SHELL")
+            .ok()
+            .and_then(|shell| Self::from_name(&shell))
+            .unwrap_or(Self::Posix)
+    }
 
-        if let Some(executable_path) = crate::find_executable(requested_version) {
-            chosen_path = Some(executable_path);
+    /// The name of the activation script in a virtual environment's `bin`
+    /// directory.
+    fn activation_script(self) -> &'static str {
+        match self {
+            Self::Posix => "activate",
+            Self::Fish => "activate.fish",
+            Self::PowerShell => "Activate.ps1",
         }
     }
 
-    chosen_path.ok_or(crate::Error::NoExecutableFound(requested_version))
+    /// Quotes a string so the shell treats it as a single, literal word.
+    fn quote(self, word: &str) -> String {
+        match self {
+            Self::Posix => format!("'{}'", word.replace('\'', r"'\''")),
+            Self::Fish => format!("'{}'", word.replace('\\', r"\\").replace('\'', r"\'")),
+            Self::PowerShell => format!("'{}'", word.replace('\'', "''")),
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use test_case::test_case;
-
-    use super::*;
-
-    #[test_case(&["py".to_string(), "--help".to_string(), "--list".to_string()] => Err(crate::Error::IllegalArgument(PathBuf::from("py"), "--help".to_string())))]
-    #[test_case(&["py".to_string(), "--list".to_string(), "--help".to_string()] => Err(crate::Error::IllegalArgument(PathBuf::from("py"), "--list".to_string())))]
-    fn from_main_illegal_argument_tests(argv: &[String]) -> crate::Result<Action> {
-        Action::from_main(argv)
+/// Returns the shell snippet to activate the virtual environment at `root`.
+///
+/// Conda environments (i.e. those with a `conda-meta` directory) are activated
+/// via `conda activate`; all other virtual environments via their activation
+/// script.
+fn activation_snippet(root: &Path, shell: Shell) -> crate::Result<String> {
+    let root_str = root.to_string_lossy();
+    if root.join("conda-meta").is_dir() {
+        return Ok(format!("conda activate {}\n", shell.quote(&root_str)));
     }
 
-    #[test_case("-S" => None ; "unrecognized short flag is None")]
-    #[test_case("--something" => None ; "unrecognized long flag is None")]
-    #[test_case("-3" => Some(RequestedVersion::MajorOnly(3)) ; "major version")]
-    #[test_case("-3.6" => Some(RequestedVersion::Exact(3, 6)) ; "Exact/major.minor")]
-    #[test_case("-42.13" => Some(RequestedVersion::Exact(42, 13)) ; "double-digit major & minor versions")]
-    #[test_case("-3.6.4" => None ; "version flag with micro version is None")]
-    fn version_from_flag_tests(flag: &str) -> Option<RequestedVersion> {
-        version_from_flag(flag)
+    let script = root.join("bin").join(shell.activation_script());
+    if !script.is_file() {
+        return Err(crate::Error::Search(
+            crate::SearchError::NoActivationScript(script),
+        ));
     }
-
+    let quoted_script = shell.quote(&script.to_string_lossy());
+    Ok(match shell {
+        Shell::Posix | Shell::PowerShell => format!(". {quoted_script}\n"),
+        Shell::Fish => format!("source {quoted_script}\n"),
+    })
+}
+
+fn help_message(launcher_path: &Path, executable_path: &Path) -> String {
+    let mut message = String::new();
+    writeln!(
+        message,
+        include_str!("HELP.txt"),
+        env!("CARGO_PKG_VERSION"),
+        launcher_path.to_string_lossy(),
+        executable_path.to_string_lossy()
+    )
+    .unwrap();
+    message
+}
+
+/// Attempts to find a version specifier, along with any exclusions, from a
+/// CLI argument.
+///
+/// It is assumed that the flag from the command-line is passed as-is
+/// (i.e. the flag starts with `-`). Only a single version may be requested.
+fn preferences_from_flag(arg: &str) -> Option<VersionPreferenceList> {
+    if !arg.starts_with('-') {
+        None
+    } else {
+        VersionPreferenceList::from_str(&arg[1..])
+            .ok()
+            .filter(|preferences| preferences.versions.len() == 1)
+    }
+}
+
+/// Splits the arguments following `venv create` into the version of Python
+/// requested and the arguments to pass to the interpreter.
+///
+/// The version is optional and may be specified with or without a leading
+/// `-`, e.g. `3.11` or `-3.11`. The directory for the virtual environment
+/// defaults to [`DEFAULT_VENV_DIR`], and `--upgrade-pip` is translated to the
+/// `--upgrade-deps` option of `venv`. All other arguments are passed on to
+/// `venv` as-is.
+fn venv_create_args(args: &[String]) -> (VersionPreferenceList, Vec<String>) {
+    let (preferences, args) = match args.split_first() {
+        Some((version, rest)) => match preferences_from_flag(version)
+            .or_else(|| preferences_from_flag(&format!("-{version}")))
+        {
+            Some(preferences) => (preferences, rest),
+            None => (VersionPreferenceList::from(RequestedVersion::Any), args),
+        },
+        None => (VersionPreferenceList::from(RequestedVersion::Any), args),
+    };
+
+    let mut venv_args = vec!["-m".to_string(), "venv".to_string()];
+    if args.first().map_or(true, |arg| arg.starts_with('-')) {
+        venv_args.push(DEFAULT_VENV_DIR.to_string());
+    }
+    venv_args.extend(args.iter().map(|arg| {
+        if arg == "--upgrade-pip" {
+            "--upgrade-deps".to_string()
+        } else {
+            arg.to_string()
+        }
+    }));
+    (preferences, venv_args)
+}
+
+/// How executables are listed by `--list`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ListFormat {
+    /// A human-readable table (the default).
+    Table,
+    /// A JSON array of objects (see [`list_json`]).
+    Json,
+    /// Tab-separated columns (see [`list_delimited`]).
+    Tsv,
+    /// NUL-terminated columns (see [`list_delimited`]).
+    Nul,
+    /// The versions which can be requested, for shell completions (see
+    /// [`list_versions`]).
+    Versions,
+}
+
+impl ListFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "table" => Some(Self::Table),
+            "json" => Some(Self::Json),
+            "tsv" => Some(Self::Tsv),
+            "nul" => Some(Self::Nul),
+            "versions" => Some(Self::Versions),
+            _ => None,
+        }
+    }
+}
+
+/// An executable listed by `--list`: its version (if known), path, and the
+/// name of its source.
+pub(crate) type ListedExecutable = (Option<ExactVersion>, PathBuf, String);
+
+/// Gathers the executables to list: every environment, followed by the
+/// executables in the order given, followed by Windows-native executables
+/// (under WSL) from newest to oldest version.
+pub(crate) fn listed_executables(
+    environments: &[(&str, VenvInfo)],
+    executables: Vec<(String, ExactVersion, PathBuf)>,
+    windows_executables: &HashMap<ExactVersion, PathBuf>,
+) -> Vec<ListedExecutable> {
+    let mut windows_executable_pairs = Vec::from_iter(windows_executables);
+    windows_executable_pairs.sort_unstable();
+    windows_executable_pairs.reverse();
+
+    // The version of a virtual environment is only known if its `pyvenv.cfg`
+    // says so.
+    let environment_rows = environments
+        .iter()
+        .map(|(kind, venv)| (venv.version, venv.executable.clone(), kind.to_string()));
+    let executable_rows = executables
+        .into_iter()
+        .map(|(source, version, path)| (Some(version), path, source));
+    let windows_rows = windows_executable_pairs
+        .into_iter()
+        .map(|(version, path)| (Some(*version), path.clone(), WSL_SOURCE.to_string()));
+    environment_rows
+        .chain(executable_rows)
+        .chain(windows_rows)
+        .collect()
+}
+
+/// The source of Windows-native executables (see
+/// [`crate::windows_executables`]).
+const WSL_SOURCE: &str = "wsl";
+
+/// Returns [`Action::Resolve`] for `--resolve`, else [`Action::Which`].
+fn report_executable(
+    flag: &str,
+    executable: PathBuf,
+    venv_detection: VenvDetection,
+    search: &Search,
+) -> Action {
+    if flag == "--resolve" {
+        let environments = search.all_environments(venv_detection);
+        let listed = listed_executables(
+            &environments,
+            search.all_executables_by_source(),
+            &search.windows_executables(),
+        );
+        Action::Resolve(format!(
+            "{}\n",
+            resolution_value(&executable, &environments, &listed)
+        ))
+    } else {
+        Action::Which(executable)
+    }
+}
+
+/// Describes the executable which was resolved as a JSON object with the keys:
+///
+/// - `path`: the path to the executable.
+/// - `version`: its version (e.g. `3.12`, `3.13t`, or `pypy3.10`), or `null`
+///   when unknown (e.g. a virtual environment lacking a version in its
+///   `pyvenv.cfg`).
+/// - `source`: the name of the source which found it, as listed by
+///   `--list --all` (e.g. `path` or `virtual-env`), or `null` when it wasn't
+///   found by one (e.g. a shebang naming the executable).
+/// - `venv`: the root of the virtual environment it belongs to, or `null`.
+///
+/// The first of `listed` (see [`listed_executables`]) for the executable is
+/// the one which found it.
+pub(crate) fn resolution_value(
+    executable: &Path,
+    environments: &[(&str, VenvInfo)],
+    listed: &[ListedExecutable],
+) -> Value {
+    let found = listed.iter().find(|(_, path, _)| path == executable);
+    let version = found
+        .and_then(|(version, _, _)| *version)
+        .or_else(|| ExactVersion::from_path(executable).ok());
+    let venv = environments
+        .iter()
+        .find(|(_, venv)| venv.executable == executable)
+        .map(|(_, venv)| &venv.root);
+    json!({
+        "path": executable.display().to_string(),
+        "version": version.map(|version| version.to_string()),
+        "source": found.map(|(_, _, source)| source),
+        "venv": venv.map(|root| root.display().to_string()),
+    })
+}
+
+/// Sorts listed executables by a column, keeping the current order for
+/// executables which are equal in it.
+///
+/// Versions are sorted from newest to oldest, with unknown versions last,
+/// while everything else is sorted alphabetically (with unknown
+/// implementations and architectures last).
+fn sort_listed(listed: &mut [ListedExecutable], column: ListColumn) {
+    match column {
+        ListColumn::Version => {
+            listed.sort_by(|(version, ..), (other_version, ..)| other_version.cmp(version))
+        }
+        ListColumn::Path => listed.sort_by(|(_, path, _), (_, other_path, _)| path.cmp(other_path)),
+        ListColumn::Source => {
+            listed.sort_by(|(.., source), (.., other_source)| source.cmp(other_source))
+        }
+        ListColumn::Implementation | ListColumn::Architecture => {
+            listed.sort_by_cached_key(|executable| {
+                let cell = list_cell(executable, column);
+                (cell.is_empty(), cell)
+            })
+        }
+    }
+}
+
+/// Which virtual environments are used when no launcher flag says: none but
+/// an activated one if the `PYLAUNCH_NO_VENV_SEARCH` environment variable is
+/// set, else as configured.
+pub(crate) fn default_venv_detection(env: &dyn EnvProvider, config: &Config) -> VenvDetection {
+    if env.var_os("PYLAUNCH_NO_VENV_SEARCH").is_some() {
+        VenvDetection::ActivatedOnly
+    } else {
+        config.venv_detection
+    }
+}
+
+/// Why the executable used when no version is requested was chosen, for a
+/// launch which isn't of a virtual environment (see [`find_executable`]).
+pub(crate) fn default_reason(search: &Search) -> &'static str {
+    if search
+        .env
+        .var("PY_PYTHON")
+        .map_or(false, |value| !value.is_empty())
+        || search.config.env_var_default("PY_PYTHON").is_some()
+    {
+        "PY_PYTHON"
+    } else if search.default_version().is_some() {
+        "pinned"
+    } else {
+        "newest"
+    }
+}
+
+/// Formats listed executables as a table of the specified columns.
+///
+/// The executable at the index of `default` is marked with why it's used by
+/// default, in a column of its own. With `color`, versions are green and the
+/// marker is bold and yellow.
+fn list_table(
+    listed: &[ListedExecutable],
+    columns: &[ListColumn],
+    default: Option<(usize, &str)>,
+    color: bool,
+) -> crate::Result<String> {
+    if listed.is_empty() {
+        return Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+            RequestedVersion::Any,
+        )));
+    }
+
+    let mut table = Table::new();
+    table.load_preset(comfy_table::presets::NOTHING);
+    // Using U+2502/"Box Drawings Light Vertical" over
+    // U+007C/"Vertical Line"/pipe simply because it looks better.
+    // Leaving out a header and other decorations to make it easier
+    // parse the output.
+    table.set_style(TableComponent::VerticalLines, '│');
+    // Whether stdout is a terminal has already been taken into account.
+    if color {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+
+    for (index, executable) in listed.iter().enumerate() {
+        let mut row = columns
+            .iter()
+            .map(|column| {
+                let cell = Cell::new(list_cell(executable, *column));
+                match column {
+                    ListColumn::Version => cell.fg(Color::Green),
+                    _ => cell,
+                }
+            })
+            .collect::<Vec<Cell>>();
+        match default {
+            Some((default_index, reason)) if default_index == index => row.push(
+                Cell::new(format!("(default: {reason})"))
+                    .fg(Color::Yellow)
+                    .add_attribute(Attribute::Bold),
+            ),
+            Some(_) => row.push(Cell::new("")),
+            None => (),
+        }
+        table.add_row(row);
+    }
+
+    Ok(table.to_string() + "\n")
+}
+
+/// Formats listed executables for scripts to read: the columns separated by
+/// tabs with one executable per line, or with `nul`, every column terminated
+/// by a NUL character (like `find -print0`) so that any path can be read
+/// safely.
+fn list_delimited(
+    listed: &[ListedExecutable],
+    columns: &[ListColumn],
+    nul: bool,
+) -> crate::Result<String> {
+    if listed.is_empty() {
+        return Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+            RequestedVersion::Any,
+        )));
+    }
+
+    let mut output = String::new();
+    for executable in listed {
+        let cells = columns.iter().map(|column| list_cell(executable, *column));
+        if nul {
+            for cell in cells {
+                output.push_str(&cell);
+                output.push('\0');
+            }
+        } else {
+            output.push_str(&cells.collect::<Vec<String>>().join("\t"));
+            output.push('\n');
+        }
+    }
+    Ok(output)
+}
+
+/// Lists every version which can be requested with a version flag (e.g. `3.12`
+/// for `-3.12`), one per line from newest to oldest, for shell completions.
+///
+/// Environments can't be requested by version and Windows-native executables
+/// are only launched via `--windows`, so they are left out. Unlike the table,
+/// finding no executables is an empty list and not an error.
+fn list_versions(listed: &[ListedExecutable], environments: &[(&str, VenvInfo)]) -> String {
+    let mut seen_versions = HashSet::new();
+    let mut versions = listed
+        .iter()
+        .filter(|(_, path, source)| {
+            source != WSL_SOURCE
+                && environments
+                    .iter()
+                    .all(|(_, venv)| venv.executable != *path)
+        })
+        .filter_map(|(version, ..)| *version)
+        .filter(|version| seen_versions.insert(*version))
+        .collect::<Vec<ExactVersion>>();
+    // Whatever `--sort` says, as the fish completions rely on this order.
+    versions.sort_by(|version, other_version| other_version.cmp(version));
+    versions
+        .iter()
+        .map(|version| format!("{version}\n"))
+        .collect()
+}
+
+/// Windows-native executables have their version marked with `(Windows)`.
+fn list_cell((version, path, source): &ListedExecutable, column: ListColumn) -> String {
+    match column {
+        ListColumn::Version => match version {
+            Some(version) if source == WSL_SOURCE => format!("{version} (Windows)"),
+            Some(version) => version.to_string(),
+            None => String::new(),
+        },
+        ListColumn::Path => path.display().to_string(),
+        ListColumn::Source => source.to_string(),
+        ListColumn::Implementation => version
+            .map(|version| version.implementation.to_string())
+            .unwrap_or_default(),
+        ListColumn::Architecture => crate::executable_architecture(path)
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Lists executables like the Windows launcher does for `-0`/`-0p`, e.g.
+/// ` -V:3.12 *        Python 3.12`, from newest to oldest version.
+///
+/// An activated environment comes first (as `Active venv`), and the default
+/// executable is marked with `*`. With `paths`, the path of each executable
+/// is given instead of a description.
+fn list_tags(
+    executables: &HashMap<ExactVersion, PathBuf>,
+    active_env: Option<&VenvInfo>,
+    default_executable: Option<&Path>,
+    paths: bool,
+) -> crate::Result<String> {
+    if executables.is_empty() && active_env.is_none() {
+        return Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+            RequestedVersion::Any,
+        )));
+    }
+
+    let mut executable_pairs = Vec::from_iter(executables);
+    executable_pairs.sort_unstable();
+    executable_pairs.reverse();
+
+    let active_row = active_env.map(|venv| {
+        (
+            String::new(),
+            "Active venv".to_string(),
+            venv.executable.as_path(),
+        )
+    });
+    let executable_rows = executable_pairs.into_iter().map(|(version, path)| {
+        let name = match version.implementation {
+            Implementation::CPython => "Python".to_string(),
+            implementation => implementation.to_string(),
+        };
+        let version_string = version.to_string();
+        let number = version_string.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+        (
+            format!("-V:{version}"),
+            format!("{name} {number}"),
+            path.as_path(),
+        )
+    });
+
+    let mut output = String::new();
+    for (tag, description, path) in active_row.into_iter().chain(executable_rows) {
+        let marker = if Some(path) == default_executable {
+            " *"
+        } else {
+            ""
+        };
+        let description = if paths {
+            path.display().to_string()
+        } else {
+            description
+        };
+        writeln!(output, " {:<16} {description}", format!("{tag}{marker}")).unwrap();
+    }
+    Ok(output)
+}
+
+/// Keeps only the first executable found for each version (like
+/// [`crate::all_executables_from`] does), from newest to oldest version.
+pub(crate) fn preferred_executables(
+    mut executables: Vec<(String, ExactVersion, PathBuf)>,
+) -> Vec<(String, ExactVersion, PathBuf)> {
+    let mut seen_versions = HashSet::new();
+    executables.retain(|(_, version, _)| seen_versions.insert(*version));
+    executables.sort_by(|(_, version, _), (_, other_version, _)| other_version.cmp(version));
+    executables
+}
+
+/// Lists executables as a JSON array of objects.
+///
+/// Each object has the `version`, `implementation`, `architecture`, `path`,
+/// and `source` of an executable (`null` when unknown, e.g. the version of a
+/// virtual environment), and whether it's the `default` (i.e. at the index of
+/// `default`). Unlike the table, finding no executables is an empty array and
+/// not an error.
+pub(crate) fn list_value(listed: &[ListedExecutable], default: Option<(usize, &str)>) -> Value {
+    listed
+        .iter()
+        .enumerate()
+        .map(|(index, (version, path, source))| {
+            json!({
+                "version": version.map(|version| version.to_string()),
+                "implementation": version.map(|version| version.implementation.to_string()),
+                "architecture": crate::executable_architecture(path),
+                "path": path.display().to_string(),
+                "source": source,
+                "default": default.map_or(false, |(default_index, _)| default_index == index),
+            })
+        })
+        .collect()
+}
+
+/// Prints [`list_value`] with one executable per line.
+fn list_json(listed: &[ListedExecutable], default: Option<(usize, &str)>) -> String {
+    match list_value(listed, default) {
+        Value::Array(objects) if !objects.is_empty() => format!(
+            "[\n{}\n]\n",
+            objects
+                .iter()
+                .map(|object| format!("  {object}"))
+                .collect::<Vec<String>>()
+                .join(",\n")
+        ),
+        _ => "[]\n".to_string(),
+    }
+}
+
+/// Lists every occurrence of an executable, grouped by version from newest to
+/// oldest, in the order they are searched.
+///
+/// The first executable for a version is marked as `used`, and the others as
+/// `shadowed` by it (noting when they are the `same file`, e.g. via a
+/// symlink).
+fn list_duplicates(mut occurrences: Vec<(String, ExactVersion, PathBuf)>) -> crate::Result<String> {
+    if occurrences.is_empty() {
+        return Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+            RequestedVersion::Any,
+        )));
+    }
+    // A stable sort keeps the search order within a version.
+    occurrences.sort_by(|(_, version, _), (_, other_version, _)| other_version.cmp(version));
+
+    let mut table = Table::new();
+    table.load_preset(comfy_table::presets::NOTHING);
+    table.set_style(TableComponent::VerticalLines, '│');
+    let mut used: Option<(ExactVersion, Option<PathBuf>)> = None;
+    for (source, version, path) in &occurrences {
+        let status = match &used {
+            Some((used_version, used_file)) if used_version == version => {
+                if used_file.is_some() && *used_file == path.canonicalize().ok() {
+                    "shadowed (same file)"
+                } else {
+                    "shadowed"
+                }
+            }
+            _ => {
+                used = Some((*version, path.canonicalize().ok()));
+                "used"
+            }
+        };
+        table.add_row(vec![
+            version.to_string(),
+            path.display().to_string(),
+            source.to_string(),
+            status.to_string(),
+        ]);
+    }
+    Ok(table.to_string() + "\n")
+}
+
+fn list_available(
+    downloads: &[crate::install::Download],
+    installs: &[crate::install::Installed],
+    found: &[ExactVersion],
+) -> String {
+    if downloads.is_empty() {
+        return String::new();
+    }
+    let mut table = Table::new();
+    table.load_preset(comfy_table::presets::NOTHING);
+    table.set_style(TableComponent::VerticalLines, '│');
+    for download in downloads {
+        let version = download.version;
+        let status = if installs.iter().any(|install| install.version == version) {
+            "installed"
+        } else if found
+            .iter()
+            .any(|found| found.supports(RequestedVersion::Exact(version.major, version.minor)))
+        {
+            "found"
+        } else {
+            ""
+        };
+        table.add_row(vec![version.to_string(), status.to_string()]);
+    }
+    table.to_string() + "\n"
+}
+
+fn list_installed(installs: &[crate::install::Installed]) -> String {
+    if installs.is_empty() {
+        return String::new();
+    }
+    let mut table = Table::new();
+    table.load_preset(comfy_table::presets::NOTHING);
+    table.set_style(TableComponent::VerticalLines, '│');
+    for install in installs {
+        table.add_row(vec![
+            install.version.to_string(),
+            install.executable().display().to_string(),
+        ]);
+    }
+    table.to_string() + "\n"
+}
+
+/// Environment variables which are reported by `--configuration` on their
+/// own, as no setting of the configuration file is equivalent.
+const REPORTED_ENV_VARS: [&str; 6] = [
+    "PY_PYTHON_PATH",
+    "PY_FLAGS",
+    "PYLAUNCH_CONFIG",
+    "PYLAUNCH_NO_CONFIG",
+    "VIRTUAL_ENV",
+    "CONDA_PREFIX",
+];
+
+fn configuration_report(
+    env: &dyn EnvProvider,
+    settings: Vec<SettingSource>,
+    launcher_flags: &[String],
+    allow_prereleases: bool,
+    venv_detection: VenvDetection,
+) -> String {
+    let mut rows = settings
+        .into_iter()
+        .map(|(key, value, path)| {
+            let source =
+                path.map_or_else(|| "default".to_string(), |path| path.display().to_string());
+            vec![key, value, source]
+        })
+        .collect::<Vec<Vec<String>>>();
+    let env_var = |name: &str| env.var(name).filter(|value| !value.is_empty());
+    // Environment variables and launcher flags take precedence over the
+    // configuration.
+    let mut set = |key: &str, value: String, source: String| {
+        let row = vec![key.to_string(), value, source];
+        match rows.iter_mut().find(|existing| existing[0] == key) {
+            Some(existing) => *existing = row,
+            None => rows.push(row),
+        }
+    };
+    if let Some((name, value)) = ["PY_PYTHON", "PYENV_VERSION"]
+        .iter()
+        .find_map(|name| env_var(name).map(|value| (name, value)))
+    {
+        set("default-version", value, format!("${name}"));
+    }
+    let mut major_env_vars = env
+        .var_names()
+        .into_iter()
+        .filter(|name| {
+            name.strip_prefix("PY_PYTHON").map_or(false, |major| {
+                !major.is_empty() && major.chars().all(|c| c.is_ascii_digit())
+            })
+        })
+        .filter_map(|name| env_var(&name).map(|value| (name, value)))
+        .collect::<Vec<(String, String)>>();
+    major_env_vars.sort();
+    for (name, value) in major_env_vars {
+        set(&name, value, format!("${name}"));
+    }
+    let prereleases_source = if !allow_prereleases {
+        None
+    } else if launcher_flags.iter().any(|flag| flag == "--pre") {
+        Some("--pre")
+    } else if env.var_os("PYLAUNCH_ALLOW_PRERELEASE").is_some() {
+        Some("$PYLAUNCH_ALLOW_PRERELEASE")
+    } else {
+        None
+    };
+    if let Some(source) = prereleases_source {
+        set("allow-prereleases", "true".to_string(), source.to_string());
+    }
+    if launcher_flags.iter().any(|flag| flag == "--pick") {
+        set("pick", "true".to_string(), "--pick".to_string());
+    }
+    if launcher_flags.iter().any(|flag| flag == "--clean-env") {
+        set("clean-env", "true".to_string(), "--clean-env".to_string());
+    }
+    if env.var_os("PYLAUNCHER_ALLOW_INSTALL").is_some() {
+        set(
+            "allow-install",
+            "true".to_string(),
+            "$PYLAUNCHER_ALLOW_INSTALL".to_string(),
+        );
+    }
+    let venv_search_source = match venv_detection {
+        VenvDetection::Search => None,
+        VenvDetection::ActivatedOnly if env.var_os("PYLAUNCH_NO_VENV_SEARCH").is_some() => {
+            Some("$PYLAUNCH_NO_VENV_SEARCH")
+        }
+        VenvDetection::ActivatedOnly => None,
+        VenvDetection::Disabled if launcher_flags.iter().any(|flag| flag == "--windows") => {
+            Some("--windows")
+        }
+        VenvDetection::Disabled => Some("--no-venv"),
+    };
+    if let Some(source) = venv_search_source {
+        set("venv-search", "false".to_string(), source.to_string());
+    }
+    for row in rows.iter_mut() {
+        let name = match row[0].strip_prefix("environment.") {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if let Some(value) = env_var(&name) {
+            *row = vec![row[0].clone(), value, format!("${name}")];
+        }
+    }
+
+    // Additions to the configuration.
+    if let Some(sources) = env_var("PY_DISABLE_SOURCES") {
+        for source in sources
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            rows.push(vec![
+                "disable-sources".to_string(),
+                source.to_string(),
+                "$PY_DISABLE_SOURCES".to_string(),
+            ]);
+        }
+    }
+    for name in REPORTED_ENV_VARS {
+        if let Some(value) = env_var(name) {
+            rows.push(vec![name.to_string(), value, format!("${name}")]);
+        }
+    }
+
+    let mut table = Table::new();
+    table.load_preset(comfy_table::presets::NOTHING);
+    table.set_style(TableComponent::VerticalLines, '│');
+    for row in rows {
+        table.add_row(row);
+    }
+    table.to_string() + "\n"
+}
+
+/// The environment variables from the configuration to set for an
+/// interpreter, as long as they aren't already set (or are about to be unset
+/// for a clean environment).
+fn child_env_vars(config: &Config, clean_env: bool) -> Vec<(String, String)> {
+    config
+        .environment
+        .iter()
+        .filter(|(name, _)| env::var_os(name).is_none() || clean_env && name.starts_with("PYTHON"))
+        .cloned()
+        .collect()
+}
+
+/// The script among the interpreter's `args`, if one is run rather than
+/// `-c` or `-m`.
+fn script(args: &[String]) -> Option<&str> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" | "-m" | "-" | "--" => return None,
+            "-W" | "-X" | "--check-hash-based-pycs" => {
+                args.next();
+            }
+            arg if arg.starts_with('-') => (),
+            arg => return Some(arg),
+        }
+    }
+    None
+}
+
+/// The windowed counterpart of `executable` in the same directory, i.e. the
+/// one which doesn't open a console on Windows and runs as an app on macOS:
+/// `pythonw3.12` for `python3.12`, or `pythonw.exe` for `python.exe`.
+///
+/// Only the exact counterpart is used, as e.g. `pythonw3` may be another
+/// version than `python3.12`. `None` is returned if there is none, e.g. for
+/// `pypy3`.
+fn windowed_executable(executable: &Path) -> Option<PathBuf> {
+    let file_name = executable.file_name()?.to_str()?;
+    let suffix = file_name.strip_prefix("python")?;
+    let windowed_executable = executable.with_file_name(format!("pythonw{suffix}"));
+    windowed_executable.is_file().then(|| windowed_executable)
+}
+
+/// Splits `text` into words like a POSIX shell, without any expansions, e.g.
+/// `-X dev -c 'print("hi")'` into `-X`, `dev`, `-c`, and `print("hi")`.
+///
+/// Words are separated by whitespace. Within single quotes everything is
+/// literal, while within double quotes a backslash only escapes `"`, `\`, `$`,
+/// and `` ` ``; elsewhere a backslash escapes any character. `None` is
+/// returned if a quote is unterminated.
+fn split_flags(text: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    // `None` between words, so that `''` is still an (empty) word.
+    let mut word: Option<String> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                let word = word.get_or_insert_with(String::new);
+                // A trailing backslash is kept as-is.
+                word.push(chars.next().unwrap_or('\\'));
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// The names of the `PYTHON*` environment variables which are set, i.e. those
+/// which are unset for a clean environment.
+fn inherited_python_env_vars() -> Vec<String> {
+    let mut names = env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .filter(|name| name.starts_with("PYTHON"))
+        .collect::<Vec<String>>();
+    names.sort_unstable();
+    names
+}
+
+/// The command run for an [`Action::Execute`] as printed by `--dry-run`: the
+/// environment variables set for the interpreter as `NAME=value` followed by
+/// the interpreter and its arguments, quoted for a POSIX shell where
+/// necessary so that the line can be run as-is. Any environment variables to
+/// unset come first via `env -u NAME`, along with `argv[0]` via `env -a NAME`
+/// if it isn't the path to the interpreter.
+fn dry_run_command(
+    executable: &Path,
+    argv0: Option<&str>,
+    args: &[String],
+    env_vars: &[(String, String)],
+    unset_env_vars: &[String],
+) -> String {
+    let quote = |word: &str| {
+        let plain = !word.is_empty()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c));
+        if plain {
+            word.to_string()
+        } else {
+            Shell::Posix.quote(word)
+        }
+    };
+    let mut words = Vec::new();
+    if !unset_env_vars.is_empty() || argv0.is_some() {
+        words.push("env".to_string());
+        for name in unset_env_vars {
+            words.push(format!("-u {}", quote(name)));
+        }
+        if let Some(argv0) = argv0 {
+            words.push(format!("-a {}", quote(argv0)));
+        }
+    }
+    words.extend(
+        env_vars
+            .iter()
+            .map(|(name, value)| format!("{name}={}", quote(value))),
+    );
+    words.push(quote(&executable.to_string_lossy()));
+    words.extend(args.iter().map(|arg| quote(arg)));
+    words.join(" ") + "\n"
+}
+
+/// Reads the shebang line of the script which is the first argument to the
+/// interpreter, if any.
+fn script_shebang(args: &[String], search: &Search) -> Option<Shebang> {
+    // Using the first argument because it's the simplest and sanest.
+    // We can't use the last argument because that could actually be an argument
+    // to the Python module being executed. This is the same reason we can't go
+    // searching for the first/last file path that we find. The only safe way to
+    // get the file path regardless of its position is to replicate Python's arg
+    // parsing and that's a **lot** of work for little gain. Hence we only care
+    // about the first argument.
+    let possible_file = args.first()?;
+    log::info!("Checking {possible_file:?} for a shebang");
+    // Relative to the directory the interpreter is run in.
+    let path = match search.env.current_dir() {
+        Ok(cwd) => cwd.join(possible_file),
+        Err(_) => PathBuf::from(possible_file),
+    };
+    crate::shebang::parse(&mut File::open(path).ok()?, &search.config.shebang_commands)
+}
+
+/// Puts the arguments to the interpreter from the shebang line ahead of the
+/// script and its arguments.
+fn with_shebang_arguments(
+    shebang: Option<&Shebang>,
+    args: &[String],
+    splitting: ArgumentSplitting,
+) -> Vec<String> {
+    let mut all_args = shebang
+        .map(|shebang| shebang.arguments(splitting))
+        .unwrap_or_default();
+    all_args.extend_from_slice(args);
+    all_args
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip(shebang, search),
+        fields(preferences = %preferences),
+        ret,
+        err
+    )
+)]
+fn find_executable(
+    preferences: VersionPreferenceList,
+    shebang: Option<&Shebang>,
+    allow_prereleases: bool,
+    windows: bool,
+    venv_detection: VenvDetection,
+    picking: Picking,
+    search: &Search,
+) -> crate::Result<PathBuf> {
+    let (preferences, requires_python) = match resolve_request(
+        preferences,
+        shebang,
+        allow_prereleases,
+        venv_detection,
+        search,
+    )? {
+        Request::Executable(executable) => return Ok(executable),
+        Request::Search {
+            preferences,
+            requires_python,
+        } => (preferences, requires_python),
+    };
+
+    let found_executable = if picking != Picking::Disabled && crate::picker::available() {
+        let candidates = if windows {
+            search.find_preferred_windows_executables(&preferences)
+        } else {
+            search.find_preferred_executables(&preferences)
+        };
+        match candidates.as_slice() {
+            [] => None,
+            [(_, executable)] => Some(executable.clone()),
+            _ => Some(crate::picker::pick(
+                &preferences.to_string(),
+                &candidates,
+                picking == Picking::Ask,
+                search.env,
+            )?),
+        }
+    } else if windows {
+        search.find_preferred_windows_executable(&preferences)
+    } else {
+        search.find_preferred_executable(&preferences)
+    };
+    found_in_search(found_executable, &preferences, requires_python.as_ref())
+}
+
+/// What a request for an interpreter comes down to before searching.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Request {
+    /// A specific executable, i.e. that of a virtual environment or the one a
+    /// shebang names.
+    Executable(PathBuf),
+    /// The versions to search for, along with the `requires-python` of the
+    /// current project (if any).
+    Search {
+        preferences: VersionPreferenceList,
+        requires_python: Option<VersionSpecifier>,
+    },
+}
+
+/// Works out what to search for from the versions requested by a flag (see
+/// [`find_executable`]): any virtual environment or shebang, environment
+/// variables like `PY_PYTHON`, the default version, and the project's
+/// `requires-python` are all taken into account.
+pub(crate) fn resolve_request(
+    mut preferences: VersionPreferenceList,
+    shebang: Option<&Shebang>,
+    allow_prereleases: bool,
+    venv_detection: VenvDetection,
+    search: &Search,
+) -> crate::Result<Request> {
+    // Flags and shebangs only ever request a single version.
+    let mut requested_version = preferences.versions[0];
+
+    if requested_version == RequestedVersion::Any {
+        // The version of a virtual environment is unknown, so it can't be
+        // checked against any exclusions.
+        if preferences.excluded.is_empty() {
+            if let Some(venv) = search.find_environment(venv_detection)? {
+                return Ok(Request::Executable(venv.executable));
+            }
+        }
+        match shebang.map(|shebang| &shebang.command) {
+            Some(ShebangCommand::Version(shebang_version)) => requested_version = *shebang_version,
+            Some(ShebangCommand::Executable(executable)) => {
+                return Ok(Request::Executable(executable.clone()))
+            }
+            None => (),
+        }
+    }
+
+    preferences.versions = vec![requested_version];
+    let mut env_var_used = false;
+    if let Some(env_var) = requested_version.env_var() {
+        log::info!("Checking the {env_var} environment variable");
+        let env_var_value = match search.env.var(&env_var) {
+            Some(env_var_value) if !env_var_value.is_empty() => Some(env_var_value),
+            _ => {
+                log::info!("{env_var} not set; checking py.ini");
+                search.config.env_var_default(&env_var).map(String::from)
+            }
+        };
+        if let Some(env_var_value) = env_var_value {
+            log::debug!("{env_var} = '{env_var_value}'");
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                name = %env_var,
+                value = %env_var_value,
+                "version from the environment"
+            );
+            let env_preferences = VersionPreferenceList::from_str(&env_var_value)?;
+            preferences.versions = env_preferences.versions;
+            preferences.excluded.extend(env_preferences.excluded);
+            env_var_used = true;
+        }
+    }
+    // Agree with pyenv and asdf on what the current Python is when nothing
+    // more specific was asked for.
+    if requested_version == RequestedVersion::Any && !env_var_used {
+        if let Some(default_preferences) = search.default_version() {
+            preferences.versions = default_preferences.versions;
+            preferences.excluded.extend(default_preferences.excluded);
+        }
+    }
+    // Whatever is used by default must be supported by the current project,
+    // but an explicit request is trusted.
+    let requires_python = search
+        .env
+        .current_dir()
+        .ok()
+        .and_then(|cwd| crate::requires_python(&cwd));
+    if let Some(requires_python) = &requires_python {
+        if requested_version == RequestedVersion::Any {
+            preferences
+                .excluded
+                .extend(requires_python.clauses.iter().cloned());
+        }
+    }
+    preferences.prereleases = allow_prereleases;
+    Ok(Request::Search {
+        preferences,
+        requires_python,
+    })
+}
+
+/// Checks the executable found when searching for `preferences`, warning if
+/// it doesn't satisfy the project's `requires-python`.
+pub(crate) fn found_in_search(
+    found_executable: Option<PathBuf>,
+    preferences: &VersionPreferenceList,
+    requires_python: Option<&VersionSpecifier>,
+) -> crate::Result<PathBuf> {
+    if let (Some(requires_python), Some(executable)) = (requires_python, &found_executable) {
+        if let Ok(version) = ExactVersion::from_path(executable) {
+            if !requires_python
+                .clauses
+                .iter()
+                .all(|clause| clause.contains(&version))
+            {
+                log::warn!(
+                    "Python {version} does not satisfy the project's `requires-python` of '{requires_python}'"
+                );
+            }
+        }
+    }
+    found_executable.ok_or_else(|| no_executable_found(preferences))
+}
+
+fn no_executable_found(preferences: &VersionPreferenceList) -> crate::Error {
+    match (
+        preferences.versions.as_slice(),
+        preferences.excluded.is_empty(),
+    ) {
+        ([requested_version], true) => {
+            crate::Error::Search(crate::SearchError::NoExecutableFound(*requested_version))
+        }
+        _ => crate::Error::Search(crate::SearchError::NoPreferredExecutableFound(
+            preferences.clone(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use crate::env_provider::MapEnv;
+
+    use super::*;
+
+    #[test_case(&["py".to_string(), "--help".to_string(), "--list".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--help".to_string()))))]
+    #[test_case(&["py".to_string(), "--list".to_string(), "--help".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--list".to_string()))))]
+    #[test_case(&["py".to_string(), "--pre".to_string(), "--list".to_string(), "-3".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--list".to_string()))) ; "--pre followed by an illegal combination")]
+    #[test_case(&["py".to_string(), "--list".to_string(), "--all".to_string(), "-3".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--list".to_string()))) ; "--list --all followed by another argument")]
+    #[test_case(&["py".to_string(), "-0p".to_string(), "-3".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "-0p".to_string()))) ; "-0p followed by another argument")]
+    #[test_case(&["py".to_string(), "--list".to_string(), "--json".to_string(), "--json".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--list".to_string()))) ; "--list --json repeated")]
+    #[test_case(&["py".to_string(), "--windows".to_string(), "--pre".to_string(), "--help".to_string(), "-3".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--help".to_string()))) ; "--windows and --pre followed by an illegal combination")]
+    #[test_case(&["py".to_string(), "--no-venv".to_string(), "--list".to_string(), "-3".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--list".to_string()))) ; "--no-venv followed by an illegal combination")]
+    #[test_case(&["py".to_string(), "--pick".to_string(), "--list".to_string(), "-3".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--list".to_string()))) ; "--pick followed by an illegal combination")]
+    fn from_main_illegal_argument_tests(argv: &[String]) -> crate::Result<Action> {
+        Action::from_main(argv)
+    }
+
+    #[test_case(&["py".to_string(), "--activate".to_string(), "fish".to_string(), "-3".to_string()] => Err(crate::Error::Cli(crate::CliError::IllegalArgument(PathBuf::from("py"), "--activate".to_string()))) ; "--activate followed by too many arguments")]
+    #[test_case(&["py".to_string(), "--activate".to_string(), "tcsh".to_string()] => Err(crate::Error::Cli(crate::CliError::UnsupportedShell("tcsh".to_string()))) ; "--activate with an unsupported shell")]
+    fn from_main_activate_error_tests(argv: &[String]) -> crate::Result<Action> {
+        Action::from_main(argv)
+    }
+
+    #[test_case("bash" => Some(Shell::Posix) ; "bash")]
+    #[test_case("/bin/zsh" => Some(Shell::Posix) ; "zsh path")]
+    #[test_case("/usr/bin/fish" => Some(Shell::Fish) ; "fish path")]
+    #[test_case("pwsh" => Some(Shell::PowerShell) ; "PowerShell")]
+    #[test_case("tcsh" => None ; "unsupported")]
+    fn shell_from_name_tests(name: &str) -> Option<Shell> {
+        Shell::from_name(name)
+    }
+
+    #[test_case(Shell::Posix, "/it's here" => r"'/it'\''s here'" ; "POSIX")]
+    #[test_case(Shell::Fish, r"/it's\here" => r"'/it\'s\\here'" ; "fish")]
+    #[test_case(Shell::PowerShell, "/it's here" => "'/it''s here'" ; "PowerShell")]
+    fn shell_quote_tests(shell: Shell, word: &str) -> String {
+        shell.quote(word)
+    }
+
+    #[test_case("-S" => None ; "unrecognized short flag is None")]
+    #[test_case("--something" => None ; "unrecognized long flag is None")]
+    #[test_case("-3" => Some(RequestedVersion::MajorOnly(3)) ; "major version")]
+    #[test_case("-3.6" => Some(RequestedVersion::Exact(3, 6)) ; "Exact/major.minor")]
+    #[test_case("-42.13" => Some(RequestedVersion::Exact(42, 13)) ; "double-digit major & minor versions")]
+    #[test_case("-3.6.4" => Some(RequestedVersion::ExactMicro(3, 6, 4)) ; "major.minor.micro")]
+    #[test_case("-3.6.4.1" => None ; "version flag with more than a micro version is None")]
+    #[test_case("-3.9+" => Some(RequestedVersion::AtLeast(3, 9)) ; "minimum major.minor")]
+    #[test_case("-3+" => None ; "minimum major version is None")]
+    #[test_case("-3.13t" => Some(RequestedVersion::FreeThreaded(3, 13)) ; "free-threaded major.minor")]
+    #[test_case("-pypy3.10" => Some(RequestedVersion::Implementation(crate::Implementation::PyPy, Some(3), Some(10))) ; "alternative implementation major.minor")]
+    #[test_case("-graalpy" => Some(RequestedVersion::Implementation(crate::Implementation::GraalPy, None, None)) ; "alternative implementation")]
+    #[test_case("-stable" => Some(RequestedVersion::Stable) ; "newest stable version")]
+    #[test_case("-latest" => Some(RequestedVersion::Latest) ; "newest version")]
+    fn version_from_flag_tests(flag: &str) -> Option<RequestedVersion> {
+        preferences_from_flag(flag).map(|preferences| preferences.versions[0])
+    }
+
+    #[test_case("-3,!3.8" => Some("Python 3 (!=3.8.*)".to_string()) ; "major version with exclusion")]
+    #[test_case("-3,!3.8,!3.9.1" => Some("Python 3 (!=3.8.*,!=3.9.1)".to_string()) ; "multiple exclusions")]
+    #[test_case("-!3.8" => Some("Python (!=3.8.*)".to_string()) ; "only exclusions")]
+    #[test_case("-3.12,3.11" => None ; "multiple versions is None")]
+    #[test_case("-3,!3.13t" => None ; "unsupported exclusion is None")]
+    fn preferences_from_flag_tests(flag: &str) -> Option<String> {
+        preferences_from_flag(flag).map(|preferences| preferences.to_string())
+    }
+
+    #[test_case(&[] => ("Python".to_string(), "-m venv .venv".to_string()) ; "defaults")]
+    #[test_case(&["3.11"] => ("Python 3.11".to_string(), "-m venv .venv".to_string()) ; "version")]
+    #[test_case(&["-3.11", "env"] => ("Python 3.11".to_string(), "-m venv env".to_string()) ; "version flag and directory")]
+    #[test_case(&["3", "env", "--upgrade-pip"] => ("Python 3".to_string(), "-m venv env --upgrade-deps".to_string()) ; "upgrade pip")]
+    #[test_case(&["--prompt", "demo"] => ("Python".to_string(), "-m venv .venv --prompt demo".to_string()) ; "venv options")]
+    #[test_case(&["env"] => ("Python".to_string(), "-m venv env".to_string()) ; "directory without version")]
+    fn venv_create_args_tests(args: &[&str]) -> (String, String) {
+        let args = args
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+        let (preferences, venv_args) = venv_create_args(&args);
+        (preferences.to_string(), venv_args.join(" "))
+    }
+
+    #[test_case(&[], &[] => "/usr/bin/python3\n" ; "no arguments")]
+    #[test_case(&["-m", "http.server", "--bind=127.0.0.1"], &[] => "/usr/bin/python3 -m http.server --bind=127.0.0.1\n" ; "plain arguments")]
+    #[test_case(&["-c", "print('hi there')", ""], &[] => "/usr/bin/python3 -c 'print('\\''hi there'\\'')' ''\n" ; "quoted arguments")]
+    #[test_case(&["script.py"], &[("PYTHONUTF8", "1"), ("PYTHONWARNINGS", "error,default")] => "PYTHONUTF8=1 PYTHONWARNINGS=error,default /usr/bin/python3 script.py\n" ; "environment variables")]
+    #[test_case(&[], &[("GREETING", "hi there")] => "GREETING='hi there' /usr/bin/python3\n" ; "quoted environment variable")]
+    fn dry_run_command_tests(args: &[&str], env_vars: &[(&str, &str)]) -> String {
+        dry_run_command_unset_tests(args, env_vars, &[])
+    }
+
+    #[test_case(&["app.pyw"] => Some("app.pyw".to_string()) ; "script")]
+    #[test_case(&["-X", "dev", "-B", "app.pyw", "arg"] => Some("app.pyw".to_string()) ; "after flags")]
+    #[test_case(&["-m", "app.pyw"] => None ; "module")]
+    #[test_case(&["-c", "pass", "app.pyw"] => None ; "command")]
+    #[test_case(&["-i"] => None ; "interactive")]
+    fn script_tests(args: &[&str]) -> Option<String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        script(&args).map(|script| script.to_string())
+    }
+
+    #[test_case("python3.12", &["pythonw3.12", "pythonw3", "pythonw"] => Some("pythonw3.12".to_string()) ; "exact")]
+    #[test_case("python3.12", &["pythonw3", "pythonw"] => None ; "major")]
+    #[test_case("python3", &["pythonw3"] => Some("pythonw3".to_string()) ; "major only")]
+    #[test_case("python3", &["pythonw"] => None ; "unversioned")]
+    #[test_case("python.exe", &["pythonw.exe"] => Some("pythonw.exe".to_string()) ; "windows")]
+    #[test_case("python3.12", &[] => None ; "missing")]
+    #[test_case("pypy3.10", &["pythonw"] => None ; "not python")]
+    fn windowed_executable_tests(name: &str, windowed_names: &[&str]) -> Option<String> {
+        let dir = tempfile::tempdir().unwrap();
+        for windowed_name in windowed_names {
+            fs::write(dir.path().join(windowed_name), "").unwrap();
+        }
+        windowed_executable(&dir.path().join(name))
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+    }
+
+    #[test_case("" => Some(vec![]) ; "empty")]
+    #[test_case("  -X dev\t-W  error " => Some(vec!["-X".to_string(), "dev".to_string(), "-W".to_string(), "error".to_string()]) ; "whitespace")]
+    #[test_case("-c 'print(\"hi there\")'" => Some(vec!["-c".to_string(), "print(\"hi there\")".to_string()]) ; "single quotes")]
+    #[test_case(r#"-W "error \"x\" \n""# => Some(vec!["-W".to_string(), r#"error "x" \n"#.to_string()]) ; "double quotes")]
+    #[test_case(r"-X\ dev a\\b" => Some(vec!["-X dev".to_string(), r"a\b".to_string()]) ; "backslashes")]
+    #[test_case("-X a'b'\"c\" ''" => Some(vec!["-X".to_string(), "abc".to_string(), String::new()]) ; "joined quotes")]
+    #[test_case("-c 'print(1)" => None ; "unterminated single quote")]
+    #[test_case("-c \"print(1)" => None ; "unterminated double quote")]
+    fn split_flags_tests(text: &str) -> Option<Vec<String>> {
+        split_flags(text)
+    }
+
+    #[test_case(&["-s"], &[], &["PYTHONPATH"] => "env -u PYTHONPATH /usr/bin/python3 -s\n" ; "unset")]
+    #[test_case(&[], &[("PYTHONUTF8", "1")], &["PYTHONHOME", "PYTHONPATH"] => "env -u PYTHONHOME -u PYTHONPATH PYTHONUTF8=1 /usr/bin/python3\n" ; "unset and set")]
+    fn dry_run_command_unset_tests(
+        args: &[&str],
+        env_vars: &[(&str, &str)],
+        unset_env_vars: &[&str],
+    ) -> String {
+        let args = args
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+        let env_vars = env_vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<(String, String)>>();
+        let unset_env_vars = unset_env_vars
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>();
+        dry_run_command(
+            Path::new("/usr/bin/python3"),
+            None,
+            &args,
+            &env_vars,
+            &unset_env_vars,
+        )
+    }
+
+    #[test_case("python3" => "env -a python3 /usr/bin/python3 -V\n" ; "name")]
+    #[test_case("my py" => "env -a 'my py' /usr/bin/python3 -V\n" ; "quoted")]
+    fn dry_run_command_argv0_tests(argv0: &str) -> String {
+        dry_run_command(
+            Path::new("/usr/bin/python3"),
+            Some(argv0),
+            &["-V".to_string()],
+            &[],
+            &[],
+        )
+    }
+
     #[test]
     fn test_help_message() {
         let launcher_path = "/some/path/to/launcher";
@@ -365,33 +2729,38 @@ mod tests {
 
     #[test]
     fn test_list_executables() {
-        let mut executables: HashMap<ExactVersion, PathBuf> = HashMap::new();
-
+        let columns = [ListColumn::Version, ListColumn::Path];
         assert_eq!(
-            list_executables(&executables),
-            Err(crate::Error::NoExecutableFound(RequestedVersion::Any))
+            list_table(&[], &columns, None, false),
+            Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+                RequestedVersion::Any
+            )))
         );
 
         let python27_path = "/path/to/2/7/python";
-        executables.insert(
-            ExactVersion { major: 2, minor: 7 },
-            PathBuf::from(python27_path),
-        );
         let python36_path = "/path/to/3/6/python";
-        executables.insert(
-            ExactVersion { major: 3, minor: 6 },
-            PathBuf::from(python36_path),
-        );
         let python37_path = "/path/to/3/7/python";
-        executables.insert(
-            ExactVersion { major: 3, minor: 7 },
-            PathBuf::from(python37_path),
+        let executables = preferred_executables(
+            [
+                (ExactVersion::new(2, 7), python27_path),
+                (ExactVersion::new(3, 6), python36_path),
+                (ExactVersion::new(3, 7), python37_path),
+            ]
+            .iter()
+            .map(|(version, path)| ("path".to_string(), *version, PathBuf::from(path)))
+            .collect(),
         );
 
         // Tests try not to make any guarantees about explicit formatting, just
         // that the interpreters are in descending order of version and the
         // interpreter version comes before the path (i.e. in column order).
-        let executables_list = list_executables(&executables).unwrap();
+        let executables_list = list_table(
+            &listed_executables(&[], executables, &HashMap::new()),
+            &columns,
+            None,
+            false,
+        )
+        .unwrap();
         // No critical data is missing.
         assert!(executables_list.contains("2.7"));
         assert!(executables_list.contains(python27_path));
@@ -414,34 +2783,623 @@ mod tests {
     }
 
     #[test]
-    fn test_venv_executable_path() {
-        let venv_root = "/path/to/venv";
+    fn test_list_all_executables() {
+        let columns = [ListColumn::Version, ListColumn::Path, ListColumn::Source];
+
+        let venv_path = "/project/.venv/bin/python";
+        let environments = [(
+            "venv",
+            VenvInfo {
+                root: PathBuf::from("/project/.venv"),
+                executable: PathBuf::from(venv_path),
+                version: Some(ExactVersion::new(3, 11)),
+                activated: false,
+            },
+        )];
+        let path_python37 = "/usr/bin/python3.7";
+        let pyenv_python37 = "/home/user/.pyenv/versions/3.7.17/bin/python3.7";
+        let executables = vec![
+            (
+                "path".to_string(),
+                ExactVersion::new(3, 7),
+                PathBuf::from(path_python37),
+            ),
+            (
+                "pyenv".to_string(),
+                ExactVersion::new(3, 7),
+                PathBuf::from(pyenv_python37),
+            ),
+        ];
+
+        let listed = listed_executables(&environments, executables, &HashMap::new());
+        let executables_list = list_table(&listed, &columns, None, false).unwrap();
+        // Environments come first and executables for the same version from
+        // different sources are all listed.
+        assert!(
+            executables_list.find(venv_path).unwrap()
+                < executables_list.find(path_python37).unwrap()
+        );
+        assert!(
+            executables_list.find(path_python37).unwrap()
+                < executables_list.find(pyenv_python37).unwrap()
+        );
+        // The source comes after the path.
+        for (path, source) in [(venv_path, "venv"), (pyenv_python37, "pyenv")] {
+            let row = executables_list
+                .lines()
+                .find(|line| line.contains(path))
+                .unwrap();
+            assert!(row.trim_end().ends_with(source));
+        }
+
+        // Columns may be left out or reordered.
+        let sources_list = list_table(
+            &listed,
+            &[ListColumn::Source, ListColumn::Version],
+            None,
+            false,
+        )
+        .unwrap();
+        assert!(!sources_list.contains(path_python37));
+        let row = sources_list
+            .lines()
+            .find(|line| line.contains("pyenv"))
+            .unwrap();
+        assert!(row.trim_end().ends_with("3.7"));
+
+        // The executable used by default is marked with why.
+        let marked_list = list_table(&listed, &columns, Some((1, "PY_PYTHON")), false).unwrap();
+        let marked_rows = marked_list
+            .lines()
+            .filter(|line| line.contains("(default: PY_PYTHON)"))
+            .collect::<Vec<&str>>();
+        assert_eq!(marked_rows.len(), 1);
+        assert!(marked_rows[0].contains(path_python37));
+
+        // Color is only used when asked for, regardless of stdout.
+        assert!(!marked_list.contains('\u{1b}'));
+        let colored_list = list_table(&listed, &columns, Some((1, "PY_PYTHON")), true).unwrap();
+        assert!(colored_list.contains('\u{1b}'));
+        assert!(colored_list.contains(path_python37));
+    }
+
+    #[test]
+    fn test_list_delimited() {
+        let columns = [ListColumn::Version, ListColumn::Path, ListColumn::Source];
+        assert_eq!(
+            list_delimited(&[], &columns, false),
+            Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+                RequestedVersion::Any
+            )))
+        );
+
+        let listed = [
+            (
+                None,
+                PathBuf::from("/my project/.venv/bin/python"),
+                "venv".to_string(),
+            ),
+            (
+                Some(ExactVersion::new(3, 12)),
+                PathBuf::from("/usr/bin/python3.12"),
+                "path".to_string(),
+            ),
+        ];
+        assert_eq!(
+            list_delimited(&listed, &columns, false).unwrap(),
+            "\t/my project/.venv/bin/python\tvenv\n3.12\t/usr/bin/python3.12\tpath\n"
+        );
+        assert_eq!(
+            list_delimited(&listed, &columns[..2], true).unwrap(),
+            "\0/my project/.venv/bin/python\x003.12\0/usr/bin/python3.12\0"
+        );
+    }
+
+    #[test]
+    fn test_sort_listed() {
+        let listed = vec![
+            (
+                None,
+                PathBuf::from("/project/.venv/bin/python"),
+                "venv".to_string(),
+            ),
+            (
+                Some(ExactVersion::new(3, 7)),
+                PathBuf::from("/usr/bin/python3.7"),
+                "path".to_string(),
+            ),
+            (
+                Some(ExactVersion::new(3, 12)),
+                PathBuf::from("/home/user/.pyenv/versions/3.12.1/bin/python3.12"),
+                "pyenv".to_string(),
+            ),
+            (
+                Some(ExactVersion::new(3, 12)),
+                PathBuf::from("/usr/bin/python3.12"),
+                "path".to_string(),
+            ),
+        ];
+        let sorted_paths = |column| {
+            let mut sorted = listed.clone();
+            sort_listed(&mut sorted, column);
+            sorted
+                .into_iter()
+                .map(|(_, path, _)| path.display().to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            sorted_paths(ListColumn::Version),
+            [
+                "/home/user/.pyenv/versions/3.12.1/bin/python3.12",
+                "/usr/bin/python3.12",
+                "/usr/bin/python3.7",
+                "/project/.venv/bin/python",
+            ]
+        );
+        assert_eq!(
+            sorted_paths(ListColumn::Path),
+            [
+                "/home/user/.pyenv/versions/3.12.1/bin/python3.12",
+                "/project/.venv/bin/python",
+                "/usr/bin/python3.12",
+                "/usr/bin/python3.7",
+            ]
+        );
+        assert_eq!(
+            sorted_paths(ListColumn::Source),
+            [
+                "/usr/bin/python3.7",
+                "/usr/bin/python3.12",
+                "/home/user/.pyenv/versions/3.12.1/bin/python3.12",
+                "/project/.venv/bin/python",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_implementation() {
+        let mut listed = vec![
+            (
+                None,
+                PathBuf::from("/project/.venv/bin/python"),
+                "venv".to_string(),
+            ),
+            (
+                Some(ExactVersion::new(3, 12)),
+                PathBuf::from("/opt/python/bin/python3.12"),
+                "path".to_string(),
+            ),
+            (
+                Some(ExactVersion {
+                    implementation: Implementation::PyPy,
+                    ..ExactVersion::new(3, 10)
+                }),
+                PathBuf::from("/opt/pypy/bin/pypy3.10"),
+                "path".to_string(),
+            ),
+        ];
+        let cells = |listed: &[ListedExecutable], column| {
+            listed
+                .iter()
+                .map(|executable| list_cell(executable, column))
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            cells(&listed, ListColumn::Implementation),
+            ["", "CPython", "PyPy"]
+        );
+        // Architectures are unknown for files which don't exist.
+        assert_eq!(cells(&listed, ListColumn::Architecture), ["", "", ""]);
+
+        // Unknown implementations are sorted last.
+        sort_listed(&mut listed, ListColumn::Implementation);
+        assert_eq!(
+            cells(&listed, ListColumn::Implementation),
+            ["CPython", "PyPy", ""]
+        );
+    }
+
+    #[test]
+    fn test_list_versions() {
+        let environments = [(
+            "venv",
+            VenvInfo {
+                root: PathBuf::from("/project/.venv"),
+                executable: PathBuf::from("/project/.venv/bin/python"),
+                version: Some(ExactVersion::new(3, 10)),
+                activated: false,
+            },
+        )];
+        let executables = vec![
+            (
+                "path".to_string(),
+                ExactVersion::new(3, 12),
+                PathBuf::from("/usr/bin/python3.12"),
+            ),
+            (
+                "path".to_string(),
+                ExactVersion {
+                    implementation: Implementation::PyPy,
+                    ..ExactVersion::new(3, 10)
+                },
+                PathBuf::from("/usr/bin/pypy3.10"),
+            ),
+            (
+                "pyenv".to_string(),
+                ExactVersion::new(3, 12),
+                PathBuf::from("/home/user/.pyenv/versions/3.12.1/bin/python3.12"),
+            ),
+        ];
+        let mut windows_executables = HashMap::new();
+        windows_executables.insert(
+            ExactVersion::new(3, 11),
+            PathBuf::from("/mnt/c/Python311/python.exe"),
+        );
+
+        let mut listed = listed_executables(&environments, executables, &windows_executables);
+        assert_eq!(list_versions(&listed, &environments), "3.12\npypy3.10\n");
+        // Always from newest to oldest.
+        listed.reverse();
+        assert_eq!(list_versions(&listed, &environments), "3.12\npypy3.10\n");
+        assert_eq!(list_versions(&[], &[]), "");
+    }
+
+    #[test]
+    fn test_list_duplicates() {
+        assert_eq!(
+            list_duplicates(Vec::new()),
+            Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+                RequestedVersion::Any
+            )))
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let python311 = dir.path().join("python3.11");
+        File::create(&python311).unwrap();
+        let linked_python311 = dir.path().join("linked-python3.11");
+        std::os::unix::fs::symlink(&python311, &linked_python311).unwrap();
+        let other_python311 = PathBuf::from("/opt/python/bin/python3.11");
+        let python312 = PathBuf::from("/opt/python/bin/python3.12");
+        let occurrences = vec![
+            ("path".to_string(), ExactVersion::new(3, 11), python311),
+            (
+                "path".to_string(),
+                ExactVersion::new(3, 11),
+                linked_python311,
+            ),
+            ("path".to_string(), ExactVersion::new(3, 12), python312),
+            (
+                "pyenv".to_string(),
+                ExactVersion::new(3, 11),
+                other_python311,
+            ),
+        ];
+        let statuses = list_duplicates(occurrences)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let cells = line.split('│').map(str::trim).collect::<Vec<&str>>();
+                format!("{} {} {}", cells[0], cells[2], cells[3])
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(
+            statuses,
+            [
+                "3.12 path used",
+                "3.11 path used",
+                "3.11 path shadowed (same file)",
+                "3.11 pyenv shadowed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_installed() {
+        assert_eq!(list_installed(&[]), "");
+
+        let installs = [
+            crate::install::Installed {
+                version: ExactVersion::with_micro(3, 12, 7),
+                directory: PathBuf::from("/installs/cpython-3.12.7-x86_64-unknown-linux-gnu"),
+            },
+            crate::install::Installed {
+                version: ExactVersion::with_micro(3, 11, 10),
+                directory: PathBuf::from("/installs/cpython-3.11.10-x86_64-unknown-linux-gnu"),
+            },
+        ];
+        let rows = list_installed(&installs)
+            .lines()
+            .map(|line| {
+                line.split('│')
+                    .map(str::trim)
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(
+            rows,
+            [
+                "3.12.7 /installs/cpython-3.12.7-x86_64-unknown-linux-gnu/bin/python3.12",
+                "3.11.10 /installs/cpython-3.11.10-x86_64-unknown-linux-gnu/bin/python3.11",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_available() {
+        let download = |version| crate::install::Download {
+            version,
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            url: String::new(),
+            checksums_url: None,
+        };
+        assert_eq!(list_available(&[], &[], &[]), "");
+
+        let downloads = [
+            download(ExactVersion::with_micro(3, 12, 7)),
+            download(ExactVersion::with_micro(3, 11, 10)),
+            download(ExactVersion::with_micro(3, 10, 15)),
+        ];
+        let installs = [crate::install::Installed {
+            version: ExactVersion::with_micro(3, 12, 7),
+            directory: PathBuf::from("/installs/cpython-3.12.7-x86_64-unknown-linux-gnu"),
+        }];
+        let found = [ExactVersion::new(3, 11), ExactVersion::new(3, 12)];
+        let rows = list_available(&downloads, &installs, &found)
+            .lines()
+            .map(|line| {
+                line.split('│')
+                    .map(str::trim)
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(rows, ["3.12.7 installed", "3.11.10 found", "3.10.15"]);
+    }
+
+    #[test_case(&["py"] => ColorChoice::Auto ; "no flags")]
+    #[test_case(&["py", "--color", "never", "--list"] => ColorChoice::Never ; "separate value")]
+    #[test_case(&["py", "--pre", "--color=always", "-3"] => ColorChoice::Always ; "inline value")]
+    #[test_case(&["py", "--no-config", "--color=never", "--color=always"] => ColorChoice::Always ; "last choice")]
+    #[test_case(&["py", "script.py", "--color=never"] => ColorChoice::Auto ; "argument for Python")]
+    fn options_color_tests(argv: &[&str]) -> ColorChoice {
+        options(argv).color()
+    }
+
+    #[test_case(&["py", "-3.12"] => Some(RequestedVersion::Exact(3, 12)) ; "version flag")]
+    #[test_case(&["py", "--pre", "-3", "script.py"] => Some(RequestedVersion::MajorOnly(3)) ; "with arguments")]
+    #[test_case(&["py"] => None ; "no version")]
+    #[test_case(&["py", "script.py"] => None ; "script")]
+    #[test_case(&["py", "-3.12", "--which"] => None ; "which")]
+    #[test_case(&["py", "-3.12", "--resolve"] => None ; "resolve")]
+    #[test_case(&["py", "--dry-run", "-3.12"] => None ; "dry run")]
+    #[test_case(&["py", "--list"] => None ; "list")]
+    fn options_requested_version_tests(argv: &[&str]) -> Option<RequestedVersion> {
+        options(argv).requested_version()
+    }
+
+    fn options(argv: &[&str]) -> Options {
+        Options::parse(
+            &argv
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_color_choice_enabled() {
+        let file = tempfile::tempfile().unwrap();
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(&file);
+        assert!(ColorChoice::Always.enabled(fd));
+        assert!(!ColorChoice::Never.enabled(fd));
+        // Not a terminal.
+        assert!(!ColorChoice::Auto.enabled(fd));
+    }
+
+    #[test]
+    fn test_list_tags() {
+        assert_eq!(
+            list_tags(&HashMap::new(), None, None, false),
+            Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+                RequestedVersion::Any
+            )))
+        );
+
+        let mut executables = HashMap::new();
+        executables.insert(
+            ExactVersion::new(3, 11),
+            PathBuf::from("/usr/bin/python3.11"),
+        );
+        executables.insert(
+            ExactVersion::new(3, 12),
+            PathBuf::from("/usr/bin/python3.12"),
+        );
+        executables.insert(
+            ExactVersion {
+                implementation: Implementation::PyPy,
+                ..ExactVersion::new(3, 10)
+            },
+            PathBuf::from("/usr/bin/pypy3.10"),
+        );
+        let default_executable = Path::new("/usr/bin/python3.12");
+        assert_eq!(
+            list_tags(&executables, None, Some(default_executable), false).unwrap(),
+            concat!(
+                " -V:3.12 *        Python 3.12\n",
+                " -V:3.11          Python 3.11\n",
+                " -V:pypy3.10      PyPy 3.10\n",
+            )
+        );
+
+        let venv = VenvInfo {
+            root: PathBuf::from("/project/.venv"),
+            executable: PathBuf::from("/project/.venv/bin/python"),
+            version: None,
+            activated: true,
+        };
+        executables.retain(|version, _| version.implementation == Implementation::CPython);
+        assert_eq!(
+            list_tags(&executables, Some(&venv), Some(&venv.executable), true).unwrap(),
+            concat!(
+                "  *               /project/.venv/bin/python\n",
+                " -V:3.12          /usr/bin/python3.12\n",
+                " -V:3.11          /usr/bin/python3.11\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_list_json() {
+        assert_eq!(list_json(&[], None), "[]\n");
+
+        let environments = [(
+            "venv",
+            VenvInfo {
+                root: PathBuf::from("/project/.venv"),
+                executable: PathBuf::from("/project/.venv/bin/python"),
+                version: None,
+                activated: false,
+            },
+        )];
+        let executables = preferred_executables(vec![
+            (
+                "path".to_string(),
+                ExactVersion::new(3, 7),
+                PathBuf::from("/path/to/bin/python3.7"),
+            ),
+            (
+                "pyenv".to_string(),
+                ExactVersion::new(3, 12),
+                PathBuf::from("/home/user/.pyenv/versions/3.12.1/bin/python3.12"),
+            ),
+            (
+                "pyenv".to_string(),
+                ExactVersion::new(3, 7),
+                PathBuf::from("/home/user/.pyenv/versions/3.7.17/bin/python3.7"),
+            ),
+        ]);
+        let mut windows_executables = HashMap::new();
+        windows_executables.insert(
+            ExactVersion::new(3, 11),
+            PathBuf::from("/mnt/c/Python311/python.exe"),
+        );
+
         assert_eq!(
-            venv_executable_path(venv_root),
-            PathBuf::from("/path/to/venv/bin/python")
+            list_json(
+                &listed_executables(&environments, executables, &windows_executables),
+                Some((0, "venv"))
+            ),
+            [
+                "[",
+                r#"  {"version":null,"implementation":null,"architecture":null,"path":"/project/.venv/bin/python","source":"venv","default":true},"#,
+                r#"  {"version":"3.12","implementation":"CPython","architecture":null,"path":"/home/user/.pyenv/versions/3.12.1/bin/python3.12","source":"pyenv","default":false},"#,
+                r#"  {"version":"3.7","implementation":"CPython","architecture":null,"path":"/path/to/bin/python3.7","source":"path","default":false},"#,
+                r#"  {"version":"3.11","implementation":"CPython","architecture":null,"path":"/mnt/c/Python311/python.exe","source":"wsl","default":false}"#,
+                "]\n",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test_case("/project/.venv/bin/python" => r#"{"path":"/project/.venv/bin/python","version":"3.12.1","source":"venv","venv":"/project/.venv"}"# ; "virtual environment")]
+    #[test_case("/usr/bin/python3.11" => r#"{"path":"/usr/bin/python3.11","version":"3.11","source":"path","venv":null}"# ; "source")]
+    #[test_case("/opt/\"odd\"/python3.10" => r#"{"path":"/opt/\"odd\"/python3.10","version":"3.10","source":null,"venv":null}"# ; "no source")]
+    #[test_case("/usr/local/bin/mypython" => r#"{"path":"/usr/local/bin/mypython","version":null,"source":null,"venv":null}"# ; "unknown version")]
+    fn resolution_value_tests(executable: &str) -> String {
+        let environments = [(
+            "venv",
+            VenvInfo {
+                root: PathBuf::from("/project/.venv"),
+                executable: PathBuf::from("/project/.venv/bin/python"),
+                version: Some(ExactVersion::with_micro(3, 12, 1)),
+                activated: false,
+            },
+        )];
+        let executables = vec![(
+            "path".to_string(),
+            ExactVersion::new(3, 11),
+            PathBuf::from("/usr/bin/python3.11"),
+        )];
+        let listed = listed_executables(&environments, executables, &HashMap::new());
+        resolution_value(Path::new(executable), &environments, &listed).to_string()
+    }
+
+    #[test]
+    fn test_list_windows_executables() {
+        let columns = [ListColumn::Version, ListColumn::Path];
+        let python37_path = "/path/to/3/7/python";
+        let executables = vec![(
+            "path".to_string(),
+            ExactVersion::new(3, 7),
+            PathBuf::from(python37_path),
+        )];
+        let mut windows_executables: HashMap<ExactVersion, PathBuf> = HashMap::new();
+        let windows_python312_path = "/mnt/c/Python312/python.exe";
+        windows_executables.insert(
+            ExactVersion::new(3, 12),
+            PathBuf::from(windows_python312_path),
+        );
+
+        let executables_list = list_table(
+            &listed_executables(&[], executables, &windows_executables),
+            &columns,
+            None,
+            false,
+        )
+        .unwrap();
+        assert!(executables_list.contains("3.12 (Windows)"));
+        assert!(executables_list.contains(windows_python312_path));
+        // Windows-native interpreters come after all others.
+        assert!(
+            executables_list.find(python37_path).unwrap()
+                < executables_list.find("3.12 (Windows)").unwrap()
         );
+
+        assert!(list_table(
+            &listed_executables(&[], Vec::new(), &windows_executables),
+            &columns,
+            None,
+            false
+        )
+        .is_ok());
+    }
+
+    #[test_case(Action::List("3.12 │ /usr/bin/python3.12\n".to_string()) => Some("3.12 │ /usr/bin/python3.12\n".to_string()) ; "list")]
+    #[test_case(Action::Which(PathBuf::from("/usr/bin/python3.12")) => Some("/usr/bin/python3.12\n".to_string()) ; "which")]
+    #[test_case(Action::Help("usage\n".to_string(), PathBuf::from("/usr/bin/python3.12")) => Some("usage\n".to_string()) ; "help")]
+    #[test_case(Action::Resolve("{}\n".to_string()) => Some("{}\n".to_string()) ; "resolve")]
+    #[test_case(Action::SelfUpdate => None ; "nothing to print")]
+    fn write_output_tests(action: Action) -> Option<String> {
+        let mut output = Vec::new();
+        action
+            .write_output(&mut output)
+            .unwrap()
+            .then(|| String::from_utf8(output).unwrap())
     }
 
-    #[test_case("/usr/bin/python" => None ; "missing shebang comment")]
-    #[test_case("# /usr/bin/python" => None ; "missing exclamation point")]
-    #[test_case("! /usr/bin/python" => None ; "missing octothorpe")]
-    #[test_case("#! /bin/sh" => None ; "non-Python shebang")]
-    #[test_case("#! /usr/bin/env python" => Some(RequestedVersion::Any) ; "typical 'env python'")]
-    #[test_case("#! /usr/bin/python" => Some(RequestedVersion::Any) ; "typical 'python'")]
-    #[test_case("#! /usr/local/bin/python" => Some(RequestedVersion::Any) ; "/usr/local")]
-    #[test_case("#! python" => Some(RequestedVersion::Any) ; "bare 'python'")]
-    #[test_case("#! /usr/bin/env python3.7" => Some(RequestedVersion::Exact(3, 7)) ; "typical 'env python' with minor version")]
-    #[test_case("#! /usr/bin/python3.7" => Some(RequestedVersion::Exact(3, 7)) ; "typical 'python' with minor version")]
-    #[test_case("#! python3.7" => Some(RequestedVersion::Exact(3, 7)) ; "bare 'python' with minor version")]
-    #[test_case("#!/usr/bin/python" => Some(RequestedVersion::Any) ; "no space between shebang and path")]
-    fn parse_python_shebang_tests(shebang: &str) -> Option<RequestedVersion> {
-        parse_python_shebang(&mut shebang.as_bytes())
-    }
-
-    #[test_case(&[0x23, 0x21, 0xc0, 0xaf] => None ; "invalid UTF-8")]
-    fn parse_python_sheban_include_invalid_bytes_tests(
-        mut shebang: &[u8],
-    ) -> Option<RequestedVersion> {
-        parse_python_shebang(&mut shebang)
+    #[test_case(MapEnv::new() => VenvDetection::Search ; "unset")]
+    #[test_case(MapEnv::new().with_var("PYLAUNCH_NO_VENV_SEARCH", "1") => VenvDetection::ActivatedOnly ; "set")]
+    fn default_venv_detection_tests(env: MapEnv) -> VenvDetection {
+        default_venv_detection(&env, &Config::default())
+    }
+
+    #[test]
+    fn configuration_report_env() {
+        let env = MapEnv::new()
+            .with_var("PY_PYTHON3", "3.11")
+            .with_var("PY_PYTHON2", "")
+            .with_var("PYLAUNCH_NO_VENV_SEARCH", "1")
+            .with_var("PY_FLAGS", "-X dev");
+        let report =
+            configuration_report(&env, Vec::new(), &[], false, VenvDetection::ActivatedOnly);
+        assert!(report.contains("$PY_PYTHON3"));
+        assert!(!report.contains("$PY_PYTHON2"));
+        assert!(report.contains("$PYLAUNCH_NO_VENV_SEARCH"));
+        assert!(report.contains("-X dev"));
     }
 }
@@ -0,0 +1,1325 @@
+//! Loading of the configuration file
+//!
+//! The [`Config`] struct represents the contents of the user's configuration
+//! file, found at `$XDG_CONFIG_HOME/python-launcher/py.toml` (defaulting to
+//! `~/.config/python-launcher/py.toml`). The file is optional; a missing file
+//! is the same as an empty one.
+//!
+//! The user's configuration file is applied on top of the system-wide
+//! configuration file at [`SYSTEM_CONFIG_PATH`], if it exists. Both can be
+//! replaced by a specific file via `PYLAUNCH_CONFIG`, or all configuration
+//! skipped via `PYLAUNCH_NO_CONFIG` (see [`Config::load`]). The `--config` and
+//! `--no-config` flags do the same for a single run (see [`ConfigFiles`]).
+//!
+//! For compatibility with the Windows launcher, its `py.ini` files are also read
+//! (see [`Config::with_py_ini`]).
+//!
+//! A project may also configure the launcher with a `.py.toml` file or a
+//! `[tool.py]` table in its `pyproject.toml`, the closest of which to the
+//! current directory is applied on top of the user's configuration file (see
+//! [`Config::with_project`]).
+//!
+//! Environment variables take precedence over the configuration file, e.g.
+//! `PY_PYTHON` over `default-version` and `PYLAUNCH_NO_VENV_SEARCH` over
+//! `venv-search`, while `PY_DISABLE_SOURCES` adds to `disable-sources`.
+//!
+//! Which file each setting came from is available via
+//! [`Config::load_with_sources`].
+//!
+//! ```toml
+//! # Used when no version is requested (see `crate::default_version`).
+//! default-version = "3.12,3.11"
+//! # Same as `PYLAUNCH_ALLOW_PRERELEASE`.
+//! allow-prereleases = false
+//! # Same as `PYLAUNCHER_ALLOW_INSTALL`.
+//! allow-install = false
+//! # Same as `--pick`, except a remembered choice is used without asking.
+//! pick = false
+//! # Setting to `false` is the same as `PYLAUNCH_NO_VENV_SEARCH`.
+//! venv-search = true
+//! # Searched after `PATH` and `PY_PYTHON_PATH`.
+//! search-directories = ["~/lab/pythons/bin", "/opt/python3.12/bin"]
+//! # Sources of interpreters to never search (see `crate::SOURCES`).
+//! disable-sources = ["snap"]
+//! # Search every time instead of caching what was found (see `crate::cache`).
+//! disable-cache = false
+//! # How to pass on the arguments in a shebang line ("split" or "single").
+//! shebang-arguments = "split"
+//! # Same as `--clean-env`.
+//! clean-env = false
+//! # How to run the interpreter ("exec" or "spawn"; see `crate::run`).
+//! run-mode = "exec"
+//! # What the interpreter sees as `argv[0]` ("path", "name", or "launcher").
+//! argv0 = "path"
+//! # The columns of `--list` and the column to sort it by.
+//! list-columns = ["version", "path", "source"]
+//! list-sort = "version"
+//!
+//! # Custom commands for shebang lines, e.g. `#!mypython`.
+//! [shebang-commands]
+//! mypython = "3.11"
+//! labpython = "~/lab/pythons/bin/python3.13"
+//!
+//! # Environment variables to set for the interpreter, unless already set.
+//! [environment]
+//! PYTHONWARNINGS = "error"
+//! PYTHONUTF8 = 1
+//! ```
+
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    env_provider::{EnvProvider, ProcessEnv},
+    filesystem::{FileSystem, OsFileSystem},
+    non_empty_env_var,
+    run::{Argv0, RunMode},
+    shebang::{ArgumentSplitting, ShebangCommand},
+    ConfigError, Error, RequestedVersion, Result, VenvDetection, VersionPreferenceList,
+};
+
+/// The contents of a configuration file.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Config {
+    /// The versions of Python to use when no version is requested
+    /// (`default-version`), in the same format as `PY_PYTHON`.
+    pub default_version: Option<VersionPreferenceList>,
+    /// Whether pre-release versions of Python may be selected
+    /// (`allow-prereleases`).
+    pub allow_prereleases: bool,
+    /// Whether a missing version of Python is installed instead of failing
+    /// (`allow-install`; see [`crate::install::allow_install`]).
+    pub allow_install: bool,
+    /// Whether the interpreter is picked interactively when several satisfy
+    /// a request (`pick`; see [`crate::picker`]).
+    pub pick: bool,
+    /// Which virtual environments are used (`venv-search`).
+    ///
+    /// Setting `venv-search` to `false` only considers activated virtual
+    /// environments, i.e. [`VenvDetection::ActivatedOnly`].
+    pub venv_detection: VenvDetection,
+    /// Extra directories to search for interpreters (`search-directories`).
+    ///
+    /// The directories are searched after `PATH` and `PY_PYTHON_PATH`.
+    /// Relative paths are relative to the directory containing the
+    /// configuration file, and a leading `~/` is relative to the home
+    /// directory.
+    pub search_directories: Vec<PathBuf>,
+    /// Names of the [sources](crate::SOURCES) of executables to not search
+    /// (`disable-sources`).
+    pub disabled_sources: Vec<String>,
+    /// Whether the executables found by searching are never
+    /// [cached](crate::cache) (`disable-cache`).
+    pub disable_cache: bool,
+    /// How the arguments to the interpreter in a shebang line are passed on
+    /// (`shebang-arguments`).
+    pub shebang_arguments: ArgumentSplitting,
+    /// Custom commands for shebang lines by name (`shebang-commands`).
+    ///
+    /// Each command either requests a version of Python (e.g. `3.11`) or is
+    /// the path to a specific interpreter, which must be absolute or start
+    /// with `~/`.
+    pub shebang_commands: Vec<(String, ShebangCommand)>,
+    /// Environment variables to set for the interpreter by name
+    /// (`environment`), e.g. `PYTHONWARNINGS`.
+    ///
+    /// Values may be written as strings or integers. Environment variables
+    /// which are already set when launching the interpreter are left as-is.
+    pub environment: Vec<(String, String)>,
+    /// Whether the interpreter runs in a clean environment (`clean-env`),
+    /// i.e. without the `PYTHON*` environment variables which are set when
+    /// launching it and without the user's site-packages directory.
+    pub clean_env: bool,
+    /// How the interpreter is run (`run-mode`).
+    pub run_mode: RunMode,
+    /// What the interpreter sees as `argv[0]` (`argv0`).
+    pub argv0: Argv0,
+    /// The columns of `--list` (`list-columns`), instead of the version and
+    /// path (along with the source for `--list --all`).
+    pub list_columns: Option<Vec<ListColumn>>,
+    /// The column which `--list` is sorted by (`list-sort`), instead of
+    /// listing executables in the order they are searched for.
+    pub list_sort: Option<ListColumn>,
+    /// Defaults for the `PY_PYTHON` environment variables from the
+    /// `[defaults]` section of a `py.ini` file, by the name of the environment
+    /// variable they stand in for (e.g. `PY_PYTHON3` for `python3`).
+    pub env_var_defaults: Vec<(String, String)>,
+}
+
+/// Which configuration files are loaded (see [`Config::load`]).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConfigFiles {
+    /// The system-wide configuration file, any `py.ini` files, and the user's
+    /// configuration file.
+    Default,
+    /// Only the specified file, e.g. via `--config` or `PYLAUNCH_CONFIG`.
+    File(PathBuf),
+    /// No configuration at all, e.g. via `--no-config` or
+    /// `PYLAUNCH_NO_CONFIG`.
+    None,
+}
+
+impl ConfigFiles {
+    /// The files chosen by the `PYLAUNCH_NO_CONFIG` and `PYLAUNCH_CONFIG`
+    /// environment variables, in that order of precedence.
+    pub fn from_env(env: &dyn EnvProvider) -> Self {
+        log::info!("Checking the PYLAUNCH_CONFIG environment variable");
+        if env.var_os("PYLAUNCH_NO_CONFIG").is_some() {
+            Self::None
+        } else {
+            env.non_empty_path("PYLAUNCH_CONFIG")
+                .map_or(Self::Default, Self::File)
+        }
+    }
+
+    /// Returns the path to the configuration file which changes are written
+    /// to, whether it exists or not.
+    ///
+    /// This is the [specified file](ConfigFiles::File), else the user's
+    /// configuration file (see [`user_config_path`]).
+    pub fn writable_path(&self) -> Option<PathBuf> {
+        match self {
+            Self::File(path) => Some(path.clone()),
+            Self::Default | Self::None => user_config_path(),
+        }
+    }
+}
+
+/// Returns the path to the configuration file which changes are written to,
+/// whether it exists or not.
+///
+/// This is the file named by the `PYLAUNCH_CONFIG` environment variable if it
+/// is set, else the user's configuration file (see [`user_config_path`]).
+pub fn writable_config_path() -> Option<PathBuf> {
+    non_empty_env_var("PYLAUNCH_CONFIG").or_else(user_config_path)
+}
+
+/// A setting of a configuration (see [`Config::settings`]) along with the file
+/// it came from, or `None` if it is the default.
+pub type SettingSource = (String, String, Option<PathBuf>);
+
+/// Tracks which file each setting of a configuration came from as files are
+/// applied on top of each other.
+#[derive(Debug, Default)]
+struct Sources(Vec<((String, String), PathBuf)>);
+
+impl Sources {
+    /// Applies a file to the configuration via `apply`, attributing any
+    /// setting which changed to `path`.
+    fn layer(
+        &mut self,
+        config: Config,
+        path: &Path,
+        apply: impl FnOnce(Config) -> Result<Config>,
+    ) -> Result<Config> {
+        let before = config.settings();
+        let config = apply(config)?;
+        for setting in config.settings() {
+            if !before.contains(&setting) {
+                self.0.retain(|(existing, _)| *existing != setting);
+                self.0.push((setting, path.to_path_buf()));
+            }
+        }
+        Ok(config)
+    }
+
+    fn of(&self, config: &Config) -> Vec<SettingSource> {
+        config
+            .settings()
+            .into_iter()
+            .map(|setting| {
+                let path = self
+                    .0
+                    .iter()
+                    .find(|(existing, _)| *existing == setting)
+                    .map(|(_, path)| path.clone());
+                (setting.0, setting.1, path)
+            })
+            .collect()
+    }
+}
+
+fn is_setting(line: &str, key: &str) -> bool {
+    line.trim_start()
+        .strip_prefix(key)
+        .map_or(false, |rest| rest.trim_start().starts_with('='))
+}
+
+/// Returns the contents of a configuration file with its `default-version`
+/// set to `version`, keeping everything else (including comments) as-is.
+///
+/// The version is expected to have already been validated, e.g. via
+/// [`VersionPreferenceList`]'s `FromStr` implementation.
+pub fn set_default_version(contents: &str, version: &str) -> String {
+    let setting = format!("default-version = \"{version}\"");
+    let mut lines = contents.lines().map(String::from).collect::<Vec<String>>();
+    // Top-level settings must come before any table.
+    let first_table = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    match lines[..first_table]
+        .iter()
+        .position(|line| is_setting(line, "default-version"))
+    {
+        Some(index) => lines[index] = setting,
+        None if first_table < lines.len() => {
+            lines.splice(first_table..first_table, [setting, String::new()]);
+        }
+        None => lines.push(setting),
+    }
+    lines.join("\n") + "\n"
+}
+
+/// The path to the system-wide configuration file, which administrators can
+/// use to set defaults for every user.
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/python-launcher/config.toml";
+
+/// Returns the path to the user's configuration file, whether it exists or
+/// not.
+///
+/// `None` is returned if neither `XDG_CONFIG_HOME` nor `HOME` is set.
+pub fn user_config_path() -> Option<PathBuf> {
+    user_config_path_in(&ProcessEnv)
+}
+
+fn user_config_path_in(env: &dyn EnvProvider) -> Option<PathBuf> {
+    env.non_empty_path("XDG_CONFIG_HOME")
+        .or_else(|| env.non_empty_path("HOME").map(|home| home.join(".config")))
+        .map(|config_home| config_home.join("python-launcher").join("py.toml"))
+}
+
+/// Returns the paths to the `py.ini` files of the Windows launcher which are
+/// read, from lowest to highest precedence: next to the launcher's executable,
+/// followed by next to the user's configuration file (see
+/// [`user_config_path`]).
+pub fn py_ini_paths() -> Vec<PathBuf> {
+    py_ini_paths_in(&ProcessEnv)
+}
+
+fn py_ini_paths_in(env: &dyn EnvProvider) -> Vec<PathBuf> {
+    let launcher_dir = env::current_exe()
+        .ok()
+        .and_then(|executable| executable.parent().map(Path::to_path_buf));
+    let config_dir = user_config_path_in(env).and_then(|path| path.parent().map(Path::to_path_buf));
+    launcher_dir
+        .into_iter()
+        .chain(config_dir)
+        .map(|dir| dir.join("py.ini"))
+        .collect()
+}
+
+/// The name of a project's configuration file.
+pub const PROJECT_CONFIG_FILE: &str = ".py.toml";
+
+/// Finds the closest project configuration in `dir` or any of its parents,
+/// returning where it is along with its contents (or why they could not be
+/// read).
+///
+/// Each directory is checked for a [`PROJECT_CONFIG_FILE`], followed by a
+/// `pyproject.toml` with a `[tool.py]` table. A `pyproject.toml` which can't be
+/// read or parsed is skipped as it may not be meant for the launcher.
+pub fn find_project_config(
+    dir: &Path,
+) -> Option<(PathBuf, std::result::Result<toml::Value, String>)> {
+    find_project_config_of(&OsFileSystem, dir)
+}
+
+fn find_project_config_of(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+) -> Option<(PathBuf, std::result::Result<toml::Value, String>)> {
+    dir.ancestors().find_map(|directory| {
+        let config_path = directory.join(PROJECT_CONFIG_FILE);
+        log::info!("Checking {}", config_path.display());
+        match file_system.read_to_string(&config_path) {
+            Ok(contents) => {
+                let document = contents
+                    .parse::<toml::Value>()
+                    .map_err(|error| error.to_string());
+                return Some((config_path, document));
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Some((config_path, Err(error.to_string()))),
+        }
+
+        let pyproject_path = directory.join("pyproject.toml");
+        log::info!("Checking {} for [tool.py]", pyproject_path.display());
+        let pyproject = file_system
+            .read_to_string(&pyproject_path)
+            .ok()?
+            .parse::<toml::Value>()
+            .ok()?;
+        let tool_py = pyproject.get("tool")?.get("py")?.clone();
+        Some((pyproject_path, Ok(tool_py)))
+    })
+}
+
+fn resolve_path(path: &str, config_dir: &Path) -> std::result::Result<PathBuf, String> {
+    match path.strip_prefix("~/") {
+        Some(home_relative) => non_empty_env_var("HOME")
+            .map(|home| home.join(home_relative))
+            .ok_or_else(|| format!("cannot expand '{path}' as HOME is not set")),
+        None => Ok(config_dir.join(path)),
+    }
+}
+
+fn shebang_command(
+    command: &str,
+    config_dir: &Path,
+) -> std::result::Result<ShebangCommand, String> {
+    if command.starts_with('/') || command.starts_with("~/") {
+        resolve_path(command, config_dir).map(ShebangCommand::Executable)
+    } else {
+        command
+            .parse::<RequestedVersion>()
+            .map(ShebangCommand::Version)
+            .map_err(|_| format!("'{command}' is neither a version nor an absolute path"))
+    }
+}
+
+fn boolean(key: &str, value: &toml::Value) -> std::result::Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("`{key}` must be a boolean"))
+}
+
+fn string_array<'a>(
+    key: &str,
+    value: &'a toml::Value,
+) -> std::result::Result<Vec<&'a str>, String> {
+    let error = || format!("`{key}` must be an array of strings");
+    value
+        .as_array()
+        .ok_or_else(error)?
+        .iter()
+        .map(|item| item.as_str().ok_or_else(error))
+        .collect()
+}
+
+/// A column of the table of executables listed by `--list`, which the
+/// executables can also be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ListColumn {
+    /// The version of the executable, e.g. `3.12` (sorted from newest to
+    /// oldest).
+    Version,
+    /// The path to the executable.
+    Path,
+    /// Where the executable was found, e.g. `path` or `pyenv`.
+    Source,
+    /// The implementation of Python, e.g. `CPython` or `PyPy`.
+    Implementation,
+    /// The CPU architecture the executable was built for, e.g. `x86_64` or
+    /// `arm64` (see [`crate::executable_architecture`]).
+    Architecture,
+}
+
+impl ListColumn {
+    /// Returns the column with the specified name, i.e. `version`, `path`,
+    /// `source`, `implementation`, or `architecture`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "version" => Some(Self::Version),
+            "path" => Some(Self::Path),
+            "source" => Some(Self::Source),
+            "implementation" => Some(Self::Implementation),
+            "architecture" => Some(Self::Architecture),
+            _ => None,
+        }
+    }
+
+    /// Parses comma-separated column names, e.g. `version,source`.
+    ///
+    /// # Errors
+    ///
+    /// [`crate::CliError::UnknownListColumn`] is returned for any unknown name.
+    pub fn from_names(names: &str) -> crate::Result<Vec<Self>> {
+        names
+            .split(',')
+            .map(|name| {
+                Self::from_name(name.trim()).ok_or_else(|| {
+                    crate::Error::Cli(crate::CliError::UnknownListColumn(name.to_string()))
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the name of the column (see [`ListColumn::from_name`]).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Version => "version",
+            Self::Path => "path",
+            Self::Source => "source",
+            Self::Implementation => "implementation",
+            Self::Architecture => "architecture",
+        }
+    }
+}
+
+impl Config {
+    fn apply_str(&mut self, contents: &str, config_dir: &Path) -> std::result::Result<(), String> {
+        let document = contents
+            .parse::<toml::Value>()
+            .map_err(|error| error.to_string())?;
+        self.apply(&document, config_dir)
+    }
+
+    /// Applies the settings of a configuration table on top of this
+    /// configuration.
+    ///
+    /// Settings which are a single value replace the current one, while
+    /// search directories and disabled sources are added to. Shebang commands
+    /// replace any current command of the same name.
+    fn apply(
+        &mut self,
+        document: &toml::Value,
+        config_dir: &Path,
+    ) -> std::result::Result<(), String> {
+        let table = document
+            .as_table()
+            .ok_or_else(|| "expected a table".to_string())?;
+
+        for (key, value) in table {
+            match key.as_str() {
+                "default-version" => {
+                    let version = value
+                        .as_str()
+                        .ok_or_else(|| format!("`{key}` must be a string"))?;
+                    self.default_version = Some(
+                        version
+                            .parse::<VersionPreferenceList>()
+                            .map_err(|error| format!("`{key}`: {error}"))?,
+                    );
+                }
+                "allow-prereleases" => self.allow_prereleases = boolean(key, value)?,
+                "allow-install" => self.allow_install = boolean(key, value)?,
+                "pick" => self.pick = boolean(key, value)?,
+                "clean-env" => self.clean_env = boolean(key, value)?,
+                "disable-cache" => self.disable_cache = boolean(key, value)?,
+                "venv-search" => {
+                    self.venv_detection = if boolean(key, value)? {
+                        VenvDetection::Search
+                    } else {
+                        VenvDetection::ActivatedOnly
+                    };
+                }
+                "search-directories" => {
+                    for directory in string_array(key, value)? {
+                        self.search_directories
+                            .push(resolve_path(directory, config_dir)?);
+                    }
+                }
+                "disable-sources" => {
+                    self.disabled_sources
+                        .extend(string_array(key, value)?.into_iter().map(String::from));
+                }
+                "shebang-commands" => {
+                    let error = || format!("`{key}` must be a table of strings");
+                    for (name, command) in value.as_table().ok_or_else(error)? {
+                        let command =
+                            shebang_command(command.as_str().ok_or_else(error)?, config_dir)?;
+                        self.shebang_commands
+                            .retain(|(existing, _)| existing != name);
+                        self.shebang_commands.push((name.to_string(), command));
+                    }
+                }
+                "environment" => {
+                    let error = || format!("`{key}` must be a table of strings or integers");
+                    for (name, value) in value.as_table().ok_or_else(error)? {
+                        if name.is_empty() || name.contains(&['=', '\0'][..]) {
+                            return Err(format!(
+                                "'{name}' is not a valid environment variable name"
+                            ));
+                        }
+                        let value = match value {
+                            toml::Value::String(value) => value.to_string(),
+                            toml::Value::Integer(value) => value.to_string(),
+                            _ => return Err(error()),
+                        };
+                        if value.contains('\0') {
+                            return Err(format!("`{key}.{name}` must not contain a NUL character"));
+                        }
+                        self.environment.retain(|(existing, _)| existing != name);
+                        self.environment.push((name.to_string(), value));
+                    }
+                }
+                "list-columns" => {
+                    let columns = string_array(key, value)?
+                        .into_iter()
+                        .map(|name| {
+                            ListColumn::from_name(name)
+                                .ok_or_else(|| format!("`{key}`: unknown column '{name}'"))
+                        })
+                        .collect::<std::result::Result<Vec<ListColumn>, String>>()?;
+                    self.list_columns = Some(columns);
+                }
+                "list-sort" => {
+                    let name = value
+                        .as_str()
+                        .ok_or_else(|| format!("`{key}` must be a string"))?;
+                    self.list_sort = Some(
+                        ListColumn::from_name(name)
+                            .ok_or_else(|| format!("`{key}`: unknown column '{name}'"))?,
+                    );
+                }
+                "shebang-arguments" => {
+                    self.shebang_arguments = match value.as_str() {
+                        Some("split") => ArgumentSplitting::Split,
+                        Some("single") => ArgumentSplitting::Single,
+                        _ => return Err(format!("`{key}` must be \"split\" or \"single\"")),
+                    };
+                }
+                "run-mode" => {
+                    self.run_mode = value
+                        .as_str()
+                        .and_then(RunMode::from_name)
+                        .ok_or_else(|| format!("`{key}` must be \"exec\" or \"spawn\""))?;
+                }
+                "argv0" => {
+                    self.argv0 = value.as_str().and_then(Argv0::from_name).ok_or_else(|| {
+                        format!("`{key}` must be \"path\", \"name\", or \"launcher\"")
+                    })?;
+                }
+                // Ignored so newer configuration files work with older
+                // versions of the launcher.
+                _ => log::warn!("Unknown configuration key `{key}`"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the configuration file at the specified path.
+    ///
+    /// If the file does not exist then the default configuration is returned.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read, [`ConfigError::ReadFailed`] is returned, and
+    /// if it is not valid, [`ConfigError::InvalidConfig`] is.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        Self::default().with_file(path)
+    }
+
+    /// Applies the configuration file at the specified path on top of this
+    /// configuration, like [`Config::with_project`] does.
+    ///
+    /// If the file does not exist then the configuration is unchanged.
+    ///
+    /// # Errors
+    ///
+    /// See [`Config::load_from`].
+    pub fn with_file(self, path: &Path) -> Result<Self> {
+        self.with_file_of(&OsFileSystem, path)
+    }
+
+    fn with_file_of(mut self, file_system: &dyn FileSystem, path: &Path) -> Result<Self> {
+        log::info!("Loading configuration from {}", path.display());
+        match file_system.read_to_string(path) {
+            Ok(contents) => self
+                .apply_str(&contents, path.parent().unwrap_or_else(|| Path::new("")))
+                .map_err(|message| {
+                    Error::Config(ConfigError::InvalidConfig(path.to_path_buf(), message))
+                })?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                log::info!("{} does not exist", path.display());
+            }
+            Err(error) => {
+                return Err(Error::Config(ConfigError::ReadFailed(
+                    path.to_path_buf(),
+                    error.into(),
+                )))
+            }
+        }
+        Ok(self)
+    }
+
+    /// Applies the `py.ini` file of the Windows launcher at the specified path
+    /// on top of this configuration.
+    ///
+    /// Each `pythonX=` setting of the `[defaults]` section is a default for
+    /// the matching `PY_PYTHONX` environment variable (see
+    /// [`Config::env_var_defaults`]), and each setting of the `[commands]`
+    /// section is a custom shebang command (see [`Config::shebang_commands`]).
+    /// Anything else is ignored, like the Windows launcher does. If the file
+    /// does not exist then the configuration is unchanged.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read, [`ConfigError::ReadFailed`] is returned.
+    pub fn with_py_ini(self, path: &Path) -> Result<Self> {
+        self.with_py_ini_of(&OsFileSystem, path)
+    }
+
+    fn with_py_ini_of(mut self, file_system: &dyn FileSystem, path: &Path) -> Result<Self> {
+        log::info!("Loading py.ini from {}", path.display());
+        match file_system.read_to_string(path) {
+            Ok(contents) => {
+                self.apply_py_ini(&contents, path.parent().unwrap_or_else(|| Path::new("")))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                log::info!("{} does not exist", path.display());
+            }
+            Err(error) => {
+                return Err(Error::Config(ConfigError::ReadFailed(
+                    path.to_path_buf(),
+                    error.into(),
+                )))
+            }
+        }
+        Ok(self)
+    }
+
+    fn apply_py_ini(&mut self, contents: &str, config_dir: &Path) {
+        let mut section = String::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+            {
+                section = name.trim().to_lowercase();
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => {
+                    log::warn!("Ignoring '{line}' in py.ini");
+                    continue;
+                }
+            };
+            match section.as_str() {
+                "defaults" if key.starts_with("python") => {
+                    let env_var = format!("PY_{}", key.to_uppercase());
+                    self.env_var_defaults
+                        .retain(|(existing, _)| *existing != env_var);
+                    self.env_var_defaults.push((env_var, value.to_string()));
+                }
+                "commands" => match shebang_command(value, config_dir) {
+                    Ok(command) => {
+                        self.shebang_commands
+                            .retain(|(existing, _)| *existing != key);
+                        self.shebang_commands.push((key, command));
+                    }
+                    Err(message) => log::warn!("Ignoring the `{key}` command in py.ini: {message}"),
+                },
+                _ => log::debug!("Ignoring `{key}` in the [{section}] section of py.ini"),
+            }
+        }
+    }
+
+    /// Returns every setting as its key and a human-readable value, with a
+    /// separate entry for each search directory, disabled source, shebang
+    /// command (e.g. `shebang-commands.mypython`), and `py.ini` default (by
+    /// the name of its environment variable, e.g. `PY_PYTHON3`).
+    ///
+    /// An unset `default-version` is left out.
+    pub fn settings(&self) -> Vec<(String, String)> {
+        let mut settings = Vec::new();
+        if let Some(version) = &self.default_version {
+            settings.push(("default-version".to_string(), version.to_string()));
+        }
+        settings.push((
+            "allow-prereleases".to_string(),
+            self.allow_prereleases.to_string(),
+        ));
+        settings.push(("allow-install".to_string(), self.allow_install.to_string()));
+        settings.push(("pick".to_string(), self.pick.to_string()));
+        settings.push((
+            "venv-search".to_string(),
+            (self.venv_detection == VenvDetection::Search).to_string(),
+        ));
+        for directory in &self.search_directories {
+            settings.push((
+                "search-directories".to_string(),
+                directory.display().to_string(),
+            ));
+        }
+        for source in &self.disabled_sources {
+            settings.push(("disable-sources".to_string(), source.to_string()));
+        }
+        settings.push(("disable-cache".to_string(), self.disable_cache.to_string()));
+        let shebang_arguments = match self.shebang_arguments {
+            ArgumentSplitting::Split => "split",
+            ArgumentSplitting::Single => "single",
+        };
+        settings.push((
+            "shebang-arguments".to_string(),
+            shebang_arguments.to_string(),
+        ));
+        for (name, command) in &self.shebang_commands {
+            let command = match command {
+                ShebangCommand::Version(version) => version.to_string(),
+                ShebangCommand::Executable(path) => path.display().to_string(),
+            };
+            settings.push((format!("shebang-commands.{name}"), command));
+        }
+        for (name, value) in &self.environment {
+            settings.push((format!("environment.{name}"), value.to_string()));
+        }
+        settings.push(("clean-env".to_string(), self.clean_env.to_string()));
+        settings.push(("run-mode".to_string(), self.run_mode.name().to_string()));
+        settings.push(("argv0".to_string(), self.argv0.name().to_string()));
+        if let Some(columns) = &self.list_columns {
+            let names = columns
+                .iter()
+                .map(|column| column.name())
+                .collect::<Vec<&str>>();
+            settings.push(("list-columns".to_string(), names.join(",")));
+        }
+        if let Some(column) = self.list_sort {
+            settings.push(("list-sort".to_string(), column.name().to_string()));
+        }
+        for (env_var, value) in &self.env_var_defaults {
+            settings.push((env_var.to_string(), value.to_string()));
+        }
+        settings
+    }
+
+    /// Returns the default for an environment variable such as `PY_PYTHON`
+    /// from a `py.ini` file (see [`Config::env_var_defaults`]).
+    pub fn env_var_default(&self, env_var: &str) -> Option<&str> {
+        self.env_var_defaults
+            .iter()
+            .find(|(name, _)| name == env_var)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Applies the closest project configuration to `dir` on top of this
+    /// configuration (see [`find_project_config`]).
+    ///
+    /// Relative paths in the project configuration are relative to the
+    /// directory containing it.
+    ///
+    /// # Errors
+    ///
+    /// If the project configuration cannot be read or is not valid,
+    /// [`ConfigError::InvalidConfig`] is returned.
+    pub fn with_project(self, dir: &Path) -> Result<Self> {
+        match find_project_config(dir) {
+            Some((path, document)) => self.with_project_config(&path, document),
+            None => Ok(self),
+        }
+    }
+
+    fn with_project_config(
+        mut self,
+        path: &Path,
+        document: std::result::Result<toml::Value, String>,
+    ) -> Result<Self> {
+        log::info!("Loading project configuration from {}", path.display());
+        document
+            .and_then(|document| {
+                self.apply(&document, path.parent().unwrap_or_else(|| Path::new("")))
+            })
+            .map_err(|message| {
+                Error::Config(ConfigError::InvalidConfig(path.to_path_buf(), message))
+            })?;
+        Ok(self)
+    }
+
+    /// Loads the system-wide configuration file ([`SYSTEM_CONFIG_PATH`]),
+    /// followed by any `py.ini` files (see [`py_ini_paths`]), the user's
+    /// configuration file (see [`user_config_path`]), and the project
+    /// configuration for the current directory (see [`Config::with_project`]),
+    /// each applied on top of the last.
+    ///
+    /// If the `PYLAUNCH_CONFIG` environment variable is set, the file it names
+    /// is loaded instead of the system-wide configuration file, the `py.ini`
+    /// files, and the user's configuration file. If
+    /// the `PYLAUNCH_NO_CONFIG` environment variable is set, no configuration
+    /// is loaded at all and the default configuration is returned.
+    ///
+    /// # Errors
+    ///
+    /// See [`Config::load_from`] and [`Config::with_project`]. A file named by
+    /// `PYLAUNCH_CONFIG` which does not exist is also an
+    /// [`ConfigError::InvalidConfig`].
+    pub fn load() -> Result<Self> {
+        Self::load_in(&ProcessEnv)
+    }
+
+    /// Loads the configuration like [`Config::load`] for an environment, i.e.
+    /// with its environment variables and current directory.
+    ///
+    /// # Errors
+    ///
+    /// See [`Config::load`].
+    pub fn load_in(env: &dyn EnvProvider) -> Result<Self> {
+        Self::load_with_sources_in(env).map(|(config, _)| config)
+    }
+
+    /// Loads the configuration like [`Config::load`], along with every
+    /// setting (see [`Config::settings`]) and the file it came from.
+    ///
+    /// A setting is attributed to the last file which changed it, so a file
+    /// which sets the value a setting already has does not show up.
+    ///
+    /// # Errors
+    ///
+    /// See [`Config::load`].
+    pub fn load_with_sources() -> Result<(Self, Vec<SettingSource>)> {
+        Self::load_with_sources_in(&ProcessEnv)
+    }
+
+    /// Loads the configuration like [`Config::load_with_sources`] for an
+    /// environment.
+    ///
+    /// # Errors
+    ///
+    /// See [`Config::load`].
+    pub fn load_with_sources_in(env: &dyn EnvProvider) -> Result<(Self, Vec<SettingSource>)> {
+        Self::load_files(env, &ConfigFiles::from_env(env))
+    }
+
+    /// Loads the configuration like [`Config::load_with_sources_in`], except
+    /// that the files to load are specified instead of being chosen by the
+    /// `PYLAUNCH_CONFIG` and `PYLAUNCH_NO_CONFIG` environment variables.
+    ///
+    /// # Errors
+    ///
+    /// See [`Config::load`].
+    pub fn load_files(
+        env: &dyn EnvProvider,
+        files: &ConfigFiles,
+    ) -> Result<(Self, Vec<SettingSource>)> {
+        Self::load_files_of(env, &OsFileSystem, files)
+    }
+
+    /// Loads the configuration like [`Config::load_files`], reading the files
+    /// from a [`FileSystem`] instead of the real file system.
+    ///
+    /// # Errors
+    ///
+    /// See [`Config::load`].
+    pub fn load_files_of(
+        env: &dyn EnvProvider,
+        file_system: &dyn FileSystem,
+        files: &ConfigFiles,
+    ) -> Result<(Self, Vec<SettingSource>)> {
+        let mut sources = Sources::default();
+        let mut config = Self::default();
+        match files {
+            ConfigFiles::None => {
+                log::info!("Skipping all configuration");
+                let settings = sources.of(&config);
+                return Ok((config, settings));
+            }
+            ConfigFiles::File(path) if !file_system.is_file(path) => {
+                return Err(Error::Config(ConfigError::InvalidConfig(
+                    path.clone(),
+                    "file not found".to_string(),
+                )))
+            }
+            ConfigFiles::File(path) => {
+                config = sources.layer(config, path, |config| {
+                    config.with_file_of(file_system, path)
+                })?
+            }
+            ConfigFiles::Default => {
+                let system_path = Path::new(SYSTEM_CONFIG_PATH);
+                config = sources.layer(config, system_path, |config| {
+                    config.with_file_of(file_system, system_path)
+                })?;
+                for path in py_ini_paths_in(env) {
+                    config = sources.layer(config, &path, |config| {
+                        config.with_py_ini_of(file_system, &path)
+                    })?;
+                }
+                if let Some(path) = user_config_path_in(env) {
+                    config = sources.layer(config, &path, |config| {
+                        config.with_file_of(file_system, &path)
+                    })?;
+                }
+            }
+        }
+        if let Some((path, document)) = env
+            .current_dir()
+            .ok()
+            .and_then(|cwd| find_project_config_of(file_system, &cwd))
+        {
+            config = sources.layer(config, &path, |config| {
+                config.with_project_config(&path, document)
+            })?;
+        }
+
+        let settings = sources.of(&config);
+        Ok((config, settings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("" => Ok(Config::default()) ; "empty")]
+    #[test_case("search-directories = []" => Ok(Config::default()) ; "no search directories")]
+    #[test_case("search-directories = ['/a/bin', 'b/bin']" => Ok(Config { search_directories: vec![PathBuf::from("/a/bin"), PathBuf::from("/config/b/bin")], ..Config::default() }) ; "absolute and relative search directories")]
+    #[test_case("disable-sources = ['snap', 'pyenv']" => Ok(Config { disabled_sources: vec!["snap".to_string(), "pyenv".to_string()], ..Config::default() }) ; "disabled sources")]
+    #[test_case("disable-sources = 'snap'" => Err("`disable-sources` must be an array of strings".to_string()) ; "disabled sources not an array")]
+    #[test_case("some-future-key = true" => Ok(Config::default()) ; "unknown keys are ignored")]
+    #[test_case("run-mode = 'spawn'" => Ok(Config { run_mode: RunMode::Spawn, ..Config::default() }) ; "spawn run mode")]
+    #[test_case("run-mode = 'fork'" => Err("`run-mode` must be \"exec\" or \"spawn\"".to_string()) ; "unknown run mode")]
+    #[test_case("argv0 = 'launcher'" => Ok(Config { argv0: Argv0::Launcher, ..Config::default() }) ; "launcher argv0")]
+    #[test_case("argv0 = 'python'" => Err("`argv0` must be \"path\", \"name\", or \"launcher\"".to_string()) ; "unknown argv0")]
+    #[test_case("shebang-arguments = 'single'" => Ok(Config { shebang_arguments: ArgumentSplitting::Single, ..Config::default() }) ; "single shebang argument")]
+    #[test_case("shebang-arguments = 'split'" => Ok(Config::default()) ; "split shebang arguments")]
+    #[test_case("[shebang-commands]\nmypython = '3.11'\nlabpython = '/lab/bin/python3'" => Ok(Config { shebang_commands: vec![("labpython".to_string(), ShebangCommand::Executable(PathBuf::from("/lab/bin/python3"))), ("mypython".to_string(), ShebangCommand::Version(RequestedVersion::Exact(3, 11)))], ..Config::default() }) ; "shebang commands")]
+    #[test_case("[shebang-commands]\nmypython = 'lab/bin/python3'" => Err("'lab/bin/python3' is neither a version nor an absolute path".to_string()) ; "relative shebang command path")]
+    #[test_case("shebang-commands = ['mypython']" => Err("`shebang-commands` must be a table of strings".to_string()) ; "shebang commands not a table")]
+    #[test_case("shebang-arguments = 'quoted'" => Err("`shebang-arguments` must be \"split\" or \"single\"".to_string()) ; "unknown shebang argument splitting")]
+    #[test_case("[environment]\nPYTHONWARNINGS = 'error'\nPYTHONUTF8 = 1" => Ok(Config { environment: vec![("PYTHONUTF8".to_string(), "1".to_string()), ("PYTHONWARNINGS".to_string(), "error".to_string())], ..Config::default() }) ; "environment")]
+    #[test_case("[environment]\nPYTHONUTF8 = true" => Err("`environment` must be a table of strings or integers".to_string()) ; "environment value not a string")]
+    #[test_case("environment = ['PYTHONUTF8=1']" => Err("`environment` must be a table of strings or integers".to_string()) ; "environment not a table")]
+    #[test_case("[environment]\n'A=B' = '1'" => Err("'A=B' is not a valid environment variable name".to_string()) ; "invalid environment variable name")]
+    #[test_case("[environment]\nA = \"a\\u0000b\"" => Err("`environment.A` must not contain a NUL character".to_string()) ; "environment value with NUL")]
+    #[test_case("list-columns = ['source', 'path']\nlist-sort = 'path'" => Ok(Config { list_columns: Some(vec![ListColumn::Source, ListColumn::Path]), list_sort: Some(ListColumn::Path), ..Config::default() }) ; "list columns and sort")]
+    #[test_case("list-columns = ['version', 'size']" => Err("`list-columns`: unknown column 'size'".to_string()) ; "unknown list column")]
+    #[test_case("list-sort = ['path']" => Err("`list-sort` must be a string".to_string()) ; "list sort not a string")]
+    #[test_case("default-version = '3.12,3.11'" => Ok(Config { default_version: Some(VersionPreferenceList { versions: vec![RequestedVersion::Exact(3, 12), RequestedVersion::Exact(3, 11)], ..VersionPreferenceList::default() }), ..Config::default() }) ; "default version")]
+    #[test_case("default-version = 3.12" => Err("`default-version` must be a string".to_string()) ; "default version not a string")]
+    #[test_case("default-version = '3.9.1+'" => Err("`default-version`: '3.9.1+' is not a valid version specifier".to_string()) ; "invalid default version")]
+    #[test_case("allow-prereleases = true" => Ok(Config { allow_prereleases: true, ..Config::default() }) ; "allow prereleases")]
+    #[test_case("allow-prereleases = 'yes'" => Err("`allow-prereleases` must be a boolean".to_string()) ; "allow prereleases not a boolean")]
+    #[test_case("allow-install = true" => Ok(Config { allow_install: true, ..Config::default() }) ; "allow install")]
+    #[test_case("allow-install = 1" => Err("`allow-install` must be a boolean".to_string()) ; "allow install not a boolean")]
+    #[test_case("pick = true" => Ok(Config { pick: true, ..Config::default() }) ; "pick")]
+    #[test_case("pick = 'always'" => Err("`pick` must be a boolean".to_string()) ; "pick not a boolean")]
+    #[test_case("clean-env = true" => Ok(Config { clean_env: true, ..Config::default() }) ; "clean env")]
+    #[test_case("clean-env = 1" => Err("`clean-env` must be a boolean".to_string()) ; "clean env not a boolean")]
+    #[test_case("disable-cache = true" => Ok(Config { disable_cache: true, ..Config::default() }) ; "disable cache")]
+    #[test_case("disable-cache = 'yes'" => Err("`disable-cache` must be a boolean".to_string()) ; "disable cache not a boolean")]
+    #[test_case("venv-search = false" => Ok(Config { venv_detection: VenvDetection::ActivatedOnly, ..Config::default() }) ; "no venv search")]
+    #[test_case("venv-search = true" => Ok(Config::default()) ; "venv search")]
+    #[test_case("search-directories = '/a/bin'" => Err("`search-directories` must be an array of strings".to_string()) ; "search directories not an array")]
+    #[test_case("search-directories = [42]" => Err("`search-directories` must be an array of strings".to_string()) ; "search directory not a string")]
+    #[test_case("search-directories = [" => matches Err(_) ; "invalid TOML")]
+    fn parse_tests(contents: &str) -> std::result::Result<Config, String> {
+        let mut config = Config::default();
+        config
+            .apply_str(contents, Path::new("/config"))
+            .map(|()| config)
+    }
+
+    #[test_case("", "3.11" => "default-version = \"3.11\"\n" ; "empty")]
+    #[test_case("# My settings.\ndisable-sources = ['snap']\n", "3.11" => "# My settings.\ndisable-sources = ['snap']\ndefault-version = \"3.11\"\n" ; "appended")]
+    #[test_case("default-version = '3.10'  \nallow-prereleases = true\n", "3.12,3.11" => "default-version = \"3.12,3.11\"\nallow-prereleases = true\n" ; "replaced")]
+    #[test_case("venv-search = false\n\n[shebang-commands]\ndefault-version = '3.10'\n", "3.11" => "venv-search = false\n\ndefault-version = \"3.11\"\n\n[shebang-commands]\ndefault-version = '3.10'\n" ; "before tables")]
+    fn set_default_version_tests(contents: &str, version: &str) -> String {
+        set_default_version(contents, version)
+    }
+
+    #[test]
+    fn load_from_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            Config::load_from(&dir.path().join("py.toml")),
+            Ok(Config::default())
+        );
+    }
+
+    #[test]
+    fn load_from_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("py.toml");
+        fs::write(&path, "search-directories = 42").unwrap();
+        assert!(matches!(
+            Config::load_from(&path),
+            Err(Error::Config(ConfigError::InvalidConfig(error_path, _))) if error_path == path
+        ));
+    }
+
+    #[test]
+    fn load_from_unreadable_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory exists but can't be read as a file.
+        assert!(matches!(
+            Config::load_from(dir.path()),
+            Err(Error::Config(ConfigError::ReadFailed(error_path, _))) if error_path == dir.path()
+        ));
+    }
+
+    #[test]
+    fn load_files_of_file_system() {
+        let file_system = crate::filesystem::MemoryFileSystem::new()
+            .file_with_contents(SYSTEM_CONFIG_PATH, "disable-sources = ['snap']\n")
+            .file_with_contents(
+                "/home/user/.config/python-launcher/py.toml",
+                "default-version = '3.12'\n",
+            )
+            .file_with_contents(
+                "/project/pyproject.toml",
+                "[tool.py]\nsearch-directories = ['bin']\n",
+            );
+        let env = crate::env_provider::MapEnv::new()
+            .with_var("HOME", "/home/user")
+            .with_current_dir("/project");
+
+        let (config, _) = Config::load_files_of(&env, &file_system, &ConfigFiles::Default).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                default_version: Some(VersionPreferenceList::from(RequestedVersion::Exact(3, 12))),
+                disabled_sources: vec!["snap".to_string()],
+                search_directories: vec![PathBuf::from("/project/bin")],
+                ..Config::default()
+            }
+        );
+        assert!(matches!(
+            Config::load_files_of(
+                &env,
+                &file_system,
+                &ConfigFiles::File(PathBuf::from("/missing.toml"))
+            ),
+            Err(Error::Config(ConfigError::InvalidConfig(_, _)))
+        ));
+    }
+
+    #[test]
+    fn with_file_layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let system_path = dir.path().join("config.toml");
+        fs::write(
+            &system_path,
+            "default-version = '3.10+'\ndisable-sources = ['snap']\n",
+        )
+        .unwrap();
+        let user_path = dir.path().join("py.toml");
+        fs::write(
+            &user_path,
+            "default-version = '3.12'\ndisable-sources = ['nix']\n",
+        )
+        .unwrap();
+
+        let system_config = Config::load_from(&system_path).unwrap();
+        assert_eq!(
+            system_config.default_version,
+            Some(VersionPreferenceList::from(RequestedVersion::AtLeast(
+                3, 10
+            )))
+        );
+        assert_eq!(
+            system_config
+                .clone()
+                .with_file(&dir.path().join("missing.toml")),
+            Ok(system_config.clone())
+        );
+        assert_eq!(
+            system_config.with_file(&user_path),
+            Ok(Config {
+                default_version: Some(VersionPreferenceList::from(RequestedVersion::Exact(3, 12))),
+                disabled_sources: vec!["snap".to_string(), "nix".to_string()],
+                ..Config::default()
+            })
+        );
+    }
+
+    #[test]
+    fn with_py_ini() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("py.ini");
+        assert_eq!(Config::default().with_py_ini(&path), Ok(Config::default()));
+
+        fs::write(
+            &path,
+            "; Shared with the Windows launcher.\n[defaults]\npython=3.11\nPython3 = 3.10\ncolor=blue\n\n[commands]\nmypython=/opt/bin/python3\nbad=relative/python3\n\n[unknown]\npython=2.7\n",
+        )
+        .unwrap();
+        let config = Config::default().with_py_ini(&path).unwrap();
+        assert_eq!(
+            config.env_var_defaults,
+            vec![
+                ("PY_PYTHON".to_string(), "3.11".to_string()),
+                ("PY_PYTHON3".to_string(), "3.10".to_string())
+            ]
+        );
+        assert_eq!(config.env_var_default("PY_PYTHON3"), Some("3.10"));
+        assert_eq!(config.env_var_default("PY_PYTHON2"), None);
+        assert_eq!(
+            config.shebang_commands,
+            vec![(
+                "mypython".to_string(),
+                ShebangCommand::Executable(PathBuf::from("/opt/bin/python3"))
+            )]
+        );
+    }
+
+    #[test]
+    fn settings() {
+        let config = Config {
+            default_version: Some(VersionPreferenceList::from(RequestedVersion::Exact(3, 12))),
+            search_directories: vec![PathBuf::from("/a/bin"), PathBuf::from("/b/bin")],
+            shebang_commands: vec![(
+                "mypython".to_string(),
+                ShebangCommand::Executable(PathBuf::from("/opt/bin/python3")),
+            )],
+            env_var_defaults: vec![("PY_PYTHON3".to_string(), "3.10".to_string())],
+            ..Config::default()
+        };
+        let settings = config
+            .settings()
+            .into_iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            settings,
+            [
+                "default-version=Python 3.12",
+                "allow-prereleases=false",
+                "allow-install=false",
+                "pick=false",
+                "venv-search=true",
+                "search-directories=/a/bin",
+                "search-directories=/b/bin",
+                "disable-cache=false",
+                "shebang-arguments=split",
+                "shebang-commands.mypython=/opt/bin/python3",
+                "clean-env=false",
+                "run-mode=exec",
+                "argv0=path",
+                "PY_PYTHON3=3.10",
+            ]
+        );
+    }
+
+    #[test]
+    fn sources_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let system_path = dir.path().join("config.toml");
+        fs::write(
+            &system_path,
+            "default-version = '3.11'
+disable-sources = ['snap']
+",
+        )
+        .unwrap();
+        let user_path = dir.path().join("py.toml");
+        fs::write(
+            &user_path,
+            "default-version = '3.12'
+allow-prereleases = false
+disable-sources = ['nix']
+",
+        )
+        .unwrap();
+
+        let mut sources = Sources::default();
+        let config = sources
+            .layer(Config::default(), &system_path, |config| {
+                config.with_file(&system_path)
+            })
+            .unwrap();
+        let config = sources
+            .layer(config, &user_path, |config| config.with_file(&user_path))
+            .unwrap();
+        assert_eq!(
+            sources.of(&config),
+            vec![
+                (
+                    "default-version".to_string(),
+                    "Python 3.12".to_string(),
+                    Some(user_path.clone())
+                ),
+                // Setting the default value is not a change.
+                ("allow-prereleases".to_string(), "false".to_string(), None),
+                ("allow-install".to_string(), "false".to_string(), None),
+                ("pick".to_string(), "false".to_string(), None),
+                ("venv-search".to_string(), "true".to_string(), None),
+                (
+                    "disable-sources".to_string(),
+                    "snap".to_string(),
+                    Some(system_path)
+                ),
+                (
+                    "disable-sources".to_string(),
+                    "nix".to_string(),
+                    Some(user_path)
+                ),
+                ("disable-cache".to_string(), "false".to_string(), None),
+                ("shebang-arguments".to_string(), "split".to_string(), None),
+                ("clean-env".to_string(), "false".to_string(), None),
+                ("run-mode".to_string(), "exec".to_string(), None),
+                ("argv0".to_string(), "path".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn with_project_closest() {
+        let project = tempfile::tempdir().unwrap();
+        let subdir = project.path().join("src").join("package");
+        fs::create_dir_all(&subdir).unwrap();
+        let user_config = Config {
+            search_directories: vec![PathBuf::from("/user/bin")],
+            shebang_commands: vec![(
+                "mypython".to_string(),
+                ShebangCommand::Version(RequestedVersion::Exact(3, 11)),
+            )],
+            ..Config::default()
+        };
+        assert_eq!(
+            user_config.clone().with_project(&subdir),
+            Ok(user_config.clone())
+        );
+
+        // A pyproject.toml without a [tool.py] table is skipped.
+        fs::write(subdir.join("pyproject.toml"), "[project]\nname = 'demo'\n").unwrap();
+        fs::write(
+            project.path().join("pyproject.toml"),
+            "[tool.py]\nsearch-directories = ['bin']\n\n[tool.py.shebang-commands]\nmypython = '3.12'\n",
+        )
+        .unwrap();
+        assert_eq!(
+            user_config.clone().with_project(&subdir),
+            Ok(Config {
+                search_directories: vec![PathBuf::from("/user/bin"), project.path().join("bin")],
+                shebang_commands: vec![(
+                    "mypython".to_string(),
+                    ShebangCommand::Version(RequestedVersion::Exact(3, 12)),
+                )],
+                ..Config::default()
+            })
+        );
+
+        // A .py.toml file takes precedence over pyproject.toml.
+        fs::write(
+            project.path().join(PROJECT_CONFIG_FILE),
+            "allow-prereleases = true\n",
+        )
+        .unwrap();
+        assert_eq!(
+            user_config.clone().with_project(&subdir),
+            Ok(Config {
+                allow_prereleases: true,
+                ..user_config.clone()
+            })
+        );
+
+        fs::write(
+            project.path().join(PROJECT_CONFIG_FILE),
+            "venv-search = 42\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            user_config.with_project(&subdir),
+            Err(Error::Config(ConfigError::InvalidConfig(path, _))) if path == project.path().join(PROJECT_CONFIG_FILE)
+        ));
+    }
+
+    #[test_case("version,path" => Ok(vec![ListColumn::Version, ListColumn::Path]) ; "columns")]
+    #[test_case("source, version" => Ok(vec![ListColumn::Source, ListColumn::Version]) ; "whitespace")]
+    #[test_case("implementation,architecture" => Ok(vec![ListColumn::Implementation, ListColumn::Architecture]) ; "implementation and architecture")]
+    #[test_case("version,size" => Err(crate::Error::Cli(crate::CliError::UnknownListColumn("size".to_string()))) ; "unknown column")]
+    fn list_column_from_names_tests(names: &str) -> crate::Result<Vec<ListColumn>> {
+        ListColumn::from_names(names)
+    }
+}
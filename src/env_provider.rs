@@ -0,0 +1,215 @@
+//! Access to the environment of the process when resolving what to run
+//!
+//! Which interpreter would be run depends on environment variables (e.g.
+//! `PATH`, `VIRTUAL_ENV`, and `PYENV_VERSION`) and the current directory.
+//! Functions which take an [`EnvProvider`] (e.g.
+//! [`crate::find_environment_in`] and [`crate::default_version_in`]) read them
+//! through it instead of from the process, so that what would be run for
+//! another environment can be worked out via a [`MapEnv`] without changing
+//! the environment of the process. The functions without the `_in` suffix use
+//! [`ProcessEnv`]. [`WithCurrentDir`] does the same for another directory,
+//! e.g. the one `--chdir` names, without changing the current directory of
+//! the process.
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Environment variables and a current directory.
+pub trait EnvProvider: fmt::Debug + Send + Sync {
+    /// The value of an environment variable, if it is set.
+    fn var_os(&self, name: &str) -> Option<OsString>;
+
+    /// The current directory.
+    fn current_dir(&self) -> io::Result<PathBuf>;
+
+    /// The names of the environment variables which are set, leaving out any
+    /// which aren't valid Unicode.
+    fn var_names(&self) -> Vec<String>;
+
+    /// The value of an environment variable, if it is set to valid Unicode.
+    fn var(&self, name: &str) -> Option<String> {
+        self.var_os(name)?.into_string().ok()
+    }
+
+    /// The value of an environment variable as a path, if it is set and not
+    /// empty.
+    fn non_empty_path(&self, name: &str) -> Option<PathBuf> {
+        self.var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    }
+}
+
+/// The environment of the process, via [`std::env`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ProcessEnv;
+
+impl EnvProvider for ProcessEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        env::var_os(name)
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .collect()
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        env::current_dir()
+    }
+}
+
+/// An environment made up by the caller, which has nothing set unless it is
+/// added.
+///
+/// # Examples
+///
+/// ```
+/// use python_launcher::env_provider::{EnvProvider, MapEnv};
+///
+/// let env = MapEnv::new()
+///     .with_var("PYENV_VERSION", "3.11")
+///     .with_current_dir("/home/user/project");
+///
+/// assert_eq!(env.var("PYENV_VERSION").as_deref(), Some("3.11"));
+/// assert_eq!(env.var("HOME"), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MapEnv {
+    vars: HashMap<String, OsString>,
+    current_dir: Option<PathBuf>,
+}
+
+impl MapEnv {
+    /// Creates an environment with no variables and no current directory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets an environment variable.
+    pub fn with_var(mut self, name: impl Into<String>, value: impl Into<OsString>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets the current directory.
+    pub fn with_current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl EnvProvider for MapEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.vars.get(name).cloned()
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.vars.keys().cloned().collect()
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        self.current_dir
+            .clone()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no current directory is set"))
+    }
+}
+
+/// Another environment with a different current directory.
+///
+/// # Examples
+///
+/// ```
+/// use python_launcher::env_provider::{EnvProvider, ProcessEnv, WithCurrentDir};
+///
+/// let env = WithCurrentDir::new(ProcessEnv, "/home/user/project");
+///
+/// assert_eq!(
+///     env.current_dir().unwrap(),
+///     std::path::Path::new("/home/user/project")
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WithCurrentDir<E> {
+    env: E,
+    current_dir: PathBuf,
+}
+
+impl<E: EnvProvider> WithCurrentDir<E> {
+    /// Wraps `env`, with `dir` as the current directory.
+    pub fn new(env: E, dir: impl AsRef<Path>) -> Self {
+        Self {
+            env,
+            current_dir: dir.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl<E: EnvProvider> EnvProvider for WithCurrentDir<E> {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.env.var_os(name)
+    }
+
+    fn var_names(&self) -> Vec<String> {
+        self.env.var_names()
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        Ok(self.current_dir.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("HOME" => Some(PathBuf::from("/home/user")) ; "set")]
+    #[test_case("XDG_DATA_HOME" => None ; "empty")]
+    #[test_case("PYENV_ROOT" => None ; "unset")]
+    fn non_empty_path_tests(name: &str) -> Option<PathBuf> {
+        MapEnv::new()
+            .with_var("HOME", "/home/user")
+            .with_var("XDG_DATA_HOME", "")
+            .non_empty_path(name)
+    }
+
+    #[test]
+    fn map_env_current_dir() {
+        assert!(MapEnv::new().current_dir().is_err());
+        assert_eq!(
+            MapEnv::new()
+                .with_current_dir("/project")
+                .current_dir()
+                .unwrap(),
+            PathBuf::from("/project")
+        );
+    }
+
+    #[test]
+    fn with_current_dir() {
+        let env = WithCurrentDir::new(MapEnv::new().with_var("HOME", "/home/user"), "/project");
+        assert_eq!(env.current_dir().unwrap(), PathBuf::from("/project"));
+        assert_eq!(env.var("HOME").as_deref(), Some("/home/user"));
+        assert_eq!(env.var_names(), vec!["HOME".to_string()]);
+    }
+
+    #[test]
+    fn process_env() {
+        // Cargo sets it for tests as well as when compiling them.
+        assert_eq!(
+            ProcessEnv.var("CARGO_MANIFEST_DIR").as_deref(),
+            Some(env!("CARGO_MANIFEST_DIR"))
+        );
+        assert!(ProcessEnv
+            .var_names()
+            .contains(&"CARGO_MANIFEST_DIR".to_string()));
+    }
+}
@@ -0,0 +1,662 @@
+//! Errors for each part of the launcher
+//!
+//! Every fallible function of the crate returns an [`Error`], which says which
+//! part of the launcher failed by wrapping the error of that part, e.g. a
+//! [`VersionError`] for parsing a version or a [`SearchError`] for searching
+//! for an interpreter. All of these are non-exhaustive, so matching them must
+//! have a wildcard arm.
+//!
+//! Errors which come from the file system or from running a program keep the
+//! underlying [`io::Error`] as their [source](std::error::Error::source), along
+//! with the path they concern.
+
+use std::{error, fmt, io, num::ParseIntError, path::PathBuf, sync::Arc};
+
+use crate::{RequestedVersion, VersionPreferenceList};
+
+/// An [`io::Error`] which can be cloned and compared (by its kind and message)
+/// like the rest of an [`Error`].
+#[derive(Clone, Debug)]
+pub struct IoError(Arc<io::Error>);
+
+impl IoError {
+    /// An error of [`io::ErrorKind::Other`] with `message`, for failures which
+    /// aren't reported by the operating system (e.g. a program exiting
+    /// unsuccessfully).
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::from(io::Error::new(io::ErrorKind::Other, message.into()))
+    }
+
+    /// The kind of the underlying [`io::Error`].
+    pub fn kind(&self) -> io::ErrorKind {
+        self.0.kind()
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(error: io::Error) -> Self {
+        Self(Arc::new(error))
+    }
+}
+
+impl AsRef<io::Error> for IoError {
+    fn as_ref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for IoError {}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Error enum for the entire crate, with a variant for each part of the
+/// launcher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Parsing a version, a version specifier, or the file name of an
+    /// executable fails.
+    Version(VersionError),
+    /// Searching for an interpreter or a virtual environment fails.
+    Search(SearchError),
+    /// Reading the configuration fails.
+    Config(ConfigError),
+    /// The launcher's command line is invalid.
+    Cli(CliError),
+    /// Installing interpreters or updating the launcher fails.
+    Install(InstallError),
+    /// Running an interpreter fails.
+    Execution(ExecutionError),
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Version(error) => error.fmt(f),
+            Self::Search(error) => error.fmt(f),
+            Self::Config(error) => error.fmt(f),
+            Self::Cli(error) => error.fmt(f),
+            Self::Install(error) => error.fmt(f),
+            Self::Execution(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // The part of the launcher is already known from the variant, so its
+        // error is transparent.
+        match self {
+            Self::Version(error) => error.source(),
+            Self::Search(error) => error.source(),
+            Self::Config(error) => error.source(),
+            Self::Cli(error) => error.source(),
+            Self::Install(error) => error.source(),
+            Self::Execution(error) => error.source(),
+        }
+    }
+}
+
+#[cfg(all(feature = "cli", not(tarpaulin_include)))]
+impl Error {
+    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Self::Version(error) => error.exit_code(),
+            Self::Search(error) => error.exit_code(),
+            Self::Config(error) => error.exit_code(),
+            Self::Cli(error) => error.exit_code(),
+            Self::Install(error) => error.exit_code(),
+            Self::Execution(error) => error.exit_code(),
+        }
+    }
+}
+
+impl From<VersionError> for Error {
+    fn from(error: VersionError) -> Self {
+        Self::Version(error)
+    }
+}
+
+impl From<SearchError> for Error {
+    fn from(error: SearchError) -> Self {
+        Self::Search(error)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(error: ConfigError) -> Self {
+        Self::Config(error)
+    }
+}
+
+impl From<CliError> for Error {
+    fn from(error: CliError) -> Self {
+        Self::Cli(error)
+    }
+}
+
+impl From<InstallError> for Error {
+    fn from(error: InstallError) -> Self {
+        Self::Install(error)
+    }
+}
+
+impl From<ExecutionError> for Error {
+    fn from(error: ExecutionError) -> Self {
+        Self::Execution(error)
+    }
+}
+
+/// Parsing a version, a version specifier, or the file name of an executable
+/// fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VersionError {
+    /// Parsing a digit component from a string fails.
+    ParseVersionComponentError(ParseIntError, String),
+    /// String parsing fails due to `.` missing.
+    DotMissing,
+    /// A [`Path`](std::path::Path) lacks a file name when it is required.
+    FileNameMissing(PathBuf),
+    /// The file name of a path cannot be converted to a string.
+    FileNameToStrError(PathBuf),
+    /// The file name of a path is not structured appropriately.
+    PathFileNameError(PathBuf),
+    /// A version specifier (e.g. `>=3.9,<3.12`) is not structured
+    /// appropriately.
+    InvalidVersionSpecifier(String),
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseVersionComponentError(int_error, bad_value) => {
+                write!(f, "Error parsing '{bad_value}' as an integer: {int_error}")
+            }
+            Self::DotMissing => write!(f, "'.' missing from the version"),
+            Self::FileNameMissing(path) => write!(f, "{} lacks a file name", path.display()),
+            Self::FileNameToStrError(path) => write!(
+                f,
+                "Failed to convert the file name of {} to `str`",
+                path.display()
+            ),
+            Self::PathFileNameError(path) => write!(
+                f,
+                "The file name of {} is not of the format `pythonX.Y`",
+                path.display()
+            ),
+            Self::InvalidVersionSpecifier(specifier) => {
+                write!(f, "'{specifier}' is not a valid version specifier")
+            }
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl error::Error for VersionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::ParseVersionComponentError(int_error, _) => Some(int_error),
+            Self::DotMissing => None,
+            Self::FileNameMissing(_) => None,
+            Self::FileNameToStrError(_) => None,
+            Self::PathFileNameError(_) => None,
+            Self::InvalidVersionSpecifier(_) => None,
+        }
+    }
+}
+
+#[cfg(all(feature = "cli", not(tarpaulin_include)))]
+impl VersionError {
+    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Self::ParseVersionComponentError(_, _) => exitcode::USAGE,
+            Self::DotMissing => exitcode::USAGE,
+            Self::FileNameMissing(_) => exitcode::USAGE,
+            Self::FileNameToStrError(_) => exitcode::SOFTWARE,
+            Self::PathFileNameError(_) => exitcode::SOFTWARE,
+            Self::InvalidVersionSpecifier(_) => exitcode::USAGE,
+        }
+    }
+}
+
+/// Searching for an interpreter or a virtual environment fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SearchError {
+    /// No Python executable could be found based on the constraints provided.
+    NoExecutableFound(RequestedVersion),
+    /// No Python executable could be found for any of the preferred versions.
+    NoPreferredExecutableFound(VersionPreferenceList),
+    /// The activated virtual environment lacks a Python executable.
+    InvalidVirtualEnv(PathBuf),
+    /// No virtual environment could be found.
+    NoVirtualEnvFound,
+    /// A virtual environment lacks the activation script for a shell.
+    NoActivationScript(PathBuf),
+    /// An interpreter has no windowed counterpart (`--windowed`).
+    NoWindowedExecutable(PathBuf),
+    /// The [cache](crate::cache) of the executables found by searching can't
+    /// be changed.
+    CacheFailed(PathBuf, IoError),
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoExecutableFound(requested_version) => {
+                write!(f, "No executable found for {requested_version}")
+            }
+            Self::NoPreferredExecutableFound(preferences) => {
+                write!(f, "No executable found for any of {preferences}")
+            }
+            Self::InvalidVirtualEnv(venv_root) => {
+                let printable_root = venv_root.display();
+                write!(
+                    f,
+                    "The activated virtual environment at {printable_root} lacks a Python executable"
+                )
+            }
+            Self::NoVirtualEnvFound => write!(f, "No virtual environment found"),
+            Self::NoActivationScript(script) => {
+                let printable_script = script.display();
+                write!(f, "The activation script {printable_script} does not exist")
+            }
+            Self::NoWindowedExecutable(executable) => {
+                let printable_executable = executable.display();
+                write!(
+                    f,
+                    "No windowed interpreter found next to {printable_executable}"
+                )
+            }
+            Self::CacheFailed(path, error) => {
+                write!(f, "Can't change the cache at {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl error::Error for SearchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::NoExecutableFound(_) => None,
+            Self::NoPreferredExecutableFound(_) => None,
+            Self::InvalidVirtualEnv(_) => None,
+            Self::NoVirtualEnvFound => None,
+            Self::NoActivationScript(_) => None,
+            Self::NoWindowedExecutable(_) => None,
+            Self::CacheFailed(_, error) => Some(error.as_ref()),
+        }
+    }
+}
+
+#[cfg(all(feature = "cli", not(tarpaulin_include)))]
+impl SearchError {
+    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Self::NoExecutableFound(_) => exitcode::USAGE,
+            Self::NoPreferredExecutableFound(_) => exitcode::USAGE,
+            Self::InvalidVirtualEnv(_) => exitcode::CONFIG,
+            Self::NoVirtualEnvFound => exitcode::USAGE,
+            Self::NoActivationScript(_) => exitcode::CONFIG,
+            Self::NoWindowedExecutable(_) => exitcode::UNAVAILABLE,
+            Self::CacheFailed(_, _) => exitcode::CANTCREAT,
+        }
+    }
+}
+
+/// Reading the configuration fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A configuration file is not structured appropriately.
+    InvalidConfig(PathBuf, String),
+    /// A configuration file exists but can't be read.
+    ReadFailed(PathBuf, IoError),
+    /// The user's configuration file can't be located.
+    NoUserConfig,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig(path, message) => {
+                let printable_path = path.display();
+                write!(f, "Invalid configuration in {printable_path}: {message}")
+            }
+            Self::ReadFailed(path, error) => {
+                let printable_path = path.display();
+                write!(
+                    f,
+                    "Failed to read the configuration in {printable_path}: {error}"
+                )
+            }
+            Self::NoUserConfig => write!(
+                f,
+                "Unable to locate the configuration file as neither XDG_CONFIG_HOME nor HOME is set"
+            ),
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidConfig(_, _) => None,
+            Self::ReadFailed(_, error) => Some(error.as_ref()),
+            Self::NoUserConfig => None,
+        }
+    }
+}
+
+#[cfg(all(feature = "cli", not(tarpaulin_include)))]
+impl ConfigError {
+    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Self::InvalidConfig(_, _) => exitcode::CONFIG,
+            Self::ReadFailed(_, _) => exitcode::CONFIG,
+            Self::NoUserConfig => exitcode::CONFIG,
+        }
+    }
+}
+
+/// The launcher's command line, including `PY_FLAGS`, is invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CliError {
+    /// An illegal combination of CLI flags are provided.
+    IllegalArgument(PathBuf, String),
+    /// A CLI flag is missing the argument it requires.
+    MissingArgument(String),
+    /// A shell is not supported for activating a virtual environment.
+    UnsupportedShell(String),
+    /// A column of `--list` is not known.
+    UnknownListColumn(String),
+    /// An output format of `--list` is not known.
+    UnknownListFormat(String),
+    /// The choice of `--color` is not known.
+    UnknownColorChoice(String),
+    /// Picking an interpreter interactively is cancelled.
+    PickCancelled,
+    /// The working directory can't be changed to a directory (`--chdir`).
+    InvalidWorkingDir(PathBuf, IoError),
+    /// An environment variable for the interpreter isn't `NAME=VALUE`
+    /// (`--env`).
+    InvalidEnvVar(String),
+    /// The arguments in `PY_FLAGS` have an unterminated quote.
+    InvalidPyFlags(String),
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IllegalArgument(launcher_path, flag) => {
+                let printable_path = launcher_path.to_string_lossy();
+                write!(
+                    f,
+                    "The `{flag}` flag must be specified on its own; see `{printable_path} --help` for details"
+                )
+            }
+            Self::MissingArgument(flag) => write!(f, "Missing an argument for `{flag}`"),
+            Self::UnsupportedShell(shell) => {
+                write!(
+                    f,
+                    "Unsupported shell '{shell}'; expected `bash`, `zsh`, `sh`, `fish`, or `pwsh`"
+                )
+            }
+            Self::UnknownListColumn(column) => write!(
+                f,
+                "Unknown column '{column}'; expected `version`, `path`, `source`, `implementation`, or `architecture`"
+            ),
+            Self::UnknownListFormat(format) => write!(
+                f,
+                "Unknown format '{format}'; expected `table`, `json`, `tsv`, `nul`, or `versions`"
+            ),
+            Self::UnknownColorChoice(choice) => write!(
+                f,
+                "Unknown color choice '{choice}'; expected `auto`, `always`, or `never`"
+            ),
+            Self::PickCancelled => write!(f, "No interpreter picked"),
+            Self::InvalidWorkingDir(path, error) => {
+                write!(f, "Can't change the working directory to {}: {error}", path.display())
+            }
+            Self::InvalidEnvVar(assignment) => {
+                write!(f, "Expected `NAME=VALUE` for `--env`, not '{assignment}'")
+            }
+            Self::InvalidPyFlags(flags) => {
+                write!(f, "Unterminated quote in PY_FLAGS ({flags})")
+            }
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl error::Error for CliError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::IllegalArgument(_, _) => None,
+            Self::MissingArgument(_) => None,
+            Self::UnsupportedShell(_) => None,
+            Self::UnknownListColumn(_) => None,
+            Self::UnknownListFormat(_) => None,
+            Self::UnknownColorChoice(_) => None,
+            Self::PickCancelled => None,
+            Self::InvalidWorkingDir(_, error) => Some(error.as_ref()),
+            Self::InvalidEnvVar(_) => None,
+            Self::InvalidPyFlags(_) => None,
+        }
+    }
+}
+
+#[cfg(all(feature = "cli", not(tarpaulin_include)))]
+impl CliError {
+    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Self::IllegalArgument(_, _) => exitcode::USAGE,
+            Self::MissingArgument(_) => exitcode::USAGE,
+            Self::UnsupportedShell(_) => exitcode::USAGE,
+            Self::UnknownListColumn(_) => exitcode::USAGE,
+            Self::UnknownListFormat(_) => exitcode::USAGE,
+            Self::UnknownColorChoice(_) => exitcode::USAGE,
+            Self::PickCancelled => exitcode::USAGE,
+            Self::InvalidWorkingDir(_, _) => exitcode::NOINPUT,
+            Self::InvalidEnvVar(_) => exitcode::USAGE,
+            Self::InvalidPyFlags(_) => exitcode::USAGE,
+        }
+    }
+}
+
+/// Installing interpreters or updating the launcher fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InstallError {
+    /// There are no builds of Python to install for the platform.
+    UnsupportedPlatform(String),
+    /// The directory to install interpreters into can't be located.
+    NoInstallsDir,
+    /// No build of Python to install satisfies the requested version.
+    NoDownloadFound(RequestedVersion),
+    /// Downloading from a URL fails.
+    DownloadFailed(String, IoError),
+    /// Installing an interpreter into a directory fails.
+    InstallFailed(PathBuf, IoError),
+    /// No checksum is published for a download.
+    NoChecksum(String),
+    /// The checksum of a download differs from the one published for it.
+    ChecksumMismatch(String, String, String),
+    /// No interpreter installed by the launcher satisfies the requested
+    /// version.
+    NotInstalled(RequestedVersion),
+    /// The latest release of the launcher has no build for the platform.
+    NoUpdateFound(String),
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedPlatform(platform) => {
+                write!(f, "No builds of Python are available to install on {platform}")
+            }
+            Self::NoInstallsDir => write!(
+                f,
+                "Unable to locate the installs directory as neither PYLAUNCH_INSTALL_DIR, XDG_DATA_HOME, nor HOME is set"
+            ),
+            Self::NoDownloadFound(requested_version) => {
+                write!(f, "No build found to install for {requested_version}")
+            }
+            Self::DownloadFailed(url, error) => {
+                write!(f, "Failed to download {url}: {error}")
+            }
+            Self::InstallFailed(path, error) => {
+                let printable_path = path.display();
+                write!(f, "Failed to install into {printable_path}: {error}")
+            }
+            Self::NoChecksum(url) => {
+                write!(f, "No SHA256 checksum is published for {url}")
+            }
+            Self::ChecksumMismatch(url, expected, actual) => write!(
+                f,
+                "The SHA256 checksum of {url} is {actual} instead of the expected {expected}"
+            ),
+            Self::NotInstalled(requested_version) => {
+                write!(f, "No install by the launcher found for {requested_version}")
+            }
+            Self::NoUpdateFound(target) => {
+                write!(f, "No build of the latest launcher release found for {target}")
+            }
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl error::Error for InstallError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::UnsupportedPlatform(_) => None,
+            Self::NoInstallsDir => None,
+            Self::NoDownloadFound(_) => None,
+            Self::DownloadFailed(_, error) => Some(error.as_ref()),
+            Self::InstallFailed(_, error) => Some(error.as_ref()),
+            Self::NoChecksum(_) => None,
+            Self::ChecksumMismatch(_, _, _) => None,
+            Self::NotInstalled(_) => None,
+            Self::NoUpdateFound(_) => None,
+        }
+    }
+}
+
+#[cfg(all(feature = "cli", not(tarpaulin_include)))]
+impl InstallError {
+    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Self::UnsupportedPlatform(_) => exitcode::UNAVAILABLE,
+            Self::NoInstallsDir => exitcode::CONFIG,
+            Self::NoDownloadFound(_) => exitcode::USAGE,
+            Self::DownloadFailed(_, _) => exitcode::UNAVAILABLE,
+            Self::InstallFailed(_, _) => exitcode::CANTCREAT,
+            Self::NoChecksum(_) => exitcode::UNAVAILABLE,
+            Self::ChecksumMismatch(_, _, _) => exitcode::DATAERR,
+            Self::NotInstalled(_) => exitcode::USAGE,
+            Self::NoUpdateFound(_) => exitcode::UNAVAILABLE,
+        }
+    }
+}
+
+/// Running an interpreter fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExecutionError {
+    /// Running an interpreter to learn about it fails.
+    IntrospectionFailed(PathBuf, IoError),
+}
+
+#[cfg(not(tarpaulin_include))]
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IntrospectionFailed(path, error) => {
+                write!(f, "Failed to introspect {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl error::Error for ExecutionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::IntrospectionFailed(_, error) => Some(error.as_ref()),
+        }
+    }
+}
+
+#[cfg(all(feature = "cli", not(tarpaulin_include)))]
+impl ExecutionError {
+    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        match self {
+            Self::IntrospectionFailed(_, _) => exitcode::UNAVAILABLE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test]
+    fn io_error_source() {
+        let error = Error::from(CliError::InvalidWorkingDir(
+            PathBuf::from("/nowhere"),
+            io::Error::from(io::ErrorKind::NotFound).into(),
+        ));
+        let source = error
+            .source()
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test_case(IoError::other("a"), IoError::other("a") => true ; "same")]
+    #[test_case(IoError::other("a"), IoError::other("b") => false ; "different message")]
+    #[test_case(IoError::other("a"), io::Error::new(io::ErrorKind::NotFound, "a").into() => false ; "different kind")]
+    fn io_error_eq(left: IoError, right: IoError) -> bool {
+        left == right
+    }
+
+    #[test]
+    fn transparent_display() {
+        let error = Error::from(VersionError::DotMissing);
+        assert_eq!(error.to_string(), VersionError::DotMissing.to_string());
+        assert!(error.source().is_none());
+    }
+}
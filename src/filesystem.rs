@@ -0,0 +1,393 @@
+//! Access to the file system when searching for interpreters
+//!
+//! Searching directories for interpreters (see
+//! [`crate::executables_in_directories_of`]), detecting virtual environments,
+//! and reading version and configuration files go through the [`FileSystem`]
+//! trait rather than [`std::fs`], so that they can be pointed at something
+//! other than the real file system. [`OsFileSystem`] is the real one, while
+//! [`MemoryFileSystem`] holds a tree in memory, which makes it possible to
+//! test how e.g. symlinks and unreadable directories are handled without
+//! creating temporary directories.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The most symlinks which are followed to reach a path in a
+/// [`MemoryFileSystem`], like the limit of Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// What is known about a path, after following any symlinks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    /// Whether the path is a file.
+    pub is_file: bool,
+    /// Whether the path is a directory.
+    pub is_dir: bool,
+    /// When the path was last modified, if the file system keeps track.
+    pub modified: Option<SystemTime>,
+}
+
+/// The operations on a file system needed to search for interpreters.
+pub trait FileSystem: fmt::Debug + Send + Sync {
+    /// The paths of the entries of a directory, in no particular order.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// The [`Metadata`] of a path, following symlinks.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// The contents of a file, following symlinks.
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    /// The absolute path of a path with all symlinks followed.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Whether a path is a file (following symlinks), i.e. not a directory, a
+    /// dangling symlink, or missing.
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path)
+            .map_or(false, |metadata| metadata.is_file)
+    }
+
+    /// Whether a path is a directory (following symlinks).
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path)
+            .map_or(false, |metadata| metadata.is_dir)
+    }
+}
+
+/// The real file system, via [`std::fs`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        // Entries which can't be read are skipped like `ReadDir` would.
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(Metadata {
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+}
+
+/// An entry of a [`MemoryFileSystem`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Entry {
+    /// A file with its contents.
+    File(String),
+    Dir,
+    /// A symlink to a path, which is relative to the symlink's directory
+    /// unless it's absolute.
+    Symlink(PathBuf),
+    /// A directory which can't be read.
+    Unreadable,
+}
+
+/// A file system held in memory.
+///
+/// Paths are expected to be absolute; the directories containing each one
+/// are created along with it.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+///
+/// use python_launcher::filesystem::{FileSystem, MemoryFileSystem};
+///
+/// let file_system = MemoryFileSystem::new()
+///     .file("/usr/bin/python3.12")
+///     .symlink("/usr/bin/python3", "python3.12");
+///
+/// assert!(file_system.is_file(Path::new("/usr/bin/python3")));
+/// assert_eq!(file_system.read_dir(Path::new("/usr/bin")).unwrap().len(), 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemoryFileSystem {
+    entries: BTreeMap<PathBuf, Entry>,
+}
+
+impl MemoryFileSystem {
+    /// Creates an empty file system.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an empty file.
+    pub fn file(self, path: impl Into<PathBuf>) -> Self {
+        self.file_with_contents(path, "")
+    }
+
+    /// Adds a file with the specified contents.
+    pub fn file_with_contents(self, path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        self.with_entry(path.into(), Entry::File(contents.into()))
+    }
+
+    /// Adds a directory.
+    pub fn dir(self, path: impl Into<PathBuf>) -> Self {
+        self.with_entry(path.into(), Entry::Dir)
+    }
+
+    /// Adds a symlink to `target`, which is relative to the symlink's
+    /// directory unless it's absolute. The target need not exist.
+    ///
+    /// Symlinks are followed anywhere in a path, including its directories.
+    pub fn symlink(self, path: impl Into<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        self.with_entry(path.into(), Entry::Symlink(target.into()))
+    }
+
+    /// Adds a directory which can't be read, e.g. for a lack of permission.
+    pub fn unreadable_dir(self, path: impl Into<PathBuf>) -> Self {
+        self.with_entry(path.into(), Entry::Unreadable)
+    }
+
+    fn with_entry(mut self, path: PathBuf, entry: Entry) -> Self {
+        for ancestor in path.ancestors().skip(1) {
+            self.entries
+                .entry(ancestor.to_path_buf())
+                .or_insert(Entry::Dir);
+        }
+        self.entries.insert(path, entry);
+        self
+    }
+
+    /// Follows the symlinks anywhere in `path` to the path of the entry it
+    /// refers to.
+    fn resolve(&self, path: &Path) -> io::Result<(PathBuf, &Entry)> {
+        let error = |kind, problem| io::Error::new(kind, format!("{} {problem}", path.display()));
+        let mut remaining = path
+            .components()
+            .map(|component| PathBuf::from(component.as_os_str()))
+            .collect::<VecDeque<PathBuf>>();
+        let mut resolved = PathBuf::new();
+        let mut entry = &Entry::Dir;
+        let mut symlinks = 0;
+        while let Some(component) = remaining.pop_front() {
+            match component.components().next() {
+                Some(Component::RootDir) => {
+                    resolved = component;
+                    entry = &Entry::Dir;
+                    continue;
+                }
+                Some(Component::ParentDir) => {
+                    resolved.pop();
+                    entry = &Entry::Dir;
+                    continue;
+                }
+                Some(Component::CurDir) => continue,
+                _ => (),
+            }
+            if let Entry::File(_) = entry {
+                return Err(error(io::ErrorKind::Other, "is not in a directory"));
+            }
+            let child = resolved.join(&component);
+            match self.entries.get(&child) {
+                Some(Entry::Symlink(target)) => {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(error(
+                            io::ErrorKind::Other,
+                            "has too many levels of symlinks",
+                        ));
+                    }
+                    for target_component in target.components().rev() {
+                        remaining.push_front(PathBuf::from(target_component.as_os_str()));
+                    }
+                }
+                Some(child_entry) => {
+                    resolved = child;
+                    entry = child_entry;
+                }
+                None => return Err(error(io::ErrorKind::NotFound, "does not exist")),
+            }
+        }
+        Ok((resolved, entry))
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let (resolved, entry) = self.resolve(dir)?;
+        match entry {
+            Entry::Dir => Ok(self
+                .entries
+                .keys()
+                .filter(|path| path.parent() == Some(&resolved))
+                .map(|path| dir.join(path.file_name().unwrap_or_default()))
+                .collect()),
+            Entry::Unreadable => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} can't be read", dir.display()),
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} is not a directory", dir.display()),
+            )),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let (_, entry) = self.resolve(path)?;
+        Ok(Metadata {
+            is_file: matches!(entry, Entry::File(_)),
+            is_dir: matches!(entry, Entry::Dir | Entry::Unreadable),
+            modified: None,
+        })
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        match self.resolve(path)? {
+            (_, Entry::File(contents)) => Ok(contents.clone()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} is not a file", path.display()),
+            )),
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.resolve(path).map(|(resolved, _)| resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn file_system() -> MemoryFileSystem {
+        MemoryFileSystem::new()
+            .file("/usr/bin/python3.12")
+            .file_with_contents("/etc/python.conf", "[python]\n")
+            .symlink("/etc/python.toml", "python.conf")
+            .symlink("/usr/bin/python3", "python3.12")
+            .symlink("/usr/local/bin/python3.12", "/usr/bin/python3.12")
+            .symlink("/usr/bin/python3.11", "/opt/missing/python3.11")
+            .symlink("/loop/a", "b")
+            .symlink("/loop/b", "a")
+            .symlink("/linked", "/usr")
+            .unreadable_dir("/root")
+    }
+
+    #[test_case("/usr/bin/python3.12" => Some(Metadata { is_file: true, is_dir: false, modified: None }) ; "file")]
+    #[test_case("/usr/bin" => Some(Metadata { is_file: false, is_dir: true, modified: None }) ; "parent directory")]
+    #[test_case("/usr/bin/python3" => Some(Metadata { is_file: true, is_dir: false, modified: None }) ; "relative symlink")]
+    #[test_case("/usr/local/bin/python3.12" => Some(Metadata { is_file: true, is_dir: false, modified: None }) ; "absolute symlink")]
+    #[test_case("/linked/bin/python3.12" => Some(Metadata { is_file: true, is_dir: false, modified: None }) ; "symlinked directory")]
+    #[test_case("/usr/bin/python3.11" => None ; "dangling symlink")]
+    #[test_case("/loop/a" => None ; "symlink loop")]
+    #[test_case("/root" => Some(Metadata { is_file: false, is_dir: true, modified: None }) ; "unreadable directory")]
+    #[test_case("/nowhere" => None ; "missing")]
+    fn memory_metadata_tests(path: &str) -> Option<Metadata> {
+        file_system().metadata(Path::new(path)).ok()
+    }
+
+    #[test]
+    fn memory_read_dir() {
+        let file_system = file_system();
+        let mut entries = file_system.read_dir(Path::new("/usr/bin")).unwrap();
+        entries.sort();
+        assert_eq!(
+            entries,
+            [
+                "/usr/bin/python3",
+                "/usr/bin/python3.11",
+                "/usr/bin/python3.12"
+            ]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<PathBuf>>()
+        );
+
+        // Entries are named after the directory which was read.
+        let mut entries = file_system.read_dir(Path::new("/linked")).unwrap();
+        entries.sort();
+        assert_eq!(
+            entries,
+            [PathBuf::from("/linked/bin"), PathBuf::from("/linked/local")]
+        );
+
+        assert_eq!(
+            file_system.read_dir(Path::new("/root")).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            file_system
+                .read_dir(Path::new("/nowhere"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(file_system
+            .read_dir(Path::new("/usr/bin/python3.12"))
+            .is_err());
+    }
+
+    #[test]
+    fn memory_read_to_string() {
+        let file_system = file_system();
+        assert_eq!(
+            file_system
+                .read_to_string(Path::new("/etc/python.toml"))
+                .unwrap(),
+            "[python]\n"
+        );
+        assert_eq!(
+            file_system
+                .read_to_string(Path::new("/usr/bin/python3.12"))
+                .unwrap(),
+            ""
+        );
+        assert!(file_system.read_to_string(Path::new("/usr/bin")).is_err());
+        assert!(file_system
+            .read_to_string(Path::new("/usr/bin/python3.11"))
+            .is_err());
+    }
+
+    #[test_case("/linked/bin/python3" => Some(PathBuf::from("/usr/bin/python3.12")) ; "symlinks")]
+    #[test_case("/usr/bin/../local/bin" => Some(PathBuf::from("/usr/local/bin")) ; "parent directory")]
+    #[test_case("/loop/a" => None ; "symlink loop")]
+    fn memory_canonicalize_tests(path: &str) -> Option<PathBuf> {
+        file_system().canonicalize(Path::new(path)).ok()
+    }
+
+    #[test]
+    fn os_file_system() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = dir.path().join("python3.12");
+        fs::write(&python, "").unwrap();
+
+        assert_eq!(OsFileSystem.read_dir(dir.path()).unwrap(), [python.clone()]);
+        assert!(OsFileSystem.is_file(&python));
+        assert!(!OsFileSystem.is_file(dir.path()));
+        assert!(OsFileSystem.metadata(dir.path()).unwrap().is_dir);
+        assert!(OsFileSystem
+            .metadata(dir.path())
+            .unwrap()
+            .modified
+            .is_some());
+        assert!(OsFileSystem.is_dir(dir.path()));
+        assert_eq!(OsFileSystem.read_to_string(&python).unwrap(), "");
+    }
+}
@@ -0,0 +1,684 @@
+//! Searching for interpreters with a builder
+//!
+//! A [`Finder`] combines the choices which the free functions at the top of
+//! the crate each make in a fixed way: which [sources](InterpreterSource) and
+//! directories to search, which versions are acceptable, whether a virtual
+//! environment is used, how executables for the same version are
+//! [deduplicated](Dedup), and how much the executables are trusted to be what
+//! their names say (see [`Validation`]), e.g.:
+//!
+//! ```no_run
+//! use python_launcher::finder::Finder;
+//!
+//! let interpreters = Finder::new().version("3.11").skip_venv().find_all()?;
+//! for (source, version, path) in interpreters {
+//!     println!("{version} from {source}: {}", path.display());
+//! }
+//! # Ok::<(), python_launcher::Error>(())
+//! ```
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    env_provider::{EnvProvider, ProcessEnv},
+    executable_occurrences, executables_by_source,
+    filesystem::{FileSystem, OsFileSystem},
+    interpreter::Interpreter,
+    is_prerelease, load_config_of, preferred_specifiers, requires_python_of, DirectorySource,
+    Error, ExactVersion, InterpreterSource, RequestedVersion, Result, Search, VenvDetection,
+    VersionPreferenceList,
+};
+
+/// Which of the executables for the same version are kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dedup {
+    /// Only the executable from the earliest source, as
+    /// [`crate::all_executables_from`] finds.
+    Version,
+    /// The executable each source prefers, as
+    /// [`crate::all_executables_by_source_from`] finds.
+    Source,
+    /// Every executable, even those shadowed by one in an earlier directory of
+    /// the same source, as [`crate::all_executable_occurrences_from`] finds.
+    Occurrence,
+}
+
+impl Default for Dedup {
+    fn default() -> Self {
+        Self::Version
+    }
+}
+
+/// How much an executable is checked before it is considered found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Validation {
+    /// Trust the version in the file name and never run anything, treating
+    /// every version as a final release.
+    FileName,
+    /// Run CPython versions too new to be known to have had a final release
+    /// to check whether they are a pre-release, like [`crate::find_executable`].
+    Prerelease,
+    /// Also run every executable to check that it works and is the version
+    /// its file name says.
+    Execute,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Self::Prerelease
+    }
+}
+
+/// A builder for searching for interpreters (see the [module](self)
+/// documentation).
+///
+/// By default, the [built-in sources](crate::default_sources) are searched for what
+/// the launcher would run when no version is requested: the
+/// [environment](crate::find_environment) comes first, followed by the
+/// version `PY_PYTHON` or the [default version](crate::default_version)
+/// names, all according to the environment of the process and the real file
+/// system.
+#[derive(Default)]
+pub struct Finder {
+    env: Option<Box<dyn EnvProvider>>,
+    file_system: Option<Arc<dyn FileSystem>>,
+    sources: Option<Vec<Box<dyn InterpreterSource>>>,
+    extra_sources: Vec<ExtraSource>,
+    preferences: Option<VersionPreferenceList>,
+    prereleases: bool,
+    venv_detection: VenvDetection,
+    dedup: Dedup,
+    validation: Validation,
+    error: Option<Error>,
+}
+
+/// A source searched after the others, whose directories (if any) are read
+/// from the file system the [`Finder`] ends up with.
+enum ExtraSource {
+    Source(Box<dyn InterpreterSource>),
+    Directories(Vec<PathBuf>),
+}
+
+impl Finder {
+    /// Creates a finder with the defaults described for [`Finder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests a version in the format of a [`VersionPreferenceList`], e.g.
+    /// `3.11` or `3.12,3.11,!3.11.2`.
+    ///
+    /// An invalid version is reported when searching.
+    pub fn version(mut self, version: &str) -> Self {
+        match VersionPreferenceList::from_str(version) {
+            Ok(preferences) => self.preferences = Some(preferences),
+            Err(error) => self.error = Some(error),
+        }
+        self
+    }
+
+    /// Requests the versions of `preferences`.
+    pub fn preferences(mut self, preferences: VersionPreferenceList) -> Self {
+        self.preferences = Some(preferences);
+        self
+    }
+
+    /// Allows pre-release versions of Python (see
+    /// [`VersionPreferenceList::prereleases`]).
+    pub fn prereleases(mut self, prereleases: bool) -> Self {
+        self.prereleases = prereleases;
+        self
+    }
+
+    /// Searches `sources` in order instead of the built-in sources.
+    pub fn sources(mut self, sources: Vec<Box<dyn InterpreterSource>>) -> Self {
+        self.sources = Some(sources);
+        self.extra_sources.clear();
+        self
+    }
+
+    /// Searches `source` after the sources chosen so far.
+    pub fn source(mut self, source: Box<dyn InterpreterSource>) -> Self {
+        self.extra_sources.push(ExtraSource::Source(source));
+        self
+    }
+
+    /// Searches `directories` in order after the sources chosen so far, as a
+    /// [`DirectorySource`] named `directories`.
+    pub fn directories(
+        mut self,
+        directories: impl IntoIterator<Item = impl Into<PathBuf>>,
+    ) -> Self {
+        let directories = directories.into_iter().map(Into::into).collect();
+        self.extra_sources
+            .push(ExtraSource::Directories(directories));
+        self
+    }
+
+    /// Chooses which virtual environments are considered when no version is
+    /// requested.
+    pub fn venv_detection(mut self, venv_detection: VenvDetection) -> Self {
+        self.venv_detection = venv_detection;
+        self
+    }
+
+    /// Ignores all virtual environments (see [`VenvDetection::Disabled`]).
+    pub fn skip_venv(self) -> Self {
+        self.venv_detection(VenvDetection::Disabled)
+    }
+
+    /// Reads environment variables and the current directory from `env`
+    /// instead of the process (see [`crate::env_provider`]), including when
+    /// finding the built-in sources.
+    pub fn env(mut self, env: impl EnvProvider + 'static) -> Self {
+        self.env = Some(Box::new(env));
+        self
+    }
+
+    /// Reads directories, virtual environments, and version and configuration
+    /// files from `file_system` instead of the real file system (see
+    /// [`crate::filesystem`]), including when finding the built-in sources and
+    /// for [`Finder::directories`].
+    ///
+    /// Sources added via [`Finder::source`] or [`Finder::sources`] read from
+    /// wherever they were created to.
+    pub fn file_system(mut self, file_system: impl FileSystem + 'static) -> Self {
+        self.file_system = Some(Arc::new(file_system));
+        self
+    }
+
+    /// Chooses which executables for the same version are kept.
+    pub fn dedup(mut self, dedup: Dedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Chooses how much executables are checked.
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Finds the executable the launcher would run for the request, i.e. the
+    /// first of [`Finder::find_all`].
+    ///
+    /// # Errors
+    ///
+    /// If the requested version or the version named by `PY_PYTHON` (or the
+    /// like) is invalid, or if `VIRTUAL_ENV` is set but does not contain a
+    /// Python executable (see [`crate::find_environment`]).
+    pub fn find(self) -> Result<Option<PathBuf>> {
+        Ok(self.find_all()?.into_iter().next().map(|(_, _, path)| path))
+    }
+
+    /// Finds the same executables as [`Finder::find_all`] as [`Interpreter`]s,
+    /// so that more can be [learned](Interpreter::metadata) about them.
+    ///
+    /// # Errors
+    ///
+    /// See [`Finder::find`].
+    pub fn find_interpreters(self) -> Result<Vec<Interpreter>> {
+        Ok(self
+            .find_all()?
+            .into_iter()
+            .map(|(source, version, path)| Interpreter {
+                path,
+                version,
+                source: Some(source),
+            })
+            .collect())
+    }
+
+    /// Finds every executable which satisfies the first requested version that
+    /// any executable satisfies, along with the name of its source, from newest
+    /// to oldest version (and then in the order they are searched).
+    ///
+    /// When no version is requested, the environment (if any, and its version
+    /// is known) comes first, with its kind as the name of its source (see
+    /// [`crate::all_environments`]). As with the launcher, a single requested
+    /// version which has an [environment variable](RequestedVersion::env_var),
+    /// like `3` and `PY_PYTHON3`, is replaced by the versions it names.
+    ///
+    /// # Errors
+    ///
+    /// See [`Finder::find`].
+    pub fn find_all(self) -> Result<Vec<(String, ExactVersion, PathBuf)>> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let env = self.env.unwrap_or_else(|| Box::new(ProcessEnv));
+        let env = env.as_ref();
+        let file_system = self.file_system.unwrap_or_else(|| Arc::new(OsFileSystem));
+        let config = load_config_of(env, &*file_system);
+        let search = Search::new(env, &config).with_file_system(Arc::clone(&file_system));
+        let mut found = Vec::new();
+        if self.preferences.is_none() {
+            if let Some(venv) = search.find_environment(self.venv_detection)? {
+                if let Some(version) = venv.version {
+                    found.push((
+                        environment_kind(&search, &venv.root),
+                        version,
+                        venv.executable,
+                    ));
+                }
+            }
+        }
+
+        let mut sources = self.sources.unwrap_or_else(|| search.sources());
+        sources.extend(self.extra_sources.into_iter().map(|source| {
+            match source {
+                ExtraSource::Source(source) => source,
+                ExtraSource::Directories(directories) => Box::new(
+                    DirectorySource::new("directories", directories)
+                        .with_file_system(Arc::clone(&file_system)),
+                ),
+            }
+        }));
+        let disabled = search.disabled_sources();
+        let candidates = match self.dedup {
+            Dedup::Version => {
+                // The first executable for each version comes from the earliest
+                // source which has one.
+                let mut versions = HashSet::new();
+                executables_by_source(&sources, &disabled)
+                    .into_iter()
+                    .filter(|(_, version, _)| versions.insert(*version))
+                    .collect()
+            }
+            Dedup::Source => executables_by_source(&sources, &disabled),
+            Dedup::Occurrence => executable_occurrences(&sources, &disabled),
+        };
+
+        let mut preferences = resolve_preferences(&search, self.preferences)?;
+        preferences.prereleases |= self.prereleases;
+        let validation = self.validation;
+        let matches = preferred_specifiers(&preferences)
+            .map(|(requested, specifier)| {
+                log::info!("Searching for every {requested}");
+                let mut matches = candidates
+                    .iter()
+                    .filter(|(_, version, path)| {
+                        specifier.contains(version)
+                            && (specifier.prereleases
+                                || validation == Validation::FileName
+                                || !is_prerelease(version, path))
+                            && (validation != Validation::Execute || runs_as(version, path))
+                    })
+                    .cloned()
+                    .collect::<Vec<(String, ExactVersion, PathBuf)>>();
+                // A stable sort keeps the executables for the same version in
+                // the order they were searched.
+                matches.sort_by(|(_, left, _), (_, right, _)| right.cmp(left));
+                matches
+            })
+            .find(|matches| !matches.is_empty())
+            .unwrap_or_default();
+        found.extend(matches);
+        Ok(found)
+    }
+}
+
+/// Works out the versions to search for like the launcher does: a single
+/// requested version is replaced by those of its environment variable (or its
+/// default in the configuration), and when no version is requested the
+/// [default version](crate::default_version) is used instead, limited by the
+/// `requires-python` of the current project.
+fn resolve_preferences(
+    search: &Search,
+    preferences: Option<VersionPreferenceList>,
+) -> Result<VersionPreferenceList> {
+    let mut preferences = preferences.unwrap_or_default();
+    if preferences.versions.is_empty() {
+        preferences.versions.push(RequestedVersion::Any);
+    }
+    let requested_version = preferences.versions[0];
+    let mut env_var_used = false;
+    if preferences.versions.len() == 1 {
+        if let Some(env_var) = requested_version.env_var() {
+            let env_var_value = search
+                .env
+                .var(&env_var)
+                .filter(|value| !value.is_empty())
+                .or_else(|| search.config.env_var_default(&env_var).map(String::from));
+            if let Some(env_var_value) = env_var_value {
+                log::debug!("{env_var} = '{env_var_value}'");
+                let env_preferences = VersionPreferenceList::from_str(&env_var_value)?;
+                preferences.versions = env_preferences.versions;
+                preferences.excluded.extend(env_preferences.excluded);
+                env_var_used = true;
+            }
+        }
+    }
+    if requested_version == RequestedVersion::Any {
+        if !env_var_used {
+            if let Some(default_preferences) = search.default_version() {
+                preferences.versions = default_preferences.versions;
+                preferences.excluded.extend(default_preferences.excluded);
+            }
+        }
+        let requires_python = search
+            .env
+            .current_dir()
+            .ok()
+            .and_then(|cwd| requires_python_of(&*search.file_system, &cwd));
+        if let Some(requires_python) = requires_python {
+            preferences.excluded.extend(requires_python.clauses);
+        }
+    }
+    Ok(preferences)
+}
+
+/// The kind of environment rooted at `root`, as named by
+/// [`crate::all_environments`].
+fn environment_kind(search: &Search, root: &Path) -> String {
+    search
+        .all_environments(VenvDetection::Search)
+        .into_iter()
+        .find(|(_, venv)| venv.root == root)
+        .map_or("venv", |(kind, _)| kind)
+        .to_string()
+}
+
+/// Checks that the executable runs and is the `major.minor` version of
+/// Python it is named for.
+fn runs_as(version: &ExactVersion, path: &Path) -> bool {
+    log::info!("Checking that {} runs", path.display());
+    match Command::new(path)
+        .args(["-c", "import sys; print('%d.%d' % sys.version_info[:2])"])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim()
+                == format!("{}.{}", version.major, version.minor)
+        }
+        _ => {
+            log::warn!("Unable to run {}", path.display());
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    use crate::{env_provider::MapEnv, touch_file, VersionError};
+
+    fn directory_finder(directories: &[&Path]) -> Finder {
+        let directories = directories.iter().map(PathBuf::from).collect();
+        Finder::new()
+            .env(MapEnv::new().with_var("PYLAUNCH_NO_CONFIG", "1"))
+            .sources(vec![Box::new(DirectorySource::new("test", directories))])
+            .skip_venv()
+            .validation(Validation::FileName)
+    }
+
+    #[test_case(None => vec!["3.12", "3.11", "3.10"] ; "any")]
+    #[test_case(Some("3.11") => vec!["3.11"] ; "exact")]
+    #[test_case(Some("3.9,3.10") => vec!["3.10"] ; "fallback")]
+    #[test_case(Some("3,!3.11") => vec!["3.12", "3.10"] ; "excluded")]
+    #[test_case(Some("2") => Vec::<String>::new() ; "missing")]
+    fn find_all_versions(version: Option<&str>) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["python3.10", "python3.11", "python3.12"] {
+            touch_file(dir.path().join(name));
+        }
+        let mut finder = directory_finder(&[dir.path()]);
+        if let Some(version) = version {
+            finder = finder.version(version);
+        }
+        finder
+            .find_all()
+            .unwrap()
+            .into_iter()
+            .map(|(_, version, _)| version.to_string())
+            .collect()
+    }
+
+    #[test_case(Dedup::Version => 1 ; "version")]
+    #[test_case(Dedup::Source => 1 ; "source")]
+    #[test_case(Dedup::Occurrence => 2 ; "occurrence")]
+    fn find_all_dedup(dedup: Dedup) -> usize {
+        let first_dir = tempfile::tempdir().unwrap();
+        let second_dir = tempfile::tempdir().unwrap();
+        let first = touch_file(first_dir.path().join("python3.11"));
+        touch_file(second_dir.path().join("python3.11"));
+        let found = directory_finder(&[first_dir.path(), second_dir.path()])
+            .dedup(dedup)
+            .find_all()
+            .unwrap();
+        assert_eq!(
+            found[0],
+            ("test".to_string(), ExactVersion::new(3, 11), first)
+        );
+        found.len()
+    }
+
+    #[test]
+    fn find_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        touch_file(dir.path().join("python3.10"));
+        let newest = touch_file(dir.path().join("python3.11"));
+        assert_eq!(
+            directory_finder(&[dir.path()]).find().unwrap(),
+            Some(newest)
+        );
+    }
+
+    #[test]
+    fn directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = touch_file(dir.path().join("python3.11"));
+        let found = Finder::new()
+            .sources(Vec::new())
+            .directories([dir.path()])
+            .skip_venv()
+            .validation(Validation::FileName)
+            .find_all()
+            .unwrap();
+        assert_eq!(
+            found,
+            [("directories".to_string(), ExactVersion::new(3, 11), python)]
+        );
+    }
+
+    #[test]
+    fn execute_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        // Not an executable, so it can't run.
+        touch_file(dir.path().join("python3.11"));
+        assert_eq!(
+            directory_finder(&[dir.path()])
+                .validation(Validation::Execute)
+                .find()
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn find_interpreters() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = touch_file(dir.path().join("python3.11"));
+        let found = directory_finder(&[dir.path()]).find_interpreters().unwrap();
+        assert_eq!(
+            found,
+            [Interpreter {
+                path: python,
+                version: ExactVersion::new(3, 11),
+                source: Some("test".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn env() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = touch_file(dir.path().join("python3.11"));
+        let venv = tempfile::tempdir().unwrap();
+        fs::create_dir(venv.path().join("bin")).unwrap();
+        let venv_python = touch_file(venv.path().join("bin").join("python"));
+        fs::write(venv.path().join("pyvenv.cfg"), "version = 3.12.1\n").unwrap();
+        let env = MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_var("PATH", dir.path())
+            .with_var("VIRTUAL_ENV", venv.path());
+
+        let found = Finder::new()
+            .env(env)
+            .validation(Validation::FileName)
+            .find_all()
+            .unwrap();
+        assert_eq!(
+            found[0],
+            (
+                "virtual-env".to_string(),
+                ExactVersion::with_micro(3, 12, 1),
+                venv_python
+            )
+        );
+        assert!(found.contains(&("path".to_string(), ExactVersion::new(3, 11), python)));
+    }
+
+    #[test_case(&[], None => vec!["3.11"] ; "py_python")]
+    #[test_case(&[], Some("3") => vec!["3.10"] ; "py_python3")]
+    #[test_case(&[], Some("3.12") => vec!["3.12"] ; "exact")]
+    #[test_case(&[("PY_PYTHON", "")], None => vec!["3.10"] ; "default_version")]
+    #[test_case(&[("PY_PYTHON", ""), ("PYENV_VERSION", "3.12")], None => vec!["3.12"] ; "pyenv_version")]
+    fn find_all_launcher_preferences(vars: &[(&str, &str)], version: Option<&str>) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["python3.10", "python3.11", "python3.12"] {
+            touch_file(dir.path().join(name));
+        }
+        let cwd = tempfile::tempdir().unwrap();
+        fs::write(cwd.path().join(".python-version"), "3.10\n").unwrap();
+        let mut env = MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_var("PY_PYTHON", "3.11")
+            .with_var("PY_PYTHON3", "3.10")
+            .with_current_dir(cwd.path());
+        for (name, value) in vars {
+            env = env.with_var(*name, *value);
+        }
+        let mut finder = directory_finder(&[dir.path()]).env(env);
+        if let Some(version) = version {
+            finder = finder.version(version);
+        }
+        finder
+            .find_all()
+            .unwrap()
+            .into_iter()
+            .map(|(_, version, _)| version.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn requires_python() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["python3.10", "python3.11"] {
+            touch_file(dir.path().join(name));
+        }
+        let cwd = tempfile::tempdir().unwrap();
+        fs::write(
+            cwd.path().join("pyproject.toml"),
+            "[project]\nrequires-python = \"<3.11\"\n",
+        )
+        .unwrap();
+        let env = MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_current_dir(cwd.path());
+        let found = directory_finder(&[dir.path()]).env(env).find().unwrap();
+        assert_eq!(found, Some(dir.path().join("python3.10")));
+    }
+
+    #[test]
+    fn env_after_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = touch_file(dir.path().join("python3.11"));
+        let extra = tempfile::tempdir().unwrap();
+        let extra_python = touch_file(extra.path().join("python3.10"));
+        let env = MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_var("PATH", dir.path());
+        let found = Finder::new()
+            .directories([extra.path()])
+            .env(env)
+            .skip_venv()
+            .validation(Validation::FileName)
+            .find_all()
+            .unwrap();
+        assert_eq!(
+            found,
+            [
+                ("path".to_string(), ExactVersion::new(3, 11), python),
+                (
+                    "directories".to_string(),
+                    ExactVersion::new(3, 10),
+                    extra_python
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn file_system() {
+        let file_system = crate::filesystem::MemoryFileSystem::new()
+            .file("/usr/bin/python3.12")
+            .file("/opt/bin/python3.12")
+            .file("/project/.venv/bin/python")
+            .file_with_contents("/project/.venv/pyvenv.cfg", "version = 3.11.4\n");
+        let env = MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_var("PATH", "/usr/bin")
+            .with_current_dir("/project");
+        // The directories are read from the file system chosen afterwards.
+        let found = Finder::new()
+            .directories(["/opt/bin"])
+            .env(env)
+            .file_system(file_system)
+            .dedup(Dedup::Source)
+            .validation(Validation::FileName)
+            .find_all()
+            .unwrap();
+        assert_eq!(
+            found,
+            [
+                (
+                    "venv".to_string(),
+                    ExactVersion::with_micro(3, 11, 4),
+                    PathBuf::from("/project/.venv/bin/python")
+                ),
+                (
+                    "path".to_string(),
+                    ExactVersion::new(3, 12),
+                    PathBuf::from("/usr/bin/python3.12")
+                ),
+                (
+                    "directories".to_string(),
+                    ExactVersion::new(3, 12),
+                    PathBuf::from("/opt/bin/python3.12")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_version() {
+        assert!(matches!(
+            Finder::new().version("three").find(),
+            Err(Error::Version(VersionError::InvalidVersionSpecifier(..)))
+                | Err(Error::Version(VersionError::ParseVersionComponentError(..)))
+        ));
+    }
+}
@@ -0,0 +1,986 @@
+//! Installing CPython from [python-build-standalone]
+//!
+//! Builds are downloaded from the latest release of python-build-standalone
+//! (or the [release](release_url) chosen by `PYLAUNCH_RELEASE_URL`) and
+//! unpacked into the [installs directory](installs_dir), where they are
+//! found like the installs of any other install manager (as the `launcher`
+//! [source](crate::SOURCES)).
+//!
+//! Every install is marked with a [receipt](RECEIPT_FILE_NAME), so that only
+//! interpreters installed by the launcher are ever [listed](installed) or
+//! [uninstalled](uninstall).
+//!
+//! Downloading and unpacking is left to `curl` and `tar`, which are expected
+//! to be on `PATH`.
+//!
+//! [python-build-standalone]: https://github.com/astral-sh/python-build-standalone
+
+use std::{
+    cmp::Reverse,
+    env,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(target_os = "wasi")]
+use std::os::wasi::ffi::OsStrExt;
+
+use sha2::{Digest, Sha256};
+
+use crate::{Error, ExactVersion, InstallError, IoError, RequestedVersion};
+
+/// The GitHub API URL of the latest release of python-build-standalone.
+pub const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/astral-sh/python-build-standalone/releases/latest";
+
+/// The name of the file within an install which marks it as installed by the
+/// launcher; it contains the URL the build was downloaded from.
+pub const RECEIPT_FILE_NAME: &str = ".python-launcher-install";
+
+/// A build of CPython which can be downloaded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Download {
+    /// The version of CPython, e.g. `3.12.7`.
+    pub version: ExactVersion,
+    /// The target triple the build is for, e.g. `x86_64-unknown-linux-gnu`.
+    pub target: String,
+    /// The URL of the build's `install_only` archive.
+    pub url: String,
+    /// The URL of the SHA256 checksum of the archive, either in a
+    /// `SHA256SUMS` file for the entire release or a `.sha256` file for just
+    /// the archive.
+    pub checksums_url: Option<String>,
+}
+
+impl Download {
+    /// The name of the directory within the [installs directory](installs_dir)
+    /// which the build is installed into, e.g.
+    /// `cpython-3.12.7-x86_64-unknown-linux-gnu`.
+    pub fn install_name(&self) -> String {
+        format!("cpython-{}-{}", self.version, self.target)
+    }
+
+    /// The file name of the archive, e.g.
+    /// `cpython-3.12.7+20241016-x86_64-unknown-linux-gnu-install_only.tar.gz`.
+    pub fn file_name(&self) -> String {
+        // The `+` separating the version from the release tag is
+        // percent-encoded in URLs.
+        self.url
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .replace("%2B", "+")
+    }
+}
+
+/// An interpreter installed by [`install`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Installed {
+    /// The version of the interpreter, e.g. `3.12.7`.
+    pub version: ExactVersion,
+    /// The directory of the install within the
+    /// [installs directory](installs_dir).
+    pub directory: PathBuf,
+}
+
+impl Installed {
+    /// The path to the `pythonX.Y` executable of the install.
+    pub fn executable(&self) -> PathBuf {
+        install_executable(&self.directory, &self.version)
+    }
+}
+
+fn install_executable(install_dir: &Path, version: &ExactVersion) -> PathBuf {
+    install_dir
+        .join("bin")
+        .join(format!("python{}.{}", version.major, version.minor))
+}
+
+/// The directory which interpreters are installed into.
+///
+/// This is `$PYLAUNCH_INSTALL_DIR`, `$XDG_DATA_HOME/python-launcher/installs`,
+/// or `~/.local/share/python-launcher/installs`, depending on which
+/// environment variables are set.
+pub fn installs_dir() -> Option<PathBuf> {
+    crate::launcher_installs_dir()
+}
+
+/// Whether a missing version of Python should be installed instead of
+/// failing, like the Windows launcher does.
+///
+/// This is the case when the `PYLAUNCHER_ALLOW_INSTALL` environment variable
+/// is set or the configuration sets `allow-install` to `true`.
+pub fn allow_install(config: &crate::config::Config) -> bool {
+    env::var_os("PYLAUNCHER_ALLOW_INSTALL").is_some() || config.allow_install
+}
+
+/// The target triple of the builds which can run on this platform, if there
+/// are any.
+pub fn target_triple() -> Option<&'static str> {
+    match (env::consts::ARCH, env::consts::OS) {
+        ("x86_64", "linux") => Some("x86_64-unknown-linux-gnu"),
+        ("aarch64", "linux") => Some("aarch64-unknown-linux-gnu"),
+        ("x86_64", "macos") => Some("x86_64-apple-darwin"),
+        ("aarch64", "macos") => Some("aarch64-apple-darwin"),
+        _ => None,
+    }
+}
+
+/// The URL of the release to install from, as returned by the GitHub API.
+///
+/// This is `$PYLAUNCH_RELEASE_URL` if it is set (e.g. to a `file://` URL of
+/// an offline copy), else [`LATEST_RELEASE_URL`].
+pub fn release_url() -> String {
+    env::var("PYLAUNCH_RELEASE_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| LATEST_RELEASE_URL.to_string())
+}
+
+fn supported_target() -> crate::Result<&'static str> {
+    target_triple().ok_or_else(|| {
+        Error::Install(InstallError::UnsupportedPlatform(format!(
+            "{}-{}",
+            env::consts::ARCH,
+            env::consts::OS
+        )))
+    })
+}
+
+fn fetch_release() -> crate::Result<String> {
+    let url = release_url();
+    log::info!("Fetching the release from {url}");
+    fetch(&url)
+}
+
+/// The builds which can be installed on this platform, from newest to oldest
+/// version (see [`available_downloads`]).
+///
+/// # Errors
+///
+/// If there are no builds for this platform,
+/// [`InstallError::UnsupportedPlatform`] is returned.
+///
+/// If the [release](release_url) fails to download,
+/// [`InstallError::DownloadFailed`] is returned.
+pub fn available() -> crate::Result<Vec<Download>> {
+    let target = supported_target()?;
+    Ok(available_downloads(&fetch_release()?, target))
+}
+
+/// Finds the newest build for `target` which satisfies `requested` among the
+/// assets of a release (see [`available_downloads`]).
+pub fn find_download(release: &str, requested: RequestedVersion, target: &str) -> Option<Download> {
+    available_downloads(release, target)
+        .into_iter()
+        .find(|download| download.version.supports(requested))
+}
+
+/// Finds the builds for `target` among the assets of a release, as returned
+/// by the GitHub API, from newest to oldest version.
+///
+/// Only the `install_only` archives of final releases are considered.
+pub fn available_downloads(release: &str, target: &str) -> Vec<Download> {
+    let suffix = format!("-{target}-install_only.tar.gz");
+    let urls = asset_urls(release);
+    let release_checksums_url = urls.iter().find(|url| url.ends_with("/SHA256SUMS"));
+    let mut downloads = urls
+        .iter()
+        .filter_map(|url| {
+            let mut download = Download {
+                version: ExactVersion::new(0, 0),
+                target: target.to_string(),
+                url: url.to_string(),
+                checksums_url: None,
+            };
+            let file_name = download.file_name();
+            let (version, build) = file_name.strip_prefix("cpython-")?.split_once('+')?;
+            if !build.ends_with(&suffix) {
+                return None;
+            }
+            // Pre-releases (e.g. `3.14.0a1`) fail to parse.
+            download.version = ExactVersion::from_str(version).ok()?;
+            let archive_checksum_url = format!("{url}.sha256");
+            download.checksums_url = release_checksums_url
+                .map(|checksums_url| checksums_url.to_string())
+                .or_else(|| {
+                    urls.contains(&archive_checksum_url)
+                        .then(|| archive_checksum_url)
+                });
+            Some(download)
+        })
+        .collect::<Vec<Download>>();
+    downloads.sort_by_key(|download| Reverse(download.version));
+    downloads.dedup_by_key(|download| download.version);
+    downloads
+}
+
+/// The download URLs of the assets of a release, as returned by the GitHub
+/// API; a release which isn't valid JSON has none.
+pub(crate) fn asset_urls(release: &str) -> Vec<String> {
+    let release = serde_json::from_str::<serde_json::Value>(release).unwrap_or_default();
+    release["assets"]
+        .as_array()
+        .map(|assets| {
+            assets
+                .iter()
+                .filter_map(|asset| asset["browser_download_url"].as_str())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Finds the SHA256 checksum of `file_name` in the contents of a checksums
+/// file, which either lists a checksum and file name per line (like the output
+/// of `sha256sum`) or consists of a single checksum.
+pub fn expected_sha256(checksums: &str, file_name: &str) -> Option<String> {
+    checksums
+        .lines()
+        .find_map(
+            |line| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [checksum] => Some(checksum),
+                // `sha256sum` marks files read in binary mode with a `*`.
+                [checksum, name] if name.trim_start_matches('*') == file_name => Some(checksum),
+                _ => None,
+            },
+        )
+        .filter(|checksum| checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_ascii_lowercase)
+}
+
+/// Downloads and installs the newest build of CPython which satisfies
+/// `requested`, returning the path to its `pythonX.Y` executable.
+///
+/// # Errors
+///
+/// If there are no builds for this platform,
+/// [`InstallError::UnsupportedPlatform`] is returned.
+///
+/// If the [installs directory](installs_dir) can't be located,
+/// [`InstallError::NoInstallsDir`] is returned.
+///
+/// If the [release](release_url) lacks a build for `requested`,
+/// [`InstallError::NoDownloadFound`] is returned.
+///
+/// See [`install_download`] for the errors of installing the build.
+pub fn install(requested: RequestedVersion) -> crate::Result<PathBuf> {
+    let target = supported_target()?;
+    let installs_dir = installs_dir().ok_or(Error::Install(InstallError::NoInstallsDir))?;
+    let release = fetch_release()?;
+    let download = find_download(&release, requested, target)
+        .ok_or(Error::Install(InstallError::NoDownloadFound(requested)))?;
+    install_download(&download, &installs_dir)
+}
+
+/// Installs a build into `installs_dir`, returning the path to its
+/// `pythonX.Y` executable.
+///
+/// Nothing is downloaded if the build is already installed. The build is
+/// unpacked outside of where installs are searched for and then moved into
+/// place, so a failed install is never found.
+///
+/// # Errors
+///
+/// If the build fails to download, [`InstallError::DownloadFailed`] is
+/// returned.
+///
+/// The archive is never unpacked unless its SHA256 checksum matches the one
+/// published with it: [`InstallError::NoChecksum`] is returned if there is none
+/// and [`InstallError::ChecksumMismatch`] if they differ.
+///
+/// If the build fails to unpack or move into place,
+/// [`InstallError::InstallFailed`] is returned.
+pub fn install_download(download: &Download, installs_dir: &Path) -> crate::Result<PathBuf> {
+    let install_name = download.install_name();
+    let install_dir = installs_dir.join(&install_name);
+    let executable = install_executable(&install_dir, &download.version);
+    if executable.is_file() {
+        log::info!("{} is already installed", download.version);
+        return Ok(executable);
+    }
+
+    let partial_dir = installs_dir.join(".partial");
+    let unpack_dir = partial_dir.join(&install_name);
+    let archive = partial_dir.join(format!("{install_name}.tar.gz"));
+    let install_failed = |error: io::Error| {
+        Error::Install(InstallError::InstallFailed(
+            install_dir.clone(),
+            error.into(),
+        ))
+    };
+    // Clear out what's left of any earlier failed attempt.
+    if unpack_dir.exists() {
+        fs::remove_dir_all(&unpack_dir).map_err(install_failed)?;
+    }
+    fs::create_dir_all(&unpack_dir).map_err(install_failed)?;
+
+    log::info!("Downloading {}", download.url);
+    let result = download_file(&download.url, &archive)
+        .and_then(|()| verify_checksum(download, &archive, &install_dir))
+        .and_then(|()| {
+            log::info!("Unpacking into {}", install_dir.display());
+            unpack(&archive, &unpack_dir, &install_dir)
+        })
+        .and_then(|()| {
+            fs::write(
+                unpack_dir.join(RECEIPT_FILE_NAME),
+                format!("{}\n", download.url),
+            )
+            .map_err(install_failed)
+        })
+        .and_then(|()| fs::rename(&unpack_dir, &install_dir).map_err(install_failed));
+    let _ = fs::remove_file(&archive);
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&unpack_dir);
+    }
+    // Only succeeds when no other install is in progress.
+    let _ = fs::remove_dir(&partial_dir);
+    result.map(|()| executable)
+}
+
+/// The interpreters installed by [`install`], from newest to oldest version.
+///
+/// # Errors
+///
+/// If the [installs directory](installs_dir) can't be located,
+/// [`InstallError::NoInstallsDir`] is returned.
+pub fn installed() -> crate::Result<Vec<Installed>> {
+    let installs_dir = installs_dir().ok_or(Error::Install(InstallError::NoInstallsDir))?;
+    installed_in(&installs_dir)
+        .map_err(|error| Error::Install(InstallError::InstallFailed(installs_dir, error.into())))
+}
+
+/// The interpreters installed into `installs_dir`, from newest to oldest
+/// version.
+///
+/// Only directories with a [receipt](RECEIPT_FILE_NAME) are included, so
+/// anything else in the directory is left alone.
+pub fn installed_in(installs_dir: &Path) -> io::Result<Vec<Installed>> {
+    let entries = match installs_dir.read_dir() {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut installs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        // Symlinks are never followed, let alone removed.
+        if !entry.file_type()?.is_dir() || !entry.path().join(RECEIPT_FILE_NAME).is_file() {
+            continue;
+        }
+        let file_name = entry.file_name();
+        let version = file_name
+            .to_str()
+            .and_then(|name| name.strip_prefix("cpython-"))
+            .and_then(|name| name.split('-').next())
+            .and_then(|version| ExactVersion::from_str(version).ok());
+        if let Some(version) = version {
+            installs.push(Installed {
+                version,
+                directory: entry.path(),
+            });
+        }
+    }
+    installs.sort_unstable_by_key(|install| Reverse(install.version));
+    Ok(installs)
+}
+
+/// Removes every interpreter installed by [`install`] which satisfies
+/// `requested`, returning what was removed.
+///
+/// # Errors
+///
+/// If the [installs directory](installs_dir) can't be located,
+/// [`InstallError::NoInstallsDir`] is returned.
+///
+/// See [`uninstall_from`] for the other errors.
+pub fn uninstall(requested: RequestedVersion) -> crate::Result<Vec<Installed>> {
+    uninstall_from(
+        requested,
+        &installs_dir().ok_or(Error::Install(InstallError::NoInstallsDir))?,
+    )
+}
+
+/// Removes every interpreter installed into `installs_dir` which satisfies
+/// `requested`, returning what was removed.
+///
+/// # Errors
+///
+/// If no install satisfies `requested`, [`InstallError::NotInstalled`] is
+/// returned.
+///
+/// If an install fails to be removed, [`InstallError::InstallFailed`] is
+/// returned.
+pub fn uninstall_from(
+    requested: RequestedVersion,
+    installs_dir: &Path,
+) -> crate::Result<Vec<Installed>> {
+    let installs = installed_in(installs_dir)
+        .map_err(|error| {
+            Error::Install(InstallError::InstallFailed(
+                installs_dir.to_path_buf(),
+                error.into(),
+            ))
+        })?
+        .into_iter()
+        .filter(|install| install.version.supports(requested))
+        .collect::<Vec<Installed>>();
+    if installs.is_empty() {
+        return Err(Error::Install(InstallError::NotInstalled(requested)));
+    }
+    for install in &installs {
+        log::info!("Removing {}", install.directory.display());
+        fs::remove_dir_all(&install.directory).map_err(|error| {
+            Error::Install(InstallError::InstallFailed(
+                install.directory.clone(),
+                error.into(),
+            ))
+        })?;
+    }
+    Ok(installs)
+}
+
+fn verify_checksum(download: &Download, archive: &Path, install_dir: &Path) -> crate::Result<()> {
+    let no_checksum = || Error::Install(InstallError::NoChecksum(download.url.clone()));
+    let checksums_url = download.checksums_url.as_ref().ok_or_else(no_checksum)?;
+    let expected =
+        expected_sha256(&fetch(checksums_url)?, &download.file_name()).ok_or_else(no_checksum)?;
+    let actual = file_sha256(archive).map_err(|error| {
+        Error::Install(InstallError::InstallFailed(
+            install_dir.to_path_buf(),
+            error.into(),
+        ))
+    })?;
+    if actual == expected {
+        log::info!("Verified the SHA256 checksum {actual}");
+        Ok(())
+    } else {
+        Err(Error::Install(InstallError::ChecksumMismatch(
+            download.url.clone(),
+            expected,
+            actual,
+        )))
+    }
+}
+
+pub(crate) fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// `file://` URLs are read directly, so offline copies work without `curl`.
+pub(crate) fn fetch(url: &str) -> crate::Result<String> {
+    let download_failed =
+        |error: IoError| Error::Install(InstallError::DownloadFailed(url.to_string(), error));
+    let output = match file_url_path(url) {
+        Some(path) => fs::read(path).map_err(|error| download_failed(error.into()))?,
+        None => curl(url, &[])?,
+    };
+    String::from_utf8(output)
+        .map_err(|error| download_failed(io::Error::new(io::ErrorKind::InvalidData, error).into()))
+}
+
+/// The path of a `file://` URL, percent-decoded (e.g. `%2B` to `+`).
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    let encoded = url.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let escape = encoded
+            .get(index + 1..index + 3)
+            .filter(|_| encoded[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(encoded[index]);
+                index += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(std::ffi::OsStr::from_bytes(&decoded)))
+}
+
+pub(crate) fn download_file(url: &str, path: &Path) -> crate::Result<()> {
+    match file_url_path(url) {
+        Some(source) => fs::copy(source, path).map(|_| ()).map_err(|error| {
+            Error::Install(InstallError::DownloadFailed(url.to_string(), error.into()))
+        }),
+        None => curl(url, &["--output".as_ref(), path.as_os_str()]).map(|_| ()),
+    }
+}
+
+fn curl(url: &str, args: &[&std::ffi::OsStr]) -> crate::Result<Vec<u8>> {
+    let download_failed =
+        |error: IoError| Error::Install(InstallError::DownloadFailed(url.to_string(), error));
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .args(args)
+        .arg(url)
+        .output()
+        .map_err(|error| {
+            download_failed(io::Error::new(error.kind(), format!("running `curl`: {error}")).into())
+        })?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(download_failed(IoError::other(
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )))
+    }
+}
+
+/// Unpacks an archive whose contents are all within a single directory (e.g.
+/// `python` for an `install_only` archive), whatever its compression.
+pub(crate) fn unpack(archive: &Path, unpack_dir: &Path, install_dir: &Path) -> crate::Result<()> {
+    let install_failed = |error: IoError| {
+        Error::Install(InstallError::InstallFailed(
+            install_dir.to_path_buf(),
+            error,
+        ))
+    };
+    let output = Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(unpack_dir)
+        .arg("--strip-components=1")
+        .output()
+        .map_err(|error| {
+            install_failed(io::Error::new(error.kind(), format!("running `tar`: {error}")).into())
+        })?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(install_failed(IoError::other(
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    const RELEASE: &str = r#"{
+  "tag_name": "20241016",
+  "assets": [
+    {
+      "name": "cpython-3.12.7+20241016-x86_64-unknown-linux-gnu-install_only.tar.gz",
+      "browser_download_url": "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.12.7%2B20241016-x86_64-unknown-linux-gnu-install_only.tar.gz"
+    },
+    {
+      "name": "cpython-3.12.7+20241016-x86_64-unknown-linux-gnu-install_only.tar.gz.sha256",
+      "browser_download_url": "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.12.7%2B20241016-x86_64-unknown-linux-gnu-install_only.tar.gz.sha256"
+    },
+    {
+      "name": "cpython-3.12.7+20241016-x86_64_v3-unknown-linux-gnu-install_only.tar.gz",
+      "browser_download_url": "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.12.7%2B20241016-x86_64_v3-unknown-linux-gnu-install_only.tar.gz"
+    },
+    {
+      "name": "cpython-3.12.7+20241016-aarch64-apple-darwin-install_only.tar.gz",
+      "browser_download_url": "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.12.7%2B20241016-aarch64-apple-darwin-install_only.tar.gz"
+    },
+    {
+      "name": "cpython-3.12.7+20241016-x86_64-unknown-linux-gnu-pgo+lto-full.tar.zst",
+      "browser_download_url": "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.12.7%2B20241016-x86_64-unknown-linux-gnu-pgo%2Blto-full.tar.zst"
+    },
+    {
+      "name": "cpython-3.11.10+20241016-x86_64-unknown-linux-gnu-install_only.tar.gz",
+      "browser_download_url": "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.11.10%2B20241016-x86_64-unknown-linux-gnu-install_only.tar.gz"
+    },
+    {
+      "name": "cpython-3.14.0a1+20241016-x86_64-unknown-linux-gnu-install_only.tar.gz",
+      "browser_download_url": "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.14.0a1%2B20241016-x86_64-unknown-linux-gnu-install_only.tar.gz"
+    }
+  ]
+}"#;
+
+    #[test_case(RequestedVersion::Any, "x86_64-unknown-linux-gnu" => Some("3.12.7".to_string()) ; "any")]
+    #[test_case(RequestedVersion::MajorOnly(3), "x86_64-unknown-linux-gnu" => Some("3.12.7".to_string()) ; "major only")]
+    #[test_case(RequestedVersion::Exact(3, 11), "x86_64-unknown-linux-gnu" => Some("3.11.10".to_string()) ; "exact")]
+    #[test_case(RequestedVersion::ExactMicro(3, 11, 10), "x86_64-unknown-linux-gnu" => Some("3.11.10".to_string()) ; "exact micro")]
+    #[test_case(RequestedVersion::ExactMicro(3, 11, 9), "x86_64-unknown-linux-gnu" => None ; "missing micro")]
+    #[test_case(RequestedVersion::Exact(3, 14), "x86_64-unknown-linux-gnu" => None ; "pre-release")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13), "x86_64-unknown-linux-gnu" => None ; "free-threaded")]
+    #[test_case(RequestedVersion::Exact(3, 11), "aarch64-apple-darwin" => None ; "other target")]
+    #[test_case(RequestedVersion::Any, "aarch64-unknown-linux-gnu" => None ; "missing target")]
+    fn find_download_tests(requested: RequestedVersion, target: &str) -> Option<String> {
+        find_download(RELEASE, requested, target).map(|download| download.version.to_string())
+    }
+
+    #[test]
+    fn find_download_test() {
+        assert_eq!(
+            find_download(RELEASE, RequestedVersion::Exact(3, 12), "aarch64-apple-darwin"),
+            Some(Download {
+                version: ExactVersion::with_micro(3, 12, 7),
+                target: "aarch64-apple-darwin".to_string(),
+                url: "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.12.7%2B20241016-aarch64-apple-darwin-install_only.tar.gz".to_string(),
+                checksums_url: None,
+            })
+        );
+        assert_eq!(
+            find_download("{}", RequestedVersion::Any, "aarch64-apple-darwin"),
+            None
+        );
+    }
+
+    #[test]
+    fn find_download_checksums_test() {
+        let download = find_download(
+            RELEASE,
+            RequestedVersion::Exact(3, 12),
+            "x86_64-unknown-linux-gnu",
+        )
+        .unwrap();
+        assert_eq!(
+            download.checksums_url,
+            Some(format!("{}.sha256", download.url))
+        );
+        assert_eq!(
+            find_download(
+                RELEASE,
+                RequestedVersion::Exact(3, 11),
+                "x86_64-unknown-linux-gnu"
+            )
+            .unwrap()
+            .checksums_url,
+            None
+        );
+
+        // A checksums file for the entire release is preferred.
+        let sums_url = "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/SHA256SUMS";
+        let release = RELEASE.replace(
+            r#""assets": ["#,
+            &format!(r#""assets": [{{"browser_download_url": "{sums_url}"}},"#),
+        );
+        for version in [
+            RequestedVersion::Exact(3, 12),
+            RequestedVersion::Exact(3, 11),
+        ] {
+            assert_eq!(
+                find_download(&release, version, "x86_64-unknown-linux-gnu")
+                    .unwrap()
+                    .checksums_url,
+                Some(sums_url.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn available_downloads_test() {
+        let versions = |release: &str, target| {
+            available_downloads(release, target)
+                .into_iter()
+                .map(|download| download.version.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            versions(RELEASE, "x86_64-unknown-linux-gnu"),
+            ["3.12.7", "3.11.10"]
+        );
+        assert_eq!(versions(RELEASE, "aarch64-apple-darwin"), ["3.12.7"]);
+        assert!(versions(RELEASE, "aarch64-unknown-linux-gnu").is_empty());
+
+        // The same build listed twice is only available once.
+        let assets = &RELEASE[RELEASE.find('[').unwrap() + 1..RELEASE.rfind(']').unwrap()];
+        let release = RELEASE.replacen(assets, &format!("{assets},{assets}"), 1);
+        assert_eq!(
+            versions(&release, "x86_64-unknown-linux-gnu"),
+            ["3.12.7", "3.11.10"]
+        );
+    }
+
+    #[test_case("file:///tmp/a.tar.gz" => Some(PathBuf::from("/tmp/a.tar.gz")) ; "plain")]
+    #[test_case("file:///tmp/cpython-3.12.7%2B20241016.tar.gz" => Some(PathBuf::from("/tmp/cpython-3.12.7+20241016.tar.gz")) ; "escaped")]
+    #[test_case("file:///tmp/a%20b%2" => Some(PathBuf::from("/tmp/a b%2")) ; "truncated escape")]
+    #[test_case("file:///tmp/100%zz" => Some(PathBuf::from("/tmp/100%zz")) ; "invalid escape")]
+    #[test_case("https://example.com/a.tar.gz" => None ; "not a file")]
+    fn file_url_path_tests(url: &str) -> Option<PathBuf> {
+        file_url_path(url)
+    }
+
+    const CHECKSUM: &str = "848a49cbbf3a1a5ec1a3ac3d4c1cc4a3a2b8e1a8c6c3ea28e2fd8cf6b5a0a9a1";
+
+    #[test_case(&format!("{CHECKSUM}\n"), "a.tar.gz" => Some(CHECKSUM.to_string()) ; "single checksum")]
+    #[test_case(&format!("{}\n", CHECKSUM.to_uppercase()), "a.tar.gz" => Some(CHECKSUM.to_string()) ; "uppercase")]
+    #[test_case(&format!("{}  b.tar.gz\n{CHECKSUM}  a.tar.gz\n", "0".repeat(64)), "a.tar.gz" => Some(CHECKSUM.to_string()) ; "sha256sum")]
+    #[test_case(&format!("{CHECKSUM} *a.tar.gz\n"), "a.tar.gz" => Some(CHECKSUM.to_string()) ; "binary mode")]
+    #[test_case(&format!("{CHECKSUM}  b.tar.gz\n"), "a.tar.gz" => None ; "missing file")]
+    #[test_case("not a checksum\n", "a.tar.gz" => None ; "malformed")]
+    #[test_case("abc123\n", "a.tar.gz" => None ; "too short")]
+    #[test_case("", "a.tar.gz" => None ; "empty")]
+    fn expected_sha256_tests(checksums: &str, file_name: &str) -> Option<String> {
+        expected_sha256(checksums, file_name)
+    }
+
+    #[test]
+    fn download_names_test() {
+        let download = Download {
+            version: ExactVersion::with_micro(3, 12, 7),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            url: "https://github.com/astral-sh/python-build-standalone/releases/download/20241016/cpython-3.12.7%2B20241016-x86_64-unknown-linux-gnu-install_only.tar.gz".to_string(),
+            checksums_url: None,
+        };
+        assert_eq!(
+            download.install_name(),
+            "cpython-3.12.7-x86_64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            download.file_name(),
+            "cpython-3.12.7+20241016-x86_64-unknown-linux-gnu-install_only.tar.gz"
+        );
+    }
+
+    #[test]
+    fn install_download_installed_test() {
+        let installs_dir = tempfile::tempdir().unwrap();
+        let download = Download {
+            version: ExactVersion::with_micro(3, 12, 7),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            // Nothing is downloaded when already installed.
+            url: "https://does.not.exist/".to_string(),
+            checksums_url: None,
+        };
+        let bin_dir = installs_dir
+            .path()
+            .join("cpython-3.12.7-x86_64-unknown-linux-gnu/bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        let python312 = bin_dir.join("python3.12");
+        fs::write(&python312, "").unwrap();
+
+        assert_eq!(
+            install_download(&download, installs_dir.path()),
+            Ok(python312)
+        );
+    }
+
+    #[test]
+    fn install_download_test() {
+        let build_dir = tempfile::tempdir().unwrap();
+        let bin_dir = build_dir.path().join("python/bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        fs::write(bin_dir.join("python3.11"), "").unwrap();
+        let archive = build_dir.path().join("build.tar.gz");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(build_dir.path())
+            .arg("python")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let checksum = file_sha256(&archive).unwrap();
+        let checksums = build_dir.path().join("SHA256SUMS");
+        fs::write(&checksums, format!("{checksum}  build.tar.gz\n")).unwrap();
+
+        let installs_dir = tempfile::tempdir().unwrap();
+        let mut download = Download {
+            version: ExactVersion::with_micro(3, 11, 10),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            url: format!("file://{}", archive.display()),
+            checksums_url: Some(format!("file://{}", checksums.display())),
+        };
+        let install_dir = installs_dir
+            .path()
+            .join("cpython-3.11.10-x86_64-unknown-linux-gnu");
+        assert_eq!(
+            install_download(&download, installs_dir.path()),
+            Ok(install_dir.join("bin/python3.11"))
+        );
+        assert!(install_dir.join("bin/python3.11").is_file());
+        assert_eq!(
+            fs::read_to_string(install_dir.join(RECEIPT_FILE_NAME)).unwrap(),
+            format!("{}\n", download.url)
+        );
+        assert!(!installs_dir
+            .path()
+            .join(".partial/cpython-3.11.10-x86_64-unknown-linux-gnu")
+            .exists());
+
+        download.version = ExactVersion::with_micro(3, 10, 15);
+        download.url = format!(
+            "file://{}",
+            build_dir.path().join("missing.tar.gz").display()
+        );
+        assert!(matches!(
+            install_download(&download, installs_dir.path()),
+            Err(Error::Install(InstallError::DownloadFailed(_, _)))
+        ));
+        assert!(!installs_dir
+            .path()
+            .join("cpython-3.10.15-x86_64-unknown-linux-gnu")
+            .exists());
+    }
+
+    #[test]
+    fn install_download_checksum_test() {
+        let build_dir = tempfile::tempdir().unwrap();
+        let archive = build_dir.path().join("build.tar.gz");
+        fs::write(&archive, "not actually an archive").unwrap();
+        let checksums = build_dir.path().join("build.tar.gz.sha256");
+        fs::write(&checksums, format!("{CHECKSUM}\n")).unwrap();
+
+        let installs_dir = tempfile::tempdir().unwrap();
+        let mut download = Download {
+            version: ExactVersion::with_micro(3, 11, 10),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            url: format!("file://{}", archive.display()),
+            checksums_url: Some(format!("file://{}", checksums.display())),
+        };
+        assert_eq!(
+            install_download(&download, installs_dir.path()),
+            Err(Error::Install(InstallError::ChecksumMismatch(
+                download.url.clone(),
+                CHECKSUM.to_string(),
+                file_sha256(&archive).unwrap()
+            )))
+        );
+
+        // Fail closed when there's no checksum to compare against.
+        fs::write(&checksums, "").unwrap();
+        assert_eq!(
+            install_download(&download, installs_dir.path()),
+            Err(Error::Install(InstallError::NoChecksum(
+                download.url.clone()
+            )))
+        );
+        download.checksums_url = None;
+        assert_eq!(
+            install_download(&download, installs_dir.path()),
+            Err(Error::Install(InstallError::NoChecksum(
+                download.url.clone()
+            )))
+        );
+
+        let install_dir = installs_dir
+            .path()
+            .join("cpython-3.11.10-x86_64-unknown-linux-gnu");
+        assert!(!install_dir.exists());
+        assert!(!installs_dir
+            .path()
+            .join(".partial/cpython-3.11.10-x86_64-unknown-linux-gnu")
+            .exists());
+    }
+
+    fn fake_install(installs_dir: &Path, name: &str, receipt: bool) -> PathBuf {
+        let install_dir = installs_dir.join(name);
+        fs::create_dir_all(install_dir.join("bin")).unwrap();
+        if receipt {
+            fs::write(install_dir.join(RECEIPT_FILE_NAME), "").unwrap();
+        }
+        install_dir
+    }
+
+    #[test]
+    fn installed_in_test() {
+        let installs_dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            installed_in(&installs_dir.path().join("missing")).unwrap(),
+            []
+        );
+
+        let python311 = fake_install(
+            installs_dir.path(),
+            "cpython-3.11.10-x86_64-unknown-linux-gnu",
+            true,
+        );
+        let python312 = fake_install(
+            installs_dir.path(),
+            "cpython-3.12.7-x86_64-unknown-linux-gnu",
+            true,
+        );
+        // Not installed by the launcher.
+        fake_install(
+            installs_dir.path(),
+            "cpython-3.13.0-x86_64-unknown-linux-gnu",
+            false,
+        );
+        std::os::unix::fs::symlink(
+            &python312,
+            installs_dir
+                .path()
+                .join("cpython-3.12.6-x86_64-unknown-linux-gnu"),
+        )
+        .unwrap();
+
+        let installs = installed_in(installs_dir.path()).unwrap();
+        assert_eq!(
+            installs,
+            [
+                Installed {
+                    version: ExactVersion::with_micro(3, 12, 7),
+                    directory: python312.clone(),
+                },
+                Installed {
+                    version: ExactVersion::with_micro(3, 11, 10),
+                    directory: python311,
+                },
+            ]
+        );
+        assert_eq!(installs[0].executable(), python312.join("bin/python3.12"));
+    }
+
+    #[test]
+    fn uninstall_from_test() {
+        let installs_dir = tempfile::tempdir().unwrap();
+        let python3_12_6 = fake_install(
+            installs_dir.path(),
+            "cpython-3.12.6-x86_64-unknown-linux-gnu",
+            true,
+        );
+        let python3_12_7 = fake_install(
+            installs_dir.path(),
+            "cpython-3.12.7-x86_64-unknown-linux-gnu",
+            true,
+        );
+        let python3_11 = fake_install(
+            installs_dir.path(),
+            "cpython-3.11.10-x86_64-unknown-linux-gnu",
+            true,
+        );
+        let unmarked_python3_12 = fake_install(
+            installs_dir.path(),
+            "cpython-3.12.5-x86_64-unknown-linux-gnu",
+            false,
+        );
+
+        assert_eq!(
+            uninstall_from(RequestedVersion::Exact(3, 10), installs_dir.path()),
+            Err(Error::Install(InstallError::NotInstalled(
+                RequestedVersion::Exact(3, 10)
+            )))
+        );
+        assert_eq!(
+            uninstall_from(RequestedVersion::Exact(3, 12), installs_dir.path()),
+            Ok(vec![
+                Installed {
+                    version: ExactVersion::with_micro(3, 12, 7),
+                    directory: python3_12_7.clone(),
+                },
+                Installed {
+                    version: ExactVersion::with_micro(3, 12, 6),
+                    directory: python3_12_6.clone(),
+                },
+            ])
+        );
+        assert!(!python3_12_7.exists());
+        assert!(!python3_12_6.exists());
+        assert!(python3_11.exists());
+        assert!(unmarked_python3_12.exists());
+    }
+}
@@ -0,0 +1,386 @@
+//! Metadata about interpreters beyond their file names
+//!
+//! An [`Interpreter`] is found via its file name, which says little more than
+//! its `major.minor` version. More can be learned on demand at the
+//! [`MetadataLevel`] the caller is willing to pay for: reading the header of
+//! its binary, or running the interpreter to ask it about itself. Callers who
+//! mustn't run untrusted executables can stop at the former.
+//!
+//! Learning about many interpreters at once via [`metadata_of`] runs up to
+//! [`MAX_CONCURRENT_INTROSPECTIONS`] of them at a time, as each takes as long
+//! as an interpreter takes to start.
+
+use std::{
+    collections::VecDeque,
+    io, panic,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    binary_architecture, binary_bits, binary_header, Error, ExactVersion, ExecutionError,
+    Implementation, IoError, Result,
+};
+
+/// The Python code which prints what is learned by
+/// [introspection](MetadataLevel::Introspection), separated by NULs.
+///
+/// It is kept compatible with Python 2.7 so that any interpreter which is
+/// found can be introspected.
+const PROBE: &str = "\
+import platform, struct, sys, sysconfig
+v = sys.version_info
+sys.stdout.write('\\0'.join([
+    platform.python_implementation(),
+    '%d.%d.%d' % tuple(v[:3]),
+    v[3],
+    str(bool(sysconfig.get_config_var('Py_GIL_DISABLED'))),
+    sys.prefix,
+    getattr(sys, 'base_prefix', sys.prefix),
+    platform.machine(),
+    str(struct.calcsize('P') * 8),
+]))
+";
+
+/// How much is done to learn about an interpreter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetadataLevel {
+    /// Only what the file name says, which costs nothing.
+    FileName,
+    /// Also parse the header of the binary (ELF, Mach-O, or PE) for its
+    /// architecture and bitness, without running anything.
+    ///
+    /// Nothing more is known for a script standing in for an interpreter, like
+    /// a pyenv shim.
+    Header,
+    /// Run the interpreter to ask it about itself.
+    Introspection,
+}
+
+/// What is known about an interpreter at some [`MetadataLevel`].
+///
+/// Anything which isn't known at the level is `None`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    /// The version and implementation, including the micro version and
+    /// whether it's free-threaded when introspected.
+    pub version: ExactVersion,
+    /// The release level of the version, i.e. `alpha`, `beta`, `candidate`,
+    /// or `final`.
+    pub release_level: Option<String>,
+    /// `sys.prefix`, which is the virtual environment for one.
+    pub prefix: Option<PathBuf>,
+    /// `sys.base_prefix`, i.e. where the interpreter is installed.
+    pub base_prefix: Option<PathBuf>,
+    /// The architecture the interpreter runs as, named like
+    /// [`crate::executable_architecture`] names it (e.g. `x86_64` or
+    /// `arm64`).
+    pub architecture: Option<String>,
+    /// Whether the interpreter is 32- or 64-bit.
+    pub bits: Option<u8>,
+}
+
+/// An interpreter which was found.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interpreter {
+    /// The path to the executable.
+    pub path: PathBuf,
+    /// The version according to the file name.
+    pub version: ExactVersion,
+    /// The name of the [source](crate::InterpreterSource) which found it, if
+    /// known.
+    pub source: Option<String>,
+}
+
+impl Interpreter {
+    /// Creates an interpreter for the executable at `path`, whose version is
+    /// parsed from its file name (see [`ExactVersion::from_path`]).
+    pub fn from_path(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let version = ExactVersion::from_path(&path)?;
+        Ok(Self {
+            path,
+            version,
+            source: None,
+        })
+    }
+
+    /// Learns about the interpreter at the specified level.
+    ///
+    /// # Errors
+    ///
+    /// When introspecting, [`ExecutionError::IntrospectionFailed`] is returned
+    /// if the interpreter can't be run or its output isn't understood.
+    pub fn metadata(&self, level: MetadataLevel) -> Result<Metadata> {
+        match level {
+            MetadataLevel::FileName => Ok(Metadata {
+                version: self.version,
+                release_level: None,
+                prefix: None,
+                base_prefix: None,
+                architecture: None,
+                bits: None,
+            }),
+            MetadataLevel::Header => {
+                let header = binary_header(&self.path).unwrap_or_default();
+                Ok(Metadata {
+                    architecture: binary_architecture(&header).map(ToString::to_string),
+                    bits: binary_bits(&header),
+                    ..self.metadata(MetadataLevel::FileName)?
+                })
+            }
+            MetadataLevel::Introspection => introspect(&self.path),
+        }
+    }
+}
+
+/// The most interpreters which [`metadata_of`] runs at the same time.
+pub const MAX_CONCURRENT_INTROSPECTIONS: usize = 8;
+
+/// Learns about every interpreter at the specified level (see
+/// [`Interpreter::metadata`]), in the order of `interpreters`.
+///
+/// When introspecting, up to [`MAX_CONCURRENT_INTROSPECTIONS`] interpreters
+/// are run at once, each on its own thread, instead of one after another.
+pub fn metadata_of(interpreters: &[Interpreter], level: MetadataLevel) -> Vec<Result<Metadata>> {
+    // WASI can't start threads, nor run interpreters.
+    if level != MetadataLevel::Introspection || interpreters.len() < 2 || cfg!(target_os = "wasi") {
+        return interpreters
+            .iter()
+            .map(|interpreter| interpreter.metadata(level))
+            .collect();
+    }
+
+    let queue = Arc::new(Mutex::new(
+        interpreters
+            .iter()
+            .cloned()
+            .enumerate()
+            .collect::<VecDeque<(usize, Interpreter)>>(),
+    ));
+    let (sender, receiver) = mpsc::channel();
+    let workers = (0..interpreters.len().min(MAX_CONCURRENT_INTROSPECTIONS))
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let next = queue
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .pop_front();
+                match next {
+                    Some((index, interpreter)) => {
+                        let _ = sender.send((index, interpreter.metadata(level)));
+                    }
+                    None => break,
+                }
+            })
+        })
+        .collect::<Vec<thread::JoinHandle<()>>>();
+    drop(sender);
+
+    let mut metadata = interpreters
+        .iter()
+        .map(|_| None)
+        .collect::<Vec<Option<Result<Metadata>>>>();
+    for (index, learned) in receiver {
+        metadata[index] = Some(learned);
+    }
+    for worker in workers {
+        if let Err(error) = worker.join() {
+            panic::resume_unwind(error);
+        }
+    }
+    metadata
+        .into_iter()
+        .map(|learned| learned.expect("every interpreter was introspected"))
+        .collect()
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", ret, err))]
+fn introspect(path: &Path) -> Result<Metadata> {
+    log::info!("Introspecting {}", path.display());
+    let failed = |error: IoError| {
+        Error::Execution(ExecutionError::IntrospectionFailed(
+            path.to_path_buf(),
+            error,
+        ))
+    };
+    let output = Command::new(path)
+        .args(["-c", PROBE])
+        .output()
+        .map_err(|error| failed(error.into()))?;
+    if !output.status.success() {
+        return Err(failed(IoError::other(format!(
+            "the probe exited with {}",
+            output.status
+        ))));
+    }
+    parse_probe_output(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|message| failed(io::Error::new(io::ErrorKind::InvalidData, message).into()))
+}
+
+/// Parses the output of [`PROBE`].
+fn parse_probe_output(output: &str) -> std::result::Result<Metadata, String> {
+    let fields = output.split('\0').collect::<Vec<&str>>();
+    let (implementation, version, release_level, free_threaded, prefix, base_prefix, machine, bits) =
+        match fields[..] {
+            [implementation, version, release_level, free_threaded, prefix, base_prefix, machine, bits] => {
+                (
+                    implementation,
+                    version,
+                    release_level,
+                    free_threaded,
+                    prefix,
+                    base_prefix,
+                    machine,
+                    bits,
+                )
+            }
+            _ => return Err(format!("unexpected output from the probe: {output:?}")),
+        };
+    let implementation = match implementation {
+        "CPython" => Implementation::CPython,
+        "PyPy" => Implementation::PyPy,
+        // GraalPy reports itself as running on GraalVM.
+        "GraalVM" | "GraalPy" => Implementation::GraalPy,
+        unknown => return Err(format!("unsupported implementation {unknown}")),
+    };
+    let version = version
+        .parse::<ExactVersion>()
+        .map_err(|error| error.to_string())?;
+    Ok(Metadata {
+        version: ExactVersion {
+            implementation,
+            free_threaded: free_threaded == "True",
+            ..version
+        },
+        release_level: Some(release_level.to_string()),
+        prefix: Some(PathBuf::from(prefix)),
+        base_prefix: Some(PathBuf::from(base_prefix)),
+        architecture: Some(normalize_architecture(machine)),
+        bits: bits.parse().ok(),
+    })
+}
+
+/// Names the architecture reported by `platform.machine()` like
+/// [`crate::executable_architecture`] does, as the name varies by platform
+/// (e.g. `AMD64` on Windows and `aarch64` on Linux).
+fn normalize_architecture(machine: &str) -> String {
+    let machine = machine.to_lowercase();
+    match machine.as_str() {
+        "amd64" | "x86_64" | "x64" => "x86_64",
+        "aarch64" | "arm64" => "arm64",
+        "i386" | "i486" | "i586" | "i686" | "x86" => "x86",
+        "ppc64" | "ppc64le" => "ppc64",
+        arm if arm.starts_with("arm") => "arm",
+        other => other,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test]
+    fn parse_probe_output_test() {
+        let metadata = parse_probe_output(
+            "CPython\u{0}3.13.1\u{0}final\u{0}True\u{0}/venv\u{0}/usr\u{0}aarch64\u{0}64",
+        )
+        .unwrap();
+        assert_eq!(
+            metadata,
+            Metadata {
+                version: ExactVersion {
+                    micro: Some(1),
+                    free_threaded: true,
+                    ..ExactVersion::new(3, 13)
+                },
+                release_level: Some("final".to_string()),
+                prefix: Some(PathBuf::from("/venv")),
+                base_prefix: Some(PathBuf::from("/usr")),
+                architecture: Some("arm64".to_string()),
+                bits: Some(64),
+            }
+        );
+    }
+
+    #[test_case("PyPy\u{0}3.10.14\u{0}final\u{0}False\u{0}/p\u{0}/p\u{0}x86_64\u{0}64" => Ok(Implementation::PyPy) ; "pypy")]
+    #[test_case("GraalVM\u{0}3.11.7\u{0}final\u{0}False\u{0}/p\u{0}/p\u{0}x86_64\u{0}64" => Ok(Implementation::GraalPy) ; "graalpy")]
+    #[test_case("Jython\u{0}2.7.3\u{0}final\u{0}False\u{0}/p\u{0}/p\u{0}x86_64\u{0}64" => Err("unsupported implementation Jython".to_string()) ; "unsupported")]
+    #[test_case("CPython\u{0}3.13.1" => matches Err(_) ; "truncated")]
+    fn parse_probe_output_implementation(
+        output: &str,
+    ) -> std::result::Result<Implementation, String> {
+        parse_probe_output(output).map(|metadata| metadata.version.implementation)
+    }
+
+    #[test_case("AMD64" => "x86_64" ; "windows x86_64")]
+    #[test_case("aarch64" => "arm64" ; "linux arm64")]
+    #[test_case("i686" => "x86" ; "x86")]
+    #[test_case("armv7l" => "arm" ; "arm")]
+    #[test_case("riscv64" => "riscv64" ; "other")]
+    fn normalize_architecture_tests(machine: &str) -> String {
+        normalize_architecture(machine)
+    }
+
+    #[test]
+    fn file_name_metadata() {
+        let interpreter = Interpreter::from_path("/usr/bin/pypy3.10").unwrap();
+        let metadata = interpreter.metadata(MetadataLevel::FileName).unwrap();
+        assert_eq!(metadata.version.implementation, Implementation::PyPy);
+        assert_eq!(metadata.release_level, None);
+        assert_eq!(metadata.architecture, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let interpreter = Interpreter {
+            source: Some("path".to_string()),
+            ..Interpreter::from_path("/usr/bin/python3.13t").unwrap()
+        };
+        let json = serde_json::to_string(&interpreter).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Interpreter>(&json).unwrap(),
+            interpreter
+        );
+
+        let requested =
+            crate::RequestedVersion::Implementation(Implementation::PyPy, Some(3), None);
+        let json = serde_json::to_string(&requested).unwrap();
+        assert_eq!(
+            serde_json::from_str::<crate::RequestedVersion>(&json).unwrap(),
+            requested
+        );
+    }
+
+    #[test]
+    fn header_metadata() {
+        let mut interpreter = Interpreter::from_path("/usr/bin/python3.12").unwrap();
+        // Any binary will do, as it isn't run.
+        interpreter.path = std::env::current_exe().unwrap();
+        let metadata = interpreter.metadata(MetadataLevel::Header).unwrap();
+        assert_eq!(metadata.version, ExactVersion::new(3, 12));
+        assert_eq!(
+            metadata.architecture.as_deref(),
+            crate::executable_architecture(&interpreter.path)
+        );
+        assert_eq!(metadata.bits, Some(usize::BITS as u8));
+        assert_eq!(metadata.release_level, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        interpreter.path = dir.path().join("python3.12");
+        std::fs::write(&interpreter.path, "#!/bin/sh\n").unwrap();
+        let metadata = interpreter.metadata(MetadataLevel::Header).unwrap();
+        assert_eq!((metadata.architecture, metadata.bits), (None, None));
+    }
+}
@@ -8,113 +8,240 @@
 //! At the top-level, the code directly related to searching is provided.
 //! The [`RequestedVersion`] enum represents the constraints the user has placed
 //! upon what version of Python they are searching for (ranging from any to a
-//! `major.minor` version). The [`ExactVersion`] struct represents an exact
-//! `major.minor` version of Python which was found.
+//! `major.minor.micro` version). The [`ExactVersion`] struct represents an
+//! exact `major.minor` (and potentially `micro`) version of Python which was
+//! found. The [`VersionSpecifier`] struct represents a range of acceptable
+//! versions using the syntax of PEP 440 (e.g. `>=3.9,<3.12`). The
+//! [`VersionPreferenceList`] struct represents an ordered chain of
+//! [`RequestedVersion`] to fall back through (e.g. `3.12,3.11,3`). The
+//! [`InterpreterSource`] trait represents somewhere to search for executables
+//! (e.g. `PATH`).
+//!
+//! The `asynchronous` module (with the `tokio` feature) contains asynchronous
+//! variants of the functions for searching.
+//!
+//! The [`cache`] module contains all code related to caching the executables
+//! found by searching.
 //!
 //! The [`cli`] module contains all code related to providing a CLI like the one
 //! the [Python Launcher for Windows] provides.
 //!
+//! The [`config`] module contains all code related to loading the
+//! configuration file.
+//!
+//! The [`env_provider`] module contains the abstraction of the environment
+//! variables and current directory which what to run is resolved from.
+//!
+//! The [`error`] module contains the errors of each part of the launcher.
+//!
+//! The [`filesystem`] module contains the abstraction of the file system which
+//! directories are searched through.
+//!
+//! The [`finder`] module contains a builder for searching which combines the
+//! choices the functions at the top-level make.
+//!
+//! The [`shebang`] module contains all code related to parsing the shebang
+//! line of a script.
+//!
+//! The [`install`] module contains all code related to downloading and
+//! installing interpreters.
+//!
+//! The [`interpreter`] module contains all code related to learning more about
+//! an interpreter than its file name says.
+//!
+//! The [`picker`] module contains all code related to picking an interpreter
+//! interactively.
+//!
+//! The `python` module (with the `python` feature) contains the bindings which
+//! make searching available to Python code.
+//!
+//! The [`run`] module contains all code related to running an interpreter.
+//!
+//! The [`server`] module contains all code related to answering requests from
+//! editors over standard input and output (i.e. `py --serve`).
+//!
+//! The [`update`] module contains all code related to updating the launcher
+//! itself.
+//!
+//! # Features
+//!
+//! - `cli` (on by default): The `py` binary, along with the [`cli`],
+//!   [`picker`], [`server`], and [`update`] modules, [`run::exec`] and [`run::spawn`], and
+//!   [`Error::exit_code`]. Turn it off (via `default-features = false`) when
+//!   only searching for interpreters is needed, to avoid the dependencies
+//!   of the CLI. Turns on `install`.
+//! - `install`: Adds the `install` module for installing CPython from
+//!   python-build-standalone, which relies on `curl` and `tar` being on
+//!   `PATH`.
+//! - `python`: Adds the `python` module, which defines a `python_launcher`
+//!   extension module via [PyO3](https://pyo3.rs/) exposing
+//!   [`find_executable`], [`all_executables`], [`ExactVersion`], and
+//!   [`RequestedVersion`] to Python (see `pyproject.toml` for building it).
+//! - `serde`: Implements `Serialize` and `Deserialize` for [`ExactVersion`],
+//!   [`RequestedVersion`], [`Implementation`], and the types of the
+//!   [`interpreter`] module, so discovered interpreters can be stored and
+//!   exchanged.
+//! - `tokio`: Adds the `asynchronous` module for searching without blocking
+//!   via [Tokio](https://tokio.rs/).
+//! - `tracing`: Records [tracing](https://docs.rs/tracing) spans and events
+//!   for searching (including each source), resolving the version to use from
+//!   environment variables and files, introspecting, and running an
+//!   interpreter, so a host application can capture structured diagnostics
+//!   alongside the [`log`](https://docs.rs/log) messages.
+//!
+//! # WebAssembly
+//!
+//! Without the `cli` feature, the crate builds for WASI (i.e.
+//! `cargo build --no-default-features --target wasm32-wasip1`), so that
+//! parsing and matching versions and file names can be reused from a
+//! WebAssembly sandbox (e.g. an editor extension running in a browser).
+//! Directories are searched through the [`filesystem`] abstraction, which is
+//! either the file system the host preopened or one provided by the caller.
+//! Anything which runs an interpreter (e.g.
+//! [introspection](interpreter::MetadataLevel::Introspection)) fails as WASI
+//! can't start processes.
+//!
 //! [Python Launcher for Windows]: https://docs.python.org/3/using/windows.html#launcher
 
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+pub mod cache;
+#[cfg(feature = "cli")]
 pub mod cli;
+pub mod config;
+pub mod env_provider;
+pub mod error;
+pub mod filesystem;
+pub mod finder;
+#[cfg(feature = "install")]
+pub mod install;
+pub mod interpreter;
+#[cfg(feature = "cli")]
+pub mod picker;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod run;
+#[cfg(feature = "cli")]
+pub mod server;
+pub mod shebang;
+#[cfg(feature = "cli")]
+pub mod update;
 
 use std::{
-    collections::HashMap,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
     convert::From,
-    env, fmt,
+    env,
+    ffi::OsStr,
+    fmt,
     fmt::Display,
-    num::ParseIntError,
+    fs::{self, File},
+    io::Read,
     path::{Path, PathBuf},
+    process::Command,
     str::FromStr,
+    sync::Arc,
+};
+
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(target_os = "wasi")]
+use std::os::wasi::ffi::OsStrExt;
+
+use sha2::{Digest, Sha256};
+
+pub use error::{
+    CliError, ConfigError, Error, ExecutionError, InstallError, IoError, SearchError, VersionError,
 };
 
+use env_provider::{EnvProvider, ProcessEnv};
+use filesystem::{FileSystem, OsFileSystem};
+
 /// [`std::result::Result`] type with [`Error`] as the error type.
 pub type Result<T> = std::result::Result<T, Error>;
 
-/// Error enum for the entire crate.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub enum Error {
-    /// Parsing a digit component from a string fails.
-    ParseVersionComponentError(ParseIntError, String),
-    /// String parsing fails due to `.` missing.
-    DotMissing,
-    /// A [`Path`] lacks a file name when it is required.
-    FileNameMissing,
-    /// A file name cannot be converted to a string.
-    FileNameToStrError,
-    /// A file name is not structured appropriately.
-    PathFileNameError,
-    /// No Python executable could be found based on the constraints provided.
-    NoExecutableFound(RequestedVersion),
-    /// An illegal combination of CLI flags are provided.
-    IllegalArgument(PathBuf, String),
-}
-
-#[cfg(not(tarpaulin_include))]
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Error::ParseVersionComponentError(int_error, bad_value) => {
-                write!(f, "Error parsing '{bad_value}' as an integer: {int_error}")
-            }
-            Self::DotMissing => write!(f, "'.' missing from the version"),
-            Self::FileNameMissing => write!(f, "Path object lacks a file name"),
-            Self::FileNameToStrError => write!(f, "Failed to convert file name to `str`"),
-            Self::PathFileNameError => write!(f, "File name not of the format `pythonX.Y`"),
-            Self::NoExecutableFound(requested_version) => {
-                write!(f, "No executable found for {requested_version}")
-            }
-            Self::IllegalArgument(launcher_path, flag) => {
-                let printable_path = launcher_path.to_string_lossy();
-                write!(
-                    f,
-                    "The `{flag}` flag must be specified on its own; see `{printable_path} --help` for details"
-                )
-            }
-        }
+/// The integral part of a version specifier (e.g. the `3` or `10` of `3.10`).
+pub type ComponentSize = u16;
+
+/// An implementation of Python.
+///
+/// Each implementation is recognized by the prefix of its executable names,
+/// e.g. `pypy3.10` for PyPy.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Implementation {
+    /// [CPython](https://www.python.org/), e.g. `python3.10`.
+    CPython,
+    /// [PyPy](https://pypy.org/), e.g. `pypy3.10`.
+    PyPy,
+    /// [GraalPy](https://www.graalvm.org/python/), e.g. `graalpy3.10`.
+    GraalPy,
+}
+
+impl Default for Implementation {
+    fn default() -> Self {
+        Self::CPython
     }
 }
 
-#[cfg(not(tarpaulin_include))]
-impl std::error::Error for Error {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::ParseVersionComponentError(int_error, _) => Some(int_error),
-            Self::DotMissing => None,
-            Self::FileNameMissing => None,
-            Self::FileNameToStrError => None,
-            Self::PathFileNameError => None,
-            Self::NoExecutableFound(_) => None,
-            Self::IllegalArgument(_, _) => None,
-        }
+impl Display for Implementation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Self::CPython => "CPython",
+            Self::PyPy => "PyPy",
+            Self::GraalPy => "GraalPy",
+        };
+        write!(f, "{repr}")
     }
 }
 
-#[cfg(not(tarpaulin_include))]
-impl Error {
-    /// Returns the appropriate [exit code](`exitcode::ExitCode`) for the error.
-    pub fn exit_code(&self) -> exitcode::ExitCode {
+impl Implementation {
+    /// All known implementations.
+    pub const ALL: [Self; 3] = [Self::CPython, Self::PyPy, Self::GraalPy];
+
+    /// Returns the prefix of executable names for the implementation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let pypy = python_launcher::Implementation::PyPy;
+    ///
+    /// assert_eq!("pypy", pypy.executable_prefix());
+    /// ```
+    pub fn executable_prefix(self) -> &'static str {
         match self {
-            Self::ParseVersionComponentError(_, _) => exitcode::USAGE,
-            Self::DotMissing => exitcode::USAGE,
-            Self::FileNameMissing => exitcode::USAGE,
-            Self::FileNameToStrError => exitcode::SOFTWARE,
-            Self::PathFileNameError => exitcode::SOFTWARE,
-            Self::NoExecutableFound(_) => exitcode::USAGE,
-            Self::IllegalArgument(_, _) => exitcode::USAGE,
+            Self::CPython => "python",
+            Self::PyPy => "pypy",
+            Self::GraalPy => "graalpy",
         }
     }
-}
 
-/// The integral part of a version specifier (e.g. the `3` or `10` of `3.10`).
-pub type ComponentSize = u16;
+    /// Splits a name into the implementation its prefix represents and the
+    /// remainder of the name.
+    fn split_name(name: &str) -> Option<(Self, &str)> {
+        Self::ALL.iter().find_map(|implementation| {
+            name.strip_prefix(implementation.executable_prefix())
+                .map(|rest| (*implementation, rest))
+        })
+    }
+
+    /// Like [`Implementation::split_name`], but CPython is implied when the
+    /// name has no prefix for an alternative implementation.
+    fn split_alternative_name(name: &str) -> (Self, &str) {
+        Self::split_name(name)
+            .filter(|(implementation, _)| *implementation != Self::CPython)
+            .unwrap_or((Self::CPython, name))
+    }
+}
 
 /// The version of Python being searched for.
 ///
 /// The constraints of what is being searched for can very from being
 /// open-ended/broad (i.e. [`RequestedVersion::Any`]) to as specific as
-/// `major.minor` (e.g. [`RequestedVersion::Exact`] to search for Python 3.10).
+/// `major.minor.micro` (e.g. [`RequestedVersion::ExactMicro`] to search for
+/// Python 3.10.2).
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RequestedVersion {
     /// Any version of Python is acceptable.
     Any,
@@ -122,6 +249,29 @@ pub enum RequestedVersion {
     MajorOnly(ComponentSize),
     /// A specific `major.minor` version of Python is required (e.g. `3.9`).
     Exact(ComponentSize, ComponentSize),
+    /// A specific `major.minor.micro` version of Python is required
+    /// (e.g. `3.9.2`).
+    ExactMicro(ComponentSize, ComponentSize, ComponentSize),
+    /// A `major.minor` version of Python or newer is required (e.g. `3.9+`).
+    AtLeast(ComponentSize, ComponentSize),
+    /// A specific `major.minor` version of a free-threaded build of Python is
+    /// required (e.g. `3.13t`).
+    ///
+    /// All other requests are only satisfied by builds which have the GIL.
+    FreeThreaded(ComponentSize, ComponentSize),
+    /// An alternative implementation of Python is required, optionally
+    /// restricted to a major version (e.g. `pypy3`) or a `major.minor` version
+    /// (e.g. `pypy3.10`).
+    ///
+    /// The minor version is only considered when a major version is
+    /// specified. All other requests are only satisfied by CPython.
+    Implementation(Implementation, Option<ComponentSize>, Option<ComponentSize>),
+    /// The newest version of Python which is not a pre-release is required
+    /// (i.e. `stable`), even when pre-releases are otherwise allowed.
+    Stable,
+    /// The newest version of Python is required, including pre-releases
+    /// (i.e. `latest`).
+    Latest,
 }
 
 impl Display for RequestedVersion {
@@ -131,6 +281,18 @@ impl Display for RequestedVersion {
             Self::Any => "Python".to_string(),
             Self::MajorOnly(major) => format!("Python {major}"),
             Self::Exact(major, minor) => format!("Python {major}.{minor}"),
+            Self::ExactMicro(major, minor, micro) => format!("Python {major}.{minor}.{micro}"),
+            Self::AtLeast(major, minor) => format!("Python {major}.{minor}+"),
+            Self::FreeThreaded(major, minor) => format!("Python {major}.{minor}t"),
+            Self::Implementation(implementation, None, _) => implementation.to_string(),
+            Self::Implementation(implementation, Some(major), None) => {
+                format!("{implementation} {major}")
+            }
+            Self::Implementation(implementation, Some(major), Some(minor)) => {
+                format!("{implementation} {major}.{minor}")
+            }
+            Self::Stable => "stable Python".to_string(),
+            Self::Latest => "latest Python".to_string(),
         };
         write!(f, "{repr}")
     }
@@ -142,16 +304,45 @@ impl FromStr for RequestedVersion {
     fn from_str(version_string: &str) -> Result<Self> {
         if version_string.is_empty() {
             Ok(Self::Any)
+        } else if version_string == "stable" {
+            Ok(Self::Stable)
+        } else if version_string == "latest" {
+            Ok(Self::Latest)
+        } else if let (implementation @ (Implementation::PyPy | Implementation::GraalPy), rest) =
+            Implementation::split_alternative_name(version_string)
+        {
+            match Self::from_str(rest)? {
+                Self::Any => Ok(Self::Implementation(implementation, None, None)),
+                Self::MajorOnly(major) => {
+                    Ok(Self::Implementation(implementation, Some(major), None))
+                }
+                Self::Exact(major, minor) => Ok(Self::Implementation(
+                    implementation,
+                    Some(major),
+                    Some(minor),
+                )),
+                _ => Err(Error::Version(VersionError::InvalidVersionSpecifier(
+                    version_string.to_string(),
+                ))),
+            }
+        } else if let Some(minimum_string) = version_string.strip_suffix('+') {
+            let minimum = ExactVersion::from_str(minimum_string)?;
+            if minimum.micro.is_some() {
+                Err(Error::Version(VersionError::InvalidVersionSpecifier(
+                    version_string.to_string(),
+                )))
+            } else {
+                Ok(Self::AtLeast(minimum.major, minimum.minor))
+            }
         } else if version_string.contains('.') {
-            let exact_version = ExactVersion::from_str(version_string)?;
-            Ok(Self::Exact(exact_version.major, exact_version.minor))
+            ExactVersion::from_str(version_string).map(Self::from)
         } else {
             match version_string.parse::<ComponentSize>() {
                 Ok(number) => Ok(Self::MajorOnly(number)),
-                Err(parse_error) => Err(Error::ParseVersionComponentError(
+                Err(parse_error) => Err(Error::Version(VersionError::ParseVersionComponentError(
                     parse_error,
                     version_string.to_string(),
-                )),
+                ))),
             }
         }
     }
@@ -183,8 +374,8 @@ impl RequestedVersion {
     /// assert_eq!(Some("PY_PYTHON3".to_string()), major_version.env_var());
     /// ```
     ///
-    /// When [`RequestedVersion::Exact`] is specified, there is no "default" to
-    /// provide/interpreter, and so no environment variable exists.
+    /// For any other request (e.g. [`RequestedVersion::Exact`]), there is no
+    /// "default" to provide/interpreter, and so no environment variable exists.
     ///
     /// ```
     /// let exact_version = python_launcher::RequestedVersion::Exact(3, 10);
@@ -200,85 +391,154 @@ impl RequestedVersion {
     }
 }
 
-/// Specifies the `major.minor` version of a Python executable.
+/// Specifies the implementation and `major.minor` version of a Python
+/// executable, along with the `micro` version when it is known and whether it
+/// is a free-threaded build.
 ///
 /// This struct is typically used to represent a found executable's version.
+///
+/// Ordering is by `major`, then `minor`, then `micro`. A version with an
+/// unknown `micro` version sorts before any version with a known `micro`
+/// version for the same `major.minor`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExactVersion {
     /// The major version of Python, e.g. `3` of `3.10`.
     pub major: ComponentSize,
     /// The minor version of Python, e.g. `10` of `3.10`.
     pub minor: ComponentSize,
+    /// The micro version of Python, e.g. `2` of `3.10.2`, if known.
+    pub micro: Option<ComponentSize>,
+    /// Whether the executable is a free-threaded build, e.g. `python3.13t`.
+    pub free_threaded: bool,
+    /// The implementation of Python, e.g. PyPy for `pypy3.10`.
+    pub implementation: Implementation,
 }
 
 impl From<ExactVersion> for RequestedVersion {
+    /// Any micro version is dropped for free-threaded builds and alternative
+    /// implementations as there is no way to request a specific micro version
+    /// of either.
     fn from(version: ExactVersion) -> Self {
-        Self::Exact(version.major, version.minor)
+        match (version.implementation, version.micro, version.free_threaded) {
+            (Implementation::CPython, _, true) => Self::FreeThreaded(version.major, version.minor),
+            (Implementation::CPython, Some(micro), false) => {
+                Self::ExactMicro(version.major, version.minor, micro)
+            }
+            (Implementation::CPython, None, false) => Self::Exact(version.major, version.minor),
+            (implementation, _, _) => {
+                Self::Implementation(implementation, Some(version.major), Some(version.minor))
+            }
+        }
     }
 }
 
 impl Display for ExactVersion {
-    /// Format to the format specifier, e.g. `3.9`.
+    /// Format to the format specifier, e.g. `3.9`, `3.9.2`, `3.13t`, or
+    /// `pypy3.10`.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.implementation != Implementation::CPython {
+            write!(f, "{}", self.implementation.executable_prefix())?;
+        }
         let major = self.major;
         let minor = self.minor;
-        write!(f, "{major}.{minor}")
+        let free_threaded = if self.free_threaded { "t" } else { "" };
+        match self.micro {
+            Some(micro) => write!(f, "{major}.{minor}.{micro}{free_threaded}"),
+            None => write!(f, "{major}.{minor}{free_threaded}"),
+        }
     }
 }
 
+fn parse_component(component_str: &str) -> Result<ComponentSize> {
+    component_str
+        .parse::<ComponentSize>()
+        .map_err(|parse_error| {
+            Error::Version(VersionError::ParseVersionComponentError(
+                parse_error,
+                component_str.to_string(),
+            ))
+        })
+}
+
 impl FromStr for ExactVersion {
     type Err = Error;
 
     fn from_str(version_string: &str) -> Result<Self> {
-        match version_string.find('.') {
-            Some(dot_index) => {
-                let major_str = &version_string[..dot_index];
-                let major = match major_str.parse::<ComponentSize>() {
-                    Ok(number) => number,
-                    Err(parse_error) => {
-                        return Err(Error::ParseVersionComponentError(
-                            parse_error,
-                            major_str.to_string(),
-                        ))
-                    }
+        let (implementation, version_string) =
+            Implementation::split_alternative_name(version_string);
+        match version_string.split_once('.') {
+            Some((major_str, rest)) => {
+                let major = parse_component(major_str)?;
+                // Free-threaded builds are only named by `major.minor`.
+                let (minor, micro, free_threaded) = match rest.split_once('.') {
+                    Some((minor_str, micro_str)) => (
+                        parse_component(minor_str)?,
+                        Some(parse_component(micro_str)?),
+                        false,
+                    ),
+                    None => match rest.strip_suffix('t') {
+                        Some(minor_str) => (parse_component(minor_str)?, None, true),
+                        None => (parse_component(rest)?, None, false),
+                    },
                 };
-                let minor_str = &version_string[dot_index + 1..];
-
-                match minor_str.parse::<ComponentSize>() {
-                    Ok(minor) => Ok(Self { major, minor }),
-                    Err(parse_error) => Err(Error::ParseVersionComponentError(
-                        parse_error,
-                        minor_str.to_string(),
-                    )),
-                }
+
+                Ok(Self {
+                    major,
+                    minor,
+                    micro,
+                    free_threaded,
+                    implementation,
+                })
             }
-            None => Err(Error::DotMissing),
+            None => Err(Error::Version(VersionError::DotMissing)),
         }
     }
 }
 
-fn acceptable_file_name(file_name: &str) -> bool {
-    file_name.len() >= "python3.0".len() && file_name.starts_with("python")
+/// Splits an acceptable executable file name into its implementation and
+/// version string, e.g. `pypy3.10` into PyPy and `3.10`.
+fn split_file_name(file_name: &str) -> Option<(Implementation, &str)> {
+    Implementation::split_name(file_name).filter(|(_, version)| version.len() >= "3.0".len())
 }
 
 impl ExactVersion {
-    /// Construct an instance of [`ExactVersion`].
+    /// Construct an instance of [`ExactVersion`] with an unknown micro version.
     pub fn new(major: ComponentSize, minor: ComponentSize) -> Self {
-        ExactVersion { major, minor }
+        ExactVersion {
+            major,
+            minor,
+            micro: None,
+            free_threaded: false,
+            implementation: Implementation::CPython,
+        }
+    }
+
+    /// Construct an instance of [`ExactVersion`] with a known micro version.
+    pub fn with_micro(major: ComponentSize, minor: ComponentSize, micro: ComponentSize) -> Self {
+        ExactVersion {
+            major,
+            minor,
+            micro: Some(micro),
+            free_threaded: false,
+            implementation: Implementation::CPython,
+        }
     }
 
-    /// Constructs a [`ExactVersion`] from a `pythonX.Y` file path.
+    /// Constructs a [`ExactVersion`] from a `pythonX.Y` or `pythonX.Y.Z` file
+    /// path (or the equivalent for an alternative [`Implementation`], e.g.
+    /// `pypyX.Y`).
     ///
     /// # Errors
     ///
     /// If the [`Path`] is missing a file name component,
-    /// [`Error::FileNameMissing`] is returned.
+    /// [`VersionError::FileNameMissing`] is returned.
     ///
     /// If the file name is not formatted appropriately,
-    /// [`Error::PathFileNameError`] is returned.
+    /// [`VersionError::PathFileNameError`] is returned.
     ///
     /// When the [`Path`] cannot be converted to a [`&str`],
-    /// [`Error::FileNameToStrError`] is returned.
+    /// [`VersionError::FileNameToStrError`] is returned.
     ///
     /// # Examples
     ///
@@ -291,18 +551,35 @@ impl ExactVersion {
     /// ```
     pub fn from_path(path: &Path) -> Result<Self> {
         path.file_name()
-            .ok_or(Error::FileNameMissing)
+            .ok_or_else(|| Error::Version(VersionError::FileNameMissing(path.to_path_buf())))
             .and_then(|raw_file_name| match raw_file_name.to_str() {
-                Some(file_name) if acceptable_file_name(file_name) => {
-                    Self::from_str(&file_name["python".len()..])
-                }
-                Some(_) => Err(Error::PathFileNameError),
-                None => Err(Error::FileNameToStrError),
+                Some(file_name) => match split_file_name(file_name) {
+                    Some((implementation, version_string)) => {
+                        Self::from_str(version_string).map(|version| Self {
+                            implementation,
+                            ..version
+                        })
+                    }
+                    None => Err(Error::Version(VersionError::PathFileNameError(
+                        path.to_path_buf(),
+                    ))),
+                },
+                None => Err(Error::Version(VersionError::FileNameToStrError(
+                    path.to_path_buf(),
+                ))),
             })
     }
 
     /// Tests whether this [`ExactVersion`] satisfies the [`RequestedVersion`].
     ///
+    /// A [`RequestedVersion::Exact`] request is satisfied by any micro
+    /// version, while [`RequestedVersion::ExactMicro`] requires the micro
+    /// version to be known and to match.
+    ///
+    /// Free-threaded builds only satisfy [`RequestedVersion::FreeThreaded`],
+    /// while builds with the GIL satisfy every other request. Alternative
+    /// implementations only satisfy [`RequestedVersion::Implementation`].
+    ///
     /// # Examples
     ///
     /// ```
@@ -314,208 +591,3724 @@ impl ExactVersion {
     /// assert!(py3_10.supports(any_version));
     /// assert!(py3_10.supports(py3_version));
     /// assert!(py3_10.supports(py3_10_version));
+    ///
+    /// let py3_10_2 = python_launcher::ExactVersion::with_micro(3, 10, 2);
+    /// let py3_10_2_version = python_launcher::RequestedVersion::ExactMicro(3, 10, 2);
+    ///
+    /// assert!(py3_10_2.supports(py3_10_version));
+    /// assert!(py3_10_2.supports(py3_10_2_version));
+    /// assert!(!py3_10.supports(py3_10_2_version));
     /// ```
     pub fn supports(&self, requested: RequestedVersion) -> bool {
+        let requested_implementation = match requested {
+            RequestedVersion::Implementation(implementation, _, _) => implementation,
+            _ => Implementation::CPython,
+        };
+        if self.implementation != requested_implementation
+            || self.free_threaded != matches!(requested, RequestedVersion::FreeThreaded(_, _))
+        {
+            return false;
+        }
+
         match requested {
-            RequestedVersion::Any => true,
+            RequestedVersion::Any | RequestedVersion::Stable | RequestedVersion::Latest => true,
             RequestedVersion::MajorOnly(major_version) => self.major == major_version,
             RequestedVersion::Exact(major_version, minor_version) => {
                 self.major == major_version && self.minor == minor_version
             }
+            RequestedVersion::ExactMicro(major_version, minor_version, micro_version) => {
+                self.major == major_version
+                    && self.minor == minor_version
+                    && self.micro == Some(micro_version)
+            }
+            RequestedVersion::AtLeast(major_version, minor_version) => {
+                (self.major, self.minor) >= (major_version, minor_version)
+            }
+            RequestedVersion::FreeThreaded(major_version, minor_version) => {
+                self.major == major_version && self.minor == minor_version
+            }
+            RequestedVersion::Implementation(_, major_version, minor_version) => {
+                match (major_version, minor_version) {
+                    (None, _) => true,
+                    (Some(major_version), None) => self.major == major_version,
+                    (Some(major_version), Some(minor_version)) => {
+                        self.major == major_version && self.minor == minor_version
+                    }
+                }
+            }
         }
     }
 }
 
-fn env_path() -> Vec<PathBuf> {
-    // Would love to have a return type of `impl Iterator<Item = PathBuf>
-    // and return just SplitPaths and iter::empty(), but Rust
-    // complains about differing return types.
-    match env::var_os("PATH") {
-        Some(path_val) => env::split_paths(&path_val).collect(),
-        None => Vec::new(),
-    }
+/// A comparison operator used in a [`VersionClause`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ComparisonOperator {
+    /// `==`; supports a trailing `.*` wildcard.
+    Equal,
+    /// `!=`; supports a trailing `.*` wildcard.
+    NotEqual,
+    /// `<`
+    LessThan,
+    /// `<=`
+    LessThanOrEqual,
+    /// `>`
+    GreaterThan,
+    /// `>=`
+    GreaterThanOrEqual,
+    /// `~=`, i.e. the "compatible release" operator.
+    Compatible,
 }
 
-fn flatten_directories(
-    directories: impl IntoIterator<Item = PathBuf>,
-) -> impl Iterator<Item = PathBuf> {
-    directories
-        .into_iter()
-        .filter_map(|p| p.read_dir().ok()) // Filter to Ok(ReadDir).
-        .flatten() // Flatten out `for DirEntry in ReadDir`.
-        .filter_map(|e| e.ok()) // Filter to Ok(DirEntry).
-        .map(|e| e.path()) // Get the PathBuf from the DirEntry.
+impl Display for ComparisonOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::LessThan => "<",
+            Self::LessThanOrEqual => "<=",
+            Self::GreaterThan => ">",
+            Self::GreaterThanOrEqual => ">=",
+            Self::Compatible => "~=",
+        };
+        write!(f, "{repr}")
+    }
 }
 
-fn all_executables_in_paths(
-    paths: impl IntoIterator<Item = PathBuf>,
-) -> HashMap<ExactVersion, PathBuf> {
-    let mut executables = HashMap::new();
-    paths.into_iter().for_each(|path| {
-        ExactVersion::from_path(&path).map_or((), |version| {
-            executables.entry(version).or_insert(path);
-        })
-    });
-
-    let found_executables = executables.values();
-    log::debug!("Found executables: {found_executables:?}",);
-    executables
+/// A single comparison within a [`VersionSpecifier`], e.g. `>=3.9`.
+///
+/// Only the release segment of a version (i.e. `major[.minor[.micro]]`) is
+/// supported.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VersionClause {
+    /// How to compare against [`VersionClause::release`].
+    pub operator: ComparisonOperator,
+    /// The release segment being compared against, e.g. `[3, 9]` for `3.9`.
+    pub release: Vec<ComponentSize>,
+    /// Whether the release ended in `.*` (only valid for
+    /// [`ComparisonOperator::Equal`] and [`ComparisonOperator::NotEqual`]).
+    pub wildcard: bool,
 }
 
-/// Finds all possible Python executables on `PATH`.
-pub fn all_executables() -> HashMap<ExactVersion, PathBuf> {
-    log::info!("Checking PATH environment variable");
-    let path_entries = env_path();
-    log::debug!("PATH: {path_entries:?}");
-    let paths = flatten_directories(path_entries);
-    all_executables_in_paths(paths)
+impl Display for VersionClause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = self.operator;
+        let release = self
+            .release
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join(".");
+        let wildcard = if self.wildcard { ".*" } else { "" };
+        write!(f, "{operator}{release}{wildcard}")
+    }
 }
 
-fn find_executable_in_hashmap(
-    requested: RequestedVersion,
-    found_executables: &HashMap<ExactVersion, PathBuf>,
-) -> Option<PathBuf> {
-    let mut iter = found_executables.iter();
-    match requested {
-        RequestedVersion::Any => iter.max(),
-        RequestedVersion::MajorOnly(_) => iter.filter(|pair| pair.0.supports(requested)).max(),
-        RequestedVersion::Exact(_, _) => iter.find(|pair| pair.0.supports(requested)),
+impl FromStr for VersionClause {
+    type Err = Error;
+
+    fn from_str(clause_string: &str) -> Result<Self> {
+        let clause = clause_string.trim();
+        // Two-character operators must be checked before their one-character
+        // prefixes.
+        let operators = [
+            ("==", ComparisonOperator::Equal),
+            ("!=", ComparisonOperator::NotEqual),
+            ("<=", ComparisonOperator::LessThanOrEqual),
+            (">=", ComparisonOperator::GreaterThanOrEqual),
+            ("~=", ComparisonOperator::Compatible),
+            ("<", ComparisonOperator::LessThan),
+            (">", ComparisonOperator::GreaterThan),
+        ];
+        let (operator, version_str) = operators
+            .iter()
+            .find_map(|(prefix, operator)| {
+                clause
+                    .strip_prefix(prefix)
+                    .map(|rest| (*operator, rest.trim()))
+            })
+            .ok_or_else(|| {
+                Error::Version(VersionError::InvalidVersionSpecifier(
+                    clause_string.to_string(),
+                ))
+            })?;
+
+        let (version_str, wildcard) = match version_str.strip_suffix(".*") {
+            Some(prefix) => (prefix, true),
+            None => (version_str, false),
+        };
+
+        if wildcard
+            && !matches!(
+                operator,
+                ComparisonOperator::Equal | ComparisonOperator::NotEqual
+            )
+        {
+            return Err(Error::Version(VersionError::InvalidVersionSpecifier(
+                clause_string.to_string(),
+            )));
+        }
+
+        let release = version_str
+            .split('.')
+            .map(parse_component)
+            .collect::<Result<Vec<ComponentSize>>>()?;
+
+        if release.len() > 3 || (operator == ComparisonOperator::Compatible && release.len() < 2) {
+            return Err(Error::Version(VersionError::InvalidVersionSpecifier(
+                clause_string.to_string(),
+            )));
+        }
+
+        Ok(Self {
+            operator,
+            release,
+            wildcard,
+        })
     }
-    .map(|pair| pair.1.clone())
 }
 
-/// Attempts to find an executable that satisfies a specified
-/// [`RequestedVersion`] on `PATH`.
-pub fn find_executable(requested: RequestedVersion) -> Option<PathBuf> {
-    let found_executables = all_executables();
-    find_executable_in_hashmap(requested, &found_executables)
+/// Compares two release segments, treating missing trailing components as `0`.
+fn compare_releases(left: &[ComponentSize], right: &[ComponentSize]) -> Ordering {
+    let length = left.len().max(right.len());
+    let padded = |release: &[ComponentSize]| {
+        let mut padded = release.to_vec();
+        padded.resize(length, 0);
+        padded
+    };
+    padded(left).cmp(&padded(right))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    use std::cmp::Ordering;
+/// Checks whether `release` starts with `prefix`, treating missing trailing
+/// components of `release` as `0`.
+fn release_has_prefix(release: &[ComponentSize], prefix: &[ComponentSize]) -> bool {
+    prefix
+        .iter()
+        .enumerate()
+        .all(|(index, component)| release.get(index).copied().unwrap_or(0) == *component)
+}
 
-    use test_case::test_case;
+impl VersionClause {
+    /// Creates a clause which is satisfied by every version except those
+    /// satisfying a [`RequestedVersion`], e.g. `!=3.8.*` for
+    /// [`RequestedVersion::Exact(3, 8)`](RequestedVersion::Exact).
+    ///
+    /// Only [`RequestedVersion::MajorOnly`], [`RequestedVersion::Exact`],
+    /// [`RequestedVersion::ExactMicro`], and [`RequestedVersion::AtLeast`] can
+    /// be excluded; [`VersionError::InvalidVersionSpecifier`] is returned
+    /// otherwise.
+    pub fn excluding(requested: RequestedVersion) -> Result<Self> {
+        let (operator, release, wildcard) = match requested {
+            RequestedVersion::MajorOnly(major) => (ComparisonOperator::NotEqual, vec![major], true),
+            RequestedVersion::Exact(major, minor) => {
+                (ComparisonOperator::NotEqual, vec![major, minor], true)
+            }
+            RequestedVersion::ExactMicro(major, minor, micro) => (
+                ComparisonOperator::NotEqual,
+                vec![major, minor, micro],
+                false,
+            ),
+            RequestedVersion::AtLeast(major, minor) => {
+                (ComparisonOperator::LessThan, vec![major, minor], false)
+            }
+            _ => {
+                return Err(Error::Version(VersionError::InvalidVersionSpecifier(
+                    format!("!{requested}"),
+                )))
+            }
+        };
 
-    #[test_case(RequestedVersion::Any => "Python" ; "Any")]
-    #[test_case(RequestedVersion::MajorOnly(3) => "Python 3" ; "Major")]
-    #[test_case(RequestedVersion::Exact(3, 8) => "Python 3.8" ; "Exact/major.minor")]
-    fn requestedversion_to_string_tests(requested_version: RequestedVersion) -> String {
-        requested_version.to_string()
+        Ok(Self {
+            operator,
+            release,
+            wildcard,
+        })
     }
 
-    #[test_case(".3" => matches Err(Error::ParseVersionComponentError(_, _)) ; "missing major version is an error")]
-    #[test_case("3." => matches Err(Error::ParseVersionComponentError(_, _)) ; "missing minor version is an error")]
-    #[test_case("h" => matches Err(Error::ParseVersionComponentError(_, _)) ; "non-number, non-emptry string is an error")]
-    #[test_case("3.b" => matches Err(Error::ParseVersionComponentError(_, _)) ; "major.minor where minor is a non-number is an error")]
-    #[test_case("a.7" => matches Err(Error::ParseVersionComponentError(_, _)) ; "major.minor where major is a non-number is an error")]
-    #[test_case("" => Ok(RequestedVersion::Any) ; "empty string is Any")]
-    #[test_case("3" => Ok(RequestedVersion::MajorOnly(3)) ; "major-only version")]
-    #[test_case("3.8" => Ok(RequestedVersion::Exact(3, 8)) ; "major.minor")]
-    #[test_case("42.13" => Ok(RequestedVersion::Exact(42, 13)) ; "double digit version components")]
-    #[test_case("3.6.5" => matches Err(Error::ParseVersionComponentError(_, _)) ; "specifying a micro version is an error")]
-    fn requestedversion_from_str_tests(version_str: &str) -> Result<RequestedVersion> {
-        RequestedVersion::from_str(version_str)
+    /// Tests whether the [`ExactVersion`] satisfies this clause.
+    ///
+    /// An unknown micro version is treated as `0`.
+    pub fn contains(&self, version: &ExactVersion) -> bool {
+        let candidate = [version.major, version.minor, version.micro.unwrap_or(0)];
+        let ordering = compare_releases(&candidate, &self.release);
+
+        match self.operator {
+            ComparisonOperator::Equal if self.wildcard => {
+                release_has_prefix(&candidate, &self.release)
+            }
+            ComparisonOperator::NotEqual if self.wildcard => {
+                !release_has_prefix(&candidate, &self.release)
+            }
+            ComparisonOperator::Equal => ordering == Ordering::Equal,
+            ComparisonOperator::NotEqual => ordering != Ordering::Equal,
+            ComparisonOperator::LessThan => ordering == Ordering::Less,
+            ComparisonOperator::LessThanOrEqual => ordering != Ordering::Greater,
+            ComparisonOperator::GreaterThan => ordering == Ordering::Greater,
+            ComparisonOperator::GreaterThanOrEqual => ordering != Ordering::Less,
+            ComparisonOperator::Compatible => {
+                let prefix = &self.release[..self.release.len() - 1];
+                ordering != Ordering::Less && release_has_prefix(&candidate, prefix)
+            }
+        }
     }
+}
 
-    #[test_case(RequestedVersion::Any => Some("PY_PYTHON".to_string()) ; "Any is PY_PYTHON")]
-    #[test_case(RequestedVersion::MajorOnly(3) => Some("PY_PYTHON3".to_string()) ; "major-only is PY_PYTHON{major}")]
-    #[test_case(RequestedVersion::MajorOnly(42) => Some("PY_PYTHON42".to_string()) ; "double-digit major component")]
-    #[test_case(RequestedVersion::Exact(42, 13) => None ; "exact/major.minor has no environment variable")]
-    fn requstedversion_env_var_tests(requested_version: RequestedVersion) -> Option<String> {
-        requested_version.env_var()
+/// A set of version constraints using the syntax of
+/// [PEP 440 version specifiers], e.g. `>=3.9,<3.12` or `~=3.10`.
+///
+/// A version must satisfy every clause to be contained within the specifier;
+/// an empty specifier contains every version. As PEP 440 has no concept of
+/// free-threaded builds or implementations,
+/// [`VersionSpecifier::free_threaded`] determines whether only free-threaded
+/// builds or only builds with the GIL are contained, and
+/// [`VersionSpecifier::implementation`] determines which implementation is
+/// contained.
+///
+/// Pre-release versions of Python are only contained when
+/// [`VersionSpecifier::prereleases`] is set; see [`find_executable`] for how
+/// pre-releases are detected.
+///
+/// Any [`RequestedVersion`] can be converted into a [`VersionSpecifier`], which
+/// is how [`find_executable`] accepts either type.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+///
+/// let specifier = python_launcher::VersionSpecifier::from_str(">=3.9,<3.12").unwrap();
+///
+/// assert!(specifier.contains(&python_launcher::ExactVersion::new(3, 10)));
+/// assert!(!specifier.contains(&python_launcher::ExactVersion::new(3, 12)));
+/// ```
+///
+/// [PEP 440 version specifiers]: https://peps.python.org/pep-0440/#version-specifiers
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct VersionSpecifier {
+    /// The clauses which must all be satisfied.
+    pub clauses: Vec<VersionClause>,
+    /// Whether only free-threaded builds are acceptable.
+    pub free_threaded: bool,
+    /// The implementation which is acceptable.
+    pub implementation: Implementation,
+    /// Whether pre-release versions of Python are acceptable.
+    pub prereleases: bool,
+}
+
+impl Display for VersionSpecifier {
+    /// Format to the PEP 440 representation, e.g. `>=3.9,<3.12`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clauses = self
+            .clauses
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join(",");
+        write!(f, "{clauses}")
     }
+}
 
-    #[test]
-    fn test_requestedversion_from_exactversion() {
-        assert_eq!(
-            RequestedVersion::from(ExactVersion {
-                major: 42,
-                minor: 13
-            }),
-            RequestedVersion::Exact(42, 13)
-        );
+impl FromStr for VersionSpecifier {
+    type Err = Error;
+
+    fn from_str(specifier_string: &str) -> Result<Self> {
+        if specifier_string.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        specifier_string
+            .split(',')
+            .map(VersionClause::from_str)
+            .collect::<Result<Vec<VersionClause>>>()
+            .map(|clauses| Self {
+                clauses,
+                ..Self::default()
+            })
     }
+}
 
-    #[test] // For some reason, having Ordering breaks test-case 1.0.0.
-    fn exactversion_comparisons() {
-        let py2_7 = ExactVersion { major: 2, minor: 7 };
-        let py3_0 = ExactVersion { major: 3, minor: 0 };
-        let py3_6 = ExactVersion { major: 3, minor: 6 };
-        let py3_10 = ExactVersion {
-            major: 3,
-            minor: 10,
+impl From<RequestedVersion> for VersionSpecifier {
+    /// Requests for a specific `major.minor` version (e.g.
+    /// [`RequestedVersion::Exact`]) allow for pre-releases as the request is
+    /// explicit about what version is desired, as does
+    /// [`RequestedVersion::Latest`].
+    fn from(requested: RequestedVersion) -> Self {
+        let implementation = match requested {
+            RequestedVersion::Implementation(implementation, _, _) => implementation,
+            _ => Implementation::CPython,
+        };
+        let (operator, release, wildcard) = match requested {
+            RequestedVersion::Any
+            | RequestedVersion::Stable
+            | RequestedVersion::Latest
+            | RequestedVersion::Implementation(_, None, _) => {
+                return Self {
+                    implementation,
+                    prereleases: requested == RequestedVersion::Latest,
+                    ..Self::default()
+                }
+            }
+            RequestedVersion::Implementation(_, Some(major), None) => {
+                (ComparisonOperator::Equal, vec![major], true)
+            }
+            RequestedVersion::Implementation(_, Some(major), Some(minor)) => {
+                (ComparisonOperator::Equal, vec![major, minor], true)
+            }
+            RequestedVersion::MajorOnly(major) => (ComparisonOperator::Equal, vec![major], true),
+            RequestedVersion::Exact(major, minor)
+            | RequestedVersion::FreeThreaded(major, minor) => {
+                (ComparisonOperator::Equal, vec![major, minor], true)
+            }
+            RequestedVersion::ExactMicro(major, minor, micro) => {
+                (ComparisonOperator::Equal, vec![major, minor, micro], false)
+            }
+            RequestedVersion::AtLeast(major, minor) => (
+                ComparisonOperator::GreaterThanOrEqual,
+                vec![major, minor],
+                false,
+            ),
         };
 
-        // ==
-        assert_eq!(py3_10.cmp(&py3_10), Ordering::Equal);
-        // <
-        assert_eq!(py3_0.cmp(&py3_6), Ordering::Less);
-        // >
-        assert_eq!(py3_6.cmp(&py3_0), Ordering::Greater);
-        // Differ by major version.
-        assert_eq!(py2_7.cmp(&py3_0), Ordering::Less);
-        assert_eq!(py3_0.cmp(&py2_7), Ordering::Greater);
-        // Sort order different from lexicographic order.
-        assert_eq!(py3_6.cmp(&py3_10), Ordering::Less);
-        assert_eq!(py3_10.cmp(&py3_6), Ordering::Greater);
-    }
+        let prereleases = operator == ComparisonOperator::Equal && release.len() >= 2;
 
-    #[test_case(3, 8 => "3.8" ; "single digits")]
-    #[test_case(42, 13 => "42.13" ; "double digits")]
-    fn exactversion_to_string_tests(major: ComponentSize, minor: ComponentSize) -> String {
-        ExactVersion { major, minor }.to_string()
+        Self {
+            clauses: vec![VersionClause {
+                operator,
+                release,
+                wildcard,
+            }],
+            free_threaded: matches!(requested, RequestedVersion::FreeThreaded(_, _)),
+            implementation,
+            prereleases,
+        }
     }
+}
 
-    #[test_case("" => Err(Error::DotMissing) ; "empty string is an error")]
-    #[test_case("3" => Err(Error::DotMissing) ; "major-only version is an error")]
-    #[test_case(".7" => matches Err(Error::ParseVersionComponentError(_, _)) ; "missing major version is an error")]
-    #[test_case("3." => matches Err(Error::ParseVersionComponentError(_, _)) ; "missing minor version is an error")]
-    #[test_case("3.Y" => matches Err(Error::ParseVersionComponentError(_, _)) ; "non-digit minor version is an error")]
-    #[test_case("X.7" => matches Err(Error::ParseVersionComponentError(_, _)) ; "non-digit major version is an error")]
-    #[test_case("42.13" => Ok(ExactVersion {major: 42, minor: 13 }) ; "double digit version components")]
-    fn exactversion_from_str_tests(version_str: &str) -> Result<ExactVersion> {
-        ExactVersion::from_str(version_str)
+impl From<&VersionSpecifier> for VersionSpecifier {
+    fn from(specifier: &VersionSpecifier) -> Self {
+        specifier.clone()
     }
+}
 
-    #[test_case("/" => Err(Error::FileNameMissing) ; "path missing a file name is an error")]
-    #[test_case("/notpython" => Err(Error::PathFileNameError) ; "path not ending with 'python' is an error")]
-    #[test_case("/python3" => Err(Error::PathFileNameError) ; "filename lacking a minor component is an error")]
-    #[test_case("/pythonX.Y" => matches Err(Error::ParseVersionComponentError(_, _)) ; "filename with non-digit version is an error")]
-    #[test_case("/python42.13" => Ok(ExactVersion { major: 42, minor: 13 }) ; "double digit version components")]
-    fn exactversion_from_path_tests(path: &str) -> Result<ExactVersion> {
-        ExactVersion::from_path(&PathBuf::from(path))
+impl VersionSpecifier {
+    /// Tests whether the [`ExactVersion`] satisfies every clause and matches
+    /// both [`VersionSpecifier::free_threaded`] and
+    /// [`VersionSpecifier::implementation`].
+    pub fn contains(&self, version: &ExactVersion) -> bool {
+        version.implementation == self.implementation
+            && version.free_threaded == self.free_threaded
+            && self.clauses.iter().all(|clause| clause.contains(version))
     }
+}
 
-    #[test]
-    fn exactversion_from_path_invalid_utf8() {
-        // From https://doc.rust-lang.org/std/ffi/struct.OsStr.html#examples-2.
-        use std::ffi::OsStr;
-        use std::os::unix::ffi::OsStrExt;
+/// An ordered list of [`RequestedVersion`] where earlier entries are preferred
+/// over later ones, e.g. `3.12,3.11,3` to try Python 3.12, then Python 3.11,
+/// and then any Python 3.
+///
+/// Versions may also be excluded from every entry by prefixing them with `!`,
+/// e.g. `3,!3.8` for any Python 3 except Python 3.8.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+///
+/// use python_launcher::{RequestedVersion, VersionPreferenceList};
+///
+/// let preferences = VersionPreferenceList::from_str("3.12,3.11,3").unwrap();
+///
+/// assert_eq!(
+///     preferences.versions,
+///     vec![
+///         RequestedVersion::Exact(3, 12),
+///         RequestedVersion::Exact(3, 11),
+///         RequestedVersion::MajorOnly(3),
+///     ]
+/// );
+///
+/// let preferences = VersionPreferenceList::from_str("3,!3.8").unwrap();
+///
+/// assert_eq!(preferences.versions, vec![RequestedVersion::MajorOnly(3)]);
+/// assert_eq!(preferences.excluded[0].to_string(), "!=3.8.*");
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct VersionPreferenceList {
+    /// The requested versions, from most to least preferred.
+    pub versions: Vec<RequestedVersion>,
+    /// Clauses which every found version must satisfy regardless of which
+    /// version was requested (see [`VersionClause::excluding`]).
+    pub excluded: Vec<VersionClause>,
+    /// Whether pre-release versions of Python are acceptable for every
+    /// requested version (see [`VersionSpecifier::prereleases`]).
+    pub prereleases: bool,
+}
 
-        let source = [0x66, 0x6f, 0x80, 0x6f];
-        let os_str = OsStr::from_bytes(&source[..]);
-        let path = PathBuf::from(os_str);
+impl Display for VersionPreferenceList {
+    /// Format to the [`RequestedVersion`] representations separated by commas,
+    /// followed by any exclusions, e.g. `Python 3.12, Python 3 (!=3.8.*)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let versions = self
+            .versions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join(", ");
+        if self.excluded.is_empty() {
+            write!(f, "{versions}")
+        } else {
+            let excluded = self
+                .excluded
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(",");
+            write!(f, "{versions} ({excluded})")
+        }
+    }
+}
+
+impl FromStr for VersionPreferenceList {
+    type Err = Error;
+
+    /// Parse a comma-separated list of versions as accepted by
+    /// [`RequestedVersion::from_str`], e.g. `3.12,3.11,3`. Versions prefixed
+    /// with `!` are excluded instead of requested; if only exclusions are
+    /// specified then [`RequestedVersion::Any`] is requested.
+    ///
+    /// Empty entries are rejected as they would otherwise silently mean
+    /// [`RequestedVersion::Any`].
+    fn from_str(preferences_string: &str) -> Result<Self> {
+        let mut preferences = Self::default();
+        for version_string in preferences_string.split(',') {
+            match version_string.trim() {
+                "" => {
+                    return Err(Error::Version(VersionError::InvalidVersionSpecifier(
+                        preferences_string.to_string(),
+                    )))
+                }
+                trimmed => match trimmed.strip_prefix('!') {
+                    Some(excluded_string) => preferences.excluded.push(VersionClause::excluding(
+                        RequestedVersion::from_str(excluded_string)?,
+                    )?),
+                    None => preferences
+                        .versions
+                        .push(RequestedVersion::from_str(trimmed)?),
+                },
+            }
+        }
+
+        if preferences.versions.is_empty() {
+            preferences.versions.push(RequestedVersion::Any);
+        }
+
+        Ok(preferences)
+    }
+}
+
+impl From<RequestedVersion> for VersionPreferenceList {
+    fn from(requested: RequestedVersion) -> Self {
+        Self {
+            versions: vec![requested],
+            ..Self::default()
+        }
+    }
+}
+
+fn env_path(env: &dyn EnvProvider, env_var: &str) -> Vec<PathBuf> {
+    // Would love to have a return type of `impl Iterator<Item = PathBuf>
+    // and return just SplitPaths and iter::empty(), but Rust
+    // complains about differing return types.
+    match env.var_os(env_var) {
+        Some(path_val) => split_paths(&path_val),
+        None => Vec::new(),
+    }
+}
+
+/// Splits a list of paths like `PATH`, i.e. separated by `:`.
+#[cfg(not(target_os = "wasi"))]
+fn split_paths(paths: &OsStr) -> Vec<PathBuf> {
+    env::split_paths(paths).collect()
+}
+
+/// Splits a list of paths like `PATH`, i.e. separated by `:`, as
+/// [`env::split_paths`] is unsupported under WASI.
+#[cfg(target_os = "wasi")]
+fn split_paths(paths: &OsStr) -> Vec<PathBuf> {
+    paths
+        .as_bytes()
+        .split(|byte| *byte == b':')
+        .map(|path| PathBuf::from(OsStr::from_bytes(path)))
+        .collect()
+}
+
+/// Directories searched in place of `PATH` when it is unset or empty (e.g.
+/// under cron or in minimal containers).
+const DEFAULT_PATH: [&str; 3] = ["/usr/local/bin", "/usr/bin", "/bin"];
+
+fn path_or_default(path: Vec<PathBuf>) -> Vec<PathBuf> {
+    if path
+        .iter()
+        .all(|directory| directory.as_os_str().is_empty())
+    {
+        log::info!("PATH is unset or empty; using {DEFAULT_PATH:?}");
+        DEFAULT_PATH.iter().map(PathBuf::from).collect()
+    } else {
+        path
+    }
+}
+
+/// The entries of every directory in turn, skipping directories which can't
+/// be read.
+fn directory_entries<'a>(
+    file_system: &'a dyn FileSystem,
+    directories: impl IntoIterator<Item = PathBuf> + 'a,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    directories
+        .into_iter()
+        .filter_map(move |directory| file_system.read_dir(&directory).ok())
+        .flatten()
+}
+
+/// The version of an entry of a directory which is an executable for it,
+/// i.e. is named after the version and is a file (following symlinks).
+///
+/// Dangling symlinks aren't files, so they are skipped (and logged): a
+/// `python3.11` left behind by an uninstalled Python isn't an executable for
+/// 3.11 and must not shadow one in a later directory.
+fn executable_version(file_system: &dyn FileSystem, path: &Path) -> Option<ExactVersion> {
+    let version = ExactVersion::from_path(path).ok()?;
+    if file_system.is_file(path) {
+        Some(version)
+    } else {
+        log::debug!("Skipping {}, which is not a file", path.display());
+        None
+    }
+}
+
+fn all_executables_in_paths(
+    file_system: &dyn FileSystem,
+    paths: impl IntoIterator<Item = PathBuf>,
+) -> HashMap<ExactVersion, PathBuf> {
+    let mut executables = HashMap::new();
+    paths.into_iter().for_each(|path| {
+        if let Some(version) = executable_version(file_system, &path) {
+            executables.entry(version).or_insert(path);
+        }
+    });
+
+    let found_executables = executables.values();
+    log::debug!("Found executables: {found_executables:?}",);
+    executables
+}
+
+/// Finds the Python executables in the specified directories, with earlier
+/// directories taking precedence when several have the same version.
+///
+/// Unlike [`all_executables`], only the directories are searched: neither
+/// `PATH`, the configuration, nor any other [source](InterpreterSource) is
+/// consulted and no [sources are disabled](SOURCES). This makes it possible to
+/// inspect the interpreters of e.g. a container image or a chroot via its
+/// root, as nothing is run:
+///
+/// ```no_run
+/// use std::path::Path;
+///
+/// let root = Path::new("/var/lib/machines/debian");
+/// let executables = python_launcher::executables_in_directories(
+///     ["usr/local/bin", "usr/bin"].iter().map(|dir| root.join(dir)),
+/// );
+/// ```
+///
+/// Directories which can't be read are skipped, as are entries which aren't
+/// files, e.g. dangling symlinks, which are logged at the debug level.
+pub fn executables_in_directories(
+    directories: impl IntoIterator<Item = impl Into<PathBuf>>,
+) -> HashMap<ExactVersion, PathBuf> {
+    executables_in_directories_of(&OsFileSystem, directories)
+}
+
+/// Finds the Python executables in the specified directories of a
+/// [`FileSystem`], as [`executables_in_directories`] does for the real one.
+///
+/// ```
+/// use python_launcher::{filesystem::MemoryFileSystem, ExactVersion};
+///
+/// let file_system = MemoryFileSystem::new()
+///     .file("/usr/bin/python3.12")
+///     .symlink("/usr/bin/python3.11", "/opt/python3.11/bin/python3.11");
+/// let executables =
+///     python_launcher::executables_in_directories_of(&file_system, ["/usr/bin"]);
+///
+/// assert_eq!(executables.len(), 1);
+/// assert!(executables.contains_key(&ExactVersion::new(3, 12)));
+/// ```
+pub fn executables_in_directories_of(
+    file_system: &dyn FileSystem,
+    directories: impl IntoIterator<Item = impl Into<PathBuf>>,
+) -> HashMap<ExactVersion, PathBuf> {
+    let directories = directories
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<PathBuf>>();
+    all_executables_in_paths(file_system, directory_entries(file_system, directories))
+}
+
+/// The directory containing the wrappers for the apps of installed snaps.
+const SNAP_BIN_DIR: &str = "/snap/bin";
+
+/// Parses the version of Python from the name of a snap's wrapper, e.g.
+/// `python38` for Python 3.8 or `python310.python3` for Python 3.10.
+///
+/// Snap names can't contain `.`, so the major version is assumed to be a
+/// single digit. Only apps whose names start with `python` are considered to
+/// avoid e.g. `python38.pip`.
+fn snap_wrapper_version(file_name: &str) -> Option<ExactVersion> {
+    let (snap_name, app_name) = match file_name.split_once('.') {
+        Some((snap_name, app_name)) => (snap_name, Some(app_name)),
+        None => (file_name, None),
+    };
+    if !app_name.map_or(true, |app_name| app_name.starts_with("python")) {
+        return None;
+    }
+
+    let digits = snap_name.strip_prefix("python")?;
+    if digits.len() < 2 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (major, minor) = digits.split_at(1);
+    Some(ExactVersion::new(major.parse().ok()?, minor.parse().ok()?))
+}
+
+fn snap_executables(
+    file_system: &dyn FileSystem,
+    snap_bin_dir: &Path,
+) -> Vec<(ExactVersion, PathBuf)> {
+    let mut executables = directory_entries(file_system, [snap_bin_dir.to_path_buf()])
+        .filter_map(|path| {
+            ExactVersion::from_path(&path)
+                .ok()
+                .or_else(|| snap_wrapper_version(path.file_name()?.to_str()?))
+                .map(|version| (version, path))
+        })
+        .collect::<Vec<(ExactVersion, PathBuf)>>();
+    // Make the search order deterministic when wrappers overlap.
+    executables.sort_unstable();
+    executables
+}
+
+/// The names of all sources of environments and executables, in the order
+/// they are searched.
+///
+/// Any of these may be disabled via the comma-separated `PY_DISABLE_SOURCES`
+/// environment variable or the `disable-sources` key of the
+/// [configuration file](config::Config). The `conda`, `poetry`, and `pipenv`
+/// sources are of [environments](all_environments) rather than executables.
+pub const SOURCES: [&str; 16] = [
+    "conda",
+    "poetry",
+    "pipenv",
+    "path",
+    "py-python-path",
+    "config",
+    "pyenv",
+    "asdf",
+    "uv",
+    "rye",
+    "github-actions",
+    "manylinux",
+    "launcher",
+    "nix",
+    "snap",
+    "wsl",
+];
+
+/// Combines the disabled sources of the configuration file with those of the
+/// `PY_DISABLE_SOURCES` environment variable.
+///
+/// Unknown sources are ignored (with a warning) so that disabling a source
+/// which only newer versions of the launcher support is not an error.
+fn disabled_sources(config_sources: &[String], env_sources: Option<&str>) -> Vec<String> {
+    let mut disabled = config_sources.to_vec();
+    disabled.extend(
+        env_sources
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|source| !source.is_empty())
+            .map(String::from),
+    );
+    for source in &disabled {
+        if !SOURCES.contains(&source.as_str()) {
+            log::warn!("Unknown source '{source}' cannot be disabled");
+        }
+    }
+    disabled
+}
+
+/// Loads the configuration file, falling back to the default configuration if
+/// it is invalid.
+fn load_config() -> config::Config {
+    load_config_in(&ProcessEnv)
+}
+
+/// Loads the configuration for an environment like [`load_config`].
+fn load_config_in(env: &dyn EnvProvider) -> config::Config {
+    load_config_of(env, &OsFileSystem)
+}
+
+/// Loads the configuration for an environment from a [`FileSystem`] like
+/// [`load_config`].
+fn load_config_of(env: &dyn EnvProvider, file_system: &dyn FileSystem) -> config::Config {
+    let files = config::ConfigFiles::from_env(env);
+    config::Config::load_files_of(env, file_system, &files)
+        .map(|(config, _)| config)
+        .unwrap_or_else(|error| {
+            log::warn!("{error}");
+            config::Config::default()
+        })
+}
+
+fn source_enabled(source: &str, disabled: &[String]) -> bool {
+    let enabled = !disabled
+        .iter()
+        .any(|disabled_source| disabled_source == source);
+    if !enabled {
+        log::info!("Skipping the disabled '{source}' source");
+    }
+    enabled
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, ret)
+)]
+fn env_disabled_sources(env: &dyn EnvProvider) -> Vec<String> {
+    Search::new(env, &load_config_in(env)).disabled_sources()
+}
+
+/// Searches an environment using a configuration which has already been
+/// loaded, so that searching for executables, environments, and the default
+/// version doesn't reload (and re-log) the configuration file each time.
+///
+/// Files and directories are read from the real file system unless
+/// [another](Search::with_file_system) is chosen, and what is found is only
+/// [cached](cache) when [asked](Search::with_cache).
+#[derive(Clone, Debug)]
+pub(crate) struct Search<'a> {
+    pub(crate) env: &'a dyn EnvProvider,
+    pub(crate) config: &'a config::Config,
+    pub(crate) file_system: Arc<dyn FileSystem>,
+    pub(crate) cache: bool,
+}
+
+impl<'a> Search<'a> {
+    pub(crate) fn new(env: &'a dyn EnvProvider, config: &'a config::Config) -> Self {
+        Self {
+            env,
+            config,
+            file_system: Arc::new(OsFileSystem),
+            cache: false,
+        }
+    }
+
+    /// Reuses and updates the [cache](cache) when searching for
+    /// [all executables](Search::all_executables).
+    pub(crate) fn with_cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Reads from `file_system` instead of the real file system.
+    pub(crate) fn with_file_system(mut self, file_system: Arc<dyn FileSystem>) -> Self {
+        self.file_system = file_system;
+        self
+    }
+
+    /// The sources disabled by the configuration or `PY_DISABLE_SOURCES`.
+    pub(crate) fn disabled_sources(&self) -> Vec<String> {
+        disabled_sources(
+            &self.config.disabled_sources,
+            self.env.var("PY_DISABLE_SOURCES").as_deref(),
+        )
+    }
+
+    /// The [built-in sources](default_sources).
+    pub(crate) fn sources(&self) -> Vec<Box<dyn InterpreterSource>> {
+        self.send_sources()
+            .into_iter()
+            .map(|source| source as Box<dyn InterpreterSource>)
+            .collect()
+    }
+
+    /// The [built-in sources](default_sources), which can be sent to other
+    /// threads.
+    pub(crate) fn send_sources(&self) -> Vec<Box<dyn InterpreterSource + Send>> {
+        built_in_sources(self.env, self.config, &self.file_system)
+    }
+
+    /// See [`all_executables`], except that the [cache](cache) is used if
+    /// [asked](Search::with_cache).
+    pub(crate) fn all_executables(&self) -> HashMap<ExactVersion, PathBuf> {
+        let sources = self.sources();
+        let disabled = self.disabled_sources();
+        if self.cache {
+            cache::cached_executables(
+                self.env,
+                &*self.file_system,
+                &sources,
+                &disabled,
+                self.config,
+                || executables_from(&sources, &disabled),
+            )
+        } else {
+            executables_from(&sources, &disabled)
+        }
+    }
+
+    /// See [`all_executables_by_source`].
+    pub(crate) fn all_executables_by_source(&self) -> Vec<(String, ExactVersion, PathBuf)> {
+        executables_by_source(&self.sources(), &self.disabled_sources())
+    }
+
+    /// See [`all_executable_occurrences`].
+    pub(crate) fn all_executable_occurrences(&self) -> Vec<(String, ExactVersion, PathBuf)> {
+        executable_occurrences(&self.sources(), &self.disabled_sources())
+    }
+
+    /// See [`windows_executables`].
+    pub(crate) fn windows_executables(&self) -> HashMap<ExactVersion, PathBuf> {
+        windows_executables_with(&*self.file_system, &self.disabled_sources())
+    }
+
+    /// See [`all_environments`].
+    pub(crate) fn all_environments(
+        &self,
+        detection: VenvDetection,
+    ) -> Vec<(&'static str, VenvInfo)> {
+        environments(
+            self.env,
+            &*self.file_system,
+            detection,
+            &self.disabled_sources(),
+        )
+    }
+
+    /// See [`find_environment`].
+    pub(crate) fn find_environment(&self, detection: VenvDetection) -> Result<Option<VenvInfo>> {
+        find_environment_with(
+            self.env,
+            &*self.file_system,
+            detection,
+            &self.disabled_sources(),
+        )
+    }
+
+    /// See [`default_version`].
+    pub(crate) fn default_version(&self) -> Option<VersionPreferenceList> {
+        default_version_with(self.env, &*self.file_system, self.config)
+    }
+
+    /// See [`find_executable`].
+    pub(crate) fn find_executable(
+        &self,
+        requested: impl Into<VersionSpecifier>,
+    ) -> Option<PathBuf> {
+        find_executable_in_hashmap(&requested.into(), &self.all_executables())
+    }
+
+    /// See [`find_preferred_executable`].
+    pub(crate) fn find_preferred_executable(
+        &self,
+        preferences: &VersionPreferenceList,
+    ) -> Option<PathBuf> {
+        find_preferred_executable_in_hashmap(preferences, &self.all_executables())
+    }
+
+    /// See [`find_preferred_windows_executable`].
+    pub(crate) fn find_preferred_windows_executable(
+        &self,
+        preferences: &VersionPreferenceList,
+    ) -> Option<PathBuf> {
+        find_preferred_executable_in_hashmap(preferences, &self.windows_executables())
+    }
+
+    /// See [`find_preferred_executables`].
+    pub(crate) fn find_preferred_executables(
+        &self,
+        preferences: &VersionPreferenceList,
+    ) -> Vec<(ExactVersion, PathBuf)> {
+        find_preferred_executables_in_hashmap(preferences, &self.all_executables())
+    }
+
+    /// See [`find_preferred_windows_executables`].
+    pub(crate) fn find_preferred_windows_executables(
+        &self,
+        preferences: &VersionPreferenceList,
+    ) -> Vec<(ExactVersion, PathBuf)> {
+        find_preferred_executables_in_hashmap(preferences, &self.windows_executables())
+    }
+}
+
+/// Runs `search` with the configuration of the process loaded once.
+fn process_search<T>(search: impl FnOnce(Search) -> T) -> T {
+    let config = load_config();
+    search(Search::new(&ProcessEnv, &config))
+}
+
+/// Where the Windows `C:` drive is mounted under WSL.
+const WSL_WINDOWS_DRIVE: &str = "/mnt/c";
+
+/// Checks if running under the
+/// [Windows Subsystem for Linux](https://learn.microsoft.com/windows/wsl/).
+pub fn is_wsl() -> bool {
+    non_empty_env_var("WSL_DISTRO_NAME").is_some()
+}
+
+/// Parses the version out of the name of a Windows install directory, e.g.
+/// `Python312` or `Python312-32` for Python 3.12.
+///
+/// Like snaps, the major version is assumed to be a single digit.
+fn windows_install_version(dir_name: &str) -> Option<ExactVersion> {
+    let version = dir_name.strip_prefix("Python")?;
+    // Drop any architecture suffix, e.g. `-32` or `-arm64`.
+    let digits = version.split('-').next()?;
+    if digits.len() < 2 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (major, minor) = digits.split_at(1);
+    Some(ExactVersion::new(major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Finds the `python.exe` of the per-user and all-users installs made by the
+/// python.org installer on the Windows drive mounted at `drive`.
+fn windows_executables_in(
+    file_system: &dyn FileSystem,
+    drive: &Path,
+) -> Vec<(ExactVersion, PathBuf)> {
+    let mut install_parents = directory_entries(file_system, [drive.join("Users")])
+        .map(|user_dir| user_dir.join("AppData/Local/Programs/Python"))
+        .collect::<Vec<PathBuf>>();
+    install_parents.sort_unstable();
+    install_parents.extend([
+        drive.join("Program Files"),
+        drive.join("Program Files (x86)"),
+        // Where installers placed Python prior to 3.5.
+        drive.to_path_buf(),
+    ]);
+
+    let mut executables = Vec::new();
+    for install_parent in install_parents {
+        let mut installs = directory_entries(file_system, [install_parent])
+            .filter_map(|install_dir| {
+                let version = windows_install_version(install_dir.file_name()?.to_str()?)?;
+                let executable = install_dir.join("python.exe");
+                file_system
+                    .is_file(&executable)
+                    .then(|| (version, executable))
+            })
+            .collect::<Vec<(ExactVersion, PathBuf)>>();
+        installs.sort_unstable();
+        executables.extend(installs);
+    }
+    executables
+}
+
+/// Finds all Windows-native Python executables when running under WSL.
+///
+/// The per-user and all-users installs made by the python.org installer on
+/// the `C:` drive (i.e. `/mnt/c`) are searched; the Windows registry is not.
+/// When not running under WSL, nothing is found.
+pub fn windows_executables() -> HashMap<ExactVersion, PathBuf> {
+    process_search(|search| search.windows_executables())
+}
+
+/// [`windows_executables`] with the specified sources disabled.
+fn windows_executables_with(
+    file_system: &dyn FileSystem,
+    disabled: &[String],
+) -> HashMap<ExactVersion, PathBuf> {
+    let mut executables = HashMap::new();
+    if !is_wsl() {
+        log::info!("Not running under WSL; skipping Windows interpreters");
+        return executables;
+    }
+    if !source_enabled("wsl", disabled) {
+        return executables;
+    }
+
+    log::info!("Checking Windows installs in {WSL_WINDOWS_DRIVE}");
+    for (version, path) in windows_executables_in(file_system, Path::new(WSL_WINDOWS_DRIVE)) {
+        executables.entry(version).or_insert(path);
+    }
+    executables
+}
+
+/// Determines the CPU architecture an executable was built for, e.g. `x86_64`
+/// or `arm64`, from the header of its binary without running it.
+///
+/// ELF, Mach-O (where a universal binary is `universal`), and the PE format of
+/// Windows-native executables are understood. `None` is returned if the file
+/// can't be read or isn't a binary of a known format and architecture (e.g. a
+/// pyenv shim, which is a shell script).
+pub fn executable_architecture(path: &Path) -> Option<&'static str> {
+    binary_architecture(&binary_header(path)?)
+}
+
+/// Reads enough of the start of an executable to parse its header.
+pub(crate) fn binary_header(path: &Path) -> Option<Vec<u8>> {
+    let mut header = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(1024)
+        .read_to_end(&mut header)
+        .ok()?;
+    Some(header)
+}
+
+pub(crate) fn binary_architecture(header: &[u8]) -> Option<&'static str> {
+    match header.get(..4)? {
+        [0x7f, b'E', b'L', b'F'] => {
+            let little_endian = *header.get(5)? == 1;
+            match read_u16(header, 18, little_endian)? {
+                0x03 => Some("x86"),
+                0x3e => Some("x86_64"),
+                0x28 => Some("arm"),
+                0xb7 => Some("arm64"),
+                0x15 => Some("ppc64"),
+                0x16 => Some("s390x"),
+                0xf3 => Some("riscv64"),
+                _ => None,
+            }
+        }
+        [0xca, 0xfe, 0xba, 0xbe] => Some("universal"),
+        [0xce | 0xcf, 0xfa, 0xed, 0xfe] => match read_u32(header, 4, true)? {
+            0x0000_0007 => Some("x86"),
+            0x0100_0007 => Some("x86_64"),
+            0x0000_000c => Some("arm"),
+            0x0100_000c => Some("arm64"),
+            _ => None,
+        },
+        [b'M', b'Z', ..] => {
+            let pe_offset = read_u32(header, 0x3c, true)? as usize;
+            if header.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+                return None;
+            }
+            match read_u16(header, pe_offset + 4, true)? {
+                0x014c => Some("x86"),
+                0x8664 => Some("x86_64"),
+                0xaa64 => Some("arm64"),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Determines whether a binary is 32- or 64-bit from its header, in the same
+/// formats as [`executable_architecture`].
+///
+/// A universal Mach-O binary has no single bitness.
+pub(crate) fn binary_bits(header: &[u8]) -> Option<u8> {
+    match header.get(..4)? {
+        [0x7f, b'E', b'L', b'F'] => match header.get(4)? {
+            1 => Some(32),
+            2 => Some(64),
+            _ => None,
+        },
+        [0xce, 0xfa, 0xed, 0xfe] => Some(32),
+        [0xcf, 0xfa, 0xed, 0xfe] => Some(64),
+        [b'M', b'Z', ..] => match binary_architecture(header)? {
+            "x86" => Some(32),
+            _ => Some(64),
+        },
+        _ => None,
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize, little_endian: bool) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if little_endian {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize, little_endian: bool) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+/// Where to find the root directory of an [`InstallManager`].
+enum RootDirectory {
+    /// The value of an environment variable.
+    EnvVar(&'static str),
+    /// A path relative to `$XDG_DATA_HOME`.
+    DataHome(&'static str),
+    /// A path relative to the home directory.
+    Home(&'static str),
+    /// An absolute path.
+    Absolute(&'static str),
+}
+
+pub(crate) fn non_empty_env_var(name: &str) -> Option<PathBuf> {
+    ProcessEnv.non_empty_path(name)
+}
+
+impl RootDirectory {
+    fn path(&self, env: &dyn EnvProvider) -> Option<PathBuf> {
+        match self {
+            Self::EnvVar(name) => env.non_empty_path(name),
+            Self::DataHome(relative_path) => env
+                .non_empty_path("XDG_DATA_HOME")
+                .map(|data_home| data_home.join(relative_path)),
+            Self::Home(relative_path) => env
+                .non_empty_path("HOME")
+                .map(|home| home.join(relative_path)),
+            Self::Absolute(path) => Some(PathBuf::from(path)),
+        }
+    }
+}
+
+/// A tool which installs each version of Python into its own directory with a
+/// `bin` subdirectory, all within a single directory.
+struct InstallManager {
+    name: &'static str,
+    /// The name of the manager in [`SOURCES`].
+    source: &'static str,
+    /// Where the tool's root directory may be; the first one which is
+    /// available is used.
+    roots: &'static [RootDirectory],
+    /// The directory containing the installs relative to the root directory.
+    installs_dir: &'static str,
+    /// How many directories deep each `bin` directory is within
+    /// [`InstallManager::installs_dir`], minus the `bin` directory itself
+    /// (e.g. `2` for `<version>/<arch>/bin`).
+    install_depth: usize,
+}
+
+/// Install managers in the order they are searched.
+const INSTALL_MANAGERS: [InstallManager; 7] = [
+    InstallManager {
+        name: "pyenv",
+        source: "pyenv",
+        roots: &[
+            RootDirectory::EnvVar("PYENV_ROOT"),
+            RootDirectory::Home(".pyenv"),
+        ],
+        installs_dir: "versions",
+        install_depth: 1,
+    },
+    InstallManager {
+        name: "asdf",
+        source: "asdf",
+        roots: &[
+            RootDirectory::EnvVar("ASDF_DATA_DIR"),
+            RootDirectory::Home(".asdf"),
+        ],
+        installs_dir: "installs/python",
+        install_depth: 1,
+    },
+    InstallManager {
+        name: "uv",
+        source: "uv",
+        roots: &[
+            RootDirectory::EnvVar("UV_PYTHON_INSTALL_DIR"),
+            RootDirectory::DataHome("uv/python"),
+            RootDirectory::Home(".local/share/uv/python"),
+        ],
+        installs_dir: "",
+        install_depth: 1,
+    },
+    InstallManager {
+        name: "Rye",
+        source: "rye",
+        roots: &[
+            RootDirectory::EnvVar("RYE_HOME"),
+            RootDirectory::Home(".rye"),
+        ],
+        installs_dir: "py",
+        install_depth: 1,
+    },
+    // Only available on GitHub Actions runners.
+    InstallManager {
+        name: "GitHub Actions tool cache",
+        source: "github-actions",
+        roots: &[RootDirectory::EnvVar("RUNNER_TOOL_CACHE")],
+        installs_dir: "Python",
+        install_depth: 2,
+    },
+    // Only available in manylinux containers, e.g. `/opt/python/cp311-cp311`.
+    InstallManager {
+        name: "manylinux",
+        source: "manylinux",
+        roots: &[RootDirectory::Absolute("/opt/python")],
+        installs_dir: "",
+        install_depth: 1,
+    },
+    // Installed via `py install` (see the `install` module).
+    InstallManager {
+        name: "Python Launcher",
+        source: "launcher",
+        roots: &[
+            RootDirectory::EnvVar("PYLAUNCH_INSTALL_DIR"),
+            RootDirectory::DataHome("python-launcher/installs"),
+            RootDirectory::Home(".local/share/python-launcher/installs"),
+        ],
+        installs_dir: "",
+        install_depth: 1,
+    },
+];
+
+impl InstallManager {
+    fn root(&self, env: &dyn EnvProvider) -> Option<PathBuf> {
+        self.roots.iter().find_map(|root| root.path(env))
+    }
+}
+
+/// The directory which [`install`] installs interpreters into.
+#[cfg(feature = "install")]
+pub(crate) fn launcher_installs_dir() -> Option<PathBuf> {
+    INSTALL_MANAGERS
+        .iter()
+        .find(|manager| manager.source == "launcher")
+        .and_then(|manager| manager.root(&ProcessEnv))
+}
+
+/// The `bin` directories of the user's Nix profile (`~/.nix-profile`), the
+/// NixOS system profile (`/run/current-system/sw`), and the space-separated
+/// profiles in `NIX_PROFILES`, in that order.
+///
+/// These are searched even when the Nix environment has not been sourced by
+/// the current shell (which is what adds them to `PATH`).
+fn nix_profile_bin_directories(home: Option<PathBuf>, nix_profiles: Option<&str>) -> Vec<PathBuf> {
+    let mut profiles = Vec::new();
+    profiles.extend(home.map(|home| home.join(".nix-profile")));
+    profiles.push(PathBuf::from("/run/current-system/sw"));
+    profiles.extend(
+        nix_profiles
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(PathBuf::from),
+    );
+
+    let mut bin_directories: Vec<PathBuf> = Vec::new();
+    for profile in profiles {
+        let bin_directory = profile.join("bin");
+        if !bin_directories.contains(&bin_directory) {
+            bin_directories.push(bin_directory);
+        }
+    }
+    bin_directories
+}
+
+fn install_bin_directories(
+    file_system: &dyn FileSystem,
+    installs_dir: &Path,
+    install_depth: usize,
+) -> Vec<PathBuf> {
+    let mut install_dirs = vec![installs_dir.to_path_buf()];
+    for _ in 0..install_depth {
+        install_dirs = directory_entries(file_system, install_dirs).collect();
+    }
+    let mut directories = install_dirs
+        .into_iter()
+        .map(|install_dir| install_dir.join("bin"))
+        .collect::<Vec<PathBuf>>();
+    // Make the search order deterministic when versions overlap.
+    directories.sort_unstable();
+    directories
+}
+
+/// A source of Python executables, e.g. the directories on `PATH`.
+///
+/// Besides the [built-in sources](default_sources), downstream code may
+/// implement this trait to search for executables in other places (e.g. a
+/// bespoke directory layout) and pass them to [`all_executables_from`].
+pub trait InterpreterSource {
+    /// The name of the source, which is what is used to
+    /// [disable](SOURCES) it.
+    fn name(&self) -> &str;
+
+    /// Finds the executables of the source.
+    ///
+    /// When the source has multiple executables for the same version, the
+    /// one it prefers is returned.
+    fn executables(&self) -> HashMap<ExactVersion, PathBuf>;
+
+    /// Finds every executable of the source, including those for a version
+    /// which [`InterpreterSource::executables`] passes over, with the
+    /// preferred executable for each version coming first.
+    ///
+    /// By default, only the executables which are preferred are found, from
+    /// newest to oldest version.
+    fn all_executables(&self) -> Vec<(ExactVersion, PathBuf)> {
+        let mut executables = Vec::from_iter(self.executables());
+        executables.sort_unstable();
+        executables.reverse();
+        executables
+    }
+
+    /// Iterates over the same executables as
+    /// [`InterpreterSource::all_executables`], finding them as they are
+    /// iterated over when the source supports it.
+    ///
+    /// By default, all of the executables are found up front.
+    fn iter_executables(&self) -> Box<dyn Iterator<Item = (ExactVersion, PathBuf)>> {
+        Box::new(self.all_executables().into_iter())
+    }
+
+    /// The directories the source reads its executables from, if that's all
+    /// it reads, so that what it finds can be [cached](cache) until one of
+    /// them is modified.
+    ///
+    /// By default, `None` is returned, meaning that what the source finds
+    /// can't be cached.
+    fn directories(&self) -> Option<Vec<PathBuf>> {
+        None
+    }
+}
+
+/// An [`InterpreterSource`] for the `pythonX.Y` executables found in a list of
+/// directories, with earlier directories taking precedence.
+///
+/// The directories are read from the real file system unless
+/// [another](DirectorySource::with_file_system) is chosen.
+#[derive(Clone, Debug)]
+pub struct DirectorySource {
+    /// The name of the source.
+    pub name: String,
+    /// The directories to search, in order.
+    pub directories: Vec<PathBuf>,
+    file_system: Arc<dyn FileSystem>,
+}
+
+impl DirectorySource {
+    /// Creates a source for the specified directories.
+    pub fn new(name: impl Into<String>, directories: Vec<PathBuf>) -> Self {
+        let name = name.into();
+        log::debug!("{name} directories: {directories:?}");
+        Self {
+            name,
+            directories,
+            file_system: Arc::new(OsFileSystem),
+        }
+    }
+
+    /// Reads the directories from `file_system` instead of the real file
+    /// system.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use python_launcher::{
+    ///     filesystem::MemoryFileSystem, DirectorySource, ExactVersion, InterpreterSource,
+    /// };
+    ///
+    /// let file_system = MemoryFileSystem::new().file("/usr/bin/python3.12");
+    /// let source = DirectorySource::new("usr", vec!["/usr/bin".into()])
+    ///     .with_file_system(Arc::new(file_system));
+    ///
+    /// assert!(source.executables().contains_key(&ExactVersion::new(3, 12)));
+    /// ```
+    pub fn with_file_system(mut self, file_system: Arc<dyn FileSystem>) -> Self {
+        self.file_system = file_system;
+        self
+    }
+}
+
+impl InterpreterSource for DirectorySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn executables(&self) -> HashMap<ExactVersion, PathBuf> {
+        executables_in_directories_of(&*self.file_system, self.directories.clone())
+    }
+
+    /// Every executable in the directories, in the order of the directories.
+    fn all_executables(&self) -> Vec<(ExactVersion, PathBuf)> {
+        self.iter_executables().collect()
+    }
+
+    /// Each directory is only read once the executables of the directories
+    /// before it have been iterated over.
+    fn iter_executables(&self) -> Box<dyn Iterator<Item = (ExactVersion, PathBuf)>> {
+        let file_system = Arc::clone(&self.file_system);
+        let entries_file_system = Arc::clone(&self.file_system);
+        Box::new(
+            self.directories
+                .clone()
+                .into_iter()
+                .filter_map(move |directory| entries_file_system.read_dir(&directory).ok())
+                .flatten()
+                .filter_map(move |path| Some((executable_version(&*file_system, &path)?, path))),
+        )
+    }
+
+    fn directories(&self) -> Option<Vec<PathBuf>> {
+        Some(self.directories.clone())
+    }
+}
+
+/// The wrappers for the apps of installed snaps, including those named after
+/// the snap's version of Python.
+#[derive(Debug)]
+struct SnapSource {
+    file_system: Arc<dyn FileSystem>,
+}
+
+impl InterpreterSource for SnapSource {
+    fn name(&self) -> &str {
+        "snap"
+    }
+
+    fn executables(&self) -> HashMap<ExactVersion, PathBuf> {
+        log::info!("Checking snaps in {SNAP_BIN_DIR}");
+        let mut executables = HashMap::new();
+        for (version, path) in snap_executables(&*self.file_system, Path::new(SNAP_BIN_DIR)) {
+            executables.entry(version).or_insert(path);
+        }
+        executables
+    }
+
+    fn directories(&self) -> Option<Vec<PathBuf>> {
+        Some(vec![PathBuf::from(SNAP_BIN_DIR)])
+    }
+}
+
+/// Returns the built-in sources of executables in the order they are searched.
+///
+/// First is `PATH` (or `/usr/local/bin`, `/usr/bin`, and `/bin` if `PATH` is
+/// unset or empty), followed by the directories in the `PY_PYTHON_PATH`
+/// environment variable (formatted like `PATH`), followed by the
+/// `search-directories` of the [configuration file](config::Config), followed
+/// by those installed by:
+///
+/// - [pyenv](https://github.com/pyenv/pyenv) (i.e.
+///   `$PYENV_ROOT/versions/*/bin`, defaulting to `~/.pyenv`)
+/// - [asdf](https://asdf-vm.com/) (i.e.
+///   `$ASDF_DATA_DIR/installs/python/*/bin`, defaulting to `~/.asdf`)
+/// - [uv](https://docs.astral.sh/uv/) (i.e. `$UV_PYTHON_INSTALL_DIR/*/bin`,
+///   defaulting to `$XDG_DATA_HOME/uv/python` or `~/.local/share/uv/python`)
+/// - [Rye](https://rye.astral.sh/) (i.e. `$RYE_HOME/py/*/bin`, defaulting to
+///   `~/.rye`)
+/// - The tool cache of GitHub Actions runners (i.e.
+///   `$RUNNER_TOOL_CACHE/Python/*/*/bin`)
+/// - [manylinux](https://github.com/pypa/manylinux) container images (i.e.
+///   `/opt/python/*/bin`)
+///
+/// Next are the `bin` directories of [Nix](https://nixos.org/) profiles (i.e.
+/// `~/.nix-profile`, `/run/current-system/sw`, and `NIX_PROFILES`).
+///
+/// Lastly, the wrappers for snaps in `/snap/bin` are searched, including those
+/// named after the snap's version of Python (e.g. `python38`).
+pub fn default_sources() -> Vec<Box<dyn InterpreterSource>> {
+    default_sources_in(&ProcessEnv)
+}
+
+/// Returns the [built-in sources](default_sources) for an environment, e.g.
+/// with its `PATH`.
+pub fn default_sources_in(env: &dyn EnvProvider) -> Vec<Box<dyn InterpreterSource>> {
+    Search::new(env, &load_config_in(env)).sources()
+}
+
+/// The [built-in sources](default_sources) for an environment and its
+/// configuration, which can be sent to other threads.
+pub(crate) fn built_in_sources(
+    env: &dyn EnvProvider,
+    config: &config::Config,
+    file_system: &Arc<dyn FileSystem>,
+) -> Vec<Box<dyn InterpreterSource + Send>> {
+    let mut sources: Vec<Box<dyn InterpreterSource + Send>> = Vec::new();
+    let directory_source = |name: &str, directories: Vec<PathBuf>| {
+        Box::new(DirectorySource::new(name, directories).with_file_system(Arc::clone(file_system)))
+    };
+
+    log::info!("Checking PATH environment variable");
+    sources.push(directory_source(
+        "path",
+        path_or_default(env_path(env, "PATH")),
+    ));
+    log::info!("Checking PY_PYTHON_PATH environment variable");
+    sources.push(directory_source(
+        "py-python-path",
+        env_path(env, "PY_PYTHON_PATH"),
+    ));
+    log::info!("Checking search directories from the configuration file");
+    sources.push(directory_source(
+        "config",
+        config.search_directories.clone(),
+    ));
+    for manager in INSTALL_MANAGERS.iter() {
+        let install_directories = match manager.root(env) {
+            Some(root) => {
+                let installs_dir = root.join(manager.installs_dir);
+                log::info!(
+                    "Checking {} installs in {}",
+                    manager.name,
+                    installs_dir.display()
+                );
+                install_bin_directories(&**file_system, &installs_dir, manager.install_depth)
+            }
+            None => Vec::new(),
+        };
+        sources.push(directory_source(manager.source, install_directories));
+    }
+    log::info!("Checking Nix profiles");
+    sources.push(directory_source(
+        "nix",
+        nix_profile_bin_directories(
+            env.non_empty_path("HOME"),
+            env.var("NIX_PROFILES").as_deref(),
+        ),
+    ));
+    sources.push(Box::new(SnapSource {
+        file_system: Arc::clone(file_system),
+    }));
+
+    sources
+}
+
+/// Finds all possible Python executables from the specified sources, skipping
+/// any which are [disabled](SOURCES).
+///
+/// When the same version is found by multiple sources, the executable from the
+/// earliest source is used.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(sources = sources.len()))
+)]
+pub fn all_executables_from(
+    sources: &[Box<dyn InterpreterSource>],
+) -> HashMap<ExactVersion, PathBuf> {
+    executables_from(sources, &env_disabled_sources(&ProcessEnv))
+}
+
+/// [`all_executables_from`] with the specified sources disabled.
+fn executables_from(
+    sources: &[Box<dyn InterpreterSource>],
+    disabled: &[String],
+) -> HashMap<ExactVersion, PathBuf> {
+    let mut executables = HashMap::new();
+    for source in sources {
+        if !source_enabled(source.name(), disabled) {
+            continue;
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("source", name = source.name()).entered();
+        for (version, path) in source.executables() {
+            executables.entry(version).or_insert(path);
+        }
+    }
+    executables
+}
+
+/// Finds all possible Python executables from the
+/// [built-in sources](default_sources).
+///
+/// When the same version is found in multiple places, the first one found is
+/// used. Any of the [sources](SOURCES) may be disabled.
+///
+/// Every directory is read each time; [`cache::all_executables`] reuses what
+/// was found until a directory which was searched is modified, like the CLI
+/// does.
+pub fn all_executables() -> HashMap<ExactVersion, PathBuf> {
+    process_search(|search| search.all_executables())
+}
+
+/// Finds every Python executable from the specified sources, along with the
+/// [name](InterpreterSource::name) of the source which found it, skipping any
+/// sources which are [disabled](SOURCES).
+///
+/// Unlike [`all_executables_from`], an executable is included even if an
+/// earlier source found the same version. The executables are in the order of
+/// the sources, and then from newest to oldest version.
+pub fn all_executables_by_source_from(
+    sources: &[Box<dyn InterpreterSource>],
+) -> Vec<(String, ExactVersion, PathBuf)> {
+    executables_by_source(sources, &env_disabled_sources(&ProcessEnv))
+}
+
+/// [`all_executables_by_source_from`] with the specified sources disabled.
+pub(crate) fn executables_by_source(
+    sources: &[Box<dyn InterpreterSource>],
+    disabled: &[String],
+) -> Vec<(String, ExactVersion, PathBuf)> {
+    let mut executables = Vec::new();
+    for source in sources {
+        if !source_enabled(source.name(), disabled) {
+            continue;
+        }
+        let mut source_executables = Vec::from_iter(source.executables());
+        source_executables.sort_unstable();
+        executables.extend(
+            source_executables
+                .into_iter()
+                .rev()
+                .map(|(version, path)| (source.name().to_string(), version, path)),
+        );
+    }
+    executables
+}
+
+/// Finds every Python executable from the [built-in sources](default_sources)
+/// (see [`all_executables_by_source_from`]).
+pub fn all_executables_by_source() -> Vec<(String, ExactVersion, PathBuf)> {
+    process_search(|search| search.all_executables_by_source())
+}
+
+/// Finds every occurrence of a Python executable from the specified sources,
+/// along with the [name](InterpreterSource::name) of the source which found
+/// it, skipping any sources which are [disabled](SOURCES).
+///
+/// Unlike [`all_executables_by_source_from`], an executable is included even if
+/// an earlier directory of the same source has one for the same version (see
+/// [`InterpreterSource::all_executables`]). The executables are in the order
+/// they are searched, so the first one for each version is the one which
+/// [`all_executables_from`] uses and any others are shadowed by it.
+pub fn all_executable_occurrences_from(
+    sources: &[Box<dyn InterpreterSource>],
+) -> Vec<(String, ExactVersion, PathBuf)> {
+    executable_occurrences(sources, &env_disabled_sources(&ProcessEnv))
+}
+
+/// [`all_executable_occurrences_from`] with the specified sources disabled.
+pub(crate) fn executable_occurrences(
+    sources: &[Box<dyn InterpreterSource>],
+    disabled: &[String],
+) -> Vec<(String, ExactVersion, PathBuf)> {
+    sources
+        .iter()
+        .filter(|source| source_enabled(source.name(), disabled))
+        .flat_map(|source| {
+            source
+                .all_executables()
+                .into_iter()
+                .map(move |(version, path)| (source.name().to_string(), version, path))
+        })
+        .collect()
+}
+
+/// Finds every occurrence of a Python executable from the
+/// [built-in sources](default_sources) (see
+/// [`all_executable_occurrences_from`]).
+pub fn all_executable_occurrences() -> Vec<(String, ExactVersion, PathBuf)> {
+    process_search(|search| search.all_executable_occurrences())
+}
+
+/// The expected directory name for virtual environments.
+pub const DEFAULT_VENV_DIR: &str = ".venv";
+
+/// The directory names searched for virtual environments, in order of
+/// preference.
+///
+/// Only [`DEFAULT_VENV_DIR`] is assumed to hold a virtual environment; any
+/// other directory must also contain a `pyvenv.cfg` file to avoid mistaking
+/// e.g. a `venv` package for one.
+pub const VENV_DIRS: [&str; 2] = [DEFAULT_VENV_DIR, "venv"];
+
+/// Details about a virtual environment.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VenvInfo {
+    /// The root directory of the virtual environment.
+    pub root: PathBuf,
+    /// The Python executable of the virtual environment (i.e. `bin/python`).
+    pub executable: PathBuf,
+    /// The version of Python the virtual environment was created with,
+    /// according to its `pyvenv.cfg`.
+    pub version: Option<ExactVersion>,
+    /// Whether the virtual environment is activated (as opposed to being found
+    /// in one of the [`VENV_DIRS`]).
+    pub activated: bool,
+}
+
+/// Parses the version of Python out of the contents of a `pyvenv.cfg` file.
+///
+/// The `version` key is written by `venv` and `version_info` by e.g. uv; the
+/// latter includes the release level, which is ignored.
+fn pyvenv_cfg_version(contents: &str) -> Option<ExactVersion> {
+    contents.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if !matches!(key.trim(), "version" | "version_info") {
+            return None;
+        }
+        let mut components = value.trim().split('.').map(str::parse::<ComponentSize>);
+        let major = components.next()?.ok()?;
+        let minor = components.next()?.ok()?;
+        Some(match components.next() {
+            Some(Ok(micro)) => ExactVersion::with_micro(major, minor, micro),
+            _ => ExactVersion::new(major, minor),
+        })
+    })
+}
+
+impl VenvInfo {
+    /// Gathers the details of the virtual environment rooted at the specified
+    /// directory.
+    ///
+    /// `None` is returned if the virtual environment has no `bin/python`
+    /// executable.
+    pub fn from_root(root: &Path, activated: bool) -> Option<Self> {
+        Self::from_root_of(&OsFileSystem, root, activated)
+    }
+
+    /// [`VenvInfo::from_root`] on a [`FileSystem`].
+    fn from_root_of(file_system: &dyn FileSystem, root: &Path, activated: bool) -> Option<Self> {
+        let executable = root.join("bin").join("python");
+        let printable_executable = executable.display();
+        log::info!("Checking {printable_executable}");
+        if !file_system.is_file(&executable) {
+            log::debug!("{printable_executable} does not exist");
+            return None;
+        }
+        let version = file_system
+            .read_to_string(&root.join("pyvenv.cfg"))
+            .ok()
+            .and_then(|contents| pyvenv_cfg_version(&contents));
+        Some(Self {
+            root: root.to_path_buf(),
+            executable,
+            version,
+            activated,
+        })
+    }
+}
+
+/// Returns the activated virtual environment.
+///
+/// A virtual environment is determined to be activated based on the
+/// existence of the `VIRTUAL_ENV` environment variable. The virtual
+/// environment is ignored if it lacks a Python executable.
+pub fn active_virtual_env() -> Option<VenvInfo> {
+    active_virtual_env_in(&ProcessEnv)
+}
+
+/// Returns the virtual environment activated in an environment (see
+/// [`active_virtual_env`]).
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(env), ret)
+)]
+pub fn active_virtual_env_in(env: &dyn EnvProvider) -> Option<VenvInfo> {
+    active_virtual_env_of(env, &OsFileSystem)
+}
+
+fn active_virtual_env_of(env: &dyn EnvProvider, file_system: &dyn FileSystem) -> Option<VenvInfo> {
+    log::info!("Checking for VIRTUAL_ENV environment variable");
+    let venv_root = env.var_os("VIRTUAL_ENV")?;
+    log::debug!("VIRTUAL_ENV set to {venv_root:?}");
+    VenvInfo::from_root_of(file_system, Path::new(&venv_root), true)
+}
+
+/// Converts the name of a version of Python known to pyenv (e.g. `3.12.1` or
+/// `pypy3.10-7.3.12`) into a requested version.
+///
+/// Only the major and minor versions are used as executables are typically
+/// named `pythonX.Y`. The `system` version is any version. Names which don't
+/// start with a version (e.g. `miniconda3-latest`) return `None`.
+pub(crate) fn pyenv_requested_version(name: &str) -> Option<RequestedVersion> {
+    if name == "system" {
+        return Some(RequestedVersion::Any);
+    }
+    // Drop any suffix, e.g. `-dev` or the PyPy version of `pypy3.10-7.3.12`.
+    let name = name.split('-').next()?;
+    let (version, free_threaded) = match name.strip_suffix('t') {
+        Some(version) => (version, "t"),
+        None => (name, ""),
+    };
+    let major_minor = version
+        .splitn(3, '.')
+        .take(2)
+        .collect::<Vec<&str>>()
+        .join(".");
+    match RequestedVersion::from_str(&format!("{major_minor}{free_threaded}")) {
+        Ok(
+            requested @ (RequestedVersion::MajorOnly(_)
+            | RequestedVersion::Exact(_, _)
+            | RequestedVersion::FreeThreaded(_, _)
+            | RequestedVersion::Implementation(_, _, _)),
+        ) => Some(requested),
+        _ => None,
+    }
+}
+
+/// Parses the versions of a pyenv version file or `PYENV_VERSION`, which are
+/// separated by whitespace or `:`, respectively.
+///
+/// Lines starting with `#` are comments.
+fn parse_pyenv_versions(contents: &str) -> Vec<RequestedVersion> {
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split(|c: char| c == ':' || c.is_whitespace()))
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let requested = pyenv_requested_version(name);
+            if requested.is_none() {
+                log::debug!("Ignoring the '{name}' pyenv version");
+            }
+            requested
+        })
+        .collect()
+}
+
+/// Parses the versions of the `python` line of an asdf `.tool-versions` file,
+/// returning `None` if there is no such line.
+///
+/// asdf uses the same names for versions as pyenv, separated by whitespace.
+/// Anything after a `#` is a comment.
+fn parse_tool_versions(contents: &str) -> Option<Vec<RequestedVersion>> {
+    contents.lines().find_map(|line| {
+        let line = line.find('#').map_or(line, |comment| &line[..comment]);
+        let mut words = line.split_whitespace();
+        (words.next() == Some("python")).then(|| {
+            words
+                .filter_map(|name| {
+                    let requested = pyenv_requested_version(name);
+                    if requested.is_none() {
+                        log::debug!("Ignoring the '{name}' asdf version");
+                    }
+                    requested
+                })
+                .collect()
+        })
+    })
+}
+
+/// Finds the path to the closest `.python-version` file in `dir` or any of its
+/// parents.
+pub fn find_python_version_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|directory| directory.join(".python-version"))
+        .find(|path| {
+            log::info!("Checking {}", path.display());
+            path.is_file()
+        })
+}
+
+/// Finds the closest `.python-version` file in `dir` or any of its parents and
+/// returns the versions of Python it selects, in order of preference.
+///
+/// This is the local version file of pyenv (as written by `pyenv local`).
+/// `None` is returned if there is no such file or the closest one selects no
+/// version known to the launcher.
+pub fn python_version_file(dir: &Path) -> Option<VersionPreferenceList> {
+    let version_file = find_python_version_file(dir)?;
+    log::debug!("Found {}", version_file.display());
+    version_preference_list(parse_pyenv_versions(
+        &fs::read_to_string(version_file).ok()?,
+    ))
+}
+
+/// Finds the closest asdf `.tool-versions` file in `dir` or any of its parents
+/// which has a `python` line and returns the versions of Python it selects, in
+/// order of preference.
+///
+/// Files without a `python` line are skipped, like asdf does. `None` is
+/// returned if there is no such file or the closest one selects no version
+/// known to the launcher.
+pub fn tool_versions_file(dir: &Path) -> Option<VersionPreferenceList> {
+    dir.ancestors()
+        .find_map(|directory| {
+            let tool_versions = directory.join(".tool-versions");
+            log::info!("Checking {}", tool_versions.display());
+            let versions = parse_tool_versions(&fs::read_to_string(&tool_versions).ok()?)?;
+            log::debug!("Found a `python` line in {}", tool_versions.display());
+            Some(version_preference_list(versions))
+        })
+        .flatten()
+}
+
+/// Finds the closest `.python-version` or `.tool-versions` file (with a
+/// `python` line) in `dir` or any of its parents and returns the versions of
+/// Python it selects, in order of preference.
+///
+/// Each directory is checked for both files before its parent, so the nearest
+/// file wins; within the same directory, `.python-version` is preferred. See
+/// [`python_version_file`] and [`tool_versions_file`].
+fn local_version_file(file_system: &dyn FileSystem, dir: &Path) -> Option<VersionPreferenceList> {
+    dir.ancestors()
+        .find_map(|directory| {
+            let python_version = directory.join(".python-version");
+            log::info!("Checking {}", python_version.display());
+            if file_system.is_file(&python_version) {
+                log::debug!("Found {}", python_version.display());
+                let contents = file_system.read_to_string(&python_version).ok()?;
+                return Some(version_preference_list(parse_pyenv_versions(&contents)));
+            }
+            let tool_versions = directory.join(".tool-versions");
+            log::info!("Checking {}", tool_versions.display());
+            let versions = parse_tool_versions(&file_system.read_to_string(&tool_versions).ok()?)?;
+            log::debug!("Found a `python` line in {}", tool_versions.display());
+            Some(version_preference_list(versions))
+        })
+        .flatten()
+}
+
+/// Returns the versions of Python to use by default, in order of preference.
+///
+/// The `PYENV_VERSION` environment variable is used if set, else the closest
+/// `.python-version` or `.tool-versions` file to the current directory (see
+/// [`python_version_file`] and [`tool_versions_file`]; a `.python-version`
+/// wins over a `.tool-versions` in the same directory), else the
+/// `default-version` of the configuration
+/// file (see [`config::Config::default_version`]), else pyenv's global version
+/// file (`$PYENV_ROOT/version`, defaulting to `~/.pyenv/version`). `None` is
+/// returned if none of them selects a version known to the launcher.
+pub fn default_version() -> Option<VersionPreferenceList> {
+    default_version_in(&ProcessEnv)
+}
+
+/// Returns the versions of Python to use by default in an environment (see
+/// [`default_version`]).
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(env), ret)
+)]
+pub fn default_version_in(env: &dyn EnvProvider) -> Option<VersionPreferenceList> {
+    Search::new(env, &load_config_in(env)).default_version()
+}
+
+/// [`default_version_in`] with the configuration already loaded.
+fn default_version_with(
+    env: &dyn EnvProvider,
+    file_system: &dyn FileSystem,
+    config: &config::Config,
+) -> Option<VersionPreferenceList> {
+    log::info!("Checking the PYENV_VERSION environment variable");
+    if let Some(pyenv_version) = env.var("PYENV_VERSION") {
+        if !pyenv_version.is_empty() {
+            log::debug!("PYENV_VERSION = '{pyenv_version}'");
+            return version_preference_list(parse_pyenv_versions(&pyenv_version));
+        }
+    }
+
+    if let Ok(cwd) = env.current_dir() {
+        if let Some(preferences) = local_version_file(file_system, &cwd) {
+            return Some(preferences);
+        }
+    }
+
+    if let Some(preferences) = &config.default_version {
+        log::debug!("Using `default-version` = '{preferences}' from the configuration file");
+        return Some(preferences.clone());
+    }
+
+    let pyenv_root = env
+        .non_empty_path("PYENV_ROOT")
+        .or_else(|| env.non_empty_path("HOME").map(|home| home.join(".pyenv")))?;
+    let version_file = pyenv_root.join("version");
+    log::info!("Checking {}", version_file.display());
+    version_preference_list(parse_pyenv_versions(
+        &file_system.read_to_string(&version_file).ok()?,
+    ))
+}
+
+/// Finds the closest `pyproject.toml` in `dir` or any of its parents and
+/// returns the versions of Python its project supports, as specified by
+/// `requires-python` in its `[project]` table.
+///
+/// `None` is returned if there is no such file, the closest one doesn't
+/// specify `requires-python`, or the specifier uses more than the release
+/// segment of a version (see [`VersionClause`]).
+pub fn requires_python(dir: &Path) -> Option<VersionSpecifier> {
+    requires_python_of(&OsFileSystem, dir)
+}
+
+/// [`requires_python`] on a [`FileSystem`].
+pub(crate) fn requires_python_of(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+) -> Option<VersionSpecifier> {
+    let pyproject_path = dir
+        .ancestors()
+        .map(|directory| directory.join("pyproject.toml"))
+        .find(|path| {
+            log::info!("Checking {}", path.display());
+            file_system.is_file(path)
+        })?;
+    let pyproject = file_system
+        .read_to_string(&pyproject_path)
+        .ok()?
+        .parse::<toml::Value>()
+        .ok()?;
+    let requires_python = pyproject
+        .get("project")
+        .and_then(|project| project.get("requires-python"))
+        .and_then(toml::Value::as_str)?;
+    log::debug!(
+        "{} requires Python '{requires_python}'",
+        pyproject_path.display()
+    );
+    VersionSpecifier::from_str(requires_python)
+        .map_err(|error| log::warn!("{}: {error}", pyproject_path.display()))
+        .ok()
+}
+
+fn version_preference_list(versions: Vec<RequestedVersion>) -> Option<VersionPreferenceList> {
+    (!versions.is_empty()).then(|| VersionPreferenceList {
+        versions,
+        ..VersionPreferenceList::default()
+    })
+}
+
+/// Returns the activated conda environment.
+///
+/// A conda environment is determined to be activated based on the existence
+/// of the `CONDA_PREFIX` environment variable. The environment is ignored if
+/// it lacks a Python executable.
+pub fn active_conda_env() -> Option<VenvInfo> {
+    active_conda_env_in(&ProcessEnv)
+}
+
+/// Returns the conda environment activated in an environment (see
+/// [`active_conda_env`]).
+pub fn active_conda_env_in(env: &dyn EnvProvider) -> Option<VenvInfo> {
+    active_conda_env_of(env, &OsFileSystem)
+}
+
+fn active_conda_env_of(env: &dyn EnvProvider, file_system: &dyn FileSystem) -> Option<VenvInfo> {
+    log::info!("Checking for CONDA_PREFIX environment variable");
+    let conda_prefix = env.non_empty_path("CONDA_PREFIX")?;
+    log::debug!("CONDA_PREFIX set to {conda_prefix:?}");
+    VenvInfo::from_root_of(file_system, Path::new(&conda_prefix), true)
+}
+
+/// Searches for a virtual environment in any of the [`VENV_DIRS`] in the
+/// specified directory or any of its parent directories.
+///
+/// The closest directory containing a virtual environment wins.
+pub fn find_virtual_env(dir: &Path) -> Option<VenvInfo> {
+    find_virtual_env_of(&OsFileSystem, dir)
+}
+
+fn find_virtual_env_of(file_system: &dyn FileSystem, dir: &Path) -> Option<VenvInfo> {
+    let printable_dir = dir.display();
+    log::info!("Searching for a venv in {printable_dir} and parent directories");
+    dir.ancestors().find_map(|path| {
+        VENV_DIRS.iter().find_map(|venv_dir| {
+            let root = path.join(venv_dir);
+            if *venv_dir != DEFAULT_VENV_DIR && !file_system.is_file(&root.join("pyvenv.cfg")) {
+                return None;
+            }
+            VenvInfo::from_root_of(file_system, &root, false)
+        })
+    })
+}
+
+/// Returns the directory containing the virtual environments Poetry creates
+/// outside of projects.
+///
+/// This is `POETRY_VIRTUALENVS_PATH` if set, else the `virtualenvs`
+/// directory of Poetry's cache directory (`POETRY_CACHE_DIR`,
+/// `$XDG_CACHE_HOME/pypoetry`, or `~/.cache/pypoetry`).
+fn poetry_virtualenvs_dir(env: &dyn EnvProvider) -> Option<PathBuf> {
+    env.non_empty_path("POETRY_VIRTUALENVS_PATH").or_else(|| {
+        env.non_empty_path("POETRY_CACHE_DIR")
+            .or_else(|| {
+                env.non_empty_path("XDG_CACHE_HOME")
+                    .map(|cache| cache.join("pypoetry"))
+            })
+            .or_else(|| {
+                env.non_empty_path("HOME")
+                    .map(|home| home.join(".cache/pypoetry"))
+            })
+            .map(|cache_dir| cache_dir.join("virtualenvs"))
+    })
+}
+
+/// Returns the name of a Poetry project, or `None` if `pyproject.toml` isn't
+/// for a Poetry project.
+///
+/// A project is considered to use Poetry if it has a `[tool.poetry]` table or
+/// a `poetry.lock` file.
+fn poetry_project_name(file_system: &dyn FileSystem, project_dir: &Path) -> Option<String> {
+    let pyproject = file_system
+        .read_to_string(&project_dir.join("pyproject.toml"))
+        .ok()?
+        .parse::<toml::Value>()
+        .ok()?;
+    let tool_poetry = pyproject.get("tool").and_then(|tool| tool.get("poetry"));
+    if tool_poetry.is_none() && !file_system.is_file(&project_dir.join("poetry.lock")) {
+        return None;
+    }
+    pyproject
+        .get("project")
+        .or(tool_poetry)
+        .and_then(|table| table.get("name"))
+        .and_then(toml::Value::as_str)
+        .map(String::from)
+}
+
+/// Generates the prefix of the names of a project's virtual environments the
+/// same way Poetry does, i.e. `{name}-{hash}` where the hash is derived from
+/// the project's directory.
+///
+/// The full name of a virtual environment appends `-pyX.Y`.
+fn poetry_env_base_name(project_name: &str, project_dir: &Path) -> String {
+    // Poetry uses the normalized name of the project (PEP 503).
+    let mut name = String::new();
+    for c in project_name.to_lowercase().chars() {
+        match c {
+            '-' | '_' | '.' if name.ends_with('-') => {}
+            '-' | '_' | '.' => name.push('-'),
+            ' ' | '$' | '`' | '!' | '*' | '@' | '"' | '\\' | '\r' | '\n' | '\t' => name.push('_'),
+            c => name.push(c),
+        }
+    }
+    let name = name.chars().take(42).collect::<String>();
+    let hash = path_hash(project_dir);
+    format!("{name}-{hash}")
+}
+
+/// Hashes a path the way Poetry and Pipenv do when naming virtual
+/// environments, i.e. the first 8 characters of the URL-safe base64 encoding
+/// of the path's SHA-256 hash.
+fn path_hash(path: &Path) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let digest = Sha256::digest(path.as_os_str().as_bytes());
+    let mut hash = String::new();
+    // 8 characters encode exactly the first 6 bytes.
+    for chunk in digest[..6].chunks(3) {
+        let bits = u32::from(chunk[0]) << 16 | u32::from(chunk[1]) << 8 | u32::from(chunk[2]);
+        for shift in [18, 12, 6, 0] {
+            hash.push(char::from(ALPHABET[(bits >> shift & 0x3f) as usize]));
+        }
+    }
+    hash
+}
+
+/// Returns the directory containing the virtual environments Pipenv creates,
+/// i.e. `WORKON_HOME` or `$XDG_DATA_HOME/virtualenvs` (defaulting to
+/// `~/.local/share/virtualenvs`).
+fn pipenv_workon_home(env: &dyn EnvProvider) -> Option<PathBuf> {
+    env.non_empty_path("WORKON_HOME").or_else(|| {
+        env.non_empty_path("XDG_DATA_HOME")
+            .or_else(|| {
+                env.non_empty_path("HOME")
+                    .map(|home| home.join(".local/share"))
+            })
+            .map(|data_home| data_home.join("virtualenvs"))
+    })
+}
+
+/// Generates the name of a project's virtual environment the same way Pipenv
+/// does, i.e. `{directory name}-{hash}` where the hash is derived from the
+/// path to the project's `Pipfile`.
+fn pipenv_env_name(project_dir: &Path) -> Option<String> {
+    let dir_name = project_dir.file_name()?.to_str()?;
+    let name = dir_name
+        .chars()
+        .map(|c| match c {
+            ' ' | '&' | '$' | '`' | '!' | '*' | '@' | '"' | '(' | ')' | '[' | ']' | '\\' | '\r'
+            | '\n' | '\t' => '_',
+            c => c,
+        })
+        .take(42)
+        .collect::<String>();
+    let hash = path_hash(&project_dir.join("Pipfile"));
+    Some(format!("{name}-{hash}"))
+}
+
+/// Searches for the Pipenv project (i.e. a directory containing a `Pipfile`)
+/// containing the specified directory and returns the virtual environment
+/// Pipenv created for it.
+///
+/// The `PIPENV_CUSTOM_VENV_NAME` environment variable is respected. Virtual
+/// environments within the project (i.e. `.venv`) are found by
+/// [`find_virtual_env`] instead.
+pub fn find_pipenv_env(dir: &Path) -> Option<VenvInfo> {
+    pipenv_env(&ProcessEnv, &OsFileSystem, dir)
+}
+
+fn pipenv_env(env: &dyn EnvProvider, file_system: &dyn FileSystem, dir: &Path) -> Option<VenvInfo> {
+    let custom_name = env
+        .var("PIPENV_CUSTOM_VENV_NAME")
+        .filter(|name| !name.is_empty());
+    find_pipenv_env_under(
+        file_system,
+        dir,
+        &pipenv_workon_home(env)?,
+        custom_name.as_deref(),
+    )
+}
+
+fn find_pipenv_env_under(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    workon_home: &Path,
+    custom_name: Option<&str>,
+) -> Option<VenvInfo> {
+    let printable_dir = dir.display();
+    log::info!("Searching for a Pipfile in {printable_dir} and parent directories");
+    let project_dir = dir
+        .ancestors()
+        .find(|path| file_system.is_file(&path.join("Pipfile")))?;
+    let project_dir = file_system.canonicalize(project_dir).ok()?;
+    log::debug!("Pipenv project found at {}", project_dir.display());
+    let env_name = match custom_name {
+        Some(name) => name.to_string(),
+        None => pipenv_env_name(&project_dir)?,
+    };
+    VenvInfo::from_root_of(file_system, &workon_home.join(env_name), false)
+}
+
+/// Searches for the Poetry project containing the specified directory and
+/// returns the virtual environment Poetry created for it outside of the
+/// project.
+///
+/// If the project has virtual environments for multiple versions of Python,
+/// the one Poetry was last told to use (via `poetry env use`) is preferred,
+/// else the newest version. Virtual environments within the project (i.e.
+/// `.venv`) are found by [`find_virtual_env`] instead.
+pub fn find_poetry_env(dir: &Path) -> Option<VenvInfo> {
+    poetry_env(&ProcessEnv, &OsFileSystem, dir)
+}
+
+fn poetry_env(env: &dyn EnvProvider, file_system: &dyn FileSystem, dir: &Path) -> Option<VenvInfo> {
+    find_poetry_env_under(file_system, dir, &poetry_virtualenvs_dir(env)?)
+}
+
+fn find_poetry_env_under(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    virtualenvs_dir: &Path,
+) -> Option<VenvInfo> {
+    let printable_dir = dir.display();
+    log::info!("Searching for a Poetry project in {printable_dir} and parent directories");
+    let (project_dir, project_name) = dir
+        .ancestors()
+        .find_map(|path| poetry_project_name(file_system, path).map(|name| (path, name)))?;
+    let project_dir = file_system.canonicalize(project_dir).ok()?;
+    log::debug!(
+        "Poetry project {project_name} found at {}",
+        project_dir.display()
+    );
+    let base_name = poetry_env_base_name(&project_name, &project_dir);
+    log::info!(
+        "Checking {} for {base_name}-py* virtual environments",
+        virtualenvs_dir.display()
+    );
+
+    let preferred_version = file_system
+        .read_to_string(&virtualenvs_dir.join("envs.toml"))
+        .ok()
+        .and_then(|contents| contents.parse::<toml::Value>().ok())
+        .and_then(|envs| {
+            envs.get(&base_name)?
+                .get("minor")?
+                .as_str()
+                .map(String::from)
+        });
+    let mut environments = directory_entries(file_system, [virtualenvs_dir.to_path_buf()])
+        .filter_map(|path| {
+            let file_name = path.file_name()?.to_str()?;
+            let version = file_name
+                .strip_prefix(&base_name)?
+                .strip_prefix("-py")?
+                .to_string();
+            let (major, minor) = version.split_once('.')?;
+            let sort_key = (
+                major.parse::<ComponentSize>().ok()?,
+                minor.parse::<ComponentSize>().ok()?,
+            );
+            Some((
+                version == preferred_version.as_deref().unwrap_or_default(),
+                sort_key,
+                path,
+            ))
+        })
+        .collect::<Vec<(bool, (ComponentSize, ComponentSize), PathBuf)>>();
+    environments.sort_unstable();
+    environments
+        .into_iter()
+        .rev()
+        .find_map(|(_, _, path)| VenvInfo::from_root_of(file_system, &path, false))
+}
+
+/// Which virtual environments are considered by [`find_environment`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VenvDetection {
+    /// Activated environments, followed by searching the current directory
+    /// and its parents.
+    Search,
+    /// Only activated environments (i.e. `VIRTUAL_ENV` and `CONDA_PREFIX`).
+    ActivatedOnly,
+    /// No environments at all.
+    Disabled,
+}
+
+impl Default for VenvDetection {
+    fn default() -> Self {
+        Self::Search
+    }
+}
+
+/// Finds every environment which is considered by [`find_environment`], along
+/// with the kind of environment it is.
+///
+/// The kinds are `virtual-env` (an [activated](active_virtual_env) virtual
+/// environment), `conda`, `venv` (found via [`find_virtual_env`]), `poetry`,
+/// and `pipenv`, in the order [`find_environment`] considers them. An
+/// activated virtual environment lacking a Python executable is skipped, as are
+/// conda, Poetry, and Pipenv environments if their [source](SOURCES) is
+/// disabled.
+pub fn all_environments(detection: VenvDetection) -> Vec<(&'static str, VenvInfo)> {
+    all_environments_in(&ProcessEnv, detection)
+}
+
+/// Finds every environment which is considered by [`find_environment_in`]
+/// (see [`all_environments`]).
+pub fn all_environments_in(
+    env: &dyn EnvProvider,
+    detection: VenvDetection,
+) -> Vec<(&'static str, VenvInfo)> {
+    environments(env, &OsFileSystem, detection, &env_disabled_sources(env))
+}
+
+/// [`all_environments_in`] on a [`FileSystem`] with the specified sources
+/// disabled.
+fn environments(
+    env: &dyn EnvProvider,
+    file_system: &dyn FileSystem,
+    detection: VenvDetection,
+    disabled: &[String],
+) -> Vec<(&'static str, VenvInfo)> {
+    let mut environments = Vec::new();
+    if detection == VenvDetection::Disabled {
+        return environments;
+    }
+
+    environments.extend(active_virtual_env_of(env, file_system).map(|venv| ("virtual-env", venv)));
+    if source_enabled("conda", disabled) {
+        environments.extend(active_conda_env_of(env, file_system).map(|venv| ("conda", venv)));
+    }
+    if detection == VenvDetection::Search {
+        if let Ok(cwd) = env.current_dir() {
+            environments.extend(find_virtual_env_of(file_system, &cwd).map(|venv| ("venv", venv)));
+            if source_enabled("poetry", disabled) {
+                environments
+                    .extend(poetry_env(env, file_system, &cwd).map(|venv| ("poetry", venv)));
+            }
+            if source_enabled("pipenv", disabled) {
+                environments
+                    .extend(pipenv_env(env, file_system, &cwd).map(|venv| ("pipenv", venv)));
+            }
+        }
+    }
+    environments
+}
+
+/// Finds the environment to use when no version of Python is requested.
+///
+/// An [activated virtual environment](active_virtual_env) is used first,
+/// followed by an [activated conda environment](active_conda_env). With
+/// [`VenvDetection::Search`], the current directory and its parents are then
+/// searched via [`find_virtual_env`], [`find_poetry_env`], and
+/// [`find_pipenv_env`], in that order. The `conda`, `poetry`, and `pipenv`
+/// [sources](SOURCES) may be disabled.
+///
+/// # Errors
+///
+/// If `VIRTUAL_ENV` is set but does not contain a Python executable,
+/// [`SearchError::InvalidVirtualEnv`] is returned (unless detection is
+/// [disabled](VenvDetection::Disabled)).
+pub fn find_environment(detection: VenvDetection) -> Result<Option<VenvInfo>> {
+    find_environment_in(&ProcessEnv, detection)
+}
+
+/// Finds the environment to use in an environment when no version of Python
+/// is requested (see [`find_environment`]).
+///
+/// # Errors
+///
+/// See [`find_environment`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(env), ret, err)
+)]
+pub fn find_environment_in(
+    env: &dyn EnvProvider,
+    detection: VenvDetection,
+) -> Result<Option<VenvInfo>> {
+    find_environment_with(env, &OsFileSystem, detection, &env_disabled_sources(env))
+}
+
+/// [`find_environment_in`] on a [`FileSystem`] with the specified sources
+/// disabled.
+fn find_environment_with(
+    env: &dyn EnvProvider,
+    file_system: &dyn FileSystem,
+    detection: VenvDetection,
+    disabled: &[String],
+) -> Result<Option<VenvInfo>> {
+    if detection == VenvDetection::Disabled {
+        log::info!("Virtual environment detection is disabled");
+        return Ok(None);
+    }
+
+    if let Some(venv) = active_virtual_env_of(env, file_system) {
+        return Ok(Some(venv));
+    } else if let Some(venv_root) = env.var_os("VIRTUAL_ENV") {
+        return Err(Error::Search(SearchError::InvalidVirtualEnv(
+            PathBuf::from(venv_root),
+        )));
+    } else if let Some(conda_env) =
+        active_conda_env_of(env, file_system).filter(|_| source_enabled("conda", disabled))
+    {
+        return Ok(Some(conda_env));
+    }
+
+    if detection == VenvDetection::ActivatedOnly {
+        log::info!("Not searching for a virtual environment");
+        return Ok(None);
+    }
+    match env.current_dir() {
+        Ok(cwd) => Ok(find_virtual_env_of(file_system, &cwd)
+            .or_else(|| {
+                poetry_env(env, file_system, &cwd).filter(|_| source_enabled("poetry", disabled))
+            })
+            .or_else(|| {
+                pipenv_env(env, file_system, &cwd).filter(|_| source_enabled("pipenv", disabled))
+            })),
+        Err(_) => {
+            log::warn!("current working directory is invalid");
+            Ok(None)
+        }
+    }
+}
+
+/// The newest `major.minor` version of CPython known to have had a final
+/// release.
+///
+/// Any newer version is checked for whether it is a pre-release by running it.
+const NEWEST_FINAL_RELEASE: (ComponentSize, ComponentSize) = (3, 14);
+
+/// Checks if the executable is a pre-release version of Python.
+///
+/// Only CPython versions newer than [`NEWEST_FINAL_RELEASE`] are considered
+/// possible pre-releases, in which case the executable is run to get its
+/// `sys.version_info.releaselevel`. If that fails then the executable is
+/// assumed to not be a pre-release.
+fn is_prerelease(version: &ExactVersion, path: &Path) -> bool {
+    if version.implementation != Implementation::CPython
+        || (version.major, version.minor) <= NEWEST_FINAL_RELEASE
+    {
+        return false;
+    }
+
+    let printable_path = path.display();
+    log::info!("Checking if {printable_path} is a pre-release");
+    match Command::new(path)
+        .args(["-c", "import sys; print(sys.version_info.releaselevel)"])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            let release_level = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            log::debug!("{printable_path} has a release level of {release_level:?}");
+            release_level != "final"
+        }
+        _ => {
+            log::warn!("Unable to determine the release level of {printable_path}");
+            false
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip(found_executables),
+        fields(requested = %requested),
+        ret
+    )
+)]
+fn find_executable_in_hashmap(
+    requested: &VersionSpecifier,
+    found_executables: &HashMap<ExactVersion, PathBuf>,
+) -> Option<PathBuf> {
+    let mut candidates = found_executables
+        .iter()
+        .filter(|pair| requested.contains(pair.0))
+        .collect::<Vec<(&ExactVersion, &PathBuf)>>();
+    candidates.sort_unstable();
+
+    candidates
+        .into_iter()
+        .rev()
+        .find(|(version, path)| requested.prereleases || !is_prerelease(version, path))
+        .map(|pair| pair.1.clone())
+}
+
+/// Every executable which satisfies `requested`, from newest to oldest
+/// version.
+fn executables_in_hashmap(
+    requested: &VersionSpecifier,
+    found_executables: &HashMap<ExactVersion, PathBuf>,
+) -> Vec<(ExactVersion, PathBuf)> {
+    let mut candidates = found_executables
+        .iter()
+        .filter(|(version, path)| {
+            requested.contains(version) && (requested.prereleases || !is_prerelease(version, path))
+        })
+        .map(|(version, path)| (*version, path.clone()))
+        .collect::<Vec<(ExactVersion, PathBuf)>>();
+    candidates.sort_unstable();
+    candidates.reverse();
+    candidates
+}
+
+fn preferred_specifiers(
+    preferences: &VersionPreferenceList,
+) -> impl Iterator<Item = (RequestedVersion, VersionSpecifier)> + '_ {
+    preferences.versions.iter().map(move |requested| {
+        let mut specifier = VersionSpecifier::from(*requested);
+        // A request for a stable version never allows for pre-releases.
+        specifier.prereleases |= preferences.prereleases && *requested != RequestedVersion::Stable;
+        specifier
+            .clauses
+            .extend(preferences.excluded.iter().cloned());
+        (*requested, specifier)
+    })
+}
+
+fn find_preferred_executable_in_hashmap(
+    preferences: &VersionPreferenceList,
+    found_executables: &HashMap<ExactVersion, PathBuf>,
+) -> Option<PathBuf> {
+    preferred_specifiers(preferences).find_map(|(requested, specifier)| {
+        log::info!("Searching for {requested}");
+        find_executable_in_hashmap(&specifier, found_executables)
+    })
+}
+
+fn find_preferred_executables_in_hashmap(
+    preferences: &VersionPreferenceList,
+    found_executables: &HashMap<ExactVersion, PathBuf>,
+) -> Vec<(ExactVersion, PathBuf)> {
+    preferred_specifiers(preferences)
+        .map(|(requested, specifier)| {
+            log::info!("Searching for every {requested}");
+            executables_in_hashmap(&specifier, found_executables)
+        })
+        .find(|candidates| !candidates.is_empty())
+        .unwrap_or_default()
+}
+
+/// Attempts to find the newest executable on `PATH` that satisfies a specified
+/// [`RequestedVersion`] or [`VersionSpecifier`].
+///
+/// Pre-release versions of Python are skipped unless allowed by
+/// [`VersionSpecifier::prereleases`]. CPython versions newer than the newest
+/// version known to this crate to have had a final release are run to
+/// determine whether they are a pre-release.
+pub fn find_executable(requested: impl Into<VersionSpecifier>) -> Option<PathBuf> {
+    process_search(|search| search.find_executable(requested))
+}
+
+/// Finds every executable which satisfies a specified [`RequestedVersion`] or
+/// [`VersionSpecifier`], from newest to oldest version.
+///
+/// Unlike [`find_executable`], no executable is passed over because another
+/// was found for the same version first: every
+/// [occurrence](all_executable_occurrences) is included, e.g. both
+/// `/usr/local/bin/python3.12` and `/usr/bin/python3.12`, in the order they are
+/// searched. Pre-releases are skipped as [`find_executable`] does.
+pub fn find_all_executables(
+    requested: impl Into<VersionSpecifier>,
+) -> Vec<(ExactVersion, PathBuf)> {
+    let requested = requested.into();
+    let mut candidates = all_executable_occurrences()
+        .into_iter()
+        .filter(|(_, version, path)| {
+            requested.contains(version) && (requested.prereleases || !is_prerelease(version, path))
+        })
+        .map(|(_, version, path)| (version, path))
+        .collect::<Vec<(ExactVersion, PathBuf)>>();
+    // A stable sort keeps the executables for the same version in the order
+    // they were searched.
+    candidates.sort_by(|(left, _), (right, _)| right.cmp(left));
+    candidates
+}
+
+/// Attempts to find an executable on `PATH` for each [`RequestedVersion`] of a
+/// [`VersionPreferenceList`] in order, returning the first one found.
+///
+/// Each requested version is searched for as [`find_executable`] would.
+pub fn find_preferred_executable(preferences: &VersionPreferenceList) -> Option<PathBuf> {
+    process_search(|search| search.find_preferred_executable(preferences))
+}
+
+/// Attempts to find a Windows-native executable under WSL for each
+/// [`RequestedVersion`] of a [`VersionPreferenceList`] in order, returning the
+/// first one found.
+///
+/// The executables searched are those found by [`windows_executables`].
+pub fn find_preferred_windows_executable(preferences: &VersionPreferenceList) -> Option<PathBuf> {
+    process_search(|search| search.find_preferred_windows_executable(preferences))
+}
+
+/// Lazily finds the executables from the specified sources which satisfy a
+/// [`RequestedVersion`] or [`VersionSpecifier`], skipping any sources which are
+/// [disabled](SOURCES).
+///
+/// Sources and their directories are only searched as the iterator is
+/// advanced, so stopping early (e.g. via [`Iterator::next`]) avoids reading
+/// any directories after the executable which was wanted. Only the first
+/// executable found for each version is yielded, in the order they are found
+/// rather than from newest to oldest version; the first executable is
+/// therefore the one [`find_executable`] would find when only one version
+/// satisfies the request (e.g. `3.12`). Pre-releases are skipped as
+/// [`find_executable`] does.
+pub fn find_executables_iter_from(
+    sources: Vec<Box<dyn InterpreterSource>>,
+    requested: impl Into<VersionSpecifier>,
+) -> impl Iterator<Item = (ExactVersion, PathBuf)> {
+    let requested = requested.into();
+    let disabled = env_disabled_sources(&ProcessEnv);
+    let mut found_versions = HashSet::new();
+    sources
+        .into_iter()
+        .filter(move |source| source_enabled(source.name(), &disabled))
+        .flat_map(|source| source.iter_executables())
+        .filter(move |(version, path)| {
+            requested.contains(version)
+                && found_versions.insert(*version)
+                && (requested.prereleases || !is_prerelease(version, path))
+        })
+}
+
+/// Lazily finds the executables from the [built-in sources](default_sources)
+/// which satisfy a [`RequestedVersion`] or [`VersionSpecifier`] (see
+/// [`find_executables_iter_from`]).
+pub fn find_executables_iter(
+    requested: impl Into<VersionSpecifier>,
+) -> impl Iterator<Item = (ExactVersion, PathBuf)> {
+    find_executables_iter_from(default_sources(), requested)
+}
+
+/// Finds every executable on `PATH` which satisfies the first
+/// [`RequestedVersion`] of a [`VersionPreferenceList`] that any executable
+/// satisfies, from newest to oldest version.
+///
+/// The first executable is the one [`find_preferred_executable`] would find.
+pub fn find_preferred_executables(
+    preferences: &VersionPreferenceList,
+) -> Vec<(ExactVersion, PathBuf)> {
+    process_search(|search| search.find_preferred_executables(preferences))
+}
+
+/// Finds every Windows-native executable under WSL as
+/// [`find_preferred_executables`] does (see
+/// [`find_preferred_windows_executable`]).
+pub fn find_preferred_windows_executables(
+    preferences: &VersionPreferenceList,
+) -> Vec<(ExactVersion, PathBuf)> {
+    process_search(|search| search.find_preferred_windows_executables(preferences))
+}
+
+/// Creates an empty file at `path` for tests, returning the path.
+#[cfg(test)]
+pub(crate) fn touch_file(path: PathBuf) -> PathBuf {
+    fs::File::create(&path).unwrap().sync_all().unwrap();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_case::test_case;
+
+    #[test_case(RequestedVersion::Any => "Python" ; "Any")]
+    #[test_case(RequestedVersion::MajorOnly(3) => "Python 3" ; "Major")]
+    #[test_case(RequestedVersion::Exact(3, 8) => "Python 3.8" ; "Exact/major.minor")]
+    #[test_case(RequestedVersion::ExactMicro(3, 8, 2) => "Python 3.8.2" ; "ExactMicro/major.minor.micro")]
+    #[test_case(RequestedVersion::AtLeast(3, 9) => "Python 3.9+" ; "AtLeast/major.minor+")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13) => "Python 3.13t" ; "FreeThreaded/major.minort")]
+    #[test_case(RequestedVersion::Implementation(Implementation::GraalPy, None, None) => "GraalPy" ; "Implementation/any")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), None) => "PyPy 3" ; "Implementation/major")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(10)) => "PyPy 3.10" ; "Implementation/major.minor")]
+    #[test_case(RequestedVersion::Stable => "stable Python" ; "Stable")]
+    #[test_case(RequestedVersion::Latest => "latest Python" ; "Latest")]
+    fn requestedversion_to_string_tests(requested_version: RequestedVersion) -> String {
+        requested_version.to_string()
+    }
+
+    #[test_case(".3" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "missing major version is an error")]
+    #[test_case("3." => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "missing minor version is an error")]
+    #[test_case("h" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "non-number, non-emptry string is an error")]
+    #[test_case("3.b" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "major.minor where minor is a non-number is an error")]
+    #[test_case("a.7" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "major.minor where major is a non-number is an error")]
+    #[test_case("" => Ok(RequestedVersion::Any) ; "empty string is Any")]
+    #[test_case("3" => Ok(RequestedVersion::MajorOnly(3)) ; "major-only version")]
+    #[test_case("3.8" => Ok(RequestedVersion::Exact(3, 8)) ; "major.minor")]
+    #[test_case("42.13" => Ok(RequestedVersion::Exact(42, 13)) ; "double digit version components")]
+    #[test_case("3.6.5" => Ok(RequestedVersion::ExactMicro(3, 6, 5)) ; "major.minor.micro")]
+    #[test_case("3.6.b" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "major.minor.micro where micro is a non-number is an error")]
+    #[test_case("3.6.5.1" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "specifying more than a micro version is an error")]
+    #[test_case("3.9+" => Ok(RequestedVersion::AtLeast(3, 9)) ; "major.minor+")]
+    #[test_case("3+" => Err(Error::Version(VersionError::DotMissing)) ; "major-only minimum version is an error")]
+    #[test_case("3.9.1+" => Err(Error::Version(VersionError::InvalidVersionSpecifier("3.9.1+".to_string()))) ; "minimum micro version is an error")]
+    #[test_case("3.13t" => Ok(RequestedVersion::FreeThreaded(3, 13)) ; "free-threaded major.minor")]
+    #[test_case("3t" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "free-threaded major-only is an error")]
+    #[test_case("3.13.1t" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "free-threaded micro version is an error")]
+    #[test_case("graalpy" => Ok(RequestedVersion::Implementation(Implementation::GraalPy, None, None)) ; "alternative implementation")]
+    #[test_case("pypy3" => Ok(RequestedVersion::Implementation(Implementation::PyPy, Some(3), None)) ; "alternative implementation with major version")]
+    #[test_case("pypy3.10" => Ok(RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(10))) ; "alternative implementation with major.minor version")]
+    #[test_case("pypy3.10.2" => Err(Error::Version(VersionError::InvalidVersionSpecifier("pypy3.10.2".to_string()))) ; "alternative implementation with micro version is an error")]
+    #[test_case("pypyX" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "alternative implementation with non-digit version is an error")]
+    #[test_case("python3.10" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "CPython prefix is an error")]
+    #[test_case("stable" => Ok(RequestedVersion::Stable) ; "stable keyword")]
+    #[test_case("latest" => Ok(RequestedVersion::Latest) ; "latest keyword")]
+    #[test_case("Latest" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "keywords are case-sensitive")]
+    #[test_case("pypylatest" => matches Err(_) ; "alternative implementation with keyword is an error")]
+    fn requestedversion_from_str_tests(version_str: &str) -> Result<RequestedVersion> {
+        RequestedVersion::from_str(version_str)
+    }
+
+    #[test_case(RequestedVersion::Any => Some("PY_PYTHON".to_string()) ; "Any is PY_PYTHON")]
+    #[test_case(RequestedVersion::MajorOnly(3) => Some("PY_PYTHON3".to_string()) ; "major-only is PY_PYTHON{major}")]
+    #[test_case(RequestedVersion::MajorOnly(42) => Some("PY_PYTHON42".to_string()) ; "double-digit major component")]
+    #[test_case(RequestedVersion::Exact(42, 13) => None ; "exact/major.minor has no environment variable")]
+    #[test_case(RequestedVersion::ExactMicro(3, 10, 2) => None ; "exact/major.minor.micro has no environment variable")]
+    #[test_case(RequestedVersion::AtLeast(3, 9) => None ; "at least major.minor has no environment variable")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13) => None ; "free-threaded has no environment variable")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, None, None) => None ; "alternative implementation has no environment variable")]
+    fn requstedversion_env_var_tests(requested_version: RequestedVersion) -> Option<String> {
+        requested_version.env_var()
+    }
+
+    #[test]
+    fn test_requestedversion_from_exactversion() {
+        assert_eq!(
+            RequestedVersion::from(ExactVersion::new(42, 13)),
+            RequestedVersion::Exact(42, 13)
+        );
+        assert_eq!(
+            RequestedVersion::from(ExactVersion::with_micro(3, 10, 2)),
+            RequestedVersion::ExactMicro(3, 10, 2)
+        );
+        assert_eq!(
+            RequestedVersion::from(ExactVersion {
+                major: 3,
+                minor: 13,
+                micro: None,
+                free_threaded: true,
+                implementation: Implementation::CPython,
+            }),
+            RequestedVersion::FreeThreaded(3, 13)
+        );
+        assert_eq!(
+            RequestedVersion::from(ExactVersion {
+                major: 3,
+                minor: 10,
+                micro: Some(2),
+                free_threaded: false,
+                implementation: Implementation::PyPy,
+            }),
+            RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(10))
+        );
+    }
+
+    #[test] // For some reason, having Ordering breaks test-case 1.0.0.
+    fn exactversion_comparisons() {
+        let py2_7 = ExactVersion::new(2, 7);
+        let py3_0 = ExactVersion::new(3, 0);
+        let py3_6 = ExactVersion::new(3, 6);
+        let py3_10 = ExactVersion::new(3, 10);
+
+        // ==
+        assert_eq!(py3_10.cmp(&py3_10), Ordering::Equal);
+        // <
+        assert_eq!(py3_0.cmp(&py3_6), Ordering::Less);
+        // >
+        assert_eq!(py3_6.cmp(&py3_0), Ordering::Greater);
+        // Differ by major version.
+        assert_eq!(py2_7.cmp(&py3_0), Ordering::Less);
+        assert_eq!(py3_0.cmp(&py2_7), Ordering::Greater);
+        // Sort order different from lexicographic order.
+        assert_eq!(py3_6.cmp(&py3_10), Ordering::Less);
+        assert_eq!(py3_10.cmp(&py3_6), Ordering::Greater);
+        // Micro versions.
+        let py3_10_2 = ExactVersion::with_micro(3, 10, 2);
+        let py3_10_11 = ExactVersion::with_micro(3, 10, 11);
+        assert_eq!(py3_10_2.cmp(&py3_10_11), Ordering::Less);
+        assert_eq!(py3_10.cmp(&py3_10_2), Ordering::Less);
+        assert_eq!(py3_6.cmp(&py3_10_2), Ordering::Less);
+    }
+
+    #[test_case(3, 8, None, false => "3.8" ; "single digits")]
+    #[test_case(42, 13, None, false => "42.13" ; "double digits")]
+    #[test_case(3, 10, Some(2), false => "3.10.2" ; "micro version")]
+    #[test_case(3, 13, None, true => "3.13t" ; "free-threaded")]
+    fn exactversion_to_string_tests(
+        major: ComponentSize,
+        minor: ComponentSize,
+        micro: Option<ComponentSize>,
+        free_threaded: bool,
+    ) -> String {
+        ExactVersion {
+            major,
+            minor,
+            micro,
+            free_threaded,
+            implementation: Implementation::CPython,
+        }
+        .to_string()
+    }
+
+    #[test_case(Implementation::CPython => "3.10" ; "CPython has no prefix")]
+    #[test_case(Implementation::PyPy => "pypy3.10" ; "PyPy")]
+    #[test_case(Implementation::GraalPy => "graalpy3.10" ; "GraalPy")]
+    fn exactversion_implementation_to_string_tests(implementation: Implementation) -> String {
+        ExactVersion {
+            implementation,
+            ..ExactVersion::new(3, 10)
+        }
+        .to_string()
+    }
+
+    #[test_case("" => Err(Error::Version(VersionError::DotMissing)) ; "empty string is an error")]
+    #[test_case("3" => Err(Error::Version(VersionError::DotMissing)) ; "major-only version is an error")]
+    #[test_case(".7" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "missing major version is an error")]
+    #[test_case("3." => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "missing minor version is an error")]
+    #[test_case("3.Y" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "non-digit minor version is an error")]
+    #[test_case("X.7" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "non-digit major version is an error")]
+    #[test_case("42.13" => Ok(ExactVersion::new(42, 13)) ; "double digit version components")]
+    #[test_case("3.10.2" => Ok(ExactVersion::with_micro(3, 10, 2)) ; "micro version")]
+    #[test_case("3.10." => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "missing micro version is an error")]
+    #[test_case("3.10.Z" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "non-digit micro version is an error")]
+    #[test_case("3.13t" => Ok(ExactVersion { major: 3, minor: 13, micro: None, free_threaded: true, implementation: Implementation::CPython }) ; "free-threaded")]
+    #[test_case("3.13tt" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "repeated free-threaded suffix is an error")]
+    #[test_case("pypy3.10" => Ok(ExactVersion { implementation: Implementation::PyPy, ..ExactVersion::new(3, 10) }) ; "alternative implementation")]
+    fn exactversion_from_str_tests(version_str: &str) -> Result<ExactVersion> {
+        ExactVersion::from_str(version_str)
+    }
+
+    #[test_case("/" => Err(Error::Version(VersionError::FileNameMissing(PathBuf::from("/")))) ; "path missing a file name is an error")]
+    #[test_case("/notpython" => Err(Error::Version(VersionError::PathFileNameError(PathBuf::from("/notpython")))) ; "path not ending with 'python' is an error")]
+    #[test_case("/python3" => Err(Error::Version(VersionError::PathFileNameError(PathBuf::from("/python3")))) ; "filename lacking a minor component is an error")]
+    #[test_case("/pythonX.Y" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "filename with non-digit version is an error")]
+    #[test_case("/python42.13" => Ok(ExactVersion::new(42, 13)) ; "double digit version components")]
+    #[test_case("/python3.10.2" => Ok(ExactVersion::with_micro(3, 10, 2)) ; "micro version")]
+    #[test_case("/python3.13t" => Ok(ExactVersion { major: 3, minor: 13, micro: None, free_threaded: true, implementation: Implementation::CPython }) ; "free-threaded")]
+    #[test_case("/pypy3.10" => Ok(ExactVersion { implementation: Implementation::PyPy, ..ExactVersion::new(3, 10) }) ; "PyPy")]
+    #[test_case("/graalpy3.10" => Ok(ExactVersion { implementation: Implementation::GraalPy, ..ExactVersion::new(3, 10) }) ; "GraalPy")]
+    #[test_case("/pypy3" => Err(Error::Version(VersionError::PathFileNameError(PathBuf::from("/pypy3")))) ; "PyPy filename lacking a minor component is an error")]
+    #[test_case("/graalpy" => Err(Error::Version(VersionError::PathFileNameError(PathBuf::from("/graalpy")))) ; "GraalPy filename lacking a version is an error")]
+    fn exactversion_from_path_tests(path: &str) -> Result<ExactVersion> {
+        ExactVersion::from_path(&PathBuf::from(path))
+    }
+
+    #[test]
+    fn path_errors_name_the_path() {
+        let error = ExactVersion::from_path(Path::new("/usr/bin/notpython")).unwrap_err();
+        assert!(error.to_string().contains("/usr/bin/notpython"));
+    }
+
+    #[test]
+    fn exactversion_from_path_invalid_utf8() {
+        // From https://doc.rust-lang.org/std/ffi/struct.OsStr.html#examples-2.
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let source = [0x66, 0x6f, 0x80, 0x6f];
+        let os_str = OsStr::from_bytes(&source[..]);
+        let path = PathBuf::from(os_str);
+        assert_eq!(
+            ExactVersion::from_path(&path),
+            Err(Error::Version(VersionError::FileNameToStrError(
+                path.clone()
+            )))
+        );
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case(RequestedVersion::Any => true ; "Any supports all versions")]
+    #[test_case(RequestedVersion::MajorOnly(2) => false ; "major-only mismatch")]
+    #[test_case(RequestedVersion::MajorOnly(3) => true ; "major-only match")]
+    #[test_case(RequestedVersion::Exact(2, 7) => false ; "older major version")]
+    #[test_case(RequestedVersion::Exact(3, 5) => false ; "older minor version")]
+    #[test_case(RequestedVersion::Exact(4, 0) => false ; "newer major version")]
+    #[test_case(RequestedVersion::Exact(3, 7) => false ; "newer minor version")]
+    #[test_case(RequestedVersion::Exact(3, 6) => true ; "same version")]
+    #[test_case(RequestedVersion::AtLeast(3, 5) => true ; "at least older minor version")]
+    #[test_case(RequestedVersion::AtLeast(2, 7) => true ; "at least older major version")]
+    #[test_case(RequestedVersion::AtLeast(3, 6) => true ; "at least same version")]
+    #[test_case(RequestedVersion::AtLeast(3, 7) => false ; "at least newer minor version")]
+    #[test_case(RequestedVersion::AtLeast(4, 0) => false ; "at least newer major version")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 6) => false ; "free-threaded same version")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, None, None) => false ; "alternative implementation")]
+    fn exactversion_supports_tests(requested_version: RequestedVersion) -> bool {
+        let example = ExactVersion::new(3, 6);
+        example.supports(requested_version)
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case(None, RequestedVersion::Exact(3, 10) => true ; "unknown micro supports major.minor")]
+    #[test_case(None, RequestedVersion::ExactMicro(3, 10, 2) => false ; "unknown micro does not support major.minor.micro")]
+    #[test_case(Some(2), RequestedVersion::Exact(3, 10) => true ; "known micro supports major.minor")]
+    #[test_case(Some(2), RequestedVersion::ExactMicro(3, 10, 2) => true ; "same micro version")]
+    #[test_case(Some(2), RequestedVersion::ExactMicro(3, 10, 3) => false ; "different micro version")]
+    #[test_case(Some(2), RequestedVersion::ExactMicro(3, 9, 2) => false ; "different minor version with same micro")]
+    fn exactversion_supports_micro_tests(
+        micro: Option<ComponentSize>,
+        requested_version: RequestedVersion,
+    ) -> bool {
+        let example = ExactVersion {
+            major: 3,
+            minor: 10,
+            micro,
+            free_threaded: false,
+            implementation: Implementation::CPython,
+        };
+        example.supports(requested_version)
+    }
+
+    #[test_case(">=3.9" => Ok(VersionClause { operator: ComparisonOperator::GreaterThanOrEqual, release: vec![3, 9], wildcard: false }) ; "greater than or equal")]
+    #[test_case(" < 3.12 " => Ok(VersionClause { operator: ComparisonOperator::LessThan, release: vec![3, 12], wildcard: false }) ; "surrounding whitespace")]
+    #[test_case("==3.*" => Ok(VersionClause { operator: ComparisonOperator::Equal, release: vec![3], wildcard: true }) ; "equal with wildcard")]
+    #[test_case("~=3.10.2" => Ok(VersionClause { operator: ComparisonOperator::Compatible, release: vec![3, 10, 2], wildcard: false }) ; "compatible with micro version")]
+    #[test_case("3.9" => Err(Error::Version(VersionError::InvalidVersionSpecifier("3.9".to_string()))) ; "missing operator is an error")]
+    #[test_case(">=3.*" => Err(Error::Version(VersionError::InvalidVersionSpecifier(">=3.*".to_string()))) ; "wildcard with ordered comparison is an error")]
+    #[test_case("~=3" => Err(Error::Version(VersionError::InvalidVersionSpecifier("~=3".to_string()))) ; "compatible with only major version is an error")]
+    #[test_case("==3.10.2.1" => Err(Error::Version(VersionError::InvalidVersionSpecifier("==3.10.2.1".to_string()))) ; "more than a micro version is an error")]
+    #[test_case(">=3.10rc1" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "pre-release is an error")]
+    fn versionclause_from_str_tests(clause_str: &str) -> Result<VersionClause> {
+        VersionClause::from_str(clause_str)
+    }
+
+    #[test_case("" => "" ; "empty specifier")]
+    #[test_case(">= 3.9, <3.12" => ">=3.9,<3.12" ; "whitespace is normalized")]
+    #[test_case("!=3.8.*" => "!=3.8.*" ; "wildcard")]
+    fn versionspecifier_to_string_tests(specifier_str: &str) -> String {
+        VersionSpecifier::from_str(specifier_str)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test_case(">=3.9,<3.12" => matches Ok(_) ; "multiple clauses")]
+    #[test_case(">=3.9," => matches Err(Error::Version(VersionError::InvalidVersionSpecifier(_))) ; "trailing comma is an error")]
+    #[test_case(">=3.9,3.12" => matches Err(Error::Version(VersionError::InvalidVersionSpecifier(_))) ; "clause missing an operator is an error")]
+    fn versionspecifier_from_str_tests(specifier_str: &str) -> Result<VersionSpecifier> {
+        VersionSpecifier::from_str(specifier_str)
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case("", 2, 7, None => true ; "empty specifier contains everything")]
+    #[test_case(">=3.9", 3, 9, None => true ; "greater than or equal, equal")]
+    #[test_case(">=3.9", 3, 8, None => false ; "greater than or equal, less")]
+    #[test_case(">3.9", 3, 9, Some(1) => true ; "greater than with micro version")]
+    #[test_case(">3.9", 3, 9, None => false ; "greater than, equal")]
+    #[test_case("<3.12", 3, 11, Some(4) => true ; "less than")]
+    #[test_case("<3.12", 3, 12, None => false ; "less than, equal")]
+    #[test_case("<=3.12", 3, 12, None => true ; "less than or equal, equal")]
+    #[test_case("==3.10", 3, 10, None => true ; "equal with unknown micro version")]
+    #[test_case("==3.10", 3, 10, Some(2) => false ; "equal is zero-padded")]
+    #[test_case("==3.10.*", 3, 10, Some(2) => true ; "equal with wildcard")]
+    #[test_case("!=3.8.*", 3, 8, Some(10) => false ; "not equal with wildcard")]
+    #[test_case("!=3.8", 3, 9, None => true ; "not equal")]
+    #[test_case("~=3.10", 3, 11, None => true ; "compatible with newer minor version")]
+    #[test_case("~=3.10", 4, 0, None => false ; "compatible with newer major version")]
+    #[test_case("~=3.10.2", 3, 10, Some(5) => true ; "compatible with newer micro version")]
+    #[test_case("~=3.10.2", 3, 11, None => false ; "compatible with newer minor version than allowed")]
+    #[test_case(">=3.9,<3.12", 3, 10, None => true ; "within range")]
+    #[test_case(">=3.9,<3.12", 3, 12, None => false ; "outside of range")]
+    fn versionspecifier_contains_tests(
+        specifier_str: &str,
+        major: ComponentSize,
+        minor: ComponentSize,
+        micro: Option<ComponentSize>,
+    ) -> bool {
+        let specifier = VersionSpecifier::from_str(specifier_str).unwrap();
+        specifier.contains(&ExactVersion {
+            major,
+            minor,
+            micro,
+            free_threaded: false,
+            implementation: Implementation::CPython,
+        })
+    }
+
+    #[test_case(RequestedVersion::Any => "" ; "Any has no clauses")]
+    #[test_case(RequestedVersion::MajorOnly(3) => "==3.*" ; "major-only")]
+    #[test_case(RequestedVersion::Exact(3, 10) => "==3.10.*" ; "major.minor")]
+    #[test_case(RequestedVersion::ExactMicro(3, 10, 2) => "==3.10.2" ; "major.minor.micro")]
+    #[test_case(RequestedVersion::AtLeast(3, 9) => ">=3.9" ; "at least major.minor")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13) => "==3.13.*" ; "free-threaded")]
+    fn versionspecifier_from_requestedversion_tests(requested_version: RequestedVersion) -> String {
+        VersionSpecifier::from(requested_version).to_string()
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case(RequestedVersion::Any, false => true ; "Any supports GIL build")]
+    #[test_case(RequestedVersion::Any, true => false ; "Any does not support free-threaded build")]
+    #[test_case(RequestedVersion::Exact(3, 13), true => false ; "major.minor does not support free-threaded build")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13), true => true ; "free-threaded supports free-threaded build")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13), false => false ; "free-threaded does not support GIL build")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 12), true => false ; "free-threaded different minor version")]
+    fn free_threaded_tests(requested_version: RequestedVersion, free_threaded: bool) -> bool {
+        let version = ExactVersion {
+            major: 3,
+            minor: 13,
+            micro: None,
+            free_threaded,
+            implementation: Implementation::CPython,
+        };
+        let specifier = VersionSpecifier::from(requested_version);
+        assert_eq!(
+            version.supports(requested_version),
+            specifier.contains(&version)
+        );
+        version.supports(requested_version)
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case(RequestedVersion::Any, Implementation::CPython => true ; "Any supports CPython")]
+    #[test_case(RequestedVersion::Any, Implementation::PyPy => false ; "Any does not support PyPy")]
+    #[test_case(RequestedVersion::Exact(3, 10), Implementation::PyPy => false ; "major.minor does not support PyPy")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, None, None), Implementation::PyPy => true ; "PyPy supports PyPy")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, None, None), Implementation::CPython => false ; "PyPy does not support CPython")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, None, None), Implementation::GraalPy => false ; "PyPy does not support GraalPy")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), None), Implementation::PyPy => true ; "PyPy major version match")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(2), None), Implementation::PyPy => false ; "PyPy major version mismatch")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(10)), Implementation::PyPy => true ; "PyPy major.minor version match")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(9)), Implementation::PyPy => false ; "PyPy major.minor version mismatch")]
+    fn implementation_tests(
+        requested_version: RequestedVersion,
+        implementation: Implementation,
+    ) -> bool {
+        let version = ExactVersion {
+            implementation,
+            ..ExactVersion::with_micro(3, 10, 2)
+        };
+        let specifier = VersionSpecifier::from(requested_version);
+        assert_eq!(
+            version.supports(requested_version),
+            specifier.contains(&version)
+        );
+        version.supports(requested_version)
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case(RequestedVersion::Any => false ; "Any")]
+    #[test_case(RequestedVersion::MajorOnly(3) => false ; "major-only")]
+    #[test_case(RequestedVersion::AtLeast(3, 9) => false ; "at least major.minor")]
+    #[test_case(RequestedVersion::Exact(3, 14) => true ; "major.minor")]
+    #[test_case(RequestedVersion::ExactMicro(3, 14, 0) => true ; "major.minor.micro")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 14) => true ; "free-threaded")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), None) => false ; "alternative implementation with major version")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(10)) => true ; "alternative implementation with major.minor version")]
+    #[test_case(RequestedVersion::Stable => false ; "stable")]
+    #[test_case(RequestedVersion::Latest => true ; "latest")]
+    fn versionspecifier_from_requestedversion_prereleases_tests(
+        requested_version: RequestedVersion,
+    ) -> bool {
+        VersionSpecifier::from(requested_version).prereleases
+    }
+
+    #[test_case("3" => Ok(vec![RequestedVersion::MajorOnly(3)]) ; "single version")]
+    #[test_case("3.12,3.11,3" => Ok(vec![RequestedVersion::Exact(3, 12), RequestedVersion::Exact(3, 11), RequestedVersion::MajorOnly(3)]) ; "multiple versions")]
+    #[test_case("3.12, 3" => Ok(vec![RequestedVersion::Exact(3, 12), RequestedVersion::MajorOnly(3)]) ; "whitespace is ignored")]
+    #[test_case("3.12,,3" => Err(Error::Version(VersionError::InvalidVersionSpecifier("3.12,,3".to_string()))) ; "empty entry")]
+    #[test_case("3.12," => Err(Error::Version(VersionError::InvalidVersionSpecifier("3.12,".to_string()))) ; "trailing comma")]
+    #[test_case("3.12,3.b" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "invalid entry")]
+    #[test_case("3,!3.8" => Ok(vec![RequestedVersion::MajorOnly(3)]) ; "exclusions are not requested")]
+    #[test_case("!3.8" => Ok(vec![RequestedVersion::Any]) ; "only exclusions requests any version")]
+    #[test_case("3,!3.b" => matches Err(Error::Version(VersionError::ParseVersionComponentError(_, _))) ; "invalid exclusion")]
+    #[test_case("3,!stable" => matches Err(Error::Version(VersionError::InvalidVersionSpecifier(_))) ; "unsupported exclusion")]
+    fn versionpreferencelist_from_str_tests(
+        preferences_str: &str,
+    ) -> Result<Vec<RequestedVersion>> {
+        VersionPreferenceList::from_str(preferences_str).map(|preferences| preferences.versions)
+    }
+
+    #[test_case(RequestedVersion::MajorOnly(3) => Ok("!=3.*".to_string()) ; "major")]
+    #[test_case(RequestedVersion::Exact(3, 8) => Ok("!=3.8.*".to_string()) ; "major.minor")]
+    #[test_case(RequestedVersion::ExactMicro(3, 8, 2) => Ok("!=3.8.2".to_string()) ; "major.minor.micro")]
+    #[test_case(RequestedVersion::AtLeast(3, 9) => Ok("<3.9".to_string()) ; "at least")]
+    #[test_case(RequestedVersion::Any => matches Err(Error::Version(VersionError::InvalidVersionSpecifier(_))) ; "any")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13) => matches Err(Error::Version(VersionError::InvalidVersionSpecifier(_))) ; "free-threaded")]
+    fn versionclause_excluding_tests(requested_version: RequestedVersion) -> Result<String> {
+        VersionClause::excluding(requested_version).map(|clause| clause.to_string())
+    }
+
+    #[test]
+    fn versionpreferencelist_to_string() {
+        let preferences = VersionPreferenceList::from_str("3.12,3").unwrap();
+        assert_eq!(preferences.to_string(), "Python 3.12, Python 3");
+    }
+
+    #[test_case("3.8" => None ; "single unavailable version")]
+    #[test_case("3.8,3.6" => Some(PathBuf::from("/python3.6")) ; "falls back to second version")]
+    #[test_case("3.7,3" => Some(PathBuf::from("/python3.7")) ; "first version preferred")]
+    #[test_case("3.8,3" => Some(PathBuf::from("/python3.7")) ; "falls back to major version")]
+    #[test_case("3.8,4" => None ; "nothing matches")]
+    #[test_case("4,stable" => Some(PathBuf::from("/python3.7")) ; "falls back to stable version")]
+    #[test_case("3,!3.7" => Some(PathBuf::from("/python3.6")) ; "excluded newest version")]
+    #[test_case("3.7,3.6,!3.7" => Some(PathBuf::from("/python3.6")) ; "exclusion applies to every version")]
+    #[test_case("3,!3" => None ; "everything excluded")]
+    fn find_preferred_executable_in_hashmap_tests(preferences_str: &str) -> Option<PathBuf> {
+        let mut executables = HashMap::new();
+        executables.insert(ExactVersion::new(3, 6), PathBuf::from("/python3.6"));
+        executables.insert(ExactVersion::new(3, 7), PathBuf::from("/python3.7"));
+
+        let preferences = VersionPreferenceList::from_str(preferences_str).unwrap();
+        find_preferred_executable_in_hashmap(&preferences, &executables)
+    }
+
+    #[test_case("3.8" => Vec::<PathBuf>::new() ; "single unavailable version")]
+    #[test_case("3" => vec![PathBuf::from("/python3.7"), PathBuf::from("/python3.6")] ; "newest first")]
+    #[test_case("3.8,3.6" => vec![PathBuf::from("/python3.6")] ; "falls back to second version")]
+    #[test_case("3.7,3" => vec![PathBuf::from("/python3.7")] ; "first version preferred")]
+    #[test_case("3,!3.7" => vec![PathBuf::from("/python3.6")] ; "excluded version")]
+    fn find_preferred_executables_in_hashmap_tests(preferences_str: &str) -> Vec<PathBuf> {
+        let mut executables = HashMap::new();
+        executables.insert(ExactVersion::new(3, 6), PathBuf::from("/python3.6"));
+        executables.insert(ExactVersion::new(3, 7), PathBuf::from("/python3.7"));
+
+        let preferences = VersionPreferenceList::from_str(preferences_str).unwrap();
+        find_preferred_executables_in_hashmap(&preferences, &executables)
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect()
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
+    #[test_case(ExactVersion::new(2, 7) => false ; "old version")]
+    #[test_case(ExactVersion::new(NEWEST_FINAL_RELEASE.0, NEWEST_FINAL_RELEASE.1) => false ; "newest final release")]
+    #[test_case(ExactVersion { implementation: Implementation::PyPy, ..ExactVersion::new(42, 0) } => false ; "alternative implementation")]
+    #[test_case(ExactVersion::new(42, 0) => false ; "unrunnable executable")]
+    fn is_prerelease_tests(version: ExactVersion) -> bool {
+        is_prerelease(&version, &PathBuf::from("/does/not/exist/python"))
+    }
+
+    #[test_case("python38" => Some(ExactVersion::new(3, 8)) ; "snap name")]
+    #[test_case("python310" => Some(ExactVersion::new(3, 10)) ; "double-digit minor version")]
+    #[test_case("python310.python3" => Some(ExactVersion::new(3, 10)) ; "snap app")]
+    #[test_case("python38.pip" => None ; "non-Python app")]
+    #[test_case("python3" => None ; "major version only")]
+    #[test_case("python" => None ; "no version")]
+    #[test_case("pythonista" => None ; "non-digit suffix")]
+    fn snap_wrapper_version_tests(file_name: &str) -> Option<ExactVersion> {
+        snap_wrapper_version(file_name)
+    }
+
+    #[test_case("home = /usr/bin\nversion = 3.12.1\n" => Some(ExactVersion::with_micro(3, 12, 1)) ; "venv")]
+    #[test_case("home = /usr/bin\nversion_info = 3.12.1.final.0\n" => Some(ExactVersion::with_micro(3, 12, 1)) ; "uv")]
+    #[test_case("version = 3.12" => Some(ExactVersion::new(3, 12)) ; "no micro version")]
+    #[test_case("home = /usr/bin\n" => None ; "missing version")]
+    #[test_case("version = three" => None ; "invalid version")]
+    fn pyvenv_cfg_version_tests(contents: &str) -> Option<ExactVersion> {
+        pyvenv_cfg_version(contents)
+    }
+
+    #[test]
+    fn venv_info_from_root_test() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        assert_eq!(VenvInfo::from_root(root, false), None);
+
+        std::fs::create_dir(root.join("bin")).unwrap();
+        std::fs::File::create(root.join("bin").join("python")).unwrap();
         assert_eq!(
-            ExactVersion::from_path(&path),
-            Err(Error::FileNameToStrError)
+            VenvInfo::from_root(root, true),
+            Some(VenvInfo {
+                root: root.to_path_buf(),
+                executable: root.join("bin").join("python"),
+                version: None,
+                activated: true,
+            })
+        );
+
+        std::fs::write(root.join("pyvenv.cfg"), "version = 3.12.1\n").unwrap();
+        assert_eq!(
+            VenvInfo::from_root(root, false).and_then(|venv| venv.version),
+            Some(ExactVersion::with_micro(3, 12, 1))
         );
     }
 
-    #[allow(clippy::bool_assert_comparison)]
-    #[test_case(RequestedVersion::Any => true ; "Any supports all versions")]
-    #[test_case(RequestedVersion::MajorOnly(2) => false ; "major-only mismatch")]
-    #[test_case(RequestedVersion::MajorOnly(3) => true ; "major-only match")]
-    #[test_case(RequestedVersion::Exact(2, 7) => false ; "older major version")]
-    #[test_case(RequestedVersion::Exact(3, 5) => false ; "older minor version")]
-    #[test_case(RequestedVersion::Exact(4, 0) => false ; "newer major version")]
-    #[test_case(RequestedVersion::Exact(3, 7) => false ; "newer minor version")]
-    #[test_case(RequestedVersion::Exact(3, 6) => true ; "same version")]
-    fn exactversion_supports_tests(requested_version: RequestedVersion) -> bool {
-        let example = ExactVersion { major: 3, minor: 6 };
-        example.supports(requested_version)
+    #[test]
+    fn find_virtual_env_test() {
+        let project = tempfile::tempdir().unwrap();
+        let project = project.path();
+        let subdir = project.join("src").join("package");
+        std::fs::create_dir_all(&subdir).unwrap();
+        assert_eq!(find_virtual_env(&subdir), None);
+
+        // `venv` requires a `pyvenv.cfg`.
+        let venv_bin = project.join("venv").join("bin");
+        std::fs::create_dir_all(&venv_bin).unwrap();
+        std::fs::File::create(venv_bin.join("python")).unwrap();
+        assert_eq!(find_virtual_env(&subdir), None);
+        std::fs::File::create(project.join("venv").join("pyvenv.cfg")).unwrap();
+        assert_eq!(
+            find_virtual_env(&subdir).map(|venv| venv.root),
+            Some(project.join("venv"))
+        );
+
+        // `.venv` is preferred over `venv`.
+        let dot_venv_bin = project.join(".venv").join("bin");
+        std::fs::create_dir_all(&dot_venv_bin).unwrap();
+        std::fs::File::create(dot_venv_bin.join("python")).unwrap();
+        assert_eq!(
+            find_virtual_env(&subdir).map(|venv| venv.root),
+            Some(project.join(".venv"))
+        );
+    }
+
+    #[test_case("My_Project", "/home/user/project" => "my-project-na0eTgiw" ; "normalized name")]
+    #[test_case("demo", "/tmp/x" => "demo-LlaqNvU4" ; "simple name")]
+    fn poetry_env_base_name_tests(project_name: &str, project_dir: &str) -> String {
+        poetry_env_base_name(project_name, Path::new(project_dir))
+    }
+
+    #[test_case("/home/user/my project" => Some("my_project-45EusJEJ".to_string()) ; "sanitized name")]
+    #[test_case("/" => None ; "no directory name")]
+    fn pipenv_env_name_tests(project_dir: &str) -> Option<String> {
+        pipenv_env_name(Path::new(project_dir))
+    }
+
+    #[test]
+    fn find_pipenv_env_in_test() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let project_dir = project_dir.path().canonicalize().unwrap();
+        let subdir = project_dir.join("src");
+        std::fs::create_dir(&subdir).unwrap();
+        let workon_home = tempfile::tempdir().unwrap();
+        let workon_home = workon_home.path();
+        let root = workon_home.join(pipenv_env_name(&project_dir).unwrap());
+        std::fs::create_dir_all(root.join("bin")).unwrap();
+        std::fs::File::create(root.join("bin").join("python")).unwrap();
+        assert_eq!(
+            find_pipenv_env_under(&OsFileSystem, &subdir, workon_home, None),
+            None
+        );
+
+        std::fs::File::create(project_dir.join("Pipfile")).unwrap();
+        assert_eq!(
+            find_pipenv_env_under(&OsFileSystem, &subdir, workon_home, None).map(|venv| venv.root),
+            Some(root)
+        );
+
+        let custom_root = workon_home.join("custom");
+        std::fs::create_dir_all(custom_root.join("bin")).unwrap();
+        std::fs::File::create(custom_root.join("bin").join("python")).unwrap();
+        assert_eq!(
+            find_pipenv_env_under(&OsFileSystem, &subdir, workon_home, Some("custom"))
+                .map(|venv| venv.root),
+            Some(custom_root)
+        );
+    }
+
+    #[test]
+    fn poetry_project_name_test() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let project_dir = project_dir.path();
+        assert_eq!(poetry_project_name(&OsFileSystem, project_dir), None);
+
+        let pyproject = project_dir.join("pyproject.toml");
+        std::fs::write(&pyproject, "[project]\nname = \"demo\"\n").unwrap();
+        assert_eq!(poetry_project_name(&OsFileSystem, project_dir), None);
+        std::fs::File::create(project_dir.join("poetry.lock")).unwrap();
+        assert_eq!(
+            poetry_project_name(&OsFileSystem, project_dir),
+            Some("demo".to_string())
+        );
+
+        std::fs::remove_file(project_dir.join("poetry.lock")).unwrap();
+        std::fs::write(&pyproject, "[tool.poetry]\nname = \"legacy\"\n").unwrap();
+        assert_eq!(
+            poetry_project_name(&OsFileSystem, project_dir),
+            Some("legacy".to_string())
+        );
+    }
+
+    #[test]
+    fn find_poetry_env_in_test() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let project_dir = project_dir.path().canonicalize().unwrap();
+        let subdir = project_dir.join("src");
+        std::fs::create_dir(&subdir).unwrap();
+        std::fs::write(
+            project_dir.join("pyproject.toml"),
+            "[tool.poetry]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        let virtualenvs_dir = tempfile::tempdir().unwrap();
+        let virtualenvs_dir = virtualenvs_dir.path();
+        assert_eq!(
+            find_poetry_env_under(&OsFileSystem, &subdir, virtualenvs_dir),
+            None
+        );
+
+        let base_name = poetry_env_base_name("demo", &project_dir);
+        let mut roots = Vec::new();
+        for version in ["3.9", "3.12", "3.10"] {
+            let root = virtualenvs_dir.join(format!("{base_name}-py{version}"));
+            std::fs::create_dir_all(root.join("bin")).unwrap();
+            std::fs::File::create(root.join("bin").join("python")).unwrap();
+            roots.push(root);
+        }
+        // Some other project's virtual environment.
+        std::fs::create_dir_all(virtualenvs_dir.join("demo-AAAAAAAA-py3.13/bin")).unwrap();
+        std::fs::File::create(virtualenvs_dir.join("demo-AAAAAAAA-py3.13/bin/python")).unwrap();
+
+        // The newest version is used by default ...
+        assert_eq!(
+            find_poetry_env_under(&OsFileSystem, &subdir, virtualenvs_dir).map(|venv| venv.root),
+            Some(roots[1].clone())
+        );
+
+        // ... unless Poetry was told to use a specific version.
+        std::fs::write(
+            virtualenvs_dir.join("envs.toml"),
+            format!("[{base_name}]\nminor = \"3.10\"\npatch = \"3.10.4\"\n"),
+        )
+        .unwrap();
+        assert_eq!(
+            find_poetry_env_under(&OsFileSystem, &subdir, virtualenvs_dir).map(|venv| venv.root),
+            Some(roots[2].clone())
+        );
+    }
+
+    #[test_case("3.12.1" => Some(RequestedVersion::Exact(3, 12)) ; "micro version")]
+    #[test_case("3.12" => Some(RequestedVersion::Exact(3, 12)) ; "minor version")]
+    #[test_case("3" => Some(RequestedVersion::MajorOnly(3)) ; "major version")]
+    #[test_case("3.13-dev" => Some(RequestedVersion::Exact(3, 13)) ; "development version")]
+    #[test_case("3.13.0t" => Some(RequestedVersion::FreeThreaded(3, 13)) ; "free-threaded")]
+    #[test_case("pypy3.10-7.3.12" => Some(RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(10))) ; "PyPy")]
+    #[test_case("system" => Some(RequestedVersion::Any) ; "system")]
+    #[test_case("miniconda3-latest" => None ; "unsupported distribution")]
+    fn pyenv_requested_version_tests(name: &str) -> Option<RequestedVersion> {
+        pyenv_requested_version(name)
+    }
+
+    #[test_case("3.12.1" => vec![RequestedVersion::Exact(3, 12)] ; "single version")]
+    #[test_case("3.12.1:3.11" => vec![RequestedVersion::Exact(3, 12), RequestedVersion::Exact(3, 11)] ; "environment variable")]
+    #[test_case("# comment\n3.12.1\n3.11 miniconda3-latest\n" => vec![RequestedVersion::Exact(3, 12), RequestedVersion::Exact(3, 11)] ; "version file")]
+    #[test_case("" => Vec::<RequestedVersion>::new() ; "empty")]
+    fn parse_pyenv_versions_tests(contents: &str) -> Vec<RequestedVersion> {
+        parse_pyenv_versions(contents)
+    }
+
+    #[test_case("python 3.12.1\n" => Some(vec![RequestedVersion::Exact(3, 12)]) ; "single version")]
+    #[test_case("nodejs 20.10.0\npython 3.12.1 3.11.4 system\n" => Some(vec![RequestedVersion::Exact(3, 12), RequestedVersion::Exact(3, 11), RequestedVersion::Any]) ; "fallbacks after another tool")]
+    #[test_case("python 3.12.1 # comment\n" => Some(vec![RequestedVersion::Exact(3, 12)]) ; "comment")]
+    #[test_case("# python 3.12.1\nnodejs 20.10.0\n" => None ; "no python line")]
+    #[test_case("python ref:v3.13.0 3.12\n" => Some(vec![RequestedVersion::Exact(3, 12)]) ; "unsupported version")]
+    fn parse_tool_versions_tests(contents: &str) -> Option<Vec<RequestedVersion>> {
+        parse_tool_versions(contents)
+    }
+
+    #[test]
+    fn python_version_file_closest() {
+        let project = tempfile::tempdir().unwrap();
+        let subdir = project.path().join("src").join("package");
+        std::fs::create_dir_all(&subdir).unwrap();
+        assert_eq!(python_version_file(&subdir), None);
+
+        std::fs::write(project.path().join(".python-version"), "3.11.4\n").unwrap();
+        assert_eq!(
+            python_version_file(&subdir).map(|preferences| preferences.versions),
+            Some(vec![RequestedVersion::Exact(3, 11)])
+        );
+
+        // The closest file wins, even if it selects nothing usable.
+        std::fs::write(subdir.join(".python-version"), "miniconda3-latest\n").unwrap();
+        assert_eq!(python_version_file(&subdir), None);
+        std::fs::write(subdir.join(".python-version"), "3.12\n3.10\n").unwrap();
+        assert_eq!(
+            python_version_file(&subdir).map(|preferences| preferences.versions),
+            Some(vec![
+                RequestedVersion::Exact(3, 12),
+                RequestedVersion::Exact(3, 10)
+            ])
+        );
+    }
+
+    #[test]
+    fn tool_versions_file_closest() {
+        let project = tempfile::tempdir().unwrap();
+        let subdir = project.path().join("src").join("package");
+        std::fs::create_dir_all(&subdir).unwrap();
+        assert_eq!(tool_versions_file(&subdir), None);
+
+        std::fs::write(project.path().join(".tool-versions"), "python 3.11.4\n").unwrap();
+        assert_eq!(
+            tool_versions_file(&subdir).map(|preferences| preferences.versions),
+            Some(vec![RequestedVersion::Exact(3, 11)])
+        );
+
+        // Files without a `python` line are skipped.
+        std::fs::write(subdir.join(".tool-versions"), "nodejs 20.10.0\n").unwrap();
+        assert_eq!(
+            tool_versions_file(&subdir).map(|preferences| preferences.versions),
+            Some(vec![RequestedVersion::Exact(3, 11)])
+        );
+
+        std::fs::write(subdir.join(".tool-versions"), "python 3.12.1\n").unwrap();
+        assert_eq!(
+            tool_versions_file(&subdir).map(|preferences| preferences.versions),
+            Some(vec![RequestedVersion::Exact(3, 12)])
+        );
+    }
+
+    #[test]
+    fn local_version_file_nearest() {
+        let project = tempfile::tempdir().unwrap();
+        let subdir = project.path().join("src");
+        std::fs::create_dir_all(&subdir).unwrap();
+        std::fs::write(project.path().join(".python-version"), "3.10\n").unwrap();
+        std::fs::write(subdir.join(".tool-versions"), "python 3.12.1\n").unwrap();
+        // The nearer `.tool-versions` wins over the farther `.python-version`.
+        assert_eq!(
+            local_version_file(&OsFileSystem, &subdir).map(|preferences| preferences.versions),
+            Some(vec![RequestedVersion::Exact(3, 12)])
+        );
+
+        // In the same directory, `.python-version` wins.
+        std::fs::write(subdir.join(".python-version"), "3.11\n").unwrap();
+        assert_eq!(
+            local_version_file(&OsFileSystem, &subdir).map(|preferences| preferences.versions),
+            Some(vec![RequestedVersion::Exact(3, 11)])
+        );
+    }
+
+    #[test]
+    fn requires_python_closest() {
+        let project = tempfile::tempdir().unwrap();
+        let subdir = project.path().join("src").join("package");
+        std::fs::create_dir_all(&subdir).unwrap();
+        assert_eq!(requires_python(&subdir), None);
+
+        std::fs::write(
+            project.path().join("pyproject.toml"),
+            "[project]\nname = \"demo\"\nrequires-python = \">=3.9, <3.13\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            requires_python(&subdir).map(|specifier| specifier.to_string()),
+            Some(">=3.9,<3.13".to_string())
+        );
+
+        // The closest project wins, even if it has no requirement.
+        std::fs::write(subdir.join("pyproject.toml"), "[project]\nname = \"sub\"\n").unwrap();
+        assert_eq!(requires_python(&subdir), None);
+
+        // Specifiers which aren't understood are ignored.
+        std::fs::write(
+            subdir.join("pyproject.toml"),
+            "[project]\nrequires-python = \">=3.9.0rc1\"\n",
+        )
+        .unwrap();
+        assert_eq!(requires_python(&subdir), None);
+    }
+
+    #[test_case(&[], None => Vec::<String>::new() ; "nothing disabled")]
+    #[test_case(&["snap".to_string()], None => vec!["snap".to_string()] ; "configuration file")]
+    #[test_case(&[], Some("pyenv, asdf,") => vec!["pyenv".to_string(), "asdf".to_string()] ; "environment variable")]
+    #[test_case(&["snap".to_string()], Some("pyenv") => vec!["snap".to_string(), "pyenv".to_string()] ; "combined")]
+    #[test_case(&[], Some("conda") => vec!["conda".to_string()] ; "unknown source")]
+    fn disabled_sources_tests(config_sources: &[String], env_sources: Option<&str>) -> Vec<String> {
+        disabled_sources(config_sources, env_sources)
+    }
+
+    #[test_case(None, None => vec![PathBuf::from("/run/current-system/sw/bin")] ; "system profile")]
+    #[test_case(Some(PathBuf::from("/home/user")), None => vec![PathBuf::from("/home/user/.nix-profile/bin"), PathBuf::from("/run/current-system/sw/bin")] ; "user profile")]
+    #[test_case(Some(PathBuf::from("/home/user")), Some("/nix/var/nix/profiles/default /home/user/.nix-profile") => vec![PathBuf::from("/home/user/.nix-profile/bin"), PathBuf::from("/run/current-system/sw/bin"), PathBuf::from("/nix/var/nix/profiles/default/bin")] ; "NIX_PROFILES")]
+    fn nix_profile_bin_directories_tests(
+        home: Option<PathBuf>,
+        nix_profiles: Option<&str>,
+    ) -> Vec<PathBuf> {
+        nix_profile_bin_directories(home, nix_profiles)
+    }
+
+    #[test_case("Python312" => Some(ExactVersion::new(3, 12)) ; "install directory")]
+    #[test_case("Python27" => Some(ExactVersion::new(2, 7)) ; "legacy install directory")]
+    #[test_case("Python312-32" => Some(ExactVersion::new(3, 12)) ; "32-bit install directory")]
+    #[test_case("Python312-arm64" => Some(ExactVersion::new(3, 12)) ; "ARM64 install directory")]
+    #[test_case("Python3" => None ; "major version only")]
+    #[test_case("PythonLauncher" => None ; "not an install directory")]
+    #[test_case("python312" => None ; "lowercase")]
+    fn windows_install_version_tests(dir_name: &str) -> Option<ExactVersion> {
+        windows_install_version(dir_name)
+    }
+
+    #[test_case(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0x3e, 0] => Some("x86_64") ; "ELF x86_64")]
+    #[test_case(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0xb7, 0] => Some("arm64") ; "ELF arm64")]
+    #[test_case(&[0x7f, b'E', b'L', b'F', 2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0x16] => Some("s390x") ; "big-endian ELF")]
+    #[test_case(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0xff, 0] => None ; "unknown ELF machine")]
+    #[test_case(&[0x7f, b'E', b'L', b'F', 2, 1] => None ; "truncated ELF")]
+    #[test_case(&[0xcf, 0xfa, 0xed, 0xfe, 0x0c, 0, 0, 0x01] => Some("arm64") ; "Mach-O arm64")]
+    #[test_case(&[0xcf, 0xfa, 0xed, 0xfe, 0x07, 0, 0, 0x01] => Some("x86_64") ; "Mach-O x86_64")]
+    #[test_case(&[0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 2] => Some("universal") ; "universal Mach-O")]
+    #[test_case(b"#!/usr/bin/env bash\nexec pyenv" => None ; "shell script")]
+    #[test_case(b"" => None ; "empty file")]
+    fn binary_architecture_tests(header: &[u8]) -> Option<&'static str> {
+        binary_architecture(header)
+    }
+
+    #[test_case(b"\x7fELF\x02\x01\x01\x00" => Some(64) ; "ELF 64-bit")]
+    #[test_case(b"\x7fELF\x01\x01\x01\x00" => Some(32) ; "ELF 32-bit")]
+    #[test_case(b"\xcf\xfa\xed\xfe\x0c\x00\x00\x01" => Some(64) ; "Mach-O 64-bit")]
+    #[test_case(b"\xce\xfa\xed\xfe\x07\x00\x00\x00" => Some(32) ; "Mach-O 32-bit")]
+    #[test_case(b"\xca\xfe\xba\xbe\x00\x00\x00\x02" => None ; "universal Mach-O")]
+    #[test_case(b"#!/usr/bin/env bash\nexec pyenv" => None ; "shell script")]
+    fn binary_bits_tests(header: &[u8]) -> Option<u8> {
+        binary_bits(header)
+    }
+
+    #[test]
+    fn binary_architecture_pe_test() {
+        let mut header = vec![0; 0x88];
+        header[..2].copy_from_slice(b"MZ");
+        header[0x3c] = 0x80;
+        header[0x80..0x84].copy_from_slice(b"PE\0\0");
+        header[0x84..0x86].copy_from_slice(&0x8664_u16.to_le_bytes());
+        assert_eq!(binary_architecture(&header), Some("x86_64"));
+        assert_eq!(binary_bits(&header), Some(64));
+        header[0x84..0x86].copy_from_slice(&0x014c_u16.to_le_bytes());
+        assert_eq!(binary_bits(&header), Some(32));
+
+        header[0x80] = b'X';
+        assert_eq!(binary_architecture(&header), None);
+        header.truncate(0x40);
+        assert_eq!(binary_architecture(&header), None);
+    }
+
+    #[test]
+    fn executable_architecture_test() {
+        let test_executable = env::current_exe().unwrap();
+        let expected_architecture = match env::consts::ARCH {
+            "aarch64" => "arm64",
+            architecture => architecture,
+        };
+        assert_eq!(
+            executable_architecture(&test_executable),
+            Some(expected_architecture)
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(executable_architecture(&dir.path().join("python3")), None);
+    }
+
+    #[test]
+    fn windows_executables_in_test() {
+        let drive = tempfile::tempdir().unwrap();
+        let drive = drive.path();
+        let mut expected = Vec::new();
+        for (install_dir, version) in [
+            (
+                "Users/alice/AppData/Local/Programs/Python/Python312",
+                (3, 12),
+            ),
+            ("Program Files/Python311", (3, 11)),
+            ("Program Files (x86)/Python310-32", (3, 10)),
+            ("Python27", (2, 7)),
+        ] {
+            let install_dir = drive.join(install_dir);
+            std::fs::create_dir_all(&install_dir).unwrap();
+            let executable = install_dir.join("python.exe");
+            std::fs::File::create(&executable).unwrap();
+            expected.push((ExactVersion::new(version.0, version.1), executable));
+        }
+        // No `python.exe`.
+        std::fs::create_dir_all(drive.join("Program Files/Python39")).unwrap();
+
+        assert_eq!(windows_executables_in(&OsFileSystem, drive), expected);
+    }
+
+    #[test]
+    fn snap_executables_test() {
+        let snap_bin_dir = tempfile::tempdir().unwrap();
+        for file_name in [
+            "python38",
+            "python3.9",
+            "python310.python3",
+            "python38.pip",
+            "firefox",
+        ] {
+            std::fs::File::create(snap_bin_dir.path().join(file_name)).unwrap();
+        }
+
+        assert_eq!(
+            snap_executables(&OsFileSystem, snap_bin_dir.path()),
+            vec![
+                (
+                    ExactVersion::new(3, 8),
+                    snap_bin_dir.path().join("python38")
+                ),
+                (
+                    ExactVersion::new(3, 9),
+                    snap_bin_dir.path().join("python3.9")
+                ),
+                (
+                    ExactVersion::new(3, 10),
+                    snap_bin_dir.path().join("python310.python3")
+                ),
+            ]
+        );
+    }
+
+    #[test_case(vec![] => vec![PathBuf::from("/usr/local/bin"), PathBuf::from("/usr/bin"), PathBuf::from("/bin")] ; "unset")]
+    #[test_case(vec![PathBuf::new()] => vec![PathBuf::from("/usr/local/bin"), PathBuf::from("/usr/bin"), PathBuf::from("/bin")] ; "empty")]
+    #[test_case(vec![PathBuf::from("/a/bin")] => vec![PathBuf::from("/a/bin")] ; "set")]
+    fn path_or_default_tests(path: Vec<PathBuf>) -> Vec<PathBuf> {
+        path_or_default(path)
+    }
+
+    #[test]
+    fn manylinux_root_test() {
+        let manylinux = INSTALL_MANAGERS
+            .iter()
+            .find(|manager| manager.name == "manylinux")
+            .unwrap();
+        assert_eq!(
+            manylinux.root(&env_provider::MapEnv::new()),
+            Some(PathBuf::from("/opt/python"))
+        );
+    }
+
+    #[test]
+    fn install_bin_directories_test() {
+        let root = tempfile::tempdir().unwrap();
+        let versions = root.path().join("versions");
+        assert!(install_bin_directories(&OsFileSystem, &versions, 1).is_empty());
+
+        for version in ["3.12.1", "3.10.4", "pypy3.10-7.3.12"] {
+            std::fs::create_dir_all(versions.join(version).join("bin")).unwrap();
+        }
+
+        assert_eq!(
+            install_bin_directories(&OsFileSystem, &versions, 1),
+            vec![
+                versions.join("3.10.4").join("bin"),
+                versions.join("3.12.1").join("bin"),
+                versions.join("pypy3.10-7.3.12").join("bin"),
+            ]
+        );
+
+        // The GitHub Actions tool cache has an architecture directory within
+        // each version's, e.g. `Python/3.12.1/x64/bin`.
+        let tool_cache = root.path().join("Python");
+        for version in ["3.12.1", "3.10.4"] {
+            std::fs::create_dir_all(tool_cache.join(version).join("x64").join("bin")).unwrap();
+        }
+
+        assert_eq!(
+            install_bin_directories(&OsFileSystem, &tool_cache, 2),
+            vec![
+                tool_cache.join("3.10.4").join("x64").join("bin"),
+                tool_cache.join("3.12.1").join("x64").join("bin"),
+            ]
+        );
     }
 
     #[test_case(2, 7, "/dir1/python2.7" ; "first directory")]
@@ -532,33 +4325,337 @@ mod tests {
             python36_dir2_path,
             python37_path,
         ];
+        let file_system = files
+            .iter()
+            .fold(filesystem::MemoryFileSystem::new(), |file_system, file| {
+                file_system.file(file)
+            });
 
-        let executables = all_executables_in_paths(files.into_iter());
+        let executables = all_executables_in_paths(&file_system, files.into_iter());
         assert_eq!(executables.len(), 3);
 
-        let version = ExactVersion { major, minor };
+        let version = ExactVersion::new(major, minor);
         assert!(executables.contains_key(&version));
         assert_eq!(executables.get(&version), Some(&PathBuf::from(path)));
     }
 
+    #[test]
+    fn executables_in_directories_of_test() {
+        let file_system = filesystem::MemoryFileSystem::new()
+            .symlink("/usr/bin/python3.12", "/opt/python/3.12/bin/python3.12")
+            .file("/opt/python/3.12/bin/python3.12")
+            .symlink("/usr/bin/python3.11", "/opt/python/3.11/bin/python3.11")
+            .dir("/usr/bin/python3.10")
+            .unreadable_dir("/secret")
+            .file("/secret/python3.9")
+            .symlink("/usr/local/bin", "/usr/bin")
+            .file("/usr/local/python3.11");
+
+        let executables = executables_in_directories_of(
+            &file_system,
+            ["/secret", "/usr/local/bin", "/usr/bin", "/usr/local"],
+        );
+        // Neither the dangling symlink nor the directory is an executable, and
+        // the unreadable directory is skipped.
+        assert_eq!(
+            executables,
+            HashMap::from([
+                (
+                    ExactVersion::new(3, 12),
+                    PathBuf::from("/usr/local/bin/python3.12")
+                ),
+                (
+                    ExactVersion::new(3, 11),
+                    PathBuf::from("/usr/local/python3.11")
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn search_with_file_system() {
+        let file_system = filesystem::MemoryFileSystem::new()
+            .file("/usr/bin/python3.12")
+            .file("/opt/bin/python3.9")
+            .file_with_contents("/project/.py.toml", "search-directories = ['/opt/bin']\n")
+            .file_with_contents("/project/.python-version", "3.10\n")
+            .file("/project/.venv/bin/python")
+            .file_with_contents("/project/.venv/pyvenv.cfg", "version = 3.11.4\n");
+        let env = env_provider::MapEnv::new()
+            .with_var("PATH", "/usr/bin")
+            .with_current_dir("/project/src");
+
+        let config = load_config_of(&env, &file_system);
+        assert_eq!(config.search_directories, [PathBuf::from("/opt/bin")]);
+
+        let search = Search::new(&env, &config).with_file_system(Arc::new(file_system));
+        assert_eq!(
+            search.find_environment(VenvDetection::Search).unwrap(),
+            Some(VenvInfo {
+                root: PathBuf::from("/project/.venv"),
+                executable: PathBuf::from("/project/.venv/bin/python"),
+                version: Some(ExactVersion::with_micro(3, 11, 4)),
+                activated: false,
+            })
+        );
+        assert_eq!(
+            search.default_version().unwrap().versions,
+            [RequestedVersion::Exact(3, 10)]
+        );
+        assert_eq!(
+            search.all_executables_by_source(),
+            [
+                (
+                    "path".to_string(),
+                    ExactVersion::new(3, 12),
+                    PathBuf::from("/usr/bin/python3.12")
+                ),
+                (
+                    "config".to_string(),
+                    ExactVersion::new(3, 9),
+                    PathBuf::from("/opt/bin/python3.9")
+                ),
+            ]
+        );
+    }
+
     #[test_case(RequestedVersion::Any => Some(PathBuf::from("/python3.7")) ; "Any version chooses newest version")]
     #[test_case(RequestedVersion::MajorOnly(42) => None ; "major-only version newer than any options")]
     #[test_case(RequestedVersion::MajorOnly(3) => Some(PathBuf::from("/python3.7")) ; "matching major version chooses newest minor version")]
     #[test_case(RequestedVersion::Exact(3, 8) => None ; "version not available")]
     #[test_case(RequestedVersion::Exact(3, 6) => Some(PathBuf::from("/python3.6")) ; "exact version match")]
+    #[test_case(RequestedVersion::AtLeast(3, 6) => Some(PathBuf::from("/python3.7")) ; "at least version chooses newest version")]
+    #[test_case(RequestedVersion::AtLeast(3, 8) => None ; "at least version newer than any options")]
     fn find_executable_in_hashmap_tests(requested_version: RequestedVersion) -> Option<PathBuf> {
         let mut executables = HashMap::new();
         assert_eq!(
-            find_executable_in_hashmap(RequestedVersion::Any, &executables),
+            find_executable_in_hashmap(&RequestedVersion::Any.into(), &executables),
             None
         );
 
         let python36_path = PathBuf::from("/python3.6");
-        executables.insert(ExactVersion { major: 3, minor: 6 }, python36_path);
+        executables.insert(ExactVersion::new(3, 6), python36_path);
 
         let python37_path = PathBuf::from("/python3.7");
-        executables.insert(ExactVersion { major: 3, minor: 7 }, python37_path);
+        executables.insert(ExactVersion::new(3, 7), python37_path);
 
-        find_executable_in_hashmap(requested_version, &executables)
+        find_executable_in_hashmap(&requested_version.into(), &executables)
+    }
+
+    #[test_case(RequestedVersion::Any => Some(PathBuf::from("/python3.10.11")) ; "Any version chooses newest micro version")]
+    #[test_case(RequestedVersion::Exact(3, 10) => Some(PathBuf::from("/python3.10.11")) ; "major.minor chooses newest micro version")]
+    #[test_case(RequestedVersion::ExactMicro(3, 10, 2) => Some(PathBuf::from("/python3.10.2")) ; "exact micro version match")]
+    #[test_case(RequestedVersion::ExactMicro(3, 10, 3) => None ; "micro version not available")]
+    fn find_executable_in_hashmap_micro_tests(
+        requested_version: RequestedVersion,
+    ) -> Option<PathBuf> {
+        let mut executables = HashMap::new();
+        executables.insert(ExactVersion::new(3, 10), PathBuf::from("/python3.10"));
+        executables.insert(
+            ExactVersion::with_micro(3, 10, 2),
+            PathBuf::from("/python3.10.2"),
+        );
+        executables.insert(
+            ExactVersion::with_micro(3, 10, 11),
+            PathBuf::from("/python3.10.11"),
+        );
+
+        find_executable_in_hashmap(&requested_version.into(), &executables)
+    }
+
+    #[test_case(">=3.6" => Some(PathBuf::from("/python3.7")) ; "newest version within range")]
+    #[test_case(">=3.6,<3.7" => Some(PathBuf::from("/python3.6")) ; "newest version excluded by range")]
+    #[test_case(">3.7" => None ; "no version within range")]
+    fn find_executable_in_hashmap_specifier_tests(specifier_str: &str) -> Option<PathBuf> {
+        let mut executables = HashMap::new();
+        executables.insert(ExactVersion::new(3, 6), PathBuf::from("/python3.6"));
+        executables.insert(ExactVersion::new(3, 7), PathBuf::from("/python3.7"));
+
+        let specifier = VersionSpecifier::from_str(specifier_str).unwrap();
+        find_executable_in_hashmap(&specifier, &executables)
+    }
+
+    #[test_case(RequestedVersion::Any => Some(PathBuf::from("/python3.13")) ; "Any version chooses GIL build")]
+    #[test_case(RequestedVersion::Exact(3, 13) => Some(PathBuf::from("/python3.13")) ; "major.minor chooses GIL build")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 13) => Some(PathBuf::from("/python3.13t")) ; "free-threaded chooses free-threaded build")]
+    #[test_case(RequestedVersion::FreeThreaded(3, 12) => None ; "free-threaded version not available")]
+    fn find_executable_in_hashmap_free_threaded_tests(
+        requested_version: RequestedVersion,
+    ) -> Option<PathBuf> {
+        let mut executables = HashMap::new();
+        executables.insert(ExactVersion::new(3, 12), PathBuf::from("/python3.12"));
+        executables.insert(ExactVersion::new(3, 13), PathBuf::from("/python3.13"));
+        executables.insert(
+            ExactVersion {
+                major: 3,
+                minor: 13,
+                micro: None,
+                free_threaded: true,
+                implementation: Implementation::CPython,
+            },
+            PathBuf::from("/python3.13t"),
+        );
+
+        find_executable_in_hashmap(&requested_version.into(), &executables)
+    }
+
+    #[test_case(RequestedVersion::Any => Some(PathBuf::from("/python3.9")) ; "Any version chooses CPython")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, None, None) => Some(PathBuf::from("/pypy3.10")) ; "PyPy chooses newest PyPy")]
+    #[test_case(RequestedVersion::Implementation(Implementation::PyPy, Some(3), Some(9)) => Some(PathBuf::from("/pypy3.9")) ; "PyPy major.minor")]
+    #[test_case(RequestedVersion::Implementation(Implementation::GraalPy, None, None) => None ; "GraalPy not available")]
+    fn find_executable_in_hashmap_implementation_tests(
+        requested_version: RequestedVersion,
+    ) -> Option<PathBuf> {
+        let mut executables = HashMap::new();
+        for path in ["/python3.9", "/pypy3.9", "/pypy3.10"] {
+            let path = PathBuf::from(path);
+            executables.insert(ExactVersion::from_path(&path).unwrap(), path);
+        }
+
+        find_executable_in_hashmap(&requested_version.into(), &executables)
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_spans() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{span, Event, Subscriber};
+
+        /// Records the name of every span.
+        struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+        impl Subscriber for SpanNames {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+                let mut names = self.0.lock().unwrap();
+                names.push(span.metadata().name());
+                span::Id::from_u64(names.len() as u64)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let sources: Vec<Box<dyn InterpreterSource>> =
+            vec![Box::new(DirectorySource::new("empty", Vec::new()))];
+        tracing::subscriber::with_default(SpanNames(Arc::clone(&names)), || {
+            all_executables_from(&sources)
+        });
+        let names = names.lock().unwrap();
+        assert_eq!(names.first(), Some(&"all_executables_from"));
+        assert!(names.contains(&"env_disabled_sources"));
+    }
+
+    #[test]
+    fn find_environment_in_test() {
+        let project = tempfile::tempdir().unwrap();
+        let project = project.path();
+        let venv = project.join(".venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::File::create(venv.join("bin").join("python")).unwrap();
+        std::fs::File::create(venv.join("pyvenv.cfg")).unwrap();
+
+        let activated = env_provider::MapEnv::new().with_var("VIRTUAL_ENV", &venv);
+        assert_eq!(
+            find_environment_in(&activated, VenvDetection::ActivatedOnly)
+                .unwrap()
+                .map(|venv| (venv.root, venv.activated)),
+            Some((venv.clone(), true))
+        );
+
+        let invalid = env_provider::MapEnv::new().with_var("VIRTUAL_ENV", project.join("missing"));
+        assert!(matches!(
+            find_environment_in(&invalid, VenvDetection::Search),
+            Err(Error::Search(SearchError::InvalidVirtualEnv(..)))
+        ));
+
+        let in_project = env_provider::MapEnv::new().with_current_dir(project);
+        assert_eq!(
+            find_environment_in(&in_project, VenvDetection::Search)
+                .unwrap()
+                .map(|venv| venv.root),
+            Some(venv)
+        );
+        assert_eq!(
+            find_environment_in(&in_project, VenvDetection::ActivatedOnly).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn disabled_environment_sources() {
+        let conda_env = tempfile::tempdir().unwrap();
+        let conda_env = conda_env.path();
+        std::fs::create_dir_all(conda_env.join("bin")).unwrap();
+        std::fs::File::create(conda_env.join("bin").join("python")).unwrap();
+
+        let env = env_provider::MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_var("CONDA_PREFIX", conda_env);
+        assert_eq!(
+            find_environment_in(&env, VenvDetection::ActivatedOnly)
+                .unwrap()
+                .map(|venv| venv.root),
+            Some(conda_env.to_path_buf())
+        );
+        assert_eq!(
+            all_environments_in(&env, VenvDetection::ActivatedOnly).len(),
+            1
+        );
+
+        let env = env.with_var("PY_DISABLE_SOURCES", "conda");
+        assert_eq!(
+            find_environment_in(&env, VenvDetection::ActivatedOnly).unwrap(),
+            None
+        );
+        assert!(all_environments_in(&env, VenvDetection::ActivatedOnly).is_empty());
+    }
+
+    #[test]
+    fn default_version_in_test() {
+        let project = tempfile::tempdir().unwrap();
+        let project = project.path();
+        std::fs::write(project.join(".python-version"), "3.10\n").unwrap();
+        let pyenv_root = tempfile::tempdir().unwrap();
+        std::fs::write(pyenv_root.path().join("version"), "3.9\n").unwrap();
+        let env = env_provider::MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_var("PYENV_ROOT", pyenv_root.path());
+        let default_version =
+            |env: &env_provider::MapEnv| default_version_in(env).map(|version| version.to_string());
+
+        assert_eq!(default_version(&env).as_deref(), Some("Python 3.9"));
+        let env = env.with_current_dir(project);
+        assert_eq!(default_version(&env).as_deref(), Some("Python 3.10"));
+        let env = env.with_var("PYENV_VERSION", "3.11");
+        assert_eq!(default_version(&env).as_deref(), Some("Python 3.11"));
+    }
+
+    #[test]
+    fn default_sources_in_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = dir.path().join("python3.11");
+        std::fs::File::create(&python).unwrap();
+        let env = env_provider::MapEnv::new()
+            .with_var("PYLAUNCH_NO_CONFIG", "1")
+            .with_var("PATH", dir.path());
+
+        let sources = default_sources_in(&env);
+        let config = config::Config::default();
+        assert!(Search::new(&env, &config)
+            .all_executables_by_source()
+            .contains(&("path".to_string(), ExactVersion::new(3, 11), python.clone())));
+
+        let env = env.with_var("PY_DISABLE_SOURCES", "path");
+        assert!(
+            !executables_by_source(&sources, &Search::new(&env, &config).disabled_sources())
+                .iter()
+                .any(|(_, _, path)| *path == python)
+        );
     }
 }
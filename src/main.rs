@@ -12,7 +12,8 @@
 //! ```
 //! It will also launch any activated virtual environments (as set by the
 //! `$VIRTUAL_ENV` environment variable) or any virtual environment in a
-//! `.venv` subdirectory in any of the current or parent directories.
+//! `.venv` (or `venv`) subdirectory in any of the current or parent
+//! directories.
 //!
 //! You can also launch a specific version of Python.
 //! ```text
@@ -33,11 +34,13 @@
 //! - `PY_PYTHON*`: specify the Python version to use by default for a major
 //!   version (e.g. `PY_PYTHON3` for `-3`).
 
-use std::{env, ffi::CString, os::unix::ffi::OsStrExt, path::Path};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+};
 
-use nix::unistd;
-
-use python_launcher::cli;
+use python_launcher::{cache, cli, config::Config, install, run, server, update};
 
 #[cfg(not(tarpaulin_include))]
 fn main() {
@@ -48,6 +51,8 @@ fn main() {
         homepage: env!("CARGO_PKG_REPOSITORY").into(),
     });
 
+    let argv = env::args().collect::<Vec<String>>();
+    let options = cli::Options::parse(&argv);
     let log_level = if env::var_os("PYLAUNCH_DEBUG").is_some() {
         3
     } else {
@@ -61,28 +66,163 @@ fn main() {
         .module("python_launcher")
         .show_level(false)
         .verbosity(log_level) // [error, warn, info, debug, trace]
+        .color(
+            // The arguments are parsed before logging is set up, so an error
+            // from parsing them is logged with the default colors.
+            if options
+                .as_ref()
+                .map_or(cli::ColorChoice::Auto, cli::Options::color)
+                .enabled(nix::libc::STDERR_FILENO)
+            {
+                stderrlog::ColorChoice::Always
+            } else {
+                stderrlog::ColorChoice::Never
+            },
+        )
         .init()
         .unwrap();
 
-    match cli::Action::from_main(&env::args().collect::<Vec<String>>()) {
-        Ok(action) => match action {
-            cli::Action::Help(message, executable) => {
-                print!("{message}");
-                run(&executable, &["--help".to_string()])
-                    .map_err(|message| log_exit(nix::errno::errno(), message))
-                    .unwrap()
+    let options = match options {
+        Ok(options) => options,
+        Err(message) => return log_exit(message.exit_code(), message),
+    };
+    let config_files = options.config_files();
+    // The configuration for what's done beyond choosing the action.
+    let load_config = || {
+        Config::load_files(&*options.env(), &config_files)
+            .map(|(config, _)| config)
+            .unwrap_or_default()
+    };
+    let action = cli::Action::from_options(&options).or_else(|error| match error {
+        // Only a version asked for by a version flag is installed.
+        python_launcher::Error::Search(python_launcher::SearchError::NoExecutableFound(_)) => {
+            match options.requested_version() {
+                Some(requested) if confirm_install(&error, &load_config()) => {
+                    install::install(requested)?;
+                    cli::Action::from_options(&options)
+                }
+                _ => Err(error),
+            }
+        }
+        error => Err(error),
+    });
+    match action {
+        Ok(action) => {
+            if let Err(error) = action.write_output(&mut io::stdout()) {
+                log_exit(exitcode::IOERR, error);
             }
-            cli::Action::List(output) => print!("{output}"),
-            cli::Action::Execute {
-                executable, args, ..
-            } => run(&executable, &args)
-                .map_err(|message| log_exit(nix::errno::errno(), message))
-                .unwrap(),
-        },
+            match action {
+                cli::Action::Help(_, executable) => launch(
+                    &executable,
+                    None,
+                    &["--help".to_string()],
+                    &[],
+                    &[],
+                    run::RunMode::default(),
+                ),
+                cli::Action::List(_)
+                | cli::Action::Configuration(_)
+                | cli::Action::Activate(_)
+                | cli::Action::Which(_)
+                | cli::Action::Resolve(_)
+                | cli::Action::DryRun(_) => {}
+                cli::Action::Install(requested) => match install::install(requested) {
+                    Ok(executable) => println!("{}", executable.display()),
+                    Err(message) => log_exit(message.exit_code(), message),
+                },
+                cli::Action::Uninstall(requested) => match install::uninstall(requested) {
+                    Ok(installs) => {
+                        for install in installs {
+                            println!("{}", install.directory.display());
+                        }
+                    }
+                    Err(message) => log_exit(message.exit_code(), message),
+                },
+                cli::Action::ClearCache => {
+                    if let Err(message) = cache::clear() {
+                        log_exit(message.exit_code(), message);
+                    }
+                }
+                cli::Action::RefreshCache => {
+                    if let Err(message) = cache::refresh(&load_config()) {
+                        log_exit(message.exit_code(), message);
+                    }
+                }
+                cli::Action::Serve => {
+                    let mut server = server::Server::new().config_files(config_files.clone());
+                    if let Some(dir) = options.working_dir() {
+                        server = server.dir(dir);
+                    }
+                    if let Err(error) = server.serve(io::stdin().lock(), &mut io::stdout()) {
+                        log_exit(exitcode::IOERR, error);
+                    }
+                }
+                cli::Action::SelfUpdate => match update::self_update() {
+                    Ok(Some(version)) => println!("Updated to {version}"),
+                    Ok(None) => println!("Already up to date ({})", update::VERSION),
+                    Err(message) => log_exit(message.exit_code(), message),
+                },
+                cli::Action::Write { path, contents } => {
+                    if let Err(error) = write(&path, &contents) {
+                        log::error!("{}: {error}", path.display());
+                        std::process::exit(exitcode::CANTCREAT);
+                    }
+                }
+                cli::Action::Execute {
+                    executable,
+                    args,
+                    env_vars,
+                    unset_env_vars,
+                    run_mode,
+                    argv0,
+                    ..
+                } => {
+                    // Everything up to here resolved paths against the
+                    // directory of `--chdir` without changing to it.
+                    if let Some(dir) = options.working_dir() {
+                        if let Err(error) = env::set_current_dir(dir) {
+                            let message = python_launcher::Error::Cli(
+                                python_launcher::CliError::InvalidWorkingDir(
+                                    dir.to_path_buf(),
+                                    error.into(),
+                                ),
+                            );
+                            log_exit(message.exit_code(), message);
+                        }
+                    }
+                    launch(
+                        &executable,
+                        argv0.as_deref(),
+                        &args,
+                        &env_vars,
+                        &unset_env_vars,
+                        run_mode,
+                    );
+                }
+            }
+        }
         Err(message) => log_exit(message.exit_code(), message),
     }
 }
 
+/// Whether to install the version which wasn't found (as reported by
+/// `error`): as answered when asked on a terminal, else if installs are
+/// allowed (see [`install::allow_install`]).
+#[cfg(not(tarpaulin_include))]
+fn confirm_install(error: &python_launcher::Error, config: &Config) -> bool {
+    let interactive = [nix::libc::STDIN_FILENO, nix::libc::STDERR_FILENO]
+        .into_iter()
+        .all(|fd| nix::unistd::isatty(fd).unwrap_or(false));
+    if !interactive {
+        return install::allow_install(config);
+    }
+    eprint!("{error}; install it? [y/N] ");
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
 #[cfg(not(tarpaulin_include))]
 fn log_exit(return_code: i32, message: impl std::error::Error) {
     log::error!("{message}");
@@ -90,7 +230,22 @@ fn log_exit(return_code: i32, message: impl std::error::Error) {
 }
 
 #[cfg(not(tarpaulin_include))]
-fn run(executable: &Path, args: &[String]) -> nix::Result<()> {
+fn write(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)
+}
+
+#[cfg(not(tarpaulin_include))]
+fn launch(
+    executable: &Path,
+    argv0: Option<&str>,
+    args: &[String],
+    env_vars: &[(String, String)],
+    unset_env_vars: &[String],
+    run_mode: run::RunMode,
+) {
     let printable_executable = executable.display();
     if executable.is_file() {
         log::info!("Executing {printable_executable} with {args:?}");
@@ -98,9 +253,17 @@ fn run(executable: &Path, args: &[String]) -> nix::Result<()> {
         log::error!("{printable_executable}: No such file");
         std::process::exit(1);
     }
-    let executable_as_cstring = CString::new(executable.as_os_str().as_bytes()).unwrap();
-    let mut argv = vec![executable_as_cstring.clone()];
-    argv.extend(args.iter().map(|arg| CString::new(arg.as_str()).unwrap()));
-
-    unistd::execv(&executable_as_cstring, &argv).map(|_| ())
+    match run_mode {
+        run::RunMode::Exec => {
+            // Only returns on failure.
+            let message = run::exec(executable, argv0, args, env_vars, unset_env_vars).unwrap_err();
+            log_exit(message.raw_os_error().unwrap_or(exitcode::OSERR), message);
+        }
+        run::RunMode::Spawn => {
+            match run::spawn(executable, argv0, args, env_vars, unset_env_vars) {
+                Ok(status) => run::exit_with(status),
+                Err(message) => log_exit(exitcode::OSERR, message),
+            }
+        }
+    }
 }
@@ -0,0 +1,363 @@
+//! Picking an interpreter interactively
+//!
+//! When several interpreters satisfy a request and picking is enabled (via
+//! `--pick` or `pick = true` in the [configuration](crate::config)), they are
+//! listed in a terminal menu to pick from with the arrow keys instead of the
+//! newest one being used.
+//!
+//! The pick is remembered for the project (see [`project_dir`]) and request in
+//! the [picks file](picks_path), so that `pick = true` only asks again once
+//! the remembered interpreter no longer satisfies the request, while `--pick`
+//! always asks.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+use nix::sys::termios::{self, LocalFlags, SetArg};
+
+use crate::{env_provider::EnvProvider, non_empty_env_var, CliError, Error, ExactVersion};
+
+/// A key pressed in the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// The up arrow or `k`.
+    Up,
+    /// The down arrow or `j`.
+    Down,
+    /// Enter.
+    Select,
+    /// `q`, Escape, or Ctrl-C.
+    Cancel,
+}
+
+impl Key {
+    /// The key read as `bytes` from a terminal in non-canonical mode, if it
+    /// means anything to the menu.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            b"\x1b[A" | b"\x1bOA" | b"k" => Some(Self::Up),
+            b"\x1b[B" | b"\x1bOB" | b"j" => Some(Self::Down),
+            b"\r" | b"\n" => Some(Self::Select),
+            b"q" | b"\x1b" | b"\x03" => Some(Self::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// Where the picks of every project are remembered, whether the file exists
+/// or not.
+///
+/// This is `$XDG_DATA_HOME/python-launcher/picks.toml` or
+/// `~/.local/share/python-launcher/picks.toml`; `None` is returned if neither
+/// `XDG_DATA_HOME` nor `HOME` is set.
+pub fn picks_path() -> Option<PathBuf> {
+    non_empty_env_var("XDG_DATA_HOME")
+        .or_else(|| non_empty_env_var("HOME").map(|home| home.join(".local").join("share")))
+        .map(|data_home| data_home.join("python-launcher").join("picks.toml"))
+}
+
+/// The directory of the project containing `dir`, which a pick is remembered
+/// for.
+///
+/// This is the closest directory with a `.python-version`, `.py.toml`, or
+/// `pyproject.toml` file, else `dir` itself.
+pub fn project_dir(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|directory| {
+            [
+                ".python-version",
+                crate::config::PROJECT_CONFIG_FILE,
+                "pyproject.toml",
+            ]
+            .iter()
+            .any(|file_name| directory.join(file_name).is_file())
+        })
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// The interpreter remembered for `request` in `project` by the contents of
+/// the picks file.
+pub fn remembered(picks: &str, project: &Path, request: &str) -> Option<PathBuf> {
+    let picks = picks.parse::<toml::Value>().ok()?;
+    picks
+        .get(project.to_str()?)?
+        .get(request)?
+        .as_str()
+        .map(PathBuf::from)
+}
+
+/// The contents of the picks file after remembering `executable` for
+/// `request` in `project`, keeping every other pick.
+pub fn remember(picks: &str, project: &Path, request: &str, executable: &Path) -> String {
+    let mut picks = match picks.parse::<toml::Value>() {
+        Ok(toml::Value::Table(table)) => table,
+        _ => toml::value::Table::new(),
+    };
+    let project_picks = picks
+        .entry(project.display().to_string())
+        .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+    if !project_picks.is_table() {
+        *project_picks = toml::Value::Table(toml::value::Table::new());
+    }
+    if let toml::Value::Table(project_picks) = project_picks {
+        project_picks.insert(
+            request.to_string(),
+            toml::Value::String(executable.display().to_string()),
+        );
+    }
+    toml::to_string(&toml::Value::Table(picks)).unwrap_or_default()
+}
+
+/// Whether the menu can be shown, i.e. both stdin and stdout are terminals.
+pub fn available() -> bool {
+    [io::stdin().as_raw_fd(), io::stdout().as_raw_fd()]
+        .iter()
+        .all(|fd| nix::unistd::isatty(*fd).unwrap_or(false))
+}
+
+/// Picks one of `candidates` (from newest to oldest version) for `request`.
+///
+/// Unless `ask` is set, the interpreter remembered for the project (of the
+/// current directory of `env`) is used as long as it is still a candidate.
+/// Otherwise the menu is shown, starting at the remembered interpreter (or the
+/// newest one), and the pick is remembered.
+///
+/// # Errors
+///
+/// If the menu is cancelled, [`CliError::PickCancelled`] is returned.
+pub fn pick(
+    request: &str,
+    candidates: &[(ExactVersion, PathBuf)],
+    ask: bool,
+    env: &dyn EnvProvider,
+) -> crate::Result<PathBuf> {
+    let project = env
+        .current_dir()
+        .map(|cwd| project_dir(&cwd))
+        .unwrap_or_default();
+    let picks_path = picks_path();
+    let picks = picks_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    let remembered_index = remembered(&picks, &project, request)
+        .and_then(|executable| candidates.iter().position(|(_, path)| *path == executable));
+    if let (Some(index), false) = (remembered_index, ask) {
+        log::info!("Using the interpreter picked for {}", project.display());
+        return Ok(candidates[index].1.clone());
+    }
+
+    let items = candidates
+        .iter()
+        .map(|(version, path)| format!("{version}  {}", path.display()))
+        .collect::<Vec<String>>();
+    let index = match menu(
+        &format!("Pick an interpreter for {request} (↑/↓, Enter; q to cancel):"),
+        &items,
+        remembered_index.unwrap_or(0),
+    ) {
+        Ok(Some(index)) => index,
+        Ok(None) => return Err(Error::Cli(CliError::PickCancelled)),
+        Err(error) => {
+            log::warn!("Unable to show the menu, so using the newest interpreter: {error}");
+            return Ok(candidates[0].1.clone());
+        }
+    };
+    let executable = candidates[index].1.clone();
+
+    if let Some(picks_path) = picks_path {
+        let contents = remember(&picks, &project, request, &executable);
+        let written = picks_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&picks_path, contents));
+        if let Err(error) = written {
+            log::warn!(
+                "Unable to remember the pick in {}: {error}",
+                picks_path.display()
+            );
+        }
+    }
+    Ok(executable)
+}
+
+/// Renders the menu's `items` with the one at `selected` marked, each line
+/// clearing whatever was previously drawn there.
+pub fn render(items: &[String], selected: usize) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let marker = if index == selected { '>' } else { ' ' };
+            format!("\r\x1b[K{marker} {item}\n")
+        })
+        .collect()
+}
+
+/// The selection after pressing `key`, staying within the `count` items.
+pub fn moved(selected: usize, count: usize, key: Key) -> usize {
+    match key {
+        Key::Up => selected.saturating_sub(1),
+        Key::Down => (selected + 1).min(count.saturating_sub(1)),
+        Key::Select | Key::Cancel => selected,
+    }
+}
+
+/// Restores the terminal's settings when dropped.
+struct RawMode(termios::Termios);
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let original = termios::tcgetattr(io::stdin().as_raw_fd())?;
+        let mut raw = original.clone();
+        // Keys are read as they are pressed, without being echoed, and
+        // Ctrl-C cancels the menu instead of leaving the terminal raw.
+        raw.local_flags
+            .remove(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG);
+        termios::tcsetattr(io::stdin().as_raw_fd(), SetArg::TCSANOW, &raw)?;
+        Ok(Self(original))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(io::stdin().as_raw_fd(), SetArg::TCSANOW, &self.0);
+    }
+}
+
+/// Shows the menu on stderr, returning the index of the item selected or
+/// `None` if the menu is cancelled.
+fn menu(title: &str, items: &[String], mut selected: usize) -> io::Result<Option<usize>> {
+    let _raw_mode = RawMode::enable()?;
+    let mut stderr = io::stderr();
+    let mut stdin = io::stdin();
+    write!(stderr, "{title}\n{}", render(items, selected))?;
+    let mut buffer = [0; 8];
+    loop {
+        let read = stdin.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        match Key::from_bytes(&buffer[..read]) {
+            Some(Key::Select) => return Ok(Some(selected)),
+            Some(Key::Cancel) => return Ok(None),
+            Some(key) => {
+                selected = moved(selected, items.len(), key);
+                // Move back up to redraw the items in place.
+                write!(stderr, "\x1b[{}A{}", items.len(), render(items, selected))?;
+            }
+            None => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(b"\x1b[A" => Some(Key::Up) ; "up arrow")]
+    #[test_case(b"\x1bOA" => Some(Key::Up) ; "up arrow in application mode")]
+    #[test_case(b"k" => Some(Key::Up) ; "k")]
+    #[test_case(b"\x1b[B" => Some(Key::Down) ; "down arrow")]
+    #[test_case(b"j" => Some(Key::Down) ; "j")]
+    #[test_case(b"\r" => Some(Key::Select) ; "enter")]
+    #[test_case(b"\n" => Some(Key::Select) ; "newline")]
+    #[test_case(b"q" => Some(Key::Cancel) ; "q")]
+    #[test_case(b"\x1b" => Some(Key::Cancel) ; "escape")]
+    #[test_case(b"\x03" => Some(Key::Cancel) ; "ctrl-c")]
+    #[test_case(b"\x1b[C" => None ; "right arrow")]
+    #[test_case(b"x" => None ; "other key")]
+    fn key_from_bytes_tests(bytes: &[u8]) -> Option<Key> {
+        Key::from_bytes(bytes)
+    }
+
+    #[test_case(0, Key::Up => 0 ; "up at the top")]
+    #[test_case(1, Key::Up => 0 ; "up")]
+    #[test_case(1, Key::Down => 2 ; "down")]
+    #[test_case(2, Key::Down => 2 ; "down at the bottom")]
+    #[test_case(1, Key::Select => 1 ; "select")]
+    fn moved_tests(selected: usize, key: Key) -> usize {
+        moved(selected, 3, key)
+    }
+
+    #[test]
+    fn test_render() {
+        let items = vec![
+            "3.12  /a/python3.12".to_string(),
+            "3.11  /a/python3.11".to_string(),
+        ];
+        assert_eq!(
+            render(&items, 1),
+            "\r\x1b[K  3.12  /a/python3.12\n\r\x1b[K> 3.11  /a/python3.11\n"
+        );
+    }
+
+    #[test]
+    fn test_project_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        let nested = project.join("src").join("package");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(project_dir(&nested), nested);
+
+        fs::write(project.join("pyproject.toml"), "").unwrap();
+        assert_eq!(project_dir(&nested), project);
+    }
+
+    #[test]
+    fn test_remember() {
+        let project = Path::new("/home/user/project");
+        let other_project = Path::new("/home/user/other");
+        assert_eq!(remembered("", project, "Python 3"), None);
+
+        let picks = remember("", project, "Python 3", Path::new("/usr/bin/python3.11"));
+        let picks = remember(&picks, project, "Python", Path::new("/usr/bin/python3.12"));
+        let picks = remember(
+            &picks,
+            other_project,
+            "Python 3",
+            Path::new("/usr/bin/python3.10"),
+        );
+        assert_eq!(
+            remembered(&picks, project, "Python 3"),
+            Some(PathBuf::from("/usr/bin/python3.11"))
+        );
+        assert_eq!(
+            remembered(&picks, project, "Python"),
+            Some(PathBuf::from("/usr/bin/python3.12"))
+        );
+        assert_eq!(
+            remembered(&picks, other_project, "Python 3"),
+            Some(PathBuf::from("/usr/bin/python3.10"))
+        );
+
+        let picks = remember(
+            &picks,
+            project,
+            "Python 3",
+            Path::new("/usr/bin/python3.12"),
+        );
+        assert_eq!(
+            remembered(&picks, project, "Python 3"),
+            Some(PathBuf::from("/usr/bin/python3.12"))
+        );
+        // A broken file is started over.
+        let picks = remember(
+            "not toml [",
+            project,
+            "Python 3",
+            Path::new("/usr/bin/python3.11"),
+        );
+        assert_eq!(
+            remembered(&picks, project, "Python 3"),
+            Some(PathBuf::from("/usr/bin/python3.11"))
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Bindings for Python
+//!
+//! With the `python` feature, this module defines the `python_launcher`
+//! extension module, so that Python tooling (e.g. build backends and test
+//! runners) finds the same interpreter as `py` would without shelling out to
+//! it. The extension module is built with [maturin](https://www.maturin.rs/)
+//! via `pyproject.toml`:
+//!
+//! ```python
+//! import python_launcher
+//!
+//! path = python_launcher.find_executable("3.11")
+//! for version, path in python_launcher.all_executables().items():
+//!     print(version, path)
+//! ```
+//!
+//! Paths are returned as strings, and the GIL is released while searching.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use pyo3::{basic::CompareOp, exceptions::PyValueError, prelude::*};
+
+use crate::{Error, ExactVersion, RequestedVersion, VersionSpecifier};
+
+impl From<Error> for PyErr {
+    fn from(error: Error) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+/// The version of a Python executable which was found (see
+/// [`crate::ExactVersion`]), e.g. `ExactVersion("3.12.1")`.
+#[pyclass(name = "ExactVersion", module = "python_launcher", frozen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PyExactVersion(pub ExactVersion);
+
+#[pymethods]
+impl PyExactVersion {
+    #[new]
+    fn new(version: &str) -> PyResult<Self> {
+        Ok(Self(ExactVersion::from_str(version)?))
+    }
+
+    /// The version of the executable at `path` according to its file name,
+    /// e.g. `3.12` for `/usr/bin/python3.12`.
+    #[staticmethod]
+    fn from_path(path: PathBuf) -> PyResult<Self> {
+        Ok(Self(ExactVersion::from_path(&path)?))
+    }
+
+    #[getter]
+    fn major(&self) -> u16 {
+        self.0.major
+    }
+
+    #[getter]
+    fn minor(&self) -> u16 {
+        self.0.minor
+    }
+
+    #[getter]
+    fn micro(&self) -> Option<u16> {
+        self.0.micro
+    }
+
+    #[getter]
+    fn free_threaded(&self) -> bool {
+        self.0.free_threaded
+    }
+
+    /// The name of the implementation, e.g. `CPython` or `PyPy`.
+    #[getter]
+    fn implementation(&self) -> String {
+        self.0.implementation.to_string()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ExactVersion('{}')", self.0)
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> bool {
+        op.matches(self.0.cmp(&other.0))
+    }
+}
+
+/// The version of Python being searched for (see
+/// [`crate::RequestedVersion`]), e.g. `RequestedVersion("3.12")`.
+#[pyclass(name = "RequestedVersion", module = "python_launcher", frozen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PyRequestedVersion(pub RequestedVersion);
+
+#[pymethods]
+impl PyRequestedVersion {
+    #[new]
+    #[pyo3(signature = (version = ""))]
+    fn new(version: &str) -> PyResult<Self> {
+        Ok(Self(RequestedVersion::from_str(version)?))
+    }
+
+    /// Whether `version` satisfies the request.
+    fn matches(&self, version: &PyExactVersion) -> bool {
+        VersionSpecifier::from(self.0).contains(&version.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<RequestedVersion {}>", self.0)
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+/// What [`find_executable`] accepts as the version to find.
+#[derive(FromPyObject)]
+enum Requested {
+    Version(PyRequestedVersion),
+    Text(String),
+}
+
+impl Requested {
+    /// Parses text as a [`RequestedVersion`] (e.g. `3.12`), falling back to a
+    /// [`VersionSpecifier`] (e.g. `>=3.9,<3.12`).
+    fn specifier(self) -> crate::Result<VersionSpecifier> {
+        match self {
+            Self::Version(requested) => Ok(requested.0.into()),
+            Self::Text(text) => RequestedVersion::from_str(&text)
+                .map(VersionSpecifier::from)
+                .or_else(|error| VersionSpecifier::from_str(&text).map_err(|_| error)),
+        }
+    }
+}
+
+/// Finds the newest executable which satisfies `version` (see
+/// [`crate::find_executable`]), or any version if it isn't specified.
+#[pyfunction]
+#[pyo3(signature = (version = None))]
+fn find_executable(py: Python<'_>, version: Option<Requested>) -> PyResult<Option<PathBuf>> {
+    let specifier = match version {
+        Some(requested) => requested.specifier()?,
+        None => RequestedVersion::Any.into(),
+    };
+    Ok(py.allow_threads(|| crate::find_executable(specifier)))
+}
+
+/// Finds every executable from the built-in sources, keyed by version (see
+/// [`crate::all_executables`]).
+#[pyfunction]
+fn all_executables(py: Python<'_>) -> HashMap<PyExactVersion, PathBuf> {
+    py.allow_threads(crate::all_executables)
+        .into_iter()
+        .map(|(version, path)| (PyExactVersion(version), path))
+        .collect()
+}
+
+/// The `python_launcher` extension module.
+#[pymodule]
+pub fn python_launcher(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyExactVersion>()?;
+    module.add_class::<PyRequestedVersion>()?;
+    module.add_function(wrap_pyfunction!(find_executable, module)?)?;
+    module.add_function(wrap_pyfunction!(all_executables, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("3.12" => Ok("==3.12.*".to_string()) ; "requested version")]
+    #[test_case(">=3.9,<3.12" => Ok(">=3.9,<3.12".to_string()) ; "version specifier")]
+    #[test_case("three" => matches Err(_) ; "invalid")]
+    fn requested_specifier(text: &str) -> crate::Result<String> {
+        Requested::Text(text.to_string())
+            .specifier()
+            .map(|specifier| specifier.to_string())
+    }
+
+    #[test]
+    fn module() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "python_launcher").unwrap();
+            python_launcher(&module).unwrap();
+            pyo3::py_run!(
+                py,
+                module,
+                r#"
+version = module.ExactVersion("3.12.1")
+assert (version.major, version.minor, version.micro) == (3, 12, 1)
+assert version.implementation == "CPython"
+assert str(version) == "3.12.1" and repr(version) == "ExactVersion('3.12.1')"
+assert module.ExactVersion("3.11") < version
+assert {version: 1}[module.ExactVersion("3.12.1")] == 1
+assert module.ExactVersion.from_path("/usr/bin/pypy3.10").implementation == "PyPy"
+
+requested = module.RequestedVersion("3.12")
+assert requested.matches(version)
+assert not requested.matches(module.ExactVersion("3.11"))
+assert requested == module.RequestedVersion("3.12")
+assert str(requested) == "Python 3.12"
+
+try:
+    module.ExactVersion("three")
+except ValueError:
+    pass
+else:
+    raise AssertionError("ValueError not raised")
+
+assert module.find_executable("42.0") is None
+assert module.find_executable(module.RequestedVersion("42")) is None
+assert isinstance(module.all_executables(), dict)
+"#
+            );
+        });
+    }
+}
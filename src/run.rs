@@ -0,0 +1,555 @@
+//! Running an interpreter
+//!
+//! By default the launcher replaces itself with the interpreter via `execv`
+//! ([`RunMode::Exec`]), so that running the interpreter through the launcher
+//! is no different from running it directly.
+//!
+//! Setting `run-mode = "spawn"` in the configuration (see
+//! [`crate::config::Config::run_mode`]) runs the interpreter as a child
+//! process instead ([`RunMode::Spawn`]). The launcher then waits for the
+//! interpreter, passing on interruptions to it, and exits the same way it did
+//! (see [`exit_with`]).
+//!
+//! Everything but the settings ([`RunMode`] and [`Argv0`]) requires the `cli`
+//! feature.
+//!
+//! The interpreter sees the path to it as `argv[0]` unless another is given
+//! (see [`Argv0`]).
+//!
+//! Only Unix is supported: Windows is not a supported platform, so there is no
+//! `CreateProcess`-based runner (use the official launcher there).
+//!
+//! `SIGINT`, `SIGTERM`, and `SIGHUP` sent to the launcher are forwarded to the
+//! interpreter, and the launcher ends itself with the same signal as the
+//! interpreter if one ended it. When the launcher is in the foreground of a
+//! terminal, the interpreter is put in its own process group which is made the
+//! terminal's foreground process group, so that Ctrl-C, Ctrl-Z, and reading
+//! from the terminal affect the interpreter like a shell job. If the
+//! interpreter is stopped (e.g. by Ctrl-Z), the launcher hands the terminal
+//! back and stops itself so the shell sees the job as stopped; when continued
+//! by `fg` or `bg`, the launcher continues the interpreter, giving it the
+//! terminal again if it was continued in the foreground.
+
+#[cfg(feature = "cli")]
+use std::{
+    convert::Infallible,
+    io,
+    path::Path,
+    process::{Command, ExitStatus},
+};
+
+/// How an interpreter is run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RunMode {
+    /// Replace the launcher with the interpreter (see [`exec`]).
+    Exec,
+    /// Run the interpreter as a child process and wait for it (see
+    /// [`spawn`]).
+    Spawn,
+}
+
+impl Default for RunMode {
+    fn default() -> Self {
+        Self::Exec
+    }
+}
+
+impl RunMode {
+    /// Returns the mode with the specified name, i.e. `exec` or `spawn`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exec" => Some(Self::Exec),
+            "spawn" => Some(Self::Spawn),
+            _ => None,
+        }
+    }
+
+    /// The name of the mode, as accepted by [`RunMode::from_name`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Exec => "exec",
+            Self::Spawn => "spawn",
+        }
+    }
+}
+
+/// What the interpreter sees as `argv[0]`, i.e. `sys.argv[0]` before the
+/// script replaces it and what `sys.executable` is worked out from.
+///
+/// CPython searches `PATH` for an `argv[0]` which isn't a path, so with
+/// anything but [`Argv0::Path`] `sys.executable` may be another interpreter
+/// (or the launcher) than the one run, e.g. the one outside of a virtual
+/// environment, which then isn't detected as such.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Argv0 {
+    /// The path to the interpreter, e.g. `/usr/bin/python3.12`.
+    Path,
+    /// The file name of the interpreter, e.g. `python3.12`, as if it had been
+    /// run from `PATH`.
+    Name,
+    /// What the launcher itself was run as, e.g. `py`.
+    Launcher,
+}
+
+impl Default for Argv0 {
+    fn default() -> Self {
+        Self::Path
+    }
+}
+
+impl Argv0 {
+    /// Returns the choice with the specified name, i.e. `path`, `name`, or
+    /// `launcher`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(Self::Path),
+            "name" => Some(Self::Name),
+            "launcher" => Some(Self::Launcher),
+            _ => None,
+        }
+    }
+
+    /// The name of the choice, as accepted by [`Argv0::from_name`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Name => "name",
+            Self::Launcher => "launcher",
+        }
+    }
+}
+
+/// Replaces the current process with `executable`, passing `args` to it and
+/// `argv0` as `argv[0]` (defaulting to `executable`).
+///
+/// The interpreter inherits the launcher's environment variables, except for
+/// `unset_env_vars`, and with `env_vars` set.
+///
+/// Only returns if the interpreter can't be run.
+#[cfg(feature = "cli")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", err))]
+pub fn exec(
+    executable: &Path,
+    argv0: Option<&str>,
+    args: &[String],
+    env_vars: &[(String, String)],
+    unset_env_vars: &[String],
+) -> io::Result<Infallible> {
+    platform::exec(executable, argv0, args, env_vars, unset_env_vars)
+}
+
+/// Runs `executable` with `args` (and `argv0` as `argv[0]`, defaulting to
+/// `executable`) as a child process and waits for it to exit, returning how it
+/// did. The environment variables are changed for the child like by
+/// [`exec`].
+///
+/// While waiting, interruptions meant for the interpreter don't end the
+/// launcher (see the [module documentation](self)).
+///
+/// # Errors
+///
+/// If the child can't be started or waited on, the [`io::Error`] is
+/// returned.
+#[cfg(feature = "cli")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", ret, err))]
+pub fn spawn(
+    executable: &Path,
+    argv0: Option<&str>,
+    args: &[String],
+    env_vars: &[(String, String)],
+    unset_env_vars: &[String],
+) -> io::Result<ExitStatus> {
+    let mut command = Command::new(executable);
+    command.args(args);
+    for name in unset_env_vars {
+        command.env_remove(name);
+    }
+    command.envs(env_vars.iter().map(|(name, value)| (name, value)));
+    if let Some(argv0) = argv0 {
+        platform::set_argv0(&mut command, argv0);
+    }
+    platform::spawn(command)
+}
+
+/// The exit code which represents `status`, i.e. the exit code of the process
+/// or 128 plus the signal which ended it, like shells use.
+#[cfg(feature = "cli")]
+pub fn exit_code(status: ExitStatus) -> i32 {
+    platform::exit_code(status)
+}
+
+/// Exits the same way as the process with `status`.
+///
+/// If the process was ended by a signal, the launcher ends itself with the
+/// same signal so that e.g. a shell knows the interpreter was interrupted,
+/// falling back to the [exit code](exit_code) of `status`.
+#[cfg(feature = "cli")]
+pub fn exit_with(status: ExitStatus) -> ! {
+    platform::exit_with(status)
+}
+
+#[cfg(feature = "cli")]
+mod platform {
+    use std::{
+        convert::Infallible,
+        env,
+        ffi::{CString, OsStr},
+        io,
+        os::{
+            raw::{c_int, c_void},
+            unix::{
+                ffi::OsStrExt,
+                io::{AsRawFd, RawFd},
+                process::{CommandExt, ExitStatusExt},
+            },
+        },
+        path::Path,
+        process::{self, Command, ExitStatus},
+        sync::atomic::{AtomicI32, Ordering},
+    };
+
+    use nix::{
+        errno::Errno,
+        libc,
+        sys::{
+            signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal},
+            wait::{self, WaitPidFlag, WaitStatus},
+        },
+        unistd::{self, Pid},
+    };
+
+    /// The signals which are forwarded to the interpreter.
+    const FORWARDED_SIGNALS: [Signal; 3] = [Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP];
+
+    /// The process ID of the interpreter being waited on, negated when the
+    /// interpreter leads its own process group so that the whole group is
+    /// signalled, or 0 when there is none.
+    static CHILD_PID: AtomicI32 = AtomicI32::new(0);
+
+    extern "C" fn forward_signal(signal: c_int, info: *mut libc::siginfo_t, _context: *mut c_void) {
+        let pid = CHILD_PID.load(Ordering::SeqCst);
+        // A signal from the terminal (e.g. Ctrl-C) is already delivered to
+        // the interpreter as part of the foreground process group, so only
+        // signals sent by a process (e.g. `kill`) are forwarded.
+        // SAFETY: `info` is provided by the kernel as `SA_SIGINFO` is set.
+        let sent_by_process = !info.is_null() && unsafe { (*info).si_pid() } != 0;
+        if pid != 0 && sent_by_process {
+            // SAFETY: `kill` is async-signal-safe.
+            unsafe {
+                libc::kill(pid, signal);
+            }
+        }
+    }
+
+    pub fn exec(
+        executable: &Path,
+        argv0: Option<&str>,
+        args: &[String],
+        env_vars: &[(String, String)],
+        unset_env_vars: &[String],
+    ) -> io::Result<Infallible> {
+        let executable_as_cstring = CString::new(executable.as_os_str().as_bytes())?;
+        let mut argv = vec![match argv0 {
+            Some(argv0) => CString::new(argv0)?,
+            None => executable_as_cstring.clone(),
+        }];
+        for arg in args {
+            argv.push(CString::new(arg.as_str())?);
+        }
+        let envp = environment(env_vars, unset_env_vars)?;
+
+        Ok(unistd::execve(&executable_as_cstring, &argv, &envp)?)
+    }
+
+    /// The launcher's environment variables as `NAME=value`, without
+    /// `unset_env_vars` and with `env_vars` set.
+    fn environment(
+        env_vars: &[(String, String)],
+        unset_env_vars: &[String],
+    ) -> io::Result<Vec<CString>> {
+        let is_changed = |name: &OsStr| {
+            unset_env_vars.iter().any(|unset| name == unset.as_str())
+                || env_vars.iter().any(|(set, _)| name == set.as_str())
+        };
+        let mut envp = Vec::new();
+        for (name, value) in env::vars_os().filter(|(name, _)| !is_changed(name)) {
+            envp.push(CString::new(
+                [name.as_bytes(), b"=", value.as_bytes()].concat(),
+            )?);
+        }
+        for (name, value) in env_vars {
+            envp.push(CString::new(format!("{name}={value}"))?);
+        }
+        Ok(envp)
+    }
+
+    pub fn set_argv0(command: &mut Command, argv0: &str) {
+        command.arg0(argv0);
+    }
+
+    /// Signal actions which are restored to what they were when dropped.
+    struct Actions(Vec<(Signal, SigAction)>);
+
+    impl Actions {
+        /// Sets the action of every one of `signals` to `action`.
+        ///
+        /// # Safety
+        ///
+        /// Any handler must only call async-signal-safe functions.
+        unsafe fn set(signals: &[Signal], action: &SigAction) -> io::Result<Self> {
+            let mut previous_actions = Self(Vec::new());
+            for &changed in signals {
+                let previous_action = signal::sigaction(changed, action)?;
+                previous_actions.0.push((changed, previous_action));
+            }
+            Ok(previous_actions)
+        }
+    }
+
+    impl Drop for Actions {
+        fn drop(&mut self) {
+            for (changed, previous_action) in &self.0 {
+                // SAFETY: restoring the action which was in place before.
+                let _ = unsafe { signal::sigaction(*changed, previous_action) };
+            }
+        }
+    }
+
+    /// The file descriptor of the terminal on stdin if the launcher is in its
+    /// foreground process group, i.e. if it can do job control like a shell.
+    fn foreground_terminal() -> Option<RawFd> {
+        let stdin = io::stdin().as_raw_fd();
+        match unistd::tcgetpgrp(stdin) {
+            Ok(foreground) if foreground == unistd::getpgrp() => Some(stdin),
+            _ => None,
+        }
+    }
+
+    pub fn spawn(mut command: Command) -> io::Result<ExitStatus> {
+        let forward = SigAction::new(
+            SigHandler::SigAction(forward_signal),
+            SaFlags::SA_SIGINFO | SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        // SAFETY: the handler only calls async-signal-safe functions.
+        let _forwarding = unsafe { Actions::set(&FORWARDED_SIGNALS, &forward) }?;
+
+        let terminal = match foreground_terminal() {
+            Some(terminal) => terminal,
+            None => {
+                // Without a terminal to hand over, the interpreter stays in
+                // the launcher's process group, so it is stopped and
+                // continued along with the launcher.
+                let mut child = command.spawn()?;
+                CHILD_PID.store(child.id() as i32, Ordering::SeqCst);
+                let status = child.wait();
+                CHILD_PID.store(0, Ordering::SeqCst);
+                return status;
+            }
+        };
+
+        // Changing the foreground process group from the background raises
+        // `SIGTTOU`, which would stop the launcher (or the interpreter before
+        // it starts).
+        let ignore = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
+        // SAFETY: no handler is set.
+        let _ignoring = unsafe { Actions::set(&[Signal::SIGTTOU], &ignore) }?;
+        // SAFETY: only async-signal-safe functions are called.
+        unsafe {
+            command.pre_exec(move || {
+                // Both processes set up the process group and the terminal so
+                // that it happens before either relies on it.
+                unistd::setpgid(Pid::from_raw(0), Pid::from_raw(0))?;
+                let _ = unistd::tcsetpgrp(terminal, unistd::getpid());
+                signal::signal(Signal::SIGTTOU, SigHandler::SigDfl)?;
+                Ok(())
+            });
+        }
+        let child = Pid::from_raw(command.spawn()?.id() as i32);
+        let _ = unistd::setpgid(child, child);
+        let _ = unistd::tcsetpgrp(terminal, child);
+        CHILD_PID.store(-child.as_raw(), Ordering::SeqCst);
+
+        let status = wait_in_foreground(child, terminal);
+
+        CHILD_PID.store(0, Ordering::SeqCst);
+        take_back(terminal, child);
+        status
+    }
+
+    /// Makes the launcher's process group the foreground process group of
+    /// `terminal` again, unless `child` no longer has it, e.g. because the
+    /// launcher was put in the background.
+    fn take_back(terminal: RawFd, child: Pid) {
+        if unistd::tcgetpgrp(terminal) == Ok(child) {
+            let _ = unistd::tcsetpgrp(terminal, unistd::getpgrp());
+        }
+    }
+
+    /// Waits for `child`, which leads the foreground process group of
+    /// `terminal`, to exit, stopping the launcher whenever the child is
+    /// stopped.
+    fn wait_in_foreground(child: Pid, terminal: RawFd) -> io::Result<ExitStatus> {
+        loop {
+            match wait::waitpid(child, Some(WaitPidFlag::WUNTRACED)) {
+                Ok(WaitStatus::Exited(_, code)) => return Ok(ExitStatus::from_raw(code << 8)),
+                Ok(WaitStatus::Signaled(_, ended_by, core_dumped)) => {
+                    let core_dump_flag = if core_dumped { 0x80 } else { 0 };
+                    return Ok(ExitStatus::from_raw(ended_by as i32 | core_dump_flag));
+                }
+                Ok(WaitStatus::Stopped(..)) => {
+                    take_back(terminal, child);
+                    // Returns once the shell continues the job.
+                    let _ = signal::kill(unistd::getpid(), Signal::SIGSTOP);
+                    // `fg` hands the terminal to the launcher while `bg`
+                    // doesn't.
+                    if unistd::tcgetpgrp(terminal) == Ok(unistd::getpgrp()) {
+                        let _ = unistd::tcsetpgrp(terminal, child);
+                    }
+                    let _ = signal::killpg(child, Signal::SIGCONT);
+                }
+                Ok(_) | Err(Errno::EINTR) => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    pub fn exit_code(status: ExitStatus) -> i32 {
+        status
+            .code()
+            .unwrap_or_else(|| 128 + status.signal().unwrap_or_default())
+    }
+
+    pub fn exit_with(status: ExitStatus) -> ! {
+        if let Some(ended_by) = status
+            .signal()
+            .and_then(|number| Signal::try_from(number).ok())
+        {
+            // SAFETY: resetting to the default handler.
+            if unsafe { signal::signal(ended_by, SigHandler::SigDfl) }.is_ok() {
+                let _ = signal::raise(ended_by);
+            }
+        }
+        process::exit(exit_code(status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "cli")]
+    use std::{thread, time::Duration};
+
+    #[cfg(feature = "cli")]
+    use nix::{
+        libc,
+        sys::signal::{self, Signal},
+        unistd,
+    };
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("exec" => Some(RunMode::Exec) ; "exec")]
+    #[test_case("spawn" => Some(RunMode::Spawn) ; "spawn")]
+    #[test_case("fork" => None ; "unknown")]
+    fn run_mode_from_name_tests(name: &str) -> Option<RunMode> {
+        RunMode::from_name(name)
+    }
+
+    #[test_case("path" => Some(Argv0::Path) ; "path")]
+    #[test_case("name" => Some(Argv0::Name) ; "name")]
+    #[test_case("launcher" => Some(Argv0::Launcher) ; "launcher")]
+    #[test_case("python" => None ; "unknown")]
+    fn argv0_from_name_tests(name: &str) -> Option<Argv0> {
+        Argv0::from_name(name)
+    }
+
+    #[test]
+    fn test_argv0_name() {
+        for argv0 in [Argv0::Path, Argv0::Name, Argv0::Launcher] {
+            assert_eq!(Argv0::from_name(argv0.name()), Some(argv0));
+        }
+    }
+
+    #[test]
+    fn test_run_mode_name() {
+        for mode in [RunMode::Exec, RunMode::Spawn] {
+            assert_eq!(RunMode::from_name(mode.name()), Some(mode));
+        }
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_exec_nul() {
+        assert_eq!(
+            exec(Path::new("/bin/sh"), None, &["a\0b".to_string()], &[], &[])
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[cfg(feature = "cli")]
+    fn sh(script: &str) -> ExitStatus {
+        spawn(
+            Path::new("/bin/sh"),
+            None,
+            &["-c".to_string(), script.to_string()],
+            &[],
+            &[],
+        )
+        .unwrap()
+    }
+
+    // Everything which spawns is in a single test as the child's process ID is
+    // global.
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_spawn() {
+        assert_eq!(exit_code(sh("exit 0")), 0);
+        assert_eq!(exit_code(sh("exit 3")), 3);
+        assert_eq!(exit_code(sh("kill -TERM $$")), 128 + libc::SIGTERM);
+
+        let forwarder = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(500));
+            signal::kill(unistd::getpid(), Signal::SIGTERM).unwrap();
+        });
+        // The child only exits early if the signal is forwarded.
+        let status = sh("trap 'exit 7' TERM; sleep 10 & wait");
+        forwarder.join().unwrap();
+        assert_eq!(exit_code(status), 7);
+
+        // The command line of a process starts with its `argv[0]`.
+        let argv0 = spawn(
+            Path::new("/bin/sh"),
+            Some("my-sh"),
+            &[
+                "-c".to_string(),
+                "ps -o args= -p $$ | grep -q '^my-sh '".to_string(),
+            ],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(exit_code(argv0), 0);
+
+        // Only the child's environment is changed.
+        let home = std::env::var_os("HOME");
+        let env_vars = spawn(
+            Path::new("/bin/sh"),
+            None,
+            &[
+                "-c".to_string(),
+                "[ \"$PY_TEST_VAR\" = set ] && [ -z \"${HOME+x}\" ]".to_string(),
+            ],
+            &[("PY_TEST_VAR".to_string(), "set".to_string())],
+            &["HOME".to_string()],
+        )
+        .unwrap();
+        assert_eq!(exit_code(env_vars), 0);
+        assert_eq!(std::env::var_os("HOME"), home);
+        assert!(std::env::var_os("PY_TEST_VAR").is_none());
+
+        assert!(spawn(Path::new("/does/not/exist"), None, &[], &[], &[]).is_err());
+    }
+}
@@ -0,0 +1,517 @@
+//! Answering requests from editors over standard input and output
+//!
+//! `py --serve` lets an editor (or any other long-running tool) ask which
+//! interpreters there are and which one would be used, without running `py`
+//! for every question. Each line of standard input is a request and each line
+//! of standard output is the response to one, both JSON objects, until
+//! standard input is closed. The executables found by searching are kept in
+//! memory between requests for each set of directories searched (which a
+//! project's `search-directories` and disabled sources can change), so only
+//! the first request for them pays for reading `PATH` and every other source;
+//! the configuration, environment variables, and virtual environments are
+//! checked anew for every request.
+//!
+//! A request has a `method` along with an optional `id`, which is echoed back
+//! in its response:
+//!
+//! - `list`: the executables `py --list --json` would print (`py --list --all
+//!   --json` when `all` is `true`).
+//! - `resolve`: the executable `py --resolve` would describe, for the
+//!   `version` if there is one (e.g. `"3.12"` or `"3.12,3.11"`).
+//! - `invalidate`: forgets the executables found so far, e.g. after an
+//!   interpreter is installed, so the next request searches again.
+//!
+//! `list` and `resolve` are answered for the directory `cwd` if it's given
+//! (like `--chdir`, relative to the directory `py --serve` was started in),
+//! else the directory `py --serve` was started in. The current directory of
+//! the server itself never changes.
+//!
+//! A response has the `id` of its request and either the `result` or an
+//! `error` object with a `message`:
+//!
+//! ```text
+//! {"id": 1, "method": "resolve", "version": "3.12"}
+//! {"id":1,"result":{"path":"/usr/bin/python3.12","version":"3.12","source":"path","venv":null}}
+//! {"id": 2, "method": "resolve", "version": "42"}
+//! {"id":2,"error":{"message":"No executable found for Python 42"}}
+//! ```
+
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    cli::{self, Request},
+    config::{Config, ConfigFiles},
+    env_provider::{EnvProvider, ProcessEnv, WithCurrentDir},
+    CliError, Error, ExactVersion, RequestedVersion, Search, VenvDetection, VersionPreferenceList,
+};
+
+/// What the executables found by a search depend on: the name and directories
+/// of each source, and the sources which are disabled.
+type DiscoveryKey = (Vec<(String, Option<Vec<PathBuf>>)>, Vec<String>);
+
+/// The executables found by searching, kept between requests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Discovered {
+    /// Every executable along with the name of its source (see
+    /// [`crate::all_executables_by_source`]).
+    by_source: Vec<(String, ExactVersion, PathBuf)>,
+    /// The executable from the earliest source for each version (see
+    /// [`crate::all_executables`]).
+    executables: HashMap<ExactVersion, PathBuf>,
+    /// Windows-native executables (see [`crate::windows_executables`]).
+    windows: HashMap<ExactVersion, PathBuf>,
+}
+
+impl Discovered {
+    fn key(search: &Search) -> DiscoveryKey {
+        let sources = search
+            .sources()
+            .iter()
+            .map(|source| (source.name().to_string(), source.directories()))
+            .collect();
+        (sources, search.disabled_sources())
+    }
+
+    fn search(search: &Search) -> Self {
+        let by_source = search.all_executables_by_source();
+        let mut executables = HashMap::new();
+        for (_, version, path) in &by_source {
+            executables.entry(*version).or_insert_with(|| path.clone());
+        }
+        Self {
+            by_source,
+            executables,
+            windows: search.windows_executables(),
+        }
+    }
+}
+
+/// Answers requests, keeping the executables found by searching until they're
+/// invalidated.
+#[derive(Clone, Debug, Default)]
+pub struct Server {
+    /// The directory requests are answered for unless they say otherwise.
+    dir: Option<PathBuf>,
+    /// The configuration files to load for each request, if not those chosen
+    /// by the environment.
+    config_files: Option<ConfigFiles>,
+    discovered: HashMap<DiscoveryKey, Discovered>,
+}
+
+impl Server {
+    /// Creates a server which answers requests for the current directory
+    /// unless they specify another.
+    pub fn new() -> Self {
+        Self {
+            dir: env::current_dir().ok(),
+            config_files: None,
+            discovered: HashMap::new(),
+        }
+    }
+
+    /// Answers requests for `dir` (e.g. the one chosen by `--chdir`) unless
+    /// they specify another.
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Loads the specified configuration files for each request (e.g. those
+    /// chosen by `--config`) instead of those chosen by the environment.
+    pub fn config_files(mut self, config_files: ConfigFiles) -> Self {
+        self.config_files = Some(config_files);
+        self
+    }
+
+    /// Loads the configuration for a request, as it may have changed since
+    /// the last one.
+    fn load_config(&self, env: &dyn EnvProvider) -> crate::Result<Config> {
+        match &self.config_files {
+            Some(config_files) => Config::load_files(env, config_files).map(|(config, _)| config),
+            None => Config::load_in(env),
+        }
+    }
+
+    /// Answers every line of `input` with a line of `output` until `input`
+    /// ends, skipping blank lines.
+    ///
+    /// # Errors
+    ///
+    /// Any error from reading `input` or writing to `output`; a request which
+    /// can't be answered gets an error response instead.
+    pub fn serve(&mut self, input: impl BufRead, output: &mut dyn Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(output, "{}", self.respond(&line))?;
+            // The client waits for the response before sending more.
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the response (without a trailing newline) to a request.
+    pub fn respond(&mut self, request: &str) -> String {
+        let (id, result) = match serde_json::from_str::<Value>(request) {
+            Ok(Value::Object(request)) => (
+                request.get("id").cloned().unwrap_or(Value::Null),
+                self.answer(&request),
+            ),
+            Ok(_) => (
+                Value::Null,
+                Err("invalid request: expected a JSON object".to_string()),
+            ),
+            Err(error) => (Value::Null, Err(format!("invalid request: {error}"))),
+        };
+        match result {
+            Ok(result) => json!({"id": id, "result": result}),
+            Err(message) => json!({"id": id, "error": {"message": message}}),
+        }
+        .to_string()
+    }
+
+    fn answer(&mut self, request: &Map<String, Value>) -> Result<Value, String> {
+        let field = |name: &str| request.get(name).filter(|value| !value.is_null());
+        let method = field("method")
+            .ok_or("invalid request: missing \"method\"")?
+            .as_str()
+            .ok_or("invalid request: \"method\" must be a string")?;
+        match method {
+            "list" => {
+                let all = match field("all") {
+                    Some(all) => all
+                        .as_bool()
+                        .ok_or("invalid request: \"all\" must be a boolean")?,
+                    None => false,
+                };
+                let env = self.request_env(field("cwd"))?;
+                self.list(&env, all).map_err(|error| error.to_string())
+            }
+            "resolve" => {
+                let preferences = match field("version") {
+                    Some(version) => version
+                        .as_str()
+                        .ok_or("invalid request: \"version\" must be a string")?,
+                    None => "",
+                };
+                let env = self.request_env(field("cwd"))?;
+                self.resolve(&env, preferences)
+                    .map_err(|error| error.to_string())
+            }
+            "invalidate" => {
+                self.discovered.clear();
+                Ok(Value::Null)
+            }
+            method => Err(format!("unknown method {method:?}")),
+        }
+    }
+
+    /// The environment of the process with the directory a request is
+    /// answered for as its current directory.
+    fn request_env(&self, cwd: Option<&Value>) -> Result<WithCurrentDir<ProcessEnv>, String> {
+        let (dir, resolved) = match cwd {
+            Some(cwd) => {
+                let dir = Path::new(
+                    cwd.as_str()
+                        .ok_or("invalid request: \"cwd\" must be a string")?,
+                );
+                (dir, cli::resolve_working_dir(self.dir.as_deref(), dir))
+            }
+            None => match &self.dir {
+                Some(dir) => (dir.as_path(), Ok(dir.clone())),
+                None => (Path::new("."), ProcessEnv.current_dir()),
+            },
+        };
+        resolved
+            .map(|resolved| WithCurrentDir::new(ProcessEnv, resolved))
+            .map_err(|error| {
+                Error::Cli(CliError::InvalidWorkingDir(dir.to_path_buf(), error.into())).to_string()
+            })
+    }
+
+    fn discovered(&mut self, search: &Search) -> &Discovered {
+        self.discovered
+            .entry(Discovered::key(search))
+            .or_insert_with(|| Discovered::search(search))
+    }
+
+    /// What `py --list --json` (or `py --list --all --json`) prints.
+    fn list(&mut self, env: &dyn EnvProvider, all: bool) -> crate::Result<Value> {
+        let config = self.load_config(env)?;
+        let search = Search::new(env, &config);
+        let environments = search.all_environments(cli::default_venv_detection(env, &config));
+        // What `py` on its own would launch, as `--list` marks it.
+        let default_executable = match environments.first() {
+            Some((kind, venv)) => Some((venv.executable.clone(), *kind)),
+            None => self
+                .find_executable(
+                    RequestedVersion::Any.into(),
+                    VenvDetection::Disabled,
+                    &search,
+                )
+                .ok()
+                .map(|path| (path, cli::default_reason(&search))),
+        };
+        let discovered = self.discovered(&search);
+        let listed = if all {
+            cli::listed_executables(
+                &environments,
+                discovered.by_source.clone(),
+                &discovered.windows,
+            )
+        } else {
+            cli::listed_executables(
+                &environments[..environments.len().min(1)],
+                cli::preferred_executables(discovered.by_source.clone()),
+                &discovered.windows,
+            )
+        };
+        let default = default_executable.and_then(|(default_path, reason)| {
+            listed
+                .iter()
+                .position(|(_, path, _)| *path == default_path)
+                .map(|index| (index, reason))
+        });
+        Ok(cli::list_value(&listed, default))
+    }
+
+    /// What `py --resolve` prints for the preferred versions (e.g. `3.12`), or
+    /// any version if there are none.
+    fn resolve(&mut self, env: &dyn EnvProvider, preferences: &str) -> crate::Result<Value> {
+        let preferences = if preferences.is_empty() {
+            RequestedVersion::Any.into()
+        } else {
+            VersionPreferenceList::from_str(preferences)?
+        };
+        let config = self.load_config(env)?;
+        let search = Search::new(env, &config);
+        let venv_detection = cli::default_venv_detection(env, &config);
+        let executable = self.find_executable(preferences, venv_detection, &search)?;
+        let environments = search.all_environments(venv_detection);
+        let discovered = self.discovered(&search);
+        let listed = cli::listed_executables(
+            &environments,
+            discovered.by_source.clone(),
+            &discovered.windows,
+        );
+        Ok(cli::resolution_value(&executable, &environments, &listed))
+    }
+
+    /// Finds the executable `py` would run like [`cli::Action::from_main`]
+    /// does, except that the executables found by searching are reused.
+    fn find_executable(
+        &mut self,
+        preferences: VersionPreferenceList,
+        venv_detection: VenvDetection,
+        search: &Search,
+    ) -> crate::Result<PathBuf> {
+        let allow_prereleases = search.env.var_os("PYLAUNCH_ALLOW_PRERELEASE").is_some()
+            || search.config.allow_prereleases;
+        match cli::resolve_request(preferences, None, allow_prereleases, venv_detection, search)? {
+            Request::Executable(executable) => Ok(executable),
+            Request::Search {
+                preferences,
+                requires_python,
+            } => {
+                let found = crate::find_preferred_executable_in_hashmap(
+                    &preferences,
+                    &self.discovered(search).executables,
+                );
+                cli::found_in_search(found, &preferences, requires_python.as_ref())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("" ; "empty")]
+    #[test_case("{" ; "invalid JSON")]
+    #[test_case("[1, 2]" ; "not an object")]
+    #[test_case(r#"{"id": 1}"# ; "missing method")]
+    #[test_case(r#"{"id": 1, "method": 1}"# ; "method not a string")]
+    #[test_case(r#"{"id": 1, "method": "run"}"# ; "unknown method")]
+    #[test_case(r#"{"id": 1, "method": "list", "all": "yes"}"# ; "all not a boolean")]
+    #[test_case(r#"{"id": 1, "method": "resolve", "version": 3}"# ; "version not a string")]
+    #[test_case(r#"{"id": 1, "method": "resolve", "version": "three"}"# ; "invalid version")]
+    #[test_case(r#"{"id": 1, "method": "resolve", "cwd": 42}"# ; "cwd not a string")]
+    fn respond_error_tests(request: &str) {
+        let response = serde_json::from_str::<Value>(&Server::default().respond(request)).unwrap();
+        assert!(response["error"]["message"].is_string());
+        assert!(response.get("result").is_none());
+    }
+
+    #[test_case(r#"{"id": 7, "method": "invalidate"}"# => json!(7) ; "number")]
+    #[test_case(r#"{"id": "a", "method": "invalidate"}"# => json!("a") ; "string")]
+    #[test_case(r#"{"method": "invalidate"}"# => Value::Null ; "missing")]
+    #[test_case(r#"{"id": [1], "method": "run"}"# => json!([1]) ; "error response")]
+    fn respond_id_tests(request: &str) -> Value {
+        let response = serde_json::from_str::<Value>(&Server::default().respond(request)).unwrap();
+        response["id"].clone()
+    }
+
+    /// `discovered` as what a request without a `cwd` finds.
+    fn discovered_for(
+        server: &Server,
+        discovered: Discovered,
+    ) -> HashMap<DiscoveryKey, Discovered> {
+        let env = server.request_env(None).unwrap();
+        let config = server.load_config(&env).unwrap();
+        HashMap::from([(Discovered::key(&Search::new(&env, &config)), discovered)])
+    }
+
+    #[test]
+    fn invalidate() {
+        let mut server = Server {
+            dir: None,
+            config_files: None,
+            discovered: HashMap::from([(DiscoveryKey::default(), Discovered::default())]),
+        };
+        assert_eq!(
+            server.respond(r#"{"id": 1, "method": "invalidate"}"#),
+            r#"{"id":1,"result":null}"#
+        );
+        assert!(server.discovered.is_empty());
+    }
+
+    #[test]
+    fn resolve_uses_discovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = dir.path().join("python42.0");
+        std::fs::write(&python, "").unwrap();
+        let mut server = Server {
+            dir: None,
+            config_files: Some(ConfigFiles::None),
+            discovered: HashMap::new(),
+        };
+        server.discovered = discovered_for(
+            &server,
+            Discovered {
+                by_source: vec![("path".to_string(), ExactVersion::new(42, 0), python.clone())],
+                executables: HashMap::from([(ExactVersion::new(42, 0), python.clone())]),
+                windows: HashMap::new(),
+            },
+        );
+        let response = serde_json::from_str::<Value>(
+            &server.respond(r#"{"id": 1, "method": "resolve", "version": "42.0"}"#),
+        )
+        .unwrap();
+        assert_eq!(
+            response["result"],
+            json!({
+                "path": python.display().to_string(),
+                "version": "42.0",
+                "source": "path",
+                "venv": null,
+            })
+        );
+    }
+
+    #[test]
+    fn resolve_in_cwd() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        let project = dir.join("project");
+        let venv = project.join(".venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::write(venv.join("bin").join("python"), "").unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "version = 42.0.1\n").unwrap();
+        let cwd = env::current_dir().unwrap();
+        let mut server = Server {
+            dir: Some(dir.clone()),
+            config_files: Some(ConfigFiles::None),
+            discovered: HashMap::new(),
+        };
+        server.discovered = discovered_for(&server, Discovered::default());
+
+        // A relative `cwd` is relative to the directory of the server.
+        let response = serde_json::from_str::<Value>(
+            &server.respond(r#"{"id": 1, "method": "resolve", "cwd": "project"}"#),
+        )
+        .unwrap();
+        assert_eq!(
+            response["result"]["venv"],
+            json!(venv.display().to_string())
+        );
+        assert_eq!(env::current_dir().unwrap(), cwd);
+
+        let response = serde_json::from_str::<Value>(
+            &server.respond(r#"{"id": 2, "method": "resolve", "cwd": "missing"}"#),
+        )
+        .unwrap();
+        assert!(response["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("missing"));
+    }
+
+    #[test]
+    fn resolve_per_search_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        let config = dir.join("config.toml");
+        std::fs::write(&config, "").unwrap();
+        let mut pythons = Vec::new();
+        for project in ["a", "b"] {
+            let bin = dir.join(project).join("bin");
+            std::fs::create_dir_all(&bin).unwrap();
+            std::fs::write(bin.join("python42.0"), "").unwrap();
+            std::fs::write(
+                dir.join(project).join(".py.toml"),
+                "search-directories = ['bin']\n",
+            )
+            .unwrap();
+            pythons.push(bin.join("python42.0"));
+        }
+        let mut server = Server::new()
+            .dir(&dir)
+            .config_files(ConfigFiles::File(config));
+
+        // Each project searches its own `search-directories`.
+        for (project, python) in ["a", "b"].iter().zip(&pythons) {
+            let response = serde_json::from_str::<Value>(&server.respond(&format!(
+                r#"{{"id": 1, "method": "resolve", "version": "42.0", "cwd": "{project}"}}"#
+            )))
+            .unwrap();
+            assert_eq!(
+                response["result"]["path"],
+                json!(python.display().to_string())
+            );
+        }
+        assert_eq!(server.discovered.len(), 2);
+    }
+
+    #[test]
+    fn serve() {
+        let input = Cursor::new(
+            "{\"id\": 1, \"method\": \"invalidate\"}\n\n{\"id\": 2, \"method\": \"resolve\", \"version\": \"42.0\"}\n",
+        );
+        let mut output = Vec::new();
+        Server::default().serve(input, &mut output).unwrap();
+        let responses = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<Value>>();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0], json!({"id": 1, "result": null}));
+        assert_eq!(responses[1]["id"], json!(2));
+        assert!(responses[1]["error"]["message"].is_string());
+    }
+}
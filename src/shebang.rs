@@ -0,0 +1,345 @@
+//! Parsing of shebang lines
+//!
+//! Like the [Windows launcher], the shebang line of a script is used to
+//! request a version of Python when none is specified on the command line.
+//! Only the "virtual commands" of the Windows launcher are recognized, i.e.
+//! `python`, `/usr/bin/python`, `/usr/local/bin/python`, and
+//! `/usr/bin/env python`, with an optional version in the executable name,
+//! e.g.:
+//!
+//! ```text
+//! #!/usr/bin/env python3.9 -u
+//! #!/usr/bin/env -S python3.11 -X dev
+//! #!python3.11
+//! ```
+//!
+//! Any arguments to the interpreter (e.g. `-u` above) are passed on to it,
+//! split according to [`ArgumentSplitting`]. Scripts written on Windows are
+//! also understood, i.e. a leading byte order mark and `\r\n` line endings.
+//!
+//! [Windows launcher]: https://docs.python.org/3/using/windows.html#shebang-lines
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use crate::RequestedVersion;
+
+/// The `env` commands which are recognized as running a Python executable
+/// found on `PATH`.
+const ENV_COMMANDS: [&str; 2] = ["/usr/bin/env", "/bin/env"];
+
+/// The directories which Python executables are recognized in.
+const PYTHON_DIRECTORIES: [&str; 2] = ["/usr/bin/", "/usr/local/bin/"];
+
+/// How the arguments to the interpreter in a shebang line are passed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ArgumentSplitting {
+    /// Split the arguments on whitespace like `env -S` does, e.g. `-I -W error`
+    /// is three arguments.
+    Split,
+    /// Pass the arguments as a single argument like the kernel does, e.g.
+    /// `-I -W error` is one argument.
+    Single,
+}
+
+impl Default for ArgumentSplitting {
+    fn default() -> Self {
+        Self::Split
+    }
+}
+
+/// What the command of a shebang line runs.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ShebangCommand {
+    /// The interpreter selected for a requested version.
+    Version(RequestedVersion),
+    /// A specific interpreter.
+    Executable(PathBuf),
+}
+
+/// A shebang line which runs Python.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Shebang {
+    /// What the command runs, e.g. the version of Python requested by the
+    /// executable name.
+    pub command: ShebangCommand,
+    /// Everything after the executable, i.e. the arguments to the interpreter.
+    pub arguments: String,
+}
+
+/// Splits off the first word of the text, returning it and the rest of the
+/// text.
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.split_at(text.find(char::is_whitespace).unwrap_or(text.len())))
+}
+
+impl Shebang {
+    /// Parses the command of a shebang line (i.e. everything after `#!`).
+    ///
+    /// The command must be a `python` executable (with an optional version,
+    /// e.g. `python3.11`), either on its own, in `/usr/bin` or
+    /// `/usr/local/bin`, or run via `/usr/bin/env` (or `/bin/env`). Any options
+    /// to `env` (e.g. `-S`) and environment variable assignments are skipped.
+    /// `None` is returned if the command is not recognized or the version is
+    /// not understood.
+    ///
+    /// Custom commands may also be specified by name (e.g. `mypython` for
+    /// `#!mypython` or `#!/usr/bin/env mypython`), which take precedence over
+    /// `python` executables.
+    pub fn from_command(
+        command: &str,
+        custom_commands: &[(String, ShebangCommand)],
+    ) -> Option<Self> {
+        // Whitespace between `#!` and the path is allowed.
+        let (mut program, mut rest) = split_word(command)?;
+        if ENV_COMMANDS.contains(&program) {
+            loop {
+                let (word, after_word) = split_word(rest)?;
+                rest = after_word;
+                if !word.starts_with('-') && !word.contains('=') {
+                    program = word;
+                    break;
+                }
+            }
+        }
+        let arguments = rest.trim().to_string();
+        if let Some((_, custom_command)) = custom_commands.iter().find(|(name, _)| name == program)
+        {
+            log::debug!("Found custom shebang command: {program}");
+            return Some(Self {
+                command: custom_command.clone(),
+                arguments,
+            });
+        }
+
+        let executable = PYTHON_DIRECTORIES
+            .iter()
+            .find_map(|directory| program.strip_prefix(directory))
+            .unwrap_or(program);
+        let version = executable.strip_prefix("python")?;
+        log::debug!("Found shebang: {program}");
+        log::debug!("Found version: {version}");
+        Some(Self {
+            command: ShebangCommand::Version(RequestedVersion::from_str(version).ok()?),
+            arguments,
+        })
+    }
+
+    /// Returns the arguments to pass to the interpreter.
+    pub fn arguments(&self, splitting: ArgumentSplitting) -> Vec<String> {
+        match splitting {
+            ArgumentSplitting::Split => self
+                .arguments
+                .split_whitespace()
+                .map(ToString::to_string)
+                .collect(),
+            ArgumentSplitting::Single if self.arguments.is_empty() => Vec::new(),
+            ArgumentSplitting::Single => vec![self.arguments.clone()],
+        }
+    }
+}
+
+/// The most bytes read from a file when looking for a shebang line.
+///
+/// This keeps a file without any newlines (e.g. a binary file) from being
+/// read in its entirety.
+const MAX_SHEBANG_LENGTH: u64 = 4096;
+
+/// The UTF-8 byte order mark which editors on Windows may put at the start of
+/// a file.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Reads the shebang line at the start of a file (see
+/// [`Shebang::from_command`]).
+///
+/// A leading UTF-8 byte order mark is skipped, and the line may end with
+/// `\n`, `\r\n`, or `\r`. `None` is returned if the file doesn't start with
+/// `#!`, the line is not UTF-8, or the line doesn't end within
+/// [`MAX_SHEBANG_LENGTH`] bytes (as its arguments would be cut short).
+pub fn parse(
+    reader: &mut impl Read,
+    custom_commands: &[(String, ShebangCommand)],
+) -> Option<Shebang> {
+    log::info!("Looking for a Python-related shebang");
+    let mut first_line = Vec::new();
+    if BufReader::new(reader.take(MAX_SHEBANG_LENGTH))
+        .read_until(b'\n', &mut first_line)
+        .is_err()
+    {
+        log::debug!("Can't read first line of the file");
+        return None;
+    }
+    if first_line.len() as u64 == MAX_SHEBANG_LENGTH
+        && !first_line
+            .iter()
+            .any(|&byte| byte == b'\n' || byte == b'\r')
+    {
+        log::debug!("The first line of the file is longer than {MAX_SHEBANG_LENGTH} bytes");
+        return None;
+    }
+
+    let line = first_line.strip_prefix(UTF8_BOM).unwrap_or(&first_line);
+    let command = match line.strip_prefix(b"#!") {
+        Some(command) => command,
+        None => {
+            // Doesn't start w/ `#!` in ASCII/UTF-8.
+            log::debug!("No '#!' at the start of the first line of the file");
+            return None;
+        }
+    };
+    let command = match std::str::from_utf8(command) {
+        Ok(command) => command,
+        Err(_) => {
+            log::debug!("The first line of the file is not UTF-8");
+            return None;
+        }
+    };
+    // Stop at a `\r` so `\r\n` and lone `\r` line endings don't leak into
+    // the command.
+    let command = command.split(&['\r', '\n'][..]).next().unwrap_or_default();
+
+    Shebang::from_command(command, custom_commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn requested_version(shebang: Option<Shebang>) -> Option<RequestedVersion> {
+        match shebang?.command {
+            ShebangCommand::Version(requested_version) => Some(requested_version),
+            ShebangCommand::Executable(_) => None,
+        }
+    }
+
+    #[test_case("/usr/bin/python" => None ; "missing shebang comment")]
+    #[test_case("# /usr/bin/python" => None ; "missing exclamation point")]
+    #[test_case("! /usr/bin/python" => None ; "missing octothorpe")]
+    #[test_case("#! /bin/sh" => None ; "non-Python shebang")]
+    #[test_case("#! /usr/bin/env python" => Some(RequestedVersion::Any) ; "typical 'env python'")]
+    #[test_case("#! /usr/bin/python" => Some(RequestedVersion::Any) ; "typical 'python'")]
+    #[test_case("#! /usr/local/bin/python" => Some(RequestedVersion::Any) ; "/usr/local")]
+    #[test_case("#! python" => Some(RequestedVersion::Any) ; "bare 'python'")]
+    #[test_case("#! /usr/bin/env python3.7" => Some(RequestedVersion::Exact(3, 7)) ; "typical 'env python' with minor version")]
+    #[test_case("#! /usr/bin/python3.7" => Some(RequestedVersion::Exact(3, 7)) ; "typical 'python' with minor version")]
+    #[test_case("#! python3.7" => Some(RequestedVersion::Exact(3, 7)) ; "bare 'python' with minor version")]
+    #[test_case("#!/usr/bin/python" => Some(RequestedVersion::Any) ; "no space between shebang and path")]
+    #[test_case("#!python3\r\nprint()" => Some(RequestedVersion::MajorOnly(3)) ; "Windows line ending")]
+    #[test_case("#!python3\rprint()" => Some(RequestedVersion::MajorOnly(3)) ; "classic Mac line ending")]
+    #[test_case("\u{feff}#!/usr/bin/env python3.11\r\n" => Some(RequestedVersion::Exact(3, 11)) ; "byte order mark")]
+    #[test_case("\u{feff}\u{feff}#!/usr/bin/env python3.11" => None ; "repeated byte order mark")]
+    #[test_case("#!\t/usr/bin/env\t python3.11 \t" => Some(RequestedVersion::Exact(3, 11)) ; "tabs and trailing whitespace")]
+    #[test_case("#!/usr/bin/env\u{a0}python3.11" => Some(RequestedVersion::Exact(3, 11)) ; "non-breaking space")]
+    #[test_case("#!" => None ; "empty shebang")]
+    #[test_case("" => None ; "empty file")]
+    fn parse_tests(shebang: &str) -> Option<RequestedVersion> {
+        requested_version(parse(&mut shebang.as_bytes(), &[]))
+    }
+
+    #[test_case(&[0x23, 0x21, 0xc0, 0xaf] => None ; "invalid UTF-8")]
+    #[test_case(&[0xef, 0xbb, 0x23, 0x21, 0x70, 0x79] => None ; "truncated byte order mark")]
+    fn parse_invalid_bytes_tests(mut shebang: &[u8]) -> Option<RequestedVersion> {
+        requested_version(parse(&mut shebang, &[]))
+    }
+
+    #[test_case("/usr/bin/python3.9 -u" => Some(RequestedVersion::Exact(3, 9)) ; "interpreter argument")]
+    #[test_case("/usr/bin/env python -X dev" => Some(RequestedVersion::Any) ; "interpreter arguments without version")]
+    #[test_case("/usr/bin/env -S python3.9 -X dev" => Some(RequestedVersion::Exact(3, 9)) ; "env -S")]
+    #[test_case("/usr/bin/env python3.13t" => Some(RequestedVersion::FreeThreaded(3, 13)) ; "free-threaded")]
+    #[test_case("/usr/bin/pythonista" => None ; "not a version")]
+    #[test_case("/usr/bin/env bash" => None ; "non-Python command")]
+    #[test_case("/usr/bin/env python3" => Some(RequestedVersion::MajorOnly(3)) ; "env with major version")]
+    #[test_case("/usr/bin/env   python3.11" => Some(RequestedVersion::Exact(3, 11)) ; "env with extra whitespace")]
+    #[test_case("/usr/bin/env\tpython3.11" => Some(RequestedVersion::Exact(3, 11)) ; "env with tab")]
+    #[test_case("/bin/env python3.11" => Some(RequestedVersion::Exact(3, 11)) ; "env in bin")]
+    #[test_case("/usr/bin/env -S PYTHONUTF8=1 python3.11 -X dev" => Some(RequestedVersion::Exact(3, 11)) ; "env -S with variable assignment")]
+    #[test_case("/usr/bin/env -i python3" => Some(RequestedVersion::MajorOnly(3)) ; "env option")]
+    #[test_case("/usr/bin/env" => None ; "env without command")]
+    #[test_case("python3.11" => Some(RequestedVersion::Exact(3, 11)) ; "bare with minor version")]
+    #[test_case("/usr/local/bin/python3" => Some(RequestedVersion::MajorOnly(3)) ; "usr local with major version")]
+    #[test_case("/opt/python/bin/python3" => None ; "unrecognized directory")]
+    fn from_command_requested_version_tests(command: &str) -> Option<RequestedVersion> {
+        requested_version(Shebang::from_command(command, &[]))
+    }
+
+    #[test_case("/usr/bin/env python3", ArgumentSplitting::Split => Vec::<String>::new() ; "no arguments")]
+    #[test_case("/usr/bin/env python3", ArgumentSplitting::Single => Vec::<String>::new() ; "no arguments as a single argument")]
+    #[test_case("/usr/bin/env python3 -I -W error", ArgumentSplitting::Split => vec!["-I", "-W", "error"] ; "split")]
+    #[test_case("/usr/bin/python3  -I -W error \r", ArgumentSplitting::Single => vec!["-I -W error"] ; "single")]
+    #[test_case("/usr/bin/env -S PYTHONUTF8=1 python3 -X dev", ArgumentSplitting::Split => vec!["-X", "dev"] ; "env -S")]
+    fn arguments_tests(command: &str, splitting: ArgumentSplitting) -> Vec<String> {
+        Shebang::from_command(command, &[])
+            .unwrap()
+            .arguments(splitting)
+    }
+
+    #[test]
+    fn parse_long_line() {
+        let mut contents = b"#!/usr/bin/env python3.11 ".to_vec();
+        contents.resize(MAX_SHEBANG_LENGTH as usize * 2, b'x');
+        // Only part of the line can be read, which would cut the arguments
+        // short.
+        assert_eq!(parse(&mut contents.as_slice(), &[]), None);
+
+        // A line which ends right at the limit is read in its entirety.
+        contents.truncate(MAX_SHEBANG_LENGTH as usize - 1);
+        contents.push(b'\n');
+        contents.extend(b"print('hi')\n");
+        let shebang = parse(&mut contents.as_slice(), &[]).unwrap();
+        assert_eq!(
+            shebang.command,
+            ShebangCommand::Version(RequestedVersion::Exact(3, 11))
+        );
+        assert_eq!(shebang.arguments.len(), MAX_SHEBANG_LENGTH as usize - 27);
+    }
+
+    #[test]
+    fn from_command_custom_commands() {
+        let custom_commands = [
+            (
+                "mypython".to_string(),
+                ShebangCommand::Version(RequestedVersion::Exact(3, 11)),
+            ),
+            (
+                "python3".to_string(),
+                ShebangCommand::Executable(PathBuf::from("/opt/python/bin/python3")),
+            ),
+        ];
+
+        assert_eq!(
+            Shebang::from_command("mypython -u", &custom_commands),
+            Some(Shebang {
+                command: ShebangCommand::Version(RequestedVersion::Exact(3, 11)),
+                arguments: "-u".to_string(),
+            })
+        );
+        assert_eq!(
+            Shebang::from_command("/usr/bin/env mypython", &custom_commands)
+                .map(|shebang| shebang.command),
+            Some(ShebangCommand::Version(RequestedVersion::Exact(3, 11)))
+        );
+        // Custom commands take precedence.
+        assert_eq!(
+            Shebang::from_command("/usr/bin/env python3", &custom_commands)
+                .map(|shebang| shebang.command),
+            Some(ShebangCommand::Executable(PathBuf::from(
+                "/opt/python/bin/python3"
+            )))
+        );
+        assert_eq!(
+            Shebang::from_command("/usr/bin/env python3.12", &custom_commands)
+                .map(|shebang| shebang.command),
+            Some(ShebangCommand::Version(RequestedVersion::Exact(3, 12)))
+        );
+        assert_eq!(Shebang::from_command("yourpython", &custom_commands), None);
+    }
+}
@@ -0,0 +1,405 @@
+//! Updating the Python Launcher itself
+//!
+//! Every [release] of the launcher publishes a tarball of it for each
+//! platform, along with the SHA256 checksum of the tarball. When the latest
+//! release (or the [release](release_url) chosen by `PYLAUNCH_UPDATE_URL`) is
+//! newer than the running launcher, its tarball is downloaded next to the
+//! running executable, verified, unpacked, and the new `py` is renamed over
+//! the running one so that it is replaced atomically.
+//!
+//! This is meant for the standalone binary; an install by a package manager
+//! should be updated by that package manager instead.
+//!
+//! [release]: https://github.com/brettcannon/python-launcher/releases
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{install, Error, InstallError, IoError};
+
+/// The GitHub API URL of the latest release of the Python Launcher.
+pub const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/brettcannon/python-launcher/releases/latest";
+
+/// The name of the directory next to the executable which an update is
+/// downloaded and unpacked into.
+const UPDATE_DIR_NAME: &str = ".py-update";
+
+/// The version of the running launcher.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The target triple of the launcher's release tarballs which can run on this
+/// platform, if there are any.
+pub fn target_triple() -> Option<&'static str> {
+    match (env::consts::ARCH, env::consts::OS) {
+        ("x86_64", "linux") => Some("x86_64-unknown-linux-gnu"),
+        ("aarch64", "linux") => Some("aarch64-unknown-linux-gnu"),
+        ("riscv64", "linux") => Some("riscv64gc-unknown-linux-gnu"),
+        ("x86_64", "macos") => Some("x86_64-apple-darwin"),
+        ("aarch64", "macos") => Some("aarch64-apple-darwin"),
+        ("x86_64", "netbsd") => Some("x86_64-unknown-netbsd"),
+        _ => None,
+    }
+}
+
+/// The URL of the release to update to, as returned by the GitHub API.
+///
+/// This is `$PYLAUNCH_UPDATE_URL` if it is set (e.g. to a `file://` URL of an
+/// offline copy), else [`LATEST_RELEASE_URL`].
+pub fn release_url() -> String {
+    env::var("PYLAUNCH_UPDATE_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| LATEST_RELEASE_URL.to_string())
+}
+
+/// Parses a `X.Y.Z` version, optionally preceded by a `v` like the tags of
+/// releases.
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let mut parts = version.split('.').map(str::parse::<u64>);
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), Some(Ok(micro)), None) => Some((major, minor, micro)),
+        _ => None,
+    }
+}
+
+/// The version of a release, from its tag (e.g. `v1.0.0` is `1.0.0`).
+pub fn release_version(release: &str) -> Option<String> {
+    let release = serde_json::from_str::<serde_json::Value>(release).ok()?;
+    let tag = release["tag_name"].as_str()?;
+    parse_version(tag)?;
+    Some(tag.strip_prefix('v').unwrap_or(tag).to_string())
+}
+
+/// The URLs of the tarball of `version` for `target` among the assets of a
+/// release, along with the URL of its checksum if one is published.
+pub fn find_tarball(
+    release: &str,
+    version: &str,
+    target: &str,
+) -> Option<(String, Option<String>)> {
+    let file_name = format!("python_launcher-{version}-{target}.tar.xz");
+    let urls = install::asset_urls(release);
+    let url = urls
+        .iter()
+        .find(|url| url.rsplit('/').next() == Some(file_name.as_str()))?;
+    let checksum_url = urls
+        .iter()
+        .find(|checksum_url| {
+            checksum_url.strip_suffix(".sha256") == Some(url.as_str())
+                || checksum_url.ends_with("/SHA256SUMS")
+        })
+        .map(|checksum_url| checksum_url.to_string());
+    Some((url.to_string(), checksum_url))
+}
+
+/// Updates the running launcher to the latest release, returning the version
+/// updated to or `None` if the launcher is already up to date (see
+/// [`update_executable`]).
+///
+/// # Errors
+///
+/// If there are no builds of the launcher for this platform,
+/// [`InstallError::UnsupportedPlatform`] is returned.
+///
+/// If the running executable can't be located,
+/// [`InstallError::InstallFailed`] is returned.
+pub fn self_update() -> crate::Result<Option<String>> {
+    let target = target_triple().ok_or_else(|| {
+        Error::Install(InstallError::UnsupportedPlatform(format!(
+            "{}-{}",
+            env::consts::ARCH,
+            env::consts::OS
+        )))
+    })?;
+    let executable = env::current_exe()
+        .and_then(fs::canonicalize)
+        .map_err(|error| {
+            Error::Install(InstallError::InstallFailed(
+                PathBuf::from("py"),
+                error.into(),
+            ))
+        })?;
+    let url = release_url();
+    log::info!("Fetching the release from {url}");
+    let release = install::fetch(&url)?;
+    update_executable(&release, &executable, VERSION, target)
+}
+
+/// Replaces `executable` with the launcher for `target` from `release` if the
+/// release is newer than `current_version`, returning the version of the
+/// release if it was.
+///
+/// The tarball is downloaded and unpacked into a directory next to
+/// `executable`, so that the new executable can be renamed over it.
+///
+/// # Errors
+///
+/// If the release has no version or no tarball for `target`,
+/// [`InstallError::NoUpdateFound`] is returned.
+///
+/// If downloading the tarball fails, [`InstallError::DownloadFailed`] is
+/// returned.
+///
+/// If no checksum is published for the tarball, [`InstallError::NoChecksum`] is
+/// returned, and if the tarball doesn't match its checksum,
+/// [`InstallError::ChecksumMismatch`] is returned.
+///
+/// If unpacking the tarball or replacing `executable` fails,
+/// [`InstallError::InstallFailed`] is returned.
+pub fn update_executable(
+    release: &str,
+    executable: &Path,
+    current_version: &str,
+    target: &str,
+) -> crate::Result<Option<String>> {
+    let no_update = || Error::Install(InstallError::NoUpdateFound(target.to_string()));
+    let version = release_version(release).ok_or_else(no_update)?;
+    if parse_version(&version) <= parse_version(current_version) {
+        log::info!("Version {version} of the release is not newer than {current_version}");
+        return Ok(None);
+    }
+    let (url, checksum_url) = find_tarball(release, &version, target).ok_or_else(no_update)?;
+    let checksum_url =
+        checksum_url.ok_or_else(|| Error::Install(InstallError::NoChecksum(url.clone())))?;
+
+    let update_failed = |error: IoError| {
+        Error::Install(InstallError::InstallFailed(executable.to_path_buf(), error))
+    };
+    let update_dir = executable
+        .parent()
+        .ok_or_else(|| update_failed(IoError::other("no parent directory")))?
+        .join(UPDATE_DIR_NAME);
+    // Clear out any update which was interrupted.
+    if update_dir.exists() {
+        fs::remove_dir_all(&update_dir).map_err(|error| update_failed(error.into()))?;
+    }
+    fs::create_dir_all(update_dir.join("unpacked")).map_err(|error| update_failed(error.into()))?;
+    let result = replace_executable(&url, &checksum_url, &update_dir, executable);
+    if let Err(error) = fs::remove_dir_all(&update_dir) {
+        log::warn!("Failed to remove {}: {error}", update_dir.display());
+    }
+    result.map(|_| Some(version))
+}
+
+fn replace_executable(
+    url: &str,
+    checksum_url: &str,
+    update_dir: &Path,
+    executable: &Path,
+) -> crate::Result<()> {
+    let file_name = url.rsplit('/').next().unwrap_or_default();
+    let tarball = update_dir.join(file_name);
+    log::info!("Downloading {url}");
+    install::download_file(url, &tarball)?;
+
+    let expected = install::expected_sha256(&install::fetch(checksum_url)?, file_name)
+        .ok_or_else(|| Error::Install(InstallError::NoChecksum(url.to_string())))?;
+    let actual = install::file_sha256(&tarball).map_err(|error| {
+        Error::Install(InstallError::DownloadFailed(url.to_string(), error.into()))
+    })?;
+    if actual != expected {
+        return Err(Error::Install(InstallError::ChecksumMismatch(
+            url.to_string(),
+            expected,
+            actual,
+        )));
+    }
+
+    let unpack_dir = update_dir.join("unpacked");
+    install::unpack(&tarball, &unpack_dir, executable)?;
+    let update_failed = |error: IoError| {
+        Error::Install(InstallError::InstallFailed(executable.to_path_buf(), error))
+    };
+    let new_executable = unpack_dir.join("bin").join("py");
+    if !new_executable.is_file() {
+        return Err(update_failed(IoError::other(format!(
+            "{file_name} does not contain bin/py"
+        ))));
+    }
+    let permissions = fs::metadata(executable)
+        .map_err(|error| update_failed(error.into()))?
+        .permissions();
+    fs::set_permissions(&new_executable, permissions)
+        .map_err(|error| update_failed(error.into()))?;
+    // Renaming within the same file system atomically replaces the executable,
+    // even while it is running.
+    log::info!("Replacing {}", executable.display());
+    fs::rename(&new_executable, executable).map_err(|error| update_failed(error.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("1.0.0" => Some((1, 0, 0)) ; "version")]
+    #[test_case("v1.2.3" => Some((1, 2, 3)) ; "tag")]
+    #[test_case("1.2" => None ; "missing micro")]
+    #[test_case("1.2.3.4" => None ; "too many parts")]
+    #[test_case("1.2.3rc1" => None ; "pre-release")]
+    #[test_case("" => None ; "empty")]
+    fn parse_version_tests(version: &str) -> Option<(u64, u64, u64)> {
+        parse_version(version)
+    }
+
+    #[test_case(r#"{"tag_name": "v1.2.3"}"# => Some("1.2.3".to_string()) ; "tag")]
+    #[test_case(r#"{"tag_name": "nightly"}"# => None ; "not a version")]
+    #[test_case(r#"{"tag_name": "v1.2.3""# => None ; "invalid JSON")]
+    #[test_case(r#"{"name": "v1.2.3"}"# => None ; "no tag")]
+    fn release_version_tests(release: &str) -> Option<String> {
+        release_version(release)
+    }
+
+    fn release(url_base: &str, checksum: bool) -> String {
+        let mut assets = vec![
+            format!(
+                r#"{{"browser_download_url": "{url_base}/python_launcher-2.0.0-x86_64-unknown-linux-gnu.tar.xz"}}"#
+            ),
+            format!(
+                r#"{{"browser_download_url": "{url_base}/python_launcher-2.0.0-aarch64-apple-darwin.tar.xz"}}"#
+            ),
+        ];
+        if checksum {
+            assets.push(format!(r#"{{"browser_download_url": "{url_base}/python_launcher-2.0.0-x86_64-unknown-linux-gnu.tar.xz.sha256"}}"#));
+        }
+        format!(
+            r#"{{"tag_name": "v2.0.0", "assets": [{}]}}"#,
+            assets.join(", ")
+        )
+    }
+
+    #[test]
+    fn find_tarball_test() {
+        let release = release("https://example.com/v2.0.0", true);
+        let url =
+            "https://example.com/v2.0.0/python_launcher-2.0.0-x86_64-unknown-linux-gnu.tar.xz";
+        assert_eq!(
+            find_tarball(&release, "2.0.0", "x86_64-unknown-linux-gnu"),
+            Some((url.to_string(), Some(format!("{url}.sha256"))))
+        );
+        assert_eq!(
+            find_tarball(&release, "2.0.0", "aarch64-apple-darwin"),
+            Some((
+                "https://example.com/v2.0.0/python_launcher-2.0.0-aarch64-apple-darwin.tar.xz"
+                    .to_string(),
+                None
+            ))
+        );
+        assert_eq!(
+            find_tarball(&release, "2.0.0", "x86_64-unknown-netbsd"),
+            None
+        );
+        assert_eq!(
+            find_tarball(&release, "1.0.0", "x86_64-unknown-linux-gnu"),
+            None
+        );
+    }
+
+    /// Creates a release tarball of a fake launcher and its checksum in `dir`.
+    fn tarball(dir: &Path, checksum: Option<&str>) {
+        let contents = dir.join("python_launcher-2.0.0").join("bin");
+        fs::create_dir_all(&contents).unwrap();
+        fs::write(contents.join("py"), "new").unwrap();
+        let file_name = "python_launcher-2.0.0-x86_64-unknown-linux-gnu.tar.xz";
+        let status = Command::new("tar")
+            .arg("-cJf")
+            .arg(dir.join(file_name))
+            .arg("-C")
+            .arg(dir)
+            .arg("python_launcher-2.0.0")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let checksum = checksum.map_or_else(
+            || install::file_sha256(&dir.join(file_name)).unwrap(),
+            str::to_string,
+        );
+        fs::write(
+            dir.join(format!("{file_name}.sha256")),
+            format!("{checksum}  {file_name}\n"),
+        )
+        .unwrap();
+    }
+
+    fn fake_executable(dir: &Path) -> PathBuf {
+        let bin_dir = dir.join("bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        let executable = bin_dir.join("py");
+        fs::write(&executable, "old").unwrap();
+        executable
+    }
+
+    #[test]
+    fn update_executable_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let release_dir = dir.path().join("release");
+        fs::create_dir(&release_dir).unwrap();
+        tarball(&release_dir, None);
+        let release = release(&format!("file://{}", release_dir.display()), true);
+        let executable = fake_executable(dir.path());
+
+        assert_eq!(
+            update_executable(&release, &executable, "1.0.0", "x86_64-unknown-linux-gnu"),
+            Ok(Some("2.0.0".to_string()))
+        );
+        assert_eq!(fs::read_to_string(&executable).unwrap(), "new");
+        assert!(!executable.with_file_name(UPDATE_DIR_NAME).exists());
+    }
+
+    #[test]
+    fn update_executable_up_to_date_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let release = release("file:///nonexistent", true);
+        let executable = fake_executable(dir.path());
+
+        for version in ["2.0.0", "2.1.0"] {
+            assert_eq!(
+                update_executable(&release, &executable, version, "x86_64-unknown-linux-gnu"),
+                Ok(None)
+            );
+        }
+        assert_eq!(fs::read_to_string(&executable).unwrap(), "old");
+    }
+
+    #[test]
+    fn update_executable_no_tarball_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let release = release("file:///nonexistent", true);
+        let executable = fake_executable(dir.path());
+
+        assert_eq!(
+            update_executable(&release, &executable, "1.0.0", "x86_64-unknown-netbsd"),
+            Err(Error::Install(InstallError::NoUpdateFound(
+                "x86_64-unknown-netbsd".to_string()
+            )))
+        );
+        assert!(matches!(
+            update_executable(&release, &executable, "1.0.0", "aarch64-apple-darwin"),
+            Err(Error::Install(InstallError::NoChecksum(_)))
+        ));
+    }
+
+    #[test]
+    fn update_executable_checksum_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let release_dir = dir.path().join("release");
+        fs::create_dir(&release_dir).unwrap();
+        tarball(&release_dir, Some(&"0".repeat(64)));
+        let release = release(&format!("file://{}", release_dir.display()), true);
+        let executable = fake_executable(dir.path());
+
+        assert!(matches!(
+            update_executable(&release, &executable, "1.0.0", "x86_64-unknown-linux-gnu"),
+            Err(Error::Install(InstallError::ChecksumMismatch(..)))
+        ));
+        assert_eq!(fs::read_to_string(&executable).unwrap(), "old");
+        assert!(!executable.with_file_name(UPDATE_DIR_NAME).exists());
+    }
+}
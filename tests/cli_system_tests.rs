@@ -4,14 +4,15 @@ use std::env;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serial_test::serial;
 
 use python_launcher::cli;
 use python_launcher::cli::Action;
-use python_launcher::Error;
+use python_launcher::server::Server;
 use python_launcher::RequestedVersion;
+use python_launcher::{CliError, ConfigError, Error, InstallError, SearchError, VersionError};
 
 use common::{EnvState, EnvVarState};
 
@@ -36,13 +37,18 @@ fn from_main_help() {
 #[test]
 #[serial]
 fn from_main_help_missing_interpreter() {
-    let _state = EnvVarState::empty();
+    let mut state = EnvVarState::empty();
+    // An unset `PATH` would fall back to searching e.g. `/usr/bin`.
+    let empty_dir = tempfile::tempdir().unwrap();
+    state.change("PATH", Some(empty_dir.path().to_str().unwrap()));
     for flag in ["-h", "--help"].iter() {
         let launcher_path = "/path/to/py";
         let help = Action::from_main(&[launcher_path.to_string(), (*flag).to_string()]);
         assert_eq!(
             help,
-            Err(crate::Error::NoExecutableFound(RequestedVersion::Any))
+            Err(crate::Error::Search(crate::SearchError::NoExecutableFound(
+                RequestedVersion::Any
+            )))
         );
     }
 }
@@ -62,6 +68,351 @@ fn from_main_list() {
     }
 }
 
+#[test]
+#[serial]
+fn from_main_list_all() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = EnvState::new();
+    let pyenv_root = tempfile::tempdir().unwrap();
+    let pyenv_bin = pyenv_root
+        .path()
+        .join("versions")
+        .join("3.7.17")
+        .join("bin");
+    fs::create_dir_all(&pyenv_bin).unwrap();
+    let pyenv_python37 = common::touch_file(pyenv_bin.join("python3.7"));
+    env_state
+        .env_vars
+        .change("PYENV_ROOT", Some(pyenv_root.path().to_str().unwrap()));
+    let venv_bin = PathBuf::from(cli::DEFAULT_VENV_DIR).join("bin");
+    fs::create_dir_all(&venv_bin).unwrap();
+    let venv_python = common::touch_file(venv_bin.join("python"))
+        .canonicalize()
+        .unwrap();
+
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--list".to_string(),
+        "--all".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            let source_of = |path: &PathBuf| {
+                let row = output
+                    .lines()
+                    .find(|line| line.contains(path.to_str().unwrap()))
+                    .unwrap();
+                row.split('│').nth(4).unwrap().trim().to_string()
+            };
+            assert_eq!(source_of(&venv_python), "venv");
+            assert_eq!(source_of(&env_state.python27), "path");
+            assert_eq!(source_of(&env_state.python37), "path");
+            // Shadowed by Python 3.7 on PATH, but still listed.
+            assert_eq!(source_of(&pyenv_python37), "pyenv");
+        }
+        _ => panic!("'--list --all' did not return Action::List"),
+    }
+
+    // Without `--all`, only the environment used by default is listed and
+    // shadowed executables are left out.
+    match Action::from_main(&["/path/to/py".to_string(), "--list".to_string()]) {
+        Ok(Action::List(output)) => {
+            let venv_row = output
+                .lines()
+                .find(|line| line.contains(venv_python.to_str().unwrap()))
+                .unwrap();
+            assert!(venv_row.trim_end().ends_with("(default: venv)"));
+            assert_eq!(output.matches("(default: ").count(), 1);
+            assert!(!output.contains(pyenv_python37.to_str().unwrap()));
+        }
+        _ => panic!("'--list' did not return Action::List"),
+    }
+
+    // Otherwise the newest version is used by default, unless `PY_PYTHON`
+    // says otherwise.
+    let default_row = |output: &str| {
+        output
+            .lines()
+            .find(|line| line.contains("(default: "))
+            .map(|line| line.trim_end().to_string())
+    };
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--no-venv".to_string(),
+        "--list".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            let row = default_row(&output).unwrap();
+            assert!(row.contains(env_state.python37.to_str().unwrap()));
+            assert!(row.ends_with("(default: newest)"));
+            assert!(!output.contains(venv_python.to_str().unwrap()));
+        }
+        _ => panic!("'--no-venv --list' did not return Action::List"),
+    }
+    env_state.env_vars.change("PY_PYTHON", Some("2.7"));
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--no-venv".to_string(),
+        "--list".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            let row = default_row(&output).unwrap();
+            assert!(row.contains(env_state.python27.to_str().unwrap()));
+            assert!(row.ends_with("(default: PY_PYTHON)"));
+        }
+        _ => panic!("'--no-venv --list' did not return Action::List"),
+    }
+    env_state.env_vars.change("PY_PYTHON", None);
+
+    // The JSON output lists the same executables along with their source.
+    let json_object = |path: &PathBuf, source: &str| {
+        format!("\"path\":\"{}\",\"source\":\"{source}\"", path.display())
+    };
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--list".to_string(),
+        "--json".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            assert!(output.starts_with('['));
+            assert!(output.contains(&json_object(&env_state.python37, "path")));
+            assert!(output.contains(&format!(
+                "{},\"default\":true}}",
+                json_object(&venv_python, "venv")
+            )));
+            assert!(!output.contains(pyenv_python37.to_str().unwrap()));
+        }
+        _ => panic!("'--list --json' did not return Action::List"),
+    }
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--list".to_string(),
+        "--json".to_string(),
+        "--all".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            assert!(output.contains(&json_object(&venv_python, "venv")));
+            assert!(output.contains(&json_object(&pyenv_python37, "pyenv")));
+        }
+        _ => panic!("'--list --json --all' did not return Action::List"),
+    }
+
+    // Columns and sorting are configurable.
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--list".to_string(),
+        "--all".to_string(),
+        "--columns".to_string(),
+        "source,path".to_string(),
+        "--sort".to_string(),
+        "source".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            let sources = output
+                .lines()
+                .map(|line| line.split('│').next().unwrap().trim())
+                .collect::<Vec<&str>>();
+            let mut sorted_sources = sources.clone();
+            sorted_sources.sort_unstable();
+            assert_eq!(sources, sorted_sources);
+            assert_eq!(sources.last(), Some(&"venv"));
+            assert!(output.contains(pyenv_python37.to_str().unwrap()));
+        }
+        _ => panic!("'--list --columns --sort' did not return Action::List"),
+    }
+    assert_eq!(
+        Action::from_main(&[
+            "/path/to/py".to_string(),
+            "--list".to_string(),
+            "--sort".to_string(),
+            "size".to_string(),
+        ]),
+        Err(Error::Cli(CliError::UnknownListColumn("size".to_string())))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            "/path/to/py".to_string(),
+            "--list".to_string(),
+            "--columns".to_string(),
+        ]),
+        Err(Error::Cli(CliError::MissingArgument(
+            "--columns".to_string()
+        )))
+    );
+
+    // Scripts can read paths containing spaces from delimited formats.
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--list".to_string(),
+        "--all".to_string(),
+        "--format=tsv".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            // The test executables are empty files of an unknown architecture.
+            assert!(output.contains(&format!("\t\t\t{}\tvenv\n", venv_python.display())));
+            assert!(output.contains(&format!(
+                "3.7\tCPython\t\t{}\tpyenv\n",
+                pyenv_python37.display()
+            )));
+        }
+        _ => panic!("'--list --format=tsv' did not return Action::List"),
+    }
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--list".to_string(),
+        "--format".to_string(),
+        "nul".to_string(),
+        "--columns=path".to_string(),
+    ]) {
+        Ok(Action::List(output)) => {
+            assert!(output.ends_with('\0'));
+            assert!(!output.contains('\n'));
+            assert!(output.contains(&format!("{}\0", env_state.python37.display())));
+        }
+        _ => panic!("'--list --format nul' did not return Action::List"),
+    }
+    // Shell completions only need the versions which can be requested.
+    assert_eq!(
+        Action::from_main(&[
+            "/path/to/py".to_string(),
+            "--list".to_string(),
+            "--all".to_string(),
+            "--format=versions".to_string(),
+        ]),
+        Ok(Action::List("3.7\n3.6\n2.7\n".to_string()))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            "/path/to/py".to_string(),
+            "--list".to_string(),
+            "--format=csv".to_string(),
+        ]),
+        Err(Error::Cli(CliError::UnknownListFormat("csv".to_string())))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            "/path/to/py".to_string(),
+            "--list".to_string(),
+            "--json".to_string(),
+            "--json".to_string(),
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from("/path/to/py"),
+            "--list".to_string()
+        )))
+    );
+
+    // Like the Windows launcher, only activated environments are listed.
+    match Action::from_main(&["/path/to/py".to_string(), "-0p".to_string()]) {
+        Ok(Action::List(output)) => {
+            assert!(output.contains(&format!(
+                " -V:3.7 *         {}\n",
+                env_state.python37.display()
+            )));
+            assert!(output.contains(&format!(
+                " -V:2.7           {}\n",
+                env_state.python27.display()
+            )));
+            assert!(!output.contains(venv_python.to_str().unwrap()));
+        }
+        _ => panic!("'-0p' did not return Action::List"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_color() {
+    let mut env_state = EnvState::new();
+    let list_with = |color_args: &[&str]| {
+        let mut argv = vec!["/path/to/py".to_string()];
+        argv.extend(color_args.iter().map(|arg| arg.to_string()));
+        argv.push("--list".to_string());
+        Action::from_main(&argv)
+    };
+
+    // Tests aren't run with stdout as a terminal.
+    match list_with(&[]) {
+        Ok(Action::List(output)) => assert!(!output.contains('\u{1b}')),
+        _ => panic!("'--list' did not return Action::List"),
+    }
+    env_state.env_vars.change("NO_COLOR", Some("1"));
+    match list_with(&["--color", "always"]) {
+        Ok(Action::List(output)) => {
+            assert!(output.contains('\u{1b}'));
+            assert!(output.contains(env_state.python37.to_str().unwrap()));
+        }
+        _ => panic!("'--color always --list' did not return Action::List"),
+    }
+    match list_with(&["--pre", "--color=never"]) {
+        Ok(Action::List(output)) => assert!(!output.contains('\u{1b}')),
+        _ => panic!("'--color=never --list' did not return Action::List"),
+    }
+    env_state.env_vars.change("NO_COLOR", None);
+
+    assert_eq!(
+        list_with(&["--color=sometimes"]),
+        Err(Error::Cli(CliError::UnknownColorChoice(
+            "sometimes".to_string()
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&["/path/to/py".to_string(), "--color".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument("--color".to_string())))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_duplicates() {
+    let env_state = EnvState::new();
+
+    match Action::from_main(&["/path/to/py".to_string(), "--duplicates".to_string()]) {
+        Ok(Action::List(output)) => {
+            let rows = output
+                .lines()
+                .map(|line| {
+                    line.split('│')
+                        .map(|cell| cell.trim().to_string())
+                        .collect::<Vec<String>>()
+                })
+                .collect::<Vec<Vec<String>>>();
+            let status_of = |version: &str, path: &PathBuf| {
+                rows.iter()
+                    .find(|row| row[0] == version && row[1] == path.to_str().unwrap())
+                    .map(|row| row[3].clone())
+            };
+            assert_eq!(
+                status_of("3.7", &env_state.python37).as_deref(),
+                Some("used")
+            );
+            assert_eq!(
+                status_of("3.6", &env_state.python36).as_deref(),
+                Some("used")
+            );
+            // `python3.6` in the second directory on PATH is shadowed.
+            let python36_rows = rows
+                .iter()
+                .filter(|row| row[0] == "3.6")
+                .collect::<Vec<&Vec<String>>>();
+            assert_eq!(python36_rows.len(), 2);
+            assert_eq!(python36_rows[1][2], "path");
+            assert_eq!(python36_rows[1][3], "shadowed");
+        }
+        _ => panic!("'--duplicates' did not return Action::List"),
+    }
+
+    assert_eq!(
+        Action::from_main(&[
+            "/path/to/py".to_string(),
+            "--duplicates".to_string(),
+            "--all".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from("/path/to/py"),
+            "--duplicates".to_string()
+        )))
+    );
+}
+
 #[test]
 #[serial]
 fn from_main_by_flag() {
@@ -75,6 +426,7 @@ fn from_main_by_flag() {
             launcher_path,
             executable,
             args,
+            ..
         }) => {
             assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
             assert_eq!(executable, env_state.python37);
@@ -82,6 +434,18 @@ fn from_main_by_flag() {
         }
         Ok(Action::Help(_, _)) => panic!("Got back help"),
         Ok(Action::List(_)) => panic!("Got back a list of executables"),
+        Ok(Action::Activate(_)) => panic!("Got back an activation snippet"),
+        Ok(Action::Which(_)) => panic!("Got back the path to an executable"),
+        Ok(Action::Resolve(_)) => panic!("Got back a description of an executable"),
+        Ok(Action::DryRun(_)) => panic!("Got back the command to execute"),
+        Ok(Action::Install(_)) => panic!("Got back a version to install"),
+        Ok(Action::Uninstall(_)) => panic!("Got back a version to uninstall"),
+        Ok(Action::SelfUpdate) => panic!("Got back a self update"),
+        Ok(Action::ClearCache) => panic!("Got back a cache to clear"),
+        Ok(Action::RefreshCache) => panic!("Got back a cache to refresh"),
+        Ok(Action::Serve) => panic!("Got back a server"),
+        Ok(Action::Configuration(_)) => panic!("Got back the configuration"),
+        Ok(Action::Write { .. }) => panic!("Got back a file to write"),
         Err(error) => panic!("No executable found in default case: {error:?}"),
     }
 
@@ -90,6 +454,7 @@ fn from_main_by_flag() {
             launcher_path,
             executable,
             args,
+            ..
         }) => {
             assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
             assert_eq!(executable, env_state.python27);
@@ -103,16 +468,64 @@ fn from_main_by_flag() {
             launcher_path,
             executable,
             args,
+            ..
+        }) => {
+            assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
+            assert_eq!(executable, env_state.python36);
+            assert_eq!(args.len(), 0);
+        }
+        _ => panic!("No executable found in `-3.6` case"),
+    }
+
+    match Action::from_main(&[launcher_location.clone(), "-2.7+".to_string()]) {
+        Ok(Action::Execute {
+            launcher_path,
+            executable,
+            args,
+            ..
+        }) => {
+            assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
+            assert_eq!(executable, env_state.python37);
+            assert_eq!(args.len(), 0);
+        }
+        _ => panic!("No executable found in `-2.7+` case"),
+    }
+
+    match Action::from_main(&[launcher_location.clone(), "-3,!3.7".to_string()]) {
+        Ok(Action::Execute {
+            launcher_path,
+            executable,
+            args,
+            ..
         }) => {
             assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
             assert_eq!(executable, env_state.python36);
             assert_eq!(args.len(), 0);
         }
+        _ => panic!("No executable found in `-3,!3.7` case"),
+    }
+
+    match Action::from_main(&[
+        launcher_location.clone(),
+        "-3.6".to_string(),
+        "-I".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            launcher_path,
+            executable,
+            args,
+            ..
+        }) => {
+            assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
+            assert_eq!(executable, env_state.python36);
+            assert_eq!(args, ["-I".to_string()]);
+        }
         _ => panic!("No executable found in `-3.6` case"),
     }
 
     match Action::from_main(&[
         launcher_location.clone(),
+        "--pre".to_string(),
         "-3.6".to_string(),
         "-I".to_string(),
     ]) {
@@ -120,27 +533,297 @@ fn from_main_by_flag() {
             launcher_path,
             executable,
             args,
+            ..
         }) => {
             assert_eq!(PathBuf::from(launcher_location), launcher_path);
             assert_eq!(executable, env_state.python36);
             assert_eq!(args, ["-I".to_string()]);
         }
-        _ => panic!("No executable found in `-3.6` case"),
+        _ => panic!("No executable found in `--pre -3.6` case"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_which() {
+    let _working_dir = common::CurrentDir::new();
+    let env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "--which".to_string()]),
+        Ok(Action::Which(env_state.python37.clone()))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "-3.6".to_string(),
+            "--which".to_string()
+        ]),
+        Ok(Action::Which(env_state.python36.clone()))
+    );
+
+    // The same resolution as executing, including virtual environments.
+    let venv_bin = PathBuf::from(cli::DEFAULT_VENV_DIR).join("bin");
+    fs::create_dir_all(&venv_bin).unwrap();
+    let venv_python = common::touch_file(venv_bin.join("python"))
+        .canonicalize()
+        .unwrap();
+    match Action::from_main(&[launcher_location.clone(), "--which".to_string()]) {
+        Ok(Action::Which(executable)) => {
+            assert_eq!(executable.canonicalize().unwrap(), venv_python)
+        }
+        result => panic!("'--which' did not return the virtual environment: {result:?}"),
+    }
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "-2".to_string(),
+            "--which".to_string()
+        ]),
+        Ok(Action::Which(env_state.python27.clone()))
+    );
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "-4.2".to_string(),
+            "--which".to_string()
+        ]),
+        Err(Error::Search(SearchError::NoExecutableFound(
+            RequestedVersion::Exact(4, 2)
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "-3".to_string(),
+            "--which".to_string(),
+            "script.py".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "--which".to_string()
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--which".to_string(),
+            "-3".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "--which".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_resolve() {
+    let _working_dir = common::CurrentDir::new();
+    let env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "-3.6".to_string(),
+            "--resolve".to_string()
+        ]),
+        Ok(Action::Resolve(format!(
+            "{{\"path\":\"{}\",\"version\":\"3.6\",\"source\":\"path\",\"venv\":null}}\n",
+            env_state.python36.display()
+        )))
+    );
+
+    // The same resolution as `--which`, including virtual environments.
+    let venv_bin = PathBuf::from(cli::DEFAULT_VENV_DIR).join("bin");
+    fs::create_dir_all(&venv_bin).unwrap();
+    common::touch_file(venv_bin.join("python"));
+    fs::write(
+        venv_bin.parent().unwrap().join("pyvenv.cfg"),
+        "version = 3.12.1\n",
+    )
+    .unwrap();
+    let json = match Action::from_main(&[launcher_location.clone(), "--resolve".to_string()]) {
+        Ok(Action::Resolve(json)) => json,
+        result => panic!("'--resolve' did not resolve: {result:?}"),
+    };
+    let resolved: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(resolved["version"], "3.12.1");
+    assert_eq!(resolved["source"], "venv");
+    assert!(resolved["path"]
+        .as_str()
+        .unwrap()
+        .starts_with(resolved["venv"].as_str().unwrap()));
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "-4.2".to_string(),
+            "--resolve".to_string()
+        ]),
+        Err(Error::Search(SearchError::NoExecutableFound(
+            RequestedVersion::Exact(4, 2)
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--resolve".to_string(),
+            "-3".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "--resolve".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_serve() {
+    let _env_state = EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "--serve".to_string()]),
+        Ok(Action::Serve)
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--serve".to_string(),
+            "-3".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "--serve".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_cache() {
+    let _env_state = EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "--clear-cache".to_string()]),
+        Ok(Action::ClearCache)
+    );
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "--refresh-cache".to_string()]),
+        Ok(Action::RefreshCache)
+    );
+    for flag in ["--clear-cache", "--refresh-cache"] {
+        assert_eq!(
+            Action::from_main(&[
+                launcher_location.clone(),
+                flag.to_string(),
+                "-3".to_string()
+            ]),
+            Err(Error::Cli(CliError::IllegalArgument(
+                PathBuf::from(&launcher_location),
+                flag.to_string()
+            )))
+        );
     }
 }
 
+#[test]
+#[serial]
+fn serve() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = EnvState::new();
+    let mut server = Server::new();
+    let mut respond = |request: &str| {
+        serde_json::from_str::<serde_json::Value>(&server.respond(request)).unwrap()
+    };
+
+    assert_eq!(
+        respond(r#"{"id": 1, "method": "resolve", "version": "3.6"}"#),
+        serde_json::json!({
+            "id": 1,
+            "result": {
+                "path": env_state.python36.display().to_string(),
+                "version": "3.6",
+                "source": "path",
+                "venv": null,
+            },
+        })
+    );
+
+    let listed = respond(r#"{"id": 2, "method": "list"}"#);
+    let versions = listed["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|executable| (executable["version"].clone(), executable["default"].clone()))
+        .collect::<Vec<(serde_json::Value, serde_json::Value)>>();
+    assert_eq!(
+        versions,
+        [
+            ("3.7".into(), true.into()),
+            ("3.6".into(), false.into()),
+            ("2.7".into(), false.into())
+        ]
+    );
+    let listed = respond(r#"{"id": 3, "method": "list", "all": true}"#);
+    assert_eq!(listed["result"].as_array().unwrap().len(), 3);
+
+    // Executables are only searched for again once invalidated.
+    let python38 = common::touch_file(env_state.python37.with_file_name("python3.8"));
+    let request = r#"{"id": 4, "method": "resolve", "version": "3.8"}"#;
+    assert!(respond(request)["error"]["message"].is_string());
+    assert_eq!(
+        respond(r#"{"id": 5, "method": "invalidate"}"#),
+        serde_json::json!({"id": 5, "result": null})
+    );
+    assert_eq!(
+        respond(request)["result"]["path"],
+        python38.display().to_string()
+    );
+
+    // ... unless other directories are searched.
+    let new_dir = tempfile::tempdir().unwrap();
+    let python39 = common::touch_file(new_dir.path().join("python3.9"));
+    env_state
+        .env_vars
+        .change("PATH", Some(new_dir.path().to_str().unwrap()));
+    assert_eq!(
+        respond(r#"{"id": 6, "method": "resolve", "version": "3.9"}"#)["result"]["path"],
+        python39.display().to_string()
+    );
+}
+
 #[test]
 #[serial]
 fn from_main_activated_virtual_env() {
-    let venv_path = "/path/to/venv";
     let mut env_state = common::EnvState::new();
+    let venv_dir = tempfile::tempdir().unwrap();
+    let venv_path = venv_dir.path().to_str().unwrap();
     env_state.env_vars.change("VIRTUAL_ENV", Some(venv_path));
 
+    // A virtual environment without an interpreter is an error.
+    assert_eq!(
+        Action::from_main(&["/path/to/py".to_string()]),
+        Err(Error::Search(SearchError::InvalidVirtualEnv(
+            PathBuf::from(venv_path)
+        )))
+    );
+
+    let mut expected = PathBuf::from(venv_path);
+    expected.push("bin");
+    fs::create_dir_all(&expected).unwrap();
+    expected.push("python");
+    common::touch_file(expected.clone());
+
     match Action::from_main(&["/path/to/py".to_string()]) {
         Ok(Action::Execute { executable, .. }) => {
-            let mut expected = PathBuf::from(venv_path);
-            expected.push("bin");
-            expected.push("python");
             assert_eq!(executable, expected);
         }
         _ => panic!("No executable found in `VIRTUAL_ENV` case"),
@@ -157,35 +840,81 @@ fn from_main_activated_virtual_env() {
 
 #[test]
 #[serial]
-fn from_main_default_cwd_venv_path() {
-    let _working_dir = common::CurrentDir::new();
-    let env_state = common::EnvState::new();
-    let mut expected = PathBuf::new();
-    expected.push(cli::DEFAULT_VENV_DIR);
-    expected.push("bin");
+fn from_main_activated_conda_env() {
+    let mut env_state = common::EnvState::new();
+    let conda_dir = tempfile::tempdir().unwrap();
+    let conda_prefix = conda_dir.path().to_str().unwrap();
+    let mut expected = conda_dir.path().join("bin");
     fs::create_dir_all(&expected).unwrap();
     expected.push("python");
     common::touch_file(expected.clone());
+    env_state
+        .env_vars
+        .change("CONDA_PREFIX", Some(conda_prefix));
 
     match Action::from_main(&["/path/to/py".to_string()]) {
         Ok(Action::Execute { executable, .. }) => {
-            assert_eq!(executable, expected.canonicalize().unwrap());
+            assert_eq!(executable, expected);
         }
-        _ => panic!("No executable found in default virtual environment case"),
+        _ => panic!("No executable found in `CONDA_PREFIX` case"),
     }
 
-    // VIRTUAL_ENV gets ignored if any specific version is requested.
+    // CONDA_PREFIX gets ignored if any specific version is requested.
     match Action::from_main(&["/path/to/py".to_string(), "-3".to_string()]) {
         Ok(Action::Execute { executable, .. }) => {
             assert_eq!(executable, env_state.python37);
         }
-        _ => panic!("No executable found in default virtual environment case"),
+        _ => panic!("No executable found in `CONDA_PREFIX` case"),
+    }
+
+    // VIRTUAL_ENV takes precedence.
+    let venv_dir = tempfile::tempdir().unwrap();
+    let mut venv_python = venv_dir.path().join("bin");
+    fs::create_dir_all(&venv_python).unwrap();
+    venv_python.push("python");
+    common::touch_file(venv_python.clone());
+    env_state
+        .env_vars
+        .change("VIRTUAL_ENV", Some(venv_dir.path().to_str().unwrap()));
+    match Action::from_main(&["/path/to/py".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, venv_python);
+        }
+        _ => panic!("No executable found in `VIRTUAL_ENV` case"),
     }
 }
 
 #[test]
 #[serial]
-fn from_main_default_parent_venv_path() {
+fn from_main_default_cwd_venv_path() {
+    let _working_dir = common::CurrentDir::new();
+    let env_state = common::EnvState::new();
+    let mut expected = PathBuf::new();
+    expected.push(cli::DEFAULT_VENV_DIR);
+    expected.push("bin");
+    fs::create_dir_all(&expected).unwrap();
+    expected.push("python");
+    common::touch_file(expected.clone());
+
+    match Action::from_main(&["/path/to/py".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, expected.canonicalize().unwrap());
+        }
+        _ => panic!("No executable found in default virtual environment case"),
+    }
+
+    // VIRTUAL_ENV gets ignored if any specific version is requested.
+    match Action::from_main(&["/path/to/py".to_string(), "-3".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in default virtual environment case"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_default_parent_venv_path() {
     let working_dir = common::CurrentDir::new();
     let temp_dir = working_dir.dir.path().to_path_buf();
     let env_state = common::EnvState::new();
@@ -216,6 +945,214 @@ fn from_main_default_parent_venv_path() {
     }
 }
 
+#[test]
+#[serial]
+fn from_main_venv_search_opt_out() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let mut venv_python = PathBuf::from("venv");
+    venv_python.push("bin");
+    fs::create_dir_all(&venv_python).unwrap();
+    venv_python.push("python");
+    common::touch_file(venv_python.clone());
+    common::touch_file(PathBuf::from("venv").join("pyvenv.cfg"));
+
+    match Action::from_main(&["/path/to/py".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, venv_python.canonicalize().unwrap());
+        }
+        _ => panic!("No executable found in `venv` virtual environment case"),
+    }
+
+    env_state
+        .env_vars
+        .change("PYLAUNCH_NO_VENV_SEARCH", Some("1"));
+    match Action::from_main(&["/path/to/py".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found with `PYLAUNCH_NO_VENV_SEARCH` set"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_no_venv() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let mut local_venv = PathBuf::from(cli::DEFAULT_VENV_DIR);
+    local_venv.push("bin");
+    fs::create_dir_all(&local_venv).unwrap();
+    local_venv.push("python");
+    common::touch_file(local_venv);
+
+    match Action::from_main(&["/path/to/py".to_string(), "--no-venv".to_string()]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, env_state.python37);
+            assert_eq!(args.len(), 0);
+        }
+        _ => panic!("No executable found with `--no-venv` and a local venv"),
+    }
+
+    // Even a broken activated virtual environment is ignored.
+    let venv_dir = tempfile::tempdir().unwrap();
+    env_state
+        .env_vars
+        .change("VIRTUAL_ENV", Some(venv_dir.path().to_str().unwrap()));
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--no-venv".to_string(),
+        "-c".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, env_state.python37);
+            assert_eq!(args, ["-c".to_string()]);
+        }
+        _ => panic!("No executable found with `--no-venv` and `VIRTUAL_ENV` set"),
+    }
+
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--no-venv".to_string(),
+        "-2".to_string(),
+    ]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python27);
+        }
+        _ => panic!("No executable found with `--no-venv -2`"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_venv_create() {
+    let _working_dir = common::CurrentDir::new();
+    let env_state = common::EnvState::new();
+    // An existing virtual environment is not used to create a new one.
+    let mut local_venv = PathBuf::from(cli::DEFAULT_VENV_DIR);
+    local_venv.push("bin");
+    fs::create_dir_all(&local_venv).unwrap();
+    common::touch_file(local_venv.join("python"));
+
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "venv".to_string(),
+        "create".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, env_state.python37);
+            assert_eq!(args, ["-m", "venv", cli::DEFAULT_VENV_DIR]);
+        }
+        _ => panic!("No executable found for `venv create`"),
+    }
+
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "venv".to_string(),
+        "create".to_string(),
+        "3.6".to_string(),
+        "env".to_string(),
+        "--upgrade-pip".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, env_state.python36);
+            assert_eq!(args, ["-m", "venv", "env", "--upgrade-deps"]);
+        }
+        _ => panic!("No executable found for `venv create 3.6`"),
+    }
+
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "venv".to_string(),
+        "create".to_string(),
+        "3.8".to_string(),
+    ]) {
+        Err(Error::Search(SearchError::NoExecutableFound(requested_version))) => {
+            assert_eq!(requested_version, RequestedVersion::Exact(3, 8));
+        }
+        _ => panic!("Unsatisfiable `venv create` version did not error out"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_activate() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let activate = ["/path/to/py".to_string(), "--activate".to_string()];
+
+    match Action::from_main(&activate) {
+        Err(Error::Search(SearchError::NoVirtualEnvFound)) => (),
+        _ => panic!("No virtual environment did not error out"),
+    }
+
+    let venv_bin = PathBuf::from(cli::DEFAULT_VENV_DIR).join("bin");
+    fs::create_dir_all(&venv_bin).unwrap();
+    common::touch_file(venv_bin.join("python"));
+    let venv_bin = venv_bin.canonicalize().unwrap();
+    let posix_script = common::touch_file(venv_bin.join("activate"));
+
+    match Action::from_main(&activate) {
+        Ok(Action::Activate(snippet)) => {
+            assert_eq!(snippet, format!(". '{}'\n", posix_script.display()));
+        }
+        _ => panic!("No activation snippet for a local virtual environment"),
+    }
+
+    // The shell is detected from SHELL unless it is specified.
+    env_state.env_vars.change("SHELL", Some("/usr/bin/fish"));
+    match Action::from_main(&activate) {
+        Err(Error::Search(SearchError::NoActivationScript(script))) => {
+            assert_eq!(script, venv_bin.join("activate.fish"));
+        }
+        _ => panic!("Missing fish activation script did not error out"),
+    }
+    let fish_script = common::touch_file(venv_bin.join("activate.fish"));
+    match Action::from_main(&activate) {
+        Ok(Action::Activate(snippet)) => {
+            assert_eq!(snippet, format!("source '{}'\n", fish_script.display()));
+        }
+        _ => panic!("No activation snippet for fish"),
+    }
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "--activate".to_string(),
+        "bash".to_string(),
+    ]) {
+        Ok(Action::Activate(snippet)) => {
+            assert_eq!(snippet, format!(". '{}'\n", posix_script.display()));
+        }
+        _ => panic!("No activation snippet for an explicit shell"),
+    }
+
+    // Conda environments are activated by conda.
+    let conda_dir = tempfile::tempdir().unwrap();
+    let conda_bin = conda_dir.path().join("bin");
+    fs::create_dir_all(&conda_bin).unwrap();
+    fs::create_dir(conda_dir.path().join("conda-meta")).unwrap();
+    common::touch_file(conda_bin.join("python"));
+    env_state
+        .env_vars
+        .change("CONDA_PREFIX", Some(conda_dir.path().to_str().unwrap()));
+    match Action::from_main(&activate) {
+        Ok(Action::Activate(snippet)) => {
+            assert_eq!(
+                snippet,
+                format!("conda activate '{}'\n", conda_dir.path().display())
+            );
+        }
+        _ => panic!("No activation snippet for a conda environment"),
+    }
+}
+
 #[test]
 #[serial]
 fn from_main_shebang() {
@@ -251,6 +1188,123 @@ fn from_main_shebang() {
     }
 }
 
+#[test]
+#[serial]
+fn from_main_shebang_arguments() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file_path = temp_dir.path().join("shebang.py");
+    let script = file_path.to_str().unwrap().to_string();
+    let mut file = File::create(&file_path).unwrap();
+    writeln!(file, "#!/usr/bin/env python3.6 -I -W error").unwrap();
+
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        script.clone(),
+        "--verbose".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, env_state.python36);
+            assert_eq!(args, ["-I", "-W", "error", &script, "--verbose"]);
+        }
+        _ => panic!("No executable found in shebang arguments case"),
+    }
+
+    // Arguments are passed on even when a version is specified.
+    match Action::from_main(&["/path/to/py".to_string(), "-2".to_string(), script.clone()]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, env_state.python27);
+            assert_eq!(args, ["-I", "-W", "error", &script]);
+        }
+        _ => panic!("No executable found in shebang arguments with a version case"),
+    }
+
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "shebang-arguments = \"single\"").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    match Action::from_main(&["/path/to/py".to_string(), script.clone()]) {
+        Ok(Action::Execute { args, .. }) => {
+            assert_eq!(args, ["-I -W error", &script]);
+        }
+        _ => panic!("No executable found in single shebang argument case"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_shebang_custom_commands() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let lab_python = common::touch_file(temp_dir.path().join("lab-python"));
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(
+        &config_path,
+        format!(
+            "[shebang-commands]\nmypython = \"3.6\"\nlabpython = \"{}\"\n",
+            lab_python.display()
+        ),
+    )
+    .unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+
+    let version_script = temp_dir.path().join("version.py");
+    let mut file = File::create(&version_script).unwrap();
+    writeln!(file, "#!/usr/bin/env mypython").unwrap();
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        version_script.to_str().unwrap().to_string(),
+    ]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found for a custom shebang version"),
+    }
+
+    let executable_script = temp_dir.path().join("executable.py");
+    let mut file = File::create(&executable_script).unwrap();
+    writeln!(file, "#!labpython -u").unwrap();
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        executable_script.to_str().unwrap().to_string(),
+    ]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, lab_python);
+            assert_eq!(args, ["-u", executable_script.to_str().unwrap()]);
+        }
+        _ => panic!("No executable found for a custom shebang executable"),
+    }
+
+    // A version on the command line takes precedence.
+    match Action::from_main(&[
+        "/path/to/py".to_string(),
+        "-2".to_string(),
+        executable_script.to_str().unwrap().to_string(),
+    ]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python27);
+        }
+        _ => panic!("No executable found for a version and a custom shebang"),
+    }
+}
+
 #[test]
 #[serial]
 fn from_main_env_var() {
@@ -264,6 +1318,7 @@ fn from_main_env_var() {
             launcher_path,
             executable,
             args,
+            ..
         }) => {
             assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
             assert_eq!(executable, env_state.python36);
@@ -272,6 +1327,27 @@ fn from_main_env_var() {
         _ => panic!("No executable found in PY_PYTHON case"),
     }
 
+    env_state.env_vars.change("PY_PYTHON", Some("3.8,3.6,3"));
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in PY_PYTHON fallback case"),
+    }
+
+    env_state.env_vars.change("PY_PYTHON", Some("3.8,3.9"));
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Err(Error::Search(SearchError::NoPreferredExecutableFound(preferences))) => {
+            assert_eq!(
+                preferences.versions,
+                [RequestedVersion::Exact(3, 8), RequestedVersion::Exact(3, 9)]
+            );
+        }
+        _ => panic!("Unsatisfiable PY_PYTHON fallbacks did not error out"),
+    }
+
     env_state.env_vars.change("PY_PYTHON3", Some("3.6"));
 
     match Action::from_main(&[launcher_location.clone(), "-3".to_string()]) {
@@ -279,6 +1355,7 @@ fn from_main_env_var() {
             launcher_path,
             executable,
             args,
+            ..
         }) => {
             assert_eq!(PathBuf::from(launcher_location.clone()), launcher_path);
             assert_eq!(executable, env_state.python36);
@@ -296,10 +1373,1201 @@ fn from_main_env_var() {
 
 #[test]
 #[serial]
-fn from_main_no_executable_found() {
-    let _env_state = common::EnvState::new();
-    assert_eq!(
-        Action::from_main(&["/path/to/py".to_string(), "-42.13".to_string()]),
-        Err(Error::NoExecutableFound(RequestedVersion::Exact(42, 13)))
+fn from_main_pyenv_version() {
+    let working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    env_state.env_vars.change("PYENV_VERSION", Some("3.6.15"));
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in PYENV_VERSION case"),
+    }
+
+    // PYENV_VERSION gets ignored if any specific version is requested.
+    match Action::from_main(&[launcher_location.clone(), "-3".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in PYENV_VERSION with `-3` case"),
+    }
+
+    // PY_PYTHON takes precedence.
+    env_state.env_vars.change("PY_PYTHON", Some("3.7"));
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in PY_PYTHON over PYENV_VERSION case"),
+    }
+    env_state.env_vars.change("PY_PYTHON", None);
+
+    // Without PYENV_VERSION, pyenv's global version file is used.
+    env_state.env_vars.change("PYENV_VERSION", None);
+    let pyenv_root = tempfile::tempdir().unwrap();
+    fs::write(pyenv_root.path().join("version"), "3.6.15\n").unwrap();
+    env_state
+        .env_vars
+        .change("PYENV_ROOT", Some(pyenv_root.path().to_str().unwrap()));
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in pyenv version file case"),
+    }
+
+    // A `.python-version` file in the current directory or any parent takes
+    // precedence over the global version file ...
+    fs::write(working_dir.dir.path().join(".python-version"), "3.7\n").unwrap();
+    let subdir = working_dir.dir.path().join("src");
+    fs::create_dir(&subdir).unwrap();
+    env::set_current_dir(&subdir).unwrap();
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in .python-version case"),
+    }
+
+    // ... but not over PYENV_VERSION.
+    env_state.env_vars.change("PYENV_VERSION", Some("3.6"));
+    match Action::from_main(&[launcher_location]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in PYENV_VERSION over .python-version case"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_tool_versions() {
+    let working_dir = common::CurrentDir::new();
+    let env_state = common::EnvState::new();
+    fs::write(
+        working_dir.dir.path().join(".tool-versions"),
+        "nodejs 20.10.0\npython 3.6.15\n",
+    )
+    .unwrap();
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in .tool-versions case"),
+    }
+
+    // A `.python-version` file takes precedence.
+    fs::write(working_dir.dir.path().join(".python-version"), "3.7\n").unwrap();
+    match Action::from_main(&[launcher_location]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in .python-version over .tool-versions case"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_requires_python() {
+    let working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    fs::write(
+        working_dir.dir.path().join("pyproject.toml"),
+        "[project]\nname = \"demo\"\nrequires-python = \"<3.7\"\n",
+    )
+    .unwrap();
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in requires-python case"),
+    }
+
+    // An explicitly requested version is used regardless.
+    match Action::from_main(&[launcher_location.clone(), "-3.7".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in requires-python with `-3.7` case"),
+    }
+
+    // A default version which the project doesn't support is an error.
+    env_state.env_vars.change("PY_PYTHON", Some("3.7"));
+    assert!(matches!(
+        Action::from_main(&[launcher_location]),
+        Err(Error::Search(SearchError::NoPreferredExecutableFound(_)))
+    ));
+}
+
+#[test]
+#[serial]
+fn from_main_no_executable_found() {
+    let _env_state = common::EnvState::new();
+    assert_eq!(
+        Action::from_main(&["/path/to/py".to_string(), "-42.13".to_string()]),
+        Err(Error::Search(SearchError::NoExecutableFound(
+            RequestedVersion::Exact(42, 13)
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_invalid_config() {
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "search-directories = 42").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+
+    match Action::from_main(&["/path/to/py".to_string()]) {
+        Err(Error::Config(ConfigError::InvalidConfig(path, _))) => assert_eq!(path, config_path),
+        result => panic!("expected an invalid configuration error, not {result:?}"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_config_defaults() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let mut local_venv = PathBuf::from(cli::DEFAULT_VENV_DIR);
+    local_venv.push("bin");
+    fs::create_dir_all(&local_venv).unwrap();
+    local_venv.push("python");
+    common::touch_file(local_venv);
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(
+        &config_path,
+        "default-version = \"3.6\"\nvenv-search = false\n",
+    )
+    .unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in `default-version` case"),
+    }
+
+    // Environment variables take precedence.
+    env_state.env_vars.change("PY_PYTHON", Some("3.7"));
+    match Action::from_main(&[launcher_location]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in PY_PYTHON over `default-version` case"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_project_config() {
+    let working_dir = common::CurrentDir::new();
+    let env_state = common::EnvState::new();
+    fs::write(
+        working_dir.dir.path().join("pyproject.toml"),
+        "[tool.py]\ndefault-version = \"3.6\"\n",
+    )
+    .unwrap();
+
+    match Action::from_main(&["/path/to/py".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in project configuration case"),
+    }
+
+    fs::write(
+        working_dir.dir.path().join(".py.toml"),
+        "search-directories = 42\n",
+    )
+    .unwrap();
+    assert!(matches!(
+        Action::from_main(&["/path/to/py".to_string()]),
+        Err(Error::Config(ConfigError::InvalidConfig(_, _)))
+    ));
+}
+
+#[test]
+#[serial]
+fn from_main_config_flags() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "default-version = \"3.6\"\n").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[launcher_location.clone(), "--no-config".to_string()]) {
+        Ok(Action::Execute {
+            executable,
+            env_vars,
+            ..
+        }) => {
+            assert_eq!(executable, env_state.python37);
+            // Only the interpreter sees the flag as an environment variable.
+            assert!(env_vars.contains(&("PYLAUNCH_NO_CONFIG".to_string(), "1".to_string())));
+            assert_eq!(env::var_os("PYLAUNCH_NO_CONFIG"), None);
+        }
+        _ => panic!("No executable found in `--no-config` case"),
+    }
+
+    let other_config = config_home.path().join("other.toml");
+    fs::write(&other_config, "default-version = \"2.7\"\n").unwrap();
+    match Action::from_main(&[
+        launcher_location.clone(),
+        "--config".to_string(),
+        other_config.to_str().unwrap().to_string(),
+        "-c".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            executable,
+            args,
+            env_vars,
+            ..
+        }) => {
+            assert_eq!(executable, env_state.python27);
+            assert_eq!(args, ["-c".to_string()]);
+            assert!(env_vars.contains(&(
+                "PYLAUNCH_CONFIG".to_string(),
+                other_config.to_str().unwrap().to_string()
+            )));
+            assert_eq!(env::var_os("PYLAUNCH_CONFIG"), None);
+        }
+        _ => panic!("No executable found in `--config` case"),
+    }
+
+    let missing_config = config_home.path().join("missing.toml");
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--config".to_string(),
+            missing_config.to_str().unwrap().to_string(),
+        ]),
+        Err(Error::Config(ConfigError::InvalidConfig(
+            missing_config,
+            "file not found".to_string()
+        )))
+    );
+
+    assert_eq!(
+        Action::from_main(&[launcher_location, "--config".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument(
+            "--config".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_chdir() {
+    let working_dir = common::CurrentDir::new();
+    let _env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+    let project_dir = working_dir.dir.path().join("project");
+    let venv_bin = project_dir.join(cli::DEFAULT_VENV_DIR).join("bin");
+    fs::create_dir_all(&venv_bin).unwrap();
+    let venv_python = common::touch_file(venv_bin.join("python"))
+        .canonicalize()
+        .unwrap();
+
+    match Action::from_main(&[
+        launcher_location.clone(),
+        "--chdir".to_string(),
+        "project".to_string(),
+        "--which".to_string(),
+    ]) {
+        Ok(Action::Which(executable)) => {
+            assert_eq!(executable.canonicalize().unwrap(), venv_python)
+        }
+        result => panic!("'--chdir' did not find the virtual environment: {result:?}"),
+    }
+    // Only the interpreter is run in the directory.
+    assert_eq!(env::current_dir().unwrap(), working_dir.dir.path());
+    let options = cli::Options::parse(&[
+        launcher_location.clone(),
+        "--chdir".to_string(),
+        "project".to_string(),
+        "--chdir".to_string(),
+        cli::DEFAULT_VENV_DIR.to_string(),
+    ])
+    .unwrap();
+    assert_eq!(
+        options.working_dir(),
+        Some(
+            project_dir
+                .join(cli::DEFAULT_VENV_DIR)
+                .canonicalize()
+                .unwrap()
+                .as_path()
+        )
+    );
+
+    assert!(matches!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--chdir".to_string(),
+            "missing".to_string(),
+        ]),
+        Err(Error::Cli(CliError::InvalidWorkingDir(path, _))) if path == Path::new("missing")
+    ));
+    let file = common::touch_file(working_dir.dir.path().join("file"));
+    assert!(matches!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--chdir".to_string(),
+            file.to_str().unwrap().to_string(),
+        ]),
+        Err(Error::Cli(CliError::InvalidWorkingDir(path, _))) if path == file
+    ));
+    assert_eq!(
+        Action::from_main(&[launcher_location, "--chdir".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument("--chdir".to_string())))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_configuration() {
+    let working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(
+        &config_path,
+        "default-version = \"3.6\"\ndisable-sources = [\"snap\"]\n",
+    )
+    .unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    let project_config_path = working_dir.dir.path().join(".py.toml");
+    fs::write(&project_config_path, "venv-search = false\n").unwrap();
+    env_state.env_vars.change("PY_PYTHON3", Some("3.7"));
+    env_state.env_vars.change("PY_DISABLE_SOURCES", Some("nix"));
+    env_state
+        .env_vars
+        .change("PYLAUNCHER_ALLOW_INSTALL", Some("1"));
+    let launcher_location = "/path/to/py".to_string();
+
+    let report = match Action::from_main(&[
+        launcher_location.clone(),
+        "--pre".to_string(),
+        "--pick".to_string(),
+        "--clean-env".to_string(),
+        "--configuration".to_string(),
+    ]) {
+        Ok(Action::Configuration(report)) => report,
+        _ => panic!("No configuration reported"),
+    };
+    let rows = report
+        .lines()
+        .map(|line| {
+            line.split('│')
+                .map(|column| column.trim().to_string())
+                .collect::<Vec<String>>()
+        })
+        .collect::<Vec<Vec<String>>>();
+    let config_source = config_path.display().to_string();
+    let project_source = project_config_path.display().to_string();
+    for row in [
+        ["default-version", "Python 3.6", config_source.as_str()],
+        ["allow-prereleases", "true", "--pre"],
+        ["allow-install", "true", "$PYLAUNCHER_ALLOW_INSTALL"],
+        ["pick", "true", "--pick"],
+        ["clean-env", "true", "--clean-env"],
+        ["venv-search", "false", project_source.as_str()],
+        ["disable-sources", "snap", config_source.as_str()],
+        ["disable-sources", "nix", "$PY_DISABLE_SOURCES"],
+        ["shebang-arguments", "split", "default"],
+        ["PY_PYTHON3", "3.7", "$PY_PYTHON3"],
+    ] {
+        assert!(
+            rows.contains(&row.map(String::from).to_vec()),
+            "{row:?} not in {report}"
+        );
+    }
+
+    env_state.env_vars.change("PY_PYTHON", Some("3.7"));
+    match Action::from_main(&[launcher_location.clone(), "--configuration".to_string()]) {
+        Ok(Action::Configuration(report)) => {
+            assert!(report
+                .lines()
+                .any(|line| line.trim_start().starts_with("default-version")
+                    && line.contains("$PY_PYTHON")));
+        }
+        _ => panic!("No configuration reported"),
+    }
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--configuration".to_string(),
+            "--list".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(launcher_location),
+            "--configuration".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_environment() {
+    let working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(
+        &config_path,
+        "[environment]\nPYTHONUTF8 = 1\nPYTHONWARNINGS = \"error\"\n",
+    )
+    .unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    env_state.env_vars.change("PYTHONUTF8", None);
+    env_state.env_vars.change("PYTHONWARNINGS", None);
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { env_vars, .. }) => {
+            assert_eq!(
+                env_vars,
+                [
+                    ("PYTHONUTF8".to_string(), "1".to_string()),
+                    ("PYTHONWARNINGS".to_string(), "error".to_string())
+                ]
+            );
+        }
+        _ => panic!("No executable found with an `environment` table"),
+    }
+
+    // A project's configuration replaces variables of the same name, and
+    // variables which are already set are left alone.
+    fs::write(
+        working_dir.dir.path().join(".py.toml"),
+        "[environment]\nPYTHONWARNINGS = \"default\"\n",
+    )
+    .unwrap();
+    env_state.env_vars.change("PYTHONUTF8", Some("0"));
+    match Action::from_main(&[launcher_location.clone(), "-3.6".to_string()]) {
+        Ok(Action::Execute { env_vars, .. }) => {
+            assert_eq!(
+                env_vars,
+                [("PYTHONWARNINGS".to_string(), "default".to_string())]
+            );
+        }
+        _ => panic!("No executable found with a project `environment` table"),
+    }
+
+    // `--env` sets variables whether or not they are already set, after and
+    // instead of those of the configuration.
+    match Action::from_main(&[
+        launcher_location.clone(),
+        "--env".to_string(),
+        "PYTHONWARNINGS=error=x".to_string(),
+        "--env".to_string(),
+        "PYTHONUTF8=1".to_string(),
+        "--env".to_string(),
+        "PYTHONUTF8=".to_string(),
+        "-3.6".to_string(),
+    ]) {
+        Ok(Action::Execute { env_vars, .. }) => {
+            assert_eq!(
+                env_vars,
+                [
+                    ("PYTHONWARNINGS".to_string(), "error=x".to_string()),
+                    ("PYTHONUTF8".to_string(), String::new())
+                ]
+            );
+        }
+        _ => panic!("No executable found with `--env`"),
+    }
+    for assignment in ["PYTHONUTF8", "=1"] {
+        assert_eq!(
+            Action::from_main(&[
+                launcher_location.clone(),
+                "--env".to_string(),
+                assignment.to_string(),
+            ]),
+            Err(Error::Cli(CliError::InvalidEnvVar(assignment.to_string())))
+        );
+    }
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "--env".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument("--env".to_string())))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--env".to_string(),
+            "PYTHONUTF8=1".to_string(),
+            "--which".to_string(),
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(launcher_location),
+            "--which".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_clean_env() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "[environment]\nPYTHONUTF8 = 1\n").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    env_state.env_vars.change("PYTHONUTF8", Some("0"));
+    env_state.env_vars.change("PYTHONPATH", Some("/somewhere"));
+    env_state.env_vars.change("PYTHONWARNINGS", Some("error"));
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[
+        launcher_location.clone(),
+        "--clean-env".to_string(),
+        "--env".to_string(),
+        "PYTHONWARNINGS=default".to_string(),
+        "-c".to_string(),
+        "pass".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            args,
+            env_vars,
+            unset_env_vars,
+            ..
+        }) => {
+            assert_eq!(args, ["-s", "-c", "pass"]);
+            // The configuration's value replaces the one which is set.
+            assert_eq!(
+                env_vars,
+                [
+                    ("PYTHONUTF8".to_string(), "1".to_string()),
+                    ("PYTHONWARNINGS".to_string(), "default".to_string())
+                ]
+            );
+            assert!(unset_env_vars.contains(&"PYTHONPATH".to_string()));
+            assert!(!unset_env_vars.contains(&"PYTHONUTF8".to_string()));
+            assert!(!unset_env_vars.contains(&"PYTHONWARNINGS".to_string()));
+        }
+        result => panic!("No executable found with `--clean-env`: {result:?}"),
+    }
+
+    // Without a clean environment, nothing is unset.
+    match Action::from_main(&[launcher_location, "-c".to_string(), "pass".to_string()]) {
+        Ok(Action::Execute {
+            args,
+            env_vars,
+            unset_env_vars,
+            ..
+        }) => {
+            assert_eq!(args, ["-c", "pass"]);
+            assert!(env_vars.is_empty());
+            assert!(unset_env_vars.is_empty());
+        }
+        result => panic!("No executable found: {result:?}"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_windowed() {
+    let _working_dir = common::CurrentDir::new();
+    let env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    // Only the exact counterpart is used, else it's an error unless a GUI
+    // script is run.
+    fs::write(env_state.python36.with_file_name("pythonw3"), "").unwrap();
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--windowed".to_string(),
+            "-3.6".to_string(),
+        ]),
+        Err(Error::Search(SearchError::NoWindowedExecutable(
+            env_state.python36.clone()
+        )))
+    );
+    match Action::from_main(&[
+        launcher_location.clone(),
+        "-3.6".to_string(),
+        "app.pyw".to_string(),
+    ]) {
+        Ok(Action::Execute { executable, .. }) => assert_eq!(executable, env_state.python36),
+        result => panic!("No executable found for a GUI script: {result:?}"),
+    }
+
+    let pythonw36 = env_state.python36.with_file_name("pythonw3.6");
+    fs::write(&pythonw36, "").unwrap();
+    for argv in [
+        vec![launcher_location.as_str(), "--windowed", "-3.6"],
+        vec!["/path/to/pyw", "-3.6"],
+        vec![launcher_location.as_str(), "-3.6", "-X", "dev", "app.pyw"],
+    ] {
+        let argv: Vec<String> = argv.iter().map(|arg| arg.to_string()).collect();
+        match Action::from_main(&argv) {
+            Ok(Action::Execute { executable, .. }) => {
+                assert_eq!(executable, pythonw36, "{argv:?}");
+            }
+            result => panic!("No executable found for {argv:?}: {result:?}"),
+        }
+    }
+
+    match Action::from_main(&[launcher_location, "-3.6".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => assert_eq!(executable, env_state.python36),
+        result => panic!("No executable found: {result:?}"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_argv0() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    let launcher_location = "/path/to/py".to_string();
+
+    for (setting, expected) in [
+        ("path", None),
+        ("name", Some("python3.6")),
+        ("launcher", Some("/path/to/py")),
+    ] {
+        fs::write(&config_path, format!("argv0 = \"{setting}\"\n")).unwrap();
+        match Action::from_main(&[launcher_location.clone(), "-3.6".to_string()]) {
+            Ok(Action::Execute { argv0, .. }) => {
+                assert_eq!(argv0.as_deref(), expected, "argv0 = {setting:?}");
+            }
+            result => panic!("No executable found with `argv0 = {setting:?}`: {result:?}"),
+        }
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_py_flags() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    env_state
+        .env_vars
+        .change("PY_FLAGS", Some("-X dev -W 'error::DeprecationWarning'"));
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[
+        launcher_location.clone(),
+        "-3.6".to_string(),
+        "-c".to_string(),
+        "pass".to_string(),
+    ]) {
+        Ok(Action::Execute {
+            executable, args, ..
+        }) => {
+            assert_eq!(executable, env_state.python36);
+            assert_eq!(
+                args,
+                ["-X", "dev", "-W", "error::DeprecationWarning", "-c", "pass"]
+            );
+        }
+        result => panic!("No executable found with `PY_FLAGS`: {result:?}"),
+    }
+
+    env_state.env_vars.change("PY_FLAGS", Some("-W 'error"));
+    assert_eq!(
+        Action::from_main(&[launcher_location]),
+        Err(Error::Cli(CliError::InvalidPyFlags(
+            "-W 'error".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_dry_run() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "[environment]\nPYTHONUTF8 = 1\n").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    env_state.env_vars.change("PYTHONUTF8", None);
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--dry-run".to_string(),
+            "-3.6".to_string(),
+            "-c".to_string(),
+            "print('hi')".to_string()
+        ]),
+        Ok(Action::DryRun(format!(
+            "PYTHONUTF8=1 {} -c 'print('\\''hi'\\'')'\n",
+            env_state.python36.display()
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--dry-run".to_string(),
+            "--list".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "--list".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_py_ini() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let py_ini_path = config_home.path().join("python-launcher").join("py.ini");
+    fs::create_dir_all(py_ini_path.parent().unwrap()).unwrap();
+    fs::write(&py_ini_path, "[defaults]\npython=3.6\npython3=3.6\n").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    let launcher_location = "/path/to/py".to_string();
+
+    match Action::from_main(&[launcher_location.clone()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in py.ini `python` case"),
+    }
+
+    match Action::from_main(&[launcher_location.clone(), "-3".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python36);
+        }
+        _ => panic!("No executable found in py.ini `python3` case"),
+    }
+
+    // Environment variables take precedence.
+    env_state.env_vars.change("PY_PYTHON3", Some("3.7"));
+    match Action::from_main(&[launcher_location, "-3".to_string()]) {
+        Ok(Action::Execute { executable, .. }) => {
+            assert_eq!(executable, env_state.python37);
+        }
+        _ => panic!("No executable found in PY_PYTHON3 over py.ini case"),
+    }
+}
+
+#[test]
+#[serial]
+fn from_main_default() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--default".to_string(),
+            "3.6".to_string()
+        ]),
+        Err(Error::Config(ConfigError::NoUserConfig))
+    );
+
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("python-launcher").join("py.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "allow-prereleases = false\n").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--default".to_string(),
+            "-3.6".to_string()
+        ]),
+        Ok(Action::Write {
+            path: config_path,
+            contents: "allow-prereleases = false\ndefault-version = \"3.6\"\n".to_string(),
+        })
+    );
+
+    // The version must be installed.
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--default".to_string(),
+            "3.8".to_string()
+        ]),
+        Err(Error::Search(SearchError::NoExecutableFound(
+            RequestedVersion::Exact(3, 8)
+        )))
+    );
+
+    assert_eq!(
+        Action::from_main(&[launcher_location, "--default".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument(
+            "--default".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_pin() {
+    let working_dir = common::CurrentDir::new();
+    let _env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+    let subdir = working_dir.dir.path().join("src");
+    fs::create_dir(&subdir).unwrap();
+    env::set_current_dir(&subdir).unwrap();
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "pin".to_string(),
+            "3.6.15".to_string()
+        ]),
+        Ok(Action::Write {
+            path: subdir.join(".python-version"),
+            contents: "3.6.15\n".to_string(),
+        })
+    );
+
+    // The closest existing file is only updated when asked for.
+    let version_file = working_dir.dir.path().join(".python-version");
+    fs::write(&version_file, "3.6\n").unwrap();
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "pin".to_string(),
+            "-3.7".to_string()
+        ]),
+        Ok(Action::Write {
+            path: subdir.join(".python-version"),
+            contents: "3.7\n".to_string(),
+        })
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "pin".to_string(),
+            "--closest".to_string(),
+            "-3.7".to_string()
+        ]),
+        Ok(Action::Write {
+            path: version_file,
+            contents: "3.7\n".to_string(),
+        })
+    );
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "pin".to_string(),
+            "3.8".to_string()
+        ]),
+        Err(Error::Search(SearchError::NoExecutableFound(
+            RequestedVersion::Exact(3, 8)
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "pin".to_string(),
+            "3.7+".to_string()
+        ]),
+        Err(Error::Version(VersionError::InvalidVersionSpecifier(
+            "3.7+".to_string()
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "pin".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument("pin".to_string())))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location,
+            "pin".to_string(),
+            "--closest".to_string()
+        ]),
+        Err(Error::Cli(CliError::MissingArgument("pin".to_string())))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_install() {
+    let _env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "install".to_string(),
+            "3.12".to_string()
+        ]),
+        Ok(Action::Install(RequestedVersion::Exact(3, 12)))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "install".to_string(),
+            "-3.11.4".to_string()
+        ]),
+        Ok(Action::Install(RequestedVersion::ExactMicro(3, 11, 4)))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "install".to_string(),
+            "3.12".to_string(),
+            "3.11".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "install".to_string()
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[launcher_location, "install".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument("install".to_string())))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_uninstall() {
+    let _env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "uninstall".to_string(),
+            "3.12".to_string()
+        ]),
+        Ok(Action::Uninstall(RequestedVersion::Exact(3, 12)))
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "uninstall".to_string(),
+            "3.12".to_string(),
+            "3.11".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "uninstall".to_string()
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[launcher_location, "uninstall".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument(
+            "uninstall".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_self_update() {
+    let _env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "self".to_string(),
+            "update".to_string()
+        ]),
+        Ok(Action::SelfUpdate)
+    );
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "self".to_string(),
+            "upgrade".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "self".to_string()
+        )))
+    );
+    assert_eq!(
+        Action::from_main(&[launcher_location, "self".to_string()]),
+        Err(Error::Cli(CliError::MissingArgument("self".to_string())))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_available() {
+    let mut env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+    let target = match python_launcher::install::target_triple() {
+        Some(target) => target,
+        None => return,
+    };
+    let release_dir = tempfile::tempdir().unwrap();
+    let release = release_dir.path().join("release.json");
+    let asset_url = |version: &str| {
+        format!(
+            "https://example.com/download/cpython-{version}%2B20241016-{target}-install_only.tar.gz"
+        )
+    };
+    fs::write(
+        &release,
+        format!(
+            r#"{{"assets": [{{"browser_download_url": "{}"}}, {{"browser_download_url": "{}"}}]}}"#,
+            asset_url("3.6.15"),
+            asset_url("3.9.20")
+        ),
+    )
+    .unwrap();
+    env_state.env_vars.change(
+        "PYLAUNCH_RELEASE_URL",
+        Some(&format!("file://{}", release.display())),
+    );
+
+    // Python 3.6 is found on `PATH`.
+    match Action::from_main(&[launcher_location.clone(), "--available".to_string()]) {
+        Ok(Action::List(output)) => {
+            let rows = output
+                .lines()
+                .map(|line| {
+                    line.split('│')
+                        .map(str::trim)
+                        .collect::<Vec<&str>>()
+                        .join(" ")
+                        .trim_end()
+                        .to_string()
+                })
+                .collect::<Vec<String>>();
+            assert_eq!(rows, ["3.9.20", "3.6.15 found"]);
+        }
+        result => panic!("Unexpected result: {result:?}"),
+    }
+
+    env_state.env_vars.change(
+        "PYLAUNCH_RELEASE_URL",
+        Some(&format!(
+            "file://{}",
+            release_dir.path().join("missing.json").display()
+        )),
+    );
+    assert!(matches!(
+        Action::from_main(&[launcher_location.clone(), "--available".to_string()]),
+        Err(Error::Install(InstallError::DownloadFailed(_, _)))
+    ));
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "--available".to_string(),
+            "--all".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "--available".to_string()
+        )))
+    );
+}
+
+#[test]
+#[serial]
+fn from_main_installed() {
+    let mut env_state = common::EnvState::new();
+    let launcher_location = "/path/to/py".to_string();
+
+    // Neither `PYLAUNCH_INSTALL_DIR`, `XDG_DATA_HOME`, nor `HOME` is set.
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "installed".to_string()]),
+        Err(Error::Install(InstallError::NoInstallsDir))
+    );
+
+    let installs_dir = tempfile::tempdir().unwrap();
+    env_state.env_vars.change(
+        "PYLAUNCH_INSTALL_DIR",
+        Some(installs_dir.path().to_str().unwrap()),
+    );
+    assert_eq!(
+        Action::from_main(&[launcher_location.clone(), "installed".to_string()]),
+        Ok(Action::List(String::new()))
+    );
+
+    let install_dir = installs_dir
+        .path()
+        .join("cpython-3.12.7-x86_64-unknown-linux-gnu");
+    fs::create_dir_all(install_dir.join("bin")).unwrap();
+    fs::write(
+        install_dir.join(python_launcher::install::RECEIPT_FILE_NAME),
+        "",
+    )
+    .unwrap();
+    match Action::from_main(&[launcher_location.clone(), "installed".to_string()]) {
+        Ok(Action::List(output)) => {
+            assert!(output.contains("3.12.7"));
+            assert!(output.contains(&install_dir.join("bin/python3.12").display().to_string()));
+        }
+        result => panic!("Unexpected result: {result:?}"),
+    }
+
+    assert_eq!(
+        Action::from_main(&[
+            launcher_location.clone(),
+            "installed".to_string(),
+            "3.12".to_string()
+        ]),
+        Err(Error::Cli(CliError::IllegalArgument(
+            PathBuf::from(&launcher_location),
+            "installed".to_string()
+        )))
     );
 }
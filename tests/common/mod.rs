@@ -30,7 +30,42 @@ impl EnvVarState {
     pub fn empty() -> Self {
         let mut state = Self::new();
         state.change("PATH", None);
-        for env_var in ["VIRTUAL_ENV", "PY_PYTHON", "PY_PYTHON3", "PY_PYTHON2"].iter() {
+        for env_var in [
+            "VIRTUAL_ENV",
+            "CONDA_PREFIX",
+            "PYLAUNCH_NO_VENV_SEARCH",
+            "PY_PYTHON",
+            "PY_PYTHON3",
+            "PY_PYTHON2",
+            "PY_PYTHON_PATH",
+            "PY_FLAGS",
+            "PY_DISABLE_SOURCES",
+            "PYENV_ROOT",
+            "PYENV_VERSION",
+            "ASDF_DATA_DIR",
+            "UV_PYTHON_INSTALL_DIR",
+            "PYLAUNCH_INSTALL_DIR",
+            "PYLAUNCH_RELEASE_URL",
+            "PYLAUNCH_UPDATE_URL",
+            "PYLAUNCHER_ALLOW_INSTALL",
+            "XDG_DATA_HOME",
+            "XDG_CONFIG_HOME",
+            "RYE_HOME",
+            "RUNNER_TOOL_CACHE",
+            "PIPENV_CUSTOM_VENV_NAME",
+            "WORKON_HOME",
+            "XDG_CACHE_HOME",
+            "POETRY_CACHE_DIR",
+            "POETRY_VIRTUALENVS_PATH",
+            "NIX_PROFILES",
+            "WSL_DISTRO_NAME",
+            "SHELL",
+            "PYLAUNCH_CONFIG",
+            "PYLAUNCH_NO_CONFIG",
+            "HOME",
+        ]
+        .iter()
+        {
             state.change(env_var, None);
         }
 
@@ -96,9 +131,36 @@ impl EnvState {
     /// - `dir2/python3.7`
     /// - `PATH` environment variable is set to `dir1` and `dir2`
     /// - `VIRTUAL_ENV` is unset
+    /// - `CONDA_PREFIX` is unset
+    /// - `PYLAUNCH_NO_VENV_SEARCH` is unset
     /// - `PY_PYTHON` is unset
     /// - `PY_PYTHON3` is unset
     /// - `PY_PYTHON2` is unset
+    /// - `PY_PYTHON_PATH` is unset
+    /// - `PY_DISABLE_SOURCES` is unset
+    /// - `PYENV_ROOT` is unset
+    /// - `PYENV_VERSION` is unset
+    /// - `ASDF_DATA_DIR` is unset
+    /// - `UV_PYTHON_INSTALL_DIR` is unset
+    /// - `PYLAUNCH_INSTALL_DIR` is unset
+    /// - `PYLAUNCH_RELEASE_URL` is unset
+    /// - `PYLAUNCH_UPDATE_URL` is unset
+    /// - `PYLAUNCHER_ALLOW_INSTALL` is unset
+    /// - `XDG_DATA_HOME` is unset
+    /// - `XDG_CONFIG_HOME` is unset
+    /// - `RYE_HOME` is unset
+    /// - `RUNNER_TOOL_CACHE` is unset
+    /// - `PIPENV_CUSTOM_VENV_NAME` is unset
+    /// - `WORKON_HOME` is unset
+    /// - `XDG_CACHE_HOME` is unset
+    /// - `POETRY_CACHE_DIR` is unset
+    /// - `POETRY_VIRTUALENVS_PATH` is unset
+    /// - `NIX_PROFILES` is unset
+    /// - `WSL_DISTRO_NAME` is unset
+    /// - `SHELL` is unset
+    /// - `PYLAUNCH_CONFIG` is unset
+    /// - `PYLAUNCH_NO_CONFIG` is unset
+    /// - `HOME` is unset
     #[allow(dead_code)]
     pub fn new() -> Self {
         let dir1 = TempDir::new().unwrap();
@@ -112,7 +174,42 @@ impl EnvState {
         let new_path = env::join_paths([dir1.path(), dir2.path()].iter()).unwrap();
         let mut env_changes = EnvVarState::new();
         env_changes.change("PATH", Some(new_path.to_str().unwrap()));
-        for env_var in ["VIRTUAL_ENV", "PY_PYTHON", "PY_PYTHON3", "PY_PYTHON2"].iter() {
+        for env_var in [
+            "VIRTUAL_ENV",
+            "CONDA_PREFIX",
+            "PYLAUNCH_NO_VENV_SEARCH",
+            "PY_PYTHON",
+            "PY_PYTHON3",
+            "PY_PYTHON2",
+            "PY_PYTHON_PATH",
+            "PY_FLAGS",
+            "PY_DISABLE_SOURCES",
+            "PYENV_ROOT",
+            "PYENV_VERSION",
+            "ASDF_DATA_DIR",
+            "UV_PYTHON_INSTALL_DIR",
+            "PYLAUNCH_INSTALL_DIR",
+            "PYLAUNCH_RELEASE_URL",
+            "PYLAUNCH_UPDATE_URL",
+            "PYLAUNCHER_ALLOW_INSTALL",
+            "XDG_DATA_HOME",
+            "XDG_CONFIG_HOME",
+            "RYE_HOME",
+            "RUNNER_TOOL_CACHE",
+            "PIPENV_CUSTOM_VENV_NAME",
+            "WORKON_HOME",
+            "XDG_CACHE_HOME",
+            "POETRY_CACHE_DIR",
+            "POETRY_VIRTUALENVS_PATH",
+            "NIX_PROFILES",
+            "WSL_DISTRO_NAME",
+            "SHELL",
+            "PYLAUNCH_CONFIG",
+            "PYLAUNCH_NO_CONFIG",
+            "HOME",
+        ]
+        .iter()
+        {
             env_changes.change(env_var, None);
         }
 
@@ -2,7 +2,26 @@ mod common;
 
 use serial_test::serial;
 
-use python_launcher::{ExactVersion, RequestedVersion};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::iter;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use python_launcher::cache;
+use python_launcher::config::Config;
+use python_launcher::interpreter::{
+    metadata_of, Interpreter, MetadataLevel, MAX_CONCURRENT_INTROSPECTIONS,
+};
+use python_launcher::{
+    DirectorySource, ExactVersion, InterpreterSource, RequestedVersion, VenvDetection,
+    VersionPreferenceList, VersionSpecifier,
+};
 
 use common::EnvState;
 
@@ -15,21 +34,21 @@ fn all_executables() {
 
     assert_eq!(executables.len(), 3);
 
-    let python27_version = ExactVersion { major: 2, minor: 7 };
+    let python27_version = ExactVersion::new(2, 7);
     assert!(executables.contains_key(&python27_version));
     assert_eq!(
         executables.get(&python27_version),
         Some(&env_state.python27)
     );
 
-    let python36_version = ExactVersion { major: 3, minor: 6 };
+    let python36_version = ExactVersion::new(3, 6);
     assert!(executables.contains_key(&python27_version));
     assert_eq!(
         executables.get(&python36_version),
         Some(&env_state.python36)
     );
 
-    let python37_version = ExactVersion { major: 3, minor: 7 };
+    let python37_version = ExactVersion::new(3, 7);
     assert!(executables.contains_key(&python37_version));
     assert_eq!(
         executables.get(&python37_version),
@@ -37,6 +56,370 @@ fn all_executables() {
     );
 }
 
+#[test]
+#[serial]
+fn all_executables_cached() {
+    let mut env_state = EnvState::new();
+    let cache_home = tempfile::tempdir().unwrap();
+    env_state
+        .env_vars
+        .change("XDG_CACHE_HOME", Some(cache_home.path().to_str().unwrap()));
+    let cache_path = cache::cache_path().unwrap();
+
+    // Nothing is cached while the directories on `PATH` were just created.
+    let executables = cache::all_executables(&Config::load().unwrap());
+    assert!(!cache_path.exists());
+
+    thread::sleep(cache::SETTLE_TIME);
+    // Caching is only done when asked for.
+    assert_eq!(python_launcher::all_executables(), executables);
+    assert!(!cache_path.exists());
+
+    let config_home = tempfile::tempdir().unwrap();
+    let config_dir = config_home.path().join("python-launcher");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("py.toml"), "disable-cache = true\n").unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    assert_eq!(
+        cache::all_executables(&Config::load().unwrap()),
+        executables
+    );
+    assert!(!cache_path.exists());
+
+    env_state.env_vars.change("XDG_CONFIG_HOME", None);
+    assert_eq!(
+        cache::all_executables(&Config::load().unwrap()),
+        executables
+    );
+    let contents = fs::read_to_string(&cache_path).unwrap();
+    let cached_python27 = PathBuf::from("/cached/python2.7");
+    fs::write(
+        &cache_path,
+        contents.replace(
+            env_state.python27.to_str().unwrap(),
+            cached_python27.to_str().unwrap(),
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        cache::all_executables(&Config::load().unwrap()).get(&ExactVersion::new(2, 7)),
+        Some(&cached_python27)
+    );
+    // The library searches again rather than reading the cache.
+    assert_eq!(
+        python_launcher::all_executables().get(&ExactVersion::new(2, 7)),
+        Some(&env_state.python27)
+    );
+
+    // Adding an executable to a directory invalidates the cache.
+    let python38 = common::touch_file(env_state.python27.with_file_name("python3.8"));
+    let executables = cache::all_executables(&Config::load().unwrap());
+    assert_eq!(executables.get(&ExactVersion::new(3, 8)), Some(&python38));
+    assert_eq!(
+        executables.get(&ExactVersion::new(2, 7)),
+        Some(&env_state.python27)
+    );
+}
+
+#[test]
+#[serial]
+fn cache_clear_and_refresh() {
+    let mut env_state = EnvState::new();
+    let cache_home = tempfile::tempdir().unwrap();
+    env_state
+        .env_vars
+        .change("XDG_CACHE_HOME", Some(cache_home.path().to_str().unwrap()));
+    let cache_path = cache::cache_path().unwrap();
+
+    assert_eq!(cache::clear(), Ok(None));
+    fs::create_dir_all(cache_path.parent().unwrap()).unwrap();
+    fs::write(&cache_path, "stale").unwrap();
+    assert_eq!(cache::clear(), Ok(Some(cache_path.clone())));
+    assert!(!cache_path.exists());
+
+    fs::write(&cache_path, "stale").unwrap();
+    let executables = cache::refresh(&Config::default()).unwrap();
+    assert_eq!(
+        executables.get(&ExactVersion::new(3, 7)),
+        Some(&env_state.python37)
+    );
+    // Nothing is cached while the directories on `PATH` were just created.
+    assert!(!cache_path.exists());
+}
+
+#[test]
+#[serial]
+fn all_executables_python_path() {
+    let mut env_state = EnvState::new();
+    let dir1 = tempfile::tempdir().unwrap();
+    let dir2 = tempfile::tempdir().unwrap();
+    let python312 = common::touch_file(dir1.path().join("python3.12"));
+    common::touch_file(dir2.path().join("python3.12"));
+    let python313 = common::touch_file(dir2.path().join("python3.13"));
+    common::touch_file(dir2.path().join("python3.7"));
+    let python_path = env::join_paths([dir1.path(), dir2.path()].iter()).unwrap();
+    env_state
+        .env_vars
+        .change("PY_PYTHON_PATH", Some(python_path.to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+    assert_eq!(executables.get(&ExactVersion::new(3, 13)), Some(&python313));
+    // PATH takes precedence over PY_PYTHON_PATH.
+    assert_eq!(
+        executables.get(&ExactVersion::new(3, 7)),
+        Some(&env_state.python37)
+    );
+}
+
+#[test]
+#[serial]
+fn all_executables_pyenv() {
+    let mut env_state = EnvState::new();
+    let pyenv_root = tempfile::tempdir().unwrap();
+    let bin_dir = pyenv_root
+        .path()
+        .join("versions")
+        .join("3.12.1")
+        .join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    common::touch_file(bin_dir.join("python3.7"));
+    env_state
+        .env_vars
+        .change("PYENV_ROOT", Some(pyenv_root.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+    // PATH takes precedence over pyenv.
+    assert_eq!(
+        executables.get(&ExactVersion::new(3, 7)),
+        Some(&env_state.python37)
+    );
+
+    // `~/.pyenv` is used when `PYENV_ROOT` is not set.
+    let home = tempfile::tempdir().unwrap();
+    let bin_dir = home.path().join(".pyenv/versions/3.11.0/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python311 = common::touch_file(bin_dir.join("python3.11"));
+    env_state.env_vars.change("PYENV_ROOT", None);
+    env_state
+        .env_vars
+        .change("HOME", Some(home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 11)), Some(&python311));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+}
+
+#[test]
+#[serial]
+fn all_executables_asdf() {
+    let mut env_state = EnvState::new();
+    let asdf_dir = tempfile::tempdir().unwrap();
+    let bin_dir = asdf_dir.path().join("installs/python/3.12.1/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    env_state
+        .env_vars
+        .change("ASDF_DATA_DIR", Some(asdf_dir.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+
+    // `~/.asdf` is used when `ASDF_DATA_DIR` is not set.
+    let home = tempfile::tempdir().unwrap();
+    let bin_dir = home.path().join(".asdf/installs/python/3.11.0/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python311 = common::touch_file(bin_dir.join("python3.11"));
+    env_state.env_vars.change("ASDF_DATA_DIR", None);
+    env_state
+        .env_vars
+        .change("HOME", Some(home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 11)), Some(&python311));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+}
+
+#[test]
+#[serial]
+fn all_executables_uv() {
+    let mut env_state = EnvState::new();
+    let install_dir = tempfile::tempdir().unwrap();
+    let bin_dir = install_dir
+        .path()
+        .join("cpython-3.12.1-linux-x86_64-gnu/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    env_state.env_vars.change(
+        "UV_PYTHON_INSTALL_DIR",
+        Some(install_dir.path().to_str().unwrap()),
+    );
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+
+    // `$XDG_DATA_HOME/uv/python` is used when `UV_PYTHON_INSTALL_DIR` is not set.
+    let data_home = tempfile::tempdir().unwrap();
+    let bin_dir = data_home
+        .path()
+        .join("uv/python/cpython-3.11.0-linux-x86_64-gnu/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python311 = common::touch_file(bin_dir.join("python3.11"));
+    env_state.env_vars.change("UV_PYTHON_INSTALL_DIR", None);
+    env_state
+        .env_vars
+        .change("XDG_DATA_HOME", Some(data_home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 11)), Some(&python311));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+
+    // `~/.local/share/uv/python` is used when `XDG_DATA_HOME` is not set.
+    let home = tempfile::tempdir().unwrap();
+    let bin_dir = home
+        .path()
+        .join(".local/share/uv/python/cpython-3.10.0-linux-x86_64-gnu/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python310 = common::touch_file(bin_dir.join("python3.10"));
+    env_state.env_vars.change("XDG_DATA_HOME", None);
+    env_state
+        .env_vars
+        .change("HOME", Some(home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 10)), Some(&python310));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 11)));
+}
+
+#[test]
+#[serial]
+fn all_executables_launcher() {
+    let mut env_state = EnvState::new();
+    let install_dir = tempfile::tempdir().unwrap();
+    let bin_dir = install_dir
+        .path()
+        .join("cpython-3.12.7-x86_64-unknown-linux-gnu/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    // Partial installs are never found.
+    let partial_bin_dir = install_dir
+        .path()
+        .join(".partial/cpython-3.11.10-x86_64-unknown-linux-gnu/bin");
+    fs::create_dir_all(&partial_bin_dir).unwrap();
+    common::touch_file(partial_bin_dir.join("python3.11"));
+    env_state.env_vars.change(
+        "PYLAUNCH_INSTALL_DIR",
+        Some(install_dir.path().to_str().unwrap()),
+    );
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 11)));
+
+    // `$XDG_DATA_HOME/python-launcher/installs` is used when
+    // `PYLAUNCH_INSTALL_DIR` is not set.
+    let data_home = tempfile::tempdir().unwrap();
+    let bin_dir = data_home
+        .path()
+        .join("python-launcher/installs/cpython-3.11.10-x86_64-unknown-linux-gnu/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python311 = common::touch_file(bin_dir.join("python3.11"));
+    env_state.env_vars.change("PYLAUNCH_INSTALL_DIR", None);
+    env_state
+        .env_vars
+        .change("XDG_DATA_HOME", Some(data_home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 11)), Some(&python311));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+    #[cfg(feature = "install")]
+    assert_eq!(
+        python_launcher::install::installs_dir(),
+        Some(data_home.path().join("python-launcher/installs"))
+    );
+}
+
+#[cfg(feature = "install")]
+#[test]
+#[serial]
+fn allow_install() {
+    let mut env_state = EnvState::new();
+    let allow_install = || python_launcher::install::allow_install(&Config::load().unwrap());
+    assert!(!allow_install());
+
+    env_state
+        .env_vars
+        .change("PYLAUNCHER_ALLOW_INSTALL", Some("1"));
+    assert!(allow_install());
+
+    let config_home = tempfile::tempdir().unwrap();
+    let config_dir = config_home.path().join("python-launcher");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(config_dir.join("py.toml"), "allow-install = true\n").unwrap();
+    env_state.env_vars.change("PYLAUNCHER_ALLOW_INSTALL", None);
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    assert!(allow_install());
+}
+
+#[test]
+#[serial]
+fn all_executables_rye() {
+    let mut env_state = EnvState::new();
+    let rye_home = tempfile::tempdir().unwrap();
+    let bin_dir = rye_home.path().join("py/cpython@3.12.1/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    env_state
+        .env_vars
+        .change("RYE_HOME", Some(rye_home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+
+    // `~/.rye` is used when `RYE_HOME` is not set.
+    let home = tempfile::tempdir().unwrap();
+    let bin_dir = home.path().join(".rye/py/cpython@3.11.0/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python311 = common::touch_file(bin_dir.join("python3.11"));
+    env_state.env_vars.change("RYE_HOME", None);
+    env_state
+        .env_vars
+        .change("HOME", Some(home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 11)), Some(&python311));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+}
+
+#[test]
+#[serial]
+fn all_executables_runner_tool_cache() {
+    let mut env_state = EnvState::new();
+    let tool_cache = tempfile::tempdir().unwrap();
+    let bin_dir = tool_cache.path().join("Python/3.10.13/x64/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    common::touch_file(tool_cache.path().join("Python/3.10.13/x64.complete"));
+    let python310 = common::touch_file(bin_dir.join("python3.10"));
+
+    assert!(!python_launcher::all_executables().contains_key(&ExactVersion::new(3, 10)));
+
+    env_state.env_vars.change(
+        "RUNNER_TOOL_CACHE",
+        Some(tool_cache.path().to_str().unwrap()),
+    );
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 10)), Some(&python310));
+}
+
 #[test]
 #[serial]
 fn find_executable() {
@@ -54,6 +437,487 @@ fn find_executable() {
 
     assert_eq!(
         python_launcher::find_executable(RequestedVersion::Exact(3, 6)),
+        Some(env_state.python36.clone())
+    );
+
+    assert_eq!(
+        python_launcher::find_executable(VersionSpecifier::from_str(">=3,<3.7").unwrap()),
         Some(env_state.python36)
     );
 }
+
+#[test]
+#[serial]
+fn find_executable_prerelease() {
+    let env_state = EnvState::new();
+    let python3_99 = env_state.python37.with_file_name("python3.99");
+    let mut file = File::create(&python3_99).unwrap();
+    writeln!(file, "#!/bin/sh\necho alpha").unwrap();
+    fs::set_permissions(&python3_99, fs::Permissions::from_mode(0o755)).unwrap();
+    drop(file);
+
+    assert_eq!(
+        python_launcher::find_executable(RequestedVersion::Any),
+        Some(env_state.python37.clone())
+    );
+
+    assert_eq!(
+        python_launcher::find_executable(RequestedVersion::Stable),
+        Some(env_state.python37.clone())
+    );
+
+    let preferences = VersionPreferenceList {
+        prereleases: true,
+        ..VersionPreferenceList::from(RequestedVersion::Stable)
+    };
+    assert_eq!(
+        python_launcher::find_preferred_executable(&preferences),
+        Some(env_state.python37)
+    );
+
+    assert_eq!(
+        python_launcher::find_executable(RequestedVersion::Latest),
+        Some(python3_99.clone())
+    );
+
+    assert_eq!(
+        python_launcher::find_executable(RequestedVersion::Exact(3, 99)),
+        Some(python3_99.clone())
+    );
+
+    let specifier = VersionSpecifier {
+        prereleases: true,
+        ..VersionSpecifier::default()
+    };
+    assert_eq!(
+        python_launcher::find_executable(specifier),
+        Some(python3_99)
+    );
+}
+
+#[test]
+#[serial]
+fn all_executables_config_search_directories() {
+    let mut env_state = EnvState::new();
+    let config_home = tempfile::tempdir().unwrap();
+    let config_dir = config_home.path().join("python-launcher");
+    let bin_dir = config_dir.join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    common::touch_file(bin_dir.join("python3.7"));
+    fs::write(
+        config_dir.join("py.toml"),
+        "search-directories = [\"bin\"]\n",
+    )
+    .unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+    // PATH takes precedence over the configuration file.
+    assert_eq!(
+        executables.get(&ExactVersion::new(3, 7)),
+        Some(&env_state.python37)
+    );
+}
+
+#[test]
+#[serial]
+fn all_executables_disabled_sources() {
+    let mut env_state = EnvState::new();
+    let pyenv_root = tempfile::tempdir().unwrap();
+    let bin_dir = pyenv_root.path().join("versions/3.12.1/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    env_state
+        .env_vars
+        .change("PYENV_ROOT", Some(pyenv_root.path().to_str().unwrap()));
+
+    env_state
+        .env_vars
+        .change("PY_DISABLE_SOURCES", Some("pyenv,conda"));
+    let executables = python_launcher::all_executables();
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+    assert_eq!(
+        executables.get(&ExactVersion::new(3, 7)),
+        Some(&env_state.python37)
+    );
+
+    env_state
+        .env_vars
+        .change("PY_DISABLE_SOURCES", Some("path"));
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+    assert!(!executables.contains_key(&ExactVersion::new(3, 7)));
+
+    // The configuration file can disable sources as well.
+    let config_home = tempfile::tempdir().unwrap();
+    let config_dir = config_home.path().join("python-launcher");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("py.toml"),
+        "disable-sources = [\"pyenv\"]\n",
+    )
+    .unwrap();
+    env_state.env_vars.change(
+        "XDG_CONFIG_HOME",
+        Some(config_home.path().to_str().unwrap()),
+    );
+    env_state.env_vars.change("PY_DISABLE_SOURCES", None);
+    let executables = python_launcher::all_executables();
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+}
+
+#[test]
+#[serial]
+fn all_executables_nix() {
+    let mut env_state = EnvState::new();
+    let home = tempfile::tempdir().unwrap();
+    let bin_dir = home.path().join(".nix-profile/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python312 = common::touch_file(bin_dir.join("python3.12"));
+    env_state
+        .env_vars
+        .change("HOME", Some(home.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+
+    let profile = tempfile::tempdir().unwrap();
+    let bin_dir = profile.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let python311 = common::touch_file(bin_dir.join("python3.11"));
+    env_state
+        .env_vars
+        .change("NIX_PROFILES", Some(profile.path().to_str().unwrap()));
+
+    let executables = python_launcher::all_executables();
+    assert_eq!(executables.get(&ExactVersion::new(3, 11)), Some(&python311));
+}
+
+struct ToolchainServer {
+    executables: HashMap<ExactVersion, PathBuf>,
+}
+
+impl InterpreterSource for ToolchainServer {
+    fn name(&self) -> &str {
+        "toolchain-server"
+    }
+
+    fn executables(&self) -> HashMap<ExactVersion, PathBuf> {
+        self.executables.clone()
+    }
+}
+
+#[test]
+#[serial]
+fn all_executables_from_custom_sources() {
+    let mut env_state = EnvState::new();
+    let mut server_executables = HashMap::new();
+    let python312 = PathBuf::from("/toolchains/python3.12");
+    server_executables.insert(ExactVersion::new(3, 12), python312.clone());
+    server_executables.insert(
+        ExactVersion::new(3, 7),
+        PathBuf::from("/toolchains/python3.7"),
+    );
+
+    let mut sources = python_launcher::default_sources();
+    sources.push(Box::new(ToolchainServer {
+        executables: server_executables,
+    }));
+    let executables = python_launcher::all_executables_from(&sources);
+    assert_eq!(executables.get(&ExactVersion::new(3, 12)), Some(&python312));
+    // Earlier sources take precedence.
+    assert_eq!(
+        executables.get(&ExactVersion::new(3, 7)),
+        Some(&env_state.python37)
+    );
+
+    // Custom sources can be disabled like any other.
+    env_state
+        .env_vars
+        .change("PY_DISABLE_SOURCES", Some("toolchain-server"));
+    let executables = python_launcher::all_executables_from(&sources);
+    assert!(!executables.contains_key(&ExactVersion::new(3, 12)));
+
+    let directory = tempfile::tempdir().unwrap();
+    let python311 = common::touch_file(directory.path().join("python3.11"));
+    let sources: Vec<Box<dyn InterpreterSource>> = vec![Box::new(DirectorySource::new(
+        "bespoke",
+        vec![directory.path().to_path_buf()],
+    ))];
+    let executables = python_launcher::all_executables_from(&sources);
+    assert_eq!(executables.len(), 1);
+    assert_eq!(executables.get(&ExactVersion::new(3, 11)), Some(&python311));
+}
+
+#[test]
+#[serial]
+fn all_executable_occurrences_from() {
+    let _env_state = EnvState::new();
+    let first_directory = tempfile::tempdir().unwrap();
+    let second_directory = tempfile::tempdir().unwrap();
+    let first_python311 = common::touch_file(first_directory.path().join("python3.11"));
+    let first_python37 = common::touch_file(first_directory.path().join("python3.7"));
+    let second_python311 = common::touch_file(second_directory.path().join("python3.11"));
+    let mut server_executables = HashMap::new();
+    let server_python311 = PathBuf::from("/toolchains/python3.11");
+    server_executables.insert(ExactVersion::new(3, 11), server_python311.clone());
+
+    let sources: Vec<Box<dyn InterpreterSource>> = vec![
+        Box::new(DirectorySource::new(
+            "bespoke",
+            vec![
+                first_directory.path().to_path_buf(),
+                second_directory.path().to_path_buf(),
+            ],
+        )),
+        Box::new(ToolchainServer {
+            executables: server_executables,
+        }),
+    ];
+    let occurrences = python_launcher::all_executable_occurrences_from(&sources);
+    // Executables in later directories of the same source are included too,
+    // in the order they are searched.
+    let python311_occurrences = occurrences
+        .iter()
+        .filter(|(_, version, _)| *version == ExactVersion::new(3, 11))
+        .map(|(source, _, path)| (source.as_str(), path.clone()))
+        .collect::<Vec<(&str, PathBuf)>>();
+    assert_eq!(
+        python311_occurrences,
+        [
+            ("bespoke", first_python311),
+            ("bespoke", second_python311),
+            ("toolchain-server", server_python311),
+        ]
+    );
+    assert!(occurrences.contains(&(
+        "bespoke".to_string(),
+        ExactVersion::new(3, 7),
+        first_python37
+    )));
+    assert_eq!(occurrences.len(), 4);
+}
+
+#[test]
+#[serial]
+fn executables_in_directories() {
+    let env_state = EnvState::new();
+    let root = tempfile::tempdir().unwrap();
+    let local_bin = root.path().join("usr/local/bin");
+    let bin = root.path().join("usr/bin");
+    fs::create_dir_all(&local_bin).unwrap();
+    fs::create_dir_all(&bin).unwrap();
+    let local_python311 = common::touch_file(local_bin.join("python3.11"));
+    common::touch_file(bin.join("python3.11"));
+    let python39 = common::touch_file(bin.join("python3.9"));
+
+    let executables =
+        python_launcher::executables_in_directories([local_bin, bin, root.path().join("missing")]);
+    // Nothing on `PATH` is included.
+    assert!(!executables.values().any(|path| *path == env_state.python37));
+    assert_eq!(executables.len(), 2);
+    assert_eq!(
+        executables.get(&ExactVersion::new(3, 11)),
+        Some(&local_python311)
+    );
+    assert_eq!(executables.get(&ExactVersion::new(3, 9)), Some(&python39));
+}
+
+#[test]
+#[serial]
+fn find_all_executables() {
+    let mut env_state = EnvState::new();
+    let directory = tempfile::tempdir().unwrap();
+    let shadowed_python36 = common::touch_file(directory.path().join("python3.6"));
+    env_state
+        .env_vars
+        .change("PY_PYTHON_PATH", Some(directory.path().to_str().unwrap()));
+
+    assert_eq!(
+        python_launcher::find_all_executables(RequestedVersion::MajorOnly(3)),
+        [
+            (ExactVersion::new(3, 7), env_state.python37.clone()),
+            (ExactVersion::new(3, 6), env_state.python36.clone()),
+            // Shadowed by the one earlier on `PATH`.
+            (
+                ExactVersion::new(3, 6),
+                env_state.python37.with_file_name("python3.6")
+            ),
+            (ExactVersion::new(3, 6), shadowed_python36),
+        ]
+    );
+    assert_eq!(
+        python_launcher::find_all_executables(RequestedVersion::Exact(3, 7)),
+        [(ExactVersion::new(3, 7), env_state.python37)]
+    );
+    assert!(python_launcher::find_all_executables(RequestedVersion::MajorOnly(4)).is_empty());
+}
+
+#[test]
+fn interpreter_metadata() {
+    let directory = tempfile::tempdir().unwrap();
+    let python = directory.path().join("python3.12");
+    // Stands in for an interpreter, printing what the probe would.
+    fs::write(
+        &python,
+        "#!/bin/sh\nprintf 'CPython\\0003.12.0\\000candidate\\000False\\000/venv\\000/usr\\000x86_64\\00064'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&python, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let interpreter = Interpreter::from_path(&python).unwrap();
+    let metadata = interpreter.metadata(MetadataLevel::Introspection).unwrap();
+    assert_eq!(
+        metadata.version,
+        ExactVersion {
+            micro: Some(0),
+            ..ExactVersion::new(3, 12)
+        }
+    );
+    assert_eq!(metadata.release_level.as_deref(), Some("candidate"));
+    assert_eq!(metadata.prefix, Some(PathBuf::from("/venv")));
+    assert_eq!(metadata.base_prefix, Some(PathBuf::from("/usr")));
+    assert_eq!(metadata.architecture.as_deref(), Some("x86_64"));
+    assert_eq!(metadata.bits, Some(64));
+
+    let broken = common::touch_file(directory.path().join("python3.11"));
+    assert!(matches!(
+        Interpreter::from_path(&broken)
+            .unwrap()
+            .metadata(MetadataLevel::Introspection),
+        Err(python_launcher::Error::Execution(python_launcher::ExecutionError::IntrospectionFailed(path, _))) if path == broken
+    ));
+}
+
+#[test]
+fn interpreter_metadata_of() {
+    let directory = tempfile::tempdir().unwrap();
+    let interpreters = (0..MAX_CONCURRENT_INTROSPECTIONS)
+        .map(|micro| {
+            let python = directory.path().join(format!("python3.{micro}"));
+            // Slow to start, so introspecting one after another would take
+            // far longer than at once.
+            fs::write(
+                &python,
+                format!("#!/bin/sh\nsleep 1\nprintf 'CPython\\0003.{micro}.1\\000final\\000False\\000/usr\\000/usr\\000x86_64\\00064'\n"),
+            )
+            .unwrap();
+            fs::set_permissions(&python, fs::Permissions::from_mode(0o755)).unwrap();
+            Interpreter::from_path(&python).unwrap()
+        })
+        .chain(iter::once(
+            Interpreter::from_path(common::touch_file(directory.path().join("python2.7")))
+                .unwrap(),
+        ))
+        .collect::<Vec<Interpreter>>();
+
+    let started = Instant::now();
+    let metadata = metadata_of(&interpreters, MetadataLevel::Introspection);
+    assert!(started.elapsed() < Duration::from_secs(4));
+    assert_eq!(metadata.len(), interpreters.len());
+    for (interpreter, learned) in interpreters
+        .iter()
+        .zip(&metadata)
+        .take(MAX_CONCURRENT_INTROSPECTIONS)
+    {
+        let learned = learned.as_ref().unwrap();
+        assert_eq!(learned.version.minor, interpreter.version.minor);
+        assert_eq!(learned.version.micro, Some(1));
+    }
+    assert!(matches!(
+        metadata.last(),
+        Some(Err(python_launcher::Error::Execution(
+            python_launcher::ExecutionError::IntrospectionFailed(..)
+        )))
+    ));
+}
+
+/// A source which must never be searched.
+struct UnreachableSource;
+
+impl InterpreterSource for UnreachableSource {
+    fn name(&self) -> &str {
+        "unreachable"
+    }
+
+    fn executables(&self) -> HashMap<ExactVersion, PathBuf> {
+        panic!("searched a source after finding the executable")
+    }
+}
+
+#[test]
+#[serial]
+fn find_executables_iter() {
+    let env_state = EnvState::new();
+
+    let found = python_launcher::find_executables_iter(RequestedVersion::MajorOnly(3))
+        .collect::<Vec<(ExactVersion, PathBuf)>>();
+    assert_eq!(found.len(), 2);
+    assert!(found.contains(&(ExactVersion::new(3, 6), env_state.python36.clone())));
+    assert!(found.contains(&(ExactVersion::new(3, 7), env_state.python37)));
+
+    let directory = tempfile::tempdir().unwrap();
+    let python311 = common::touch_file(directory.path().join("python3.11"));
+    common::touch_file(directory.path().join("python3.12"));
+    let sources: Vec<Box<dyn InterpreterSource>> = vec![
+        Box::new(DirectorySource::new(
+            "bespoke",
+            vec![directory.path().to_path_buf()],
+        )),
+        Box::new(DirectorySource::new(
+            "other",
+            vec![env_state.python36.parent().unwrap().to_path_buf()],
+        )),
+        Box::new(UnreachableSource),
+    ];
+    let mut found =
+        python_launcher::find_executables_iter_from(sources, RequestedVersion::Exact(3, 11));
+    // Stopping at the first executable leaves the later sources unsearched.
+    assert_eq!(found.next(), Some((ExactVersion::new(3, 11), python311)));
+}
+
+#[test]
+#[serial]
+fn find_environment_detection() {
+    let _working_dir = common::CurrentDir::new();
+    let mut env_state = EnvState::new();
+    let mut local_python = PathBuf::from(python_launcher::DEFAULT_VENV_DIR);
+    local_python.push("bin");
+    fs::create_dir_all(&local_python).unwrap();
+    local_python.push("python");
+    common::touch_file(local_python.clone());
+
+    let local_venv = python_launcher::find_environment(VenvDetection::Search)
+        .unwrap()
+        .unwrap();
+    assert_eq!(local_venv.executable, local_python.canonicalize().unwrap());
+    assert_eq!(
+        python_launcher::find_environment(VenvDetection::ActivatedOnly),
+        Ok(None)
+    );
+    assert_eq!(
+        python_launcher::find_environment(VenvDetection::Disabled),
+        Ok(None)
+    );
+
+    let conda_dir = tempfile::tempdir().unwrap();
+    let conda_bin = conda_dir.path().join("bin");
+    fs::create_dir_all(&conda_bin).unwrap();
+    let conda_python = common::touch_file(conda_bin.join("python"));
+    env_state
+        .env_vars
+        .change("CONDA_PREFIX", Some(conda_dir.path().to_str().unwrap()));
+    let conda_env = python_launcher::find_environment(VenvDetection::ActivatedOnly)
+        .unwrap()
+        .unwrap();
+    assert_eq!(conda_env.executable, conda_python);
+    assert_eq!(
+        python_launcher::find_environment(VenvDetection::Disabled),
+        Ok(None)
+    );
+}
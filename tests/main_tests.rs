@@ -1,6 +1,6 @@
 mod common;
 
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
 
 use common::CurrentDir;
 
@@ -46,7 +46,9 @@ fn any_version() {
     let python = python_launcher::find_executable(RequestedVersion::Any)
         .expect("no Python executable found");
     let version = ExactVersion::from_path(&python).unwrap();
+    // Keep a pyenv version file on the machine from selecting another version.
     let result = py_executable()
+        .env("PYENV_VERSION", "system")
         .args(["-c", "import sys; print(sys.version)"])
         .assert();
 
@@ -56,6 +58,26 @@ fn any_version() {
         .stderr(str::is_empty());
 }
 
+#[test]
+fn default_flag() {
+    let python = python_launcher::find_executable(RequestedVersion::Any)
+        .expect("no Python executable found");
+    let version = ExactVersion::from_path(&python).unwrap();
+    let version_string = format!("{}.{}", version.major, version.minor);
+    let config_home = tempfile::tempdir().unwrap();
+    let result = py_executable()
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .env_remove("PYLAUNCH_CONFIG")
+        .args(["--default", &version_string])
+        .assert();
+
+    result.success().stdout(str::is_empty());
+    assert_eq!(
+        std::fs::read_to_string(config_home.path().join("python-launcher/py.toml")).unwrap(),
+        format!("default-version = \"{version_string}\"\n")
+    );
+}
+
 #[test]
 fn major_version() {
     let python = python_launcher::find_executable(RequestedVersion::Any)
@@ -96,6 +118,101 @@ fn exact_version() {
         .stderr(str::is_empty());
 }
 
+#[test]
+fn resolve() {
+    let python = python_launcher::find_executable(RequestedVersion::Any)
+        .expect("no Python executable found");
+    let version = ExactVersion::from_path(&python).unwrap();
+    let version_flag = format!("-{version}");
+    let output = py_executable()
+        .args([version_flag.as_str(), "--resolve"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let resolved: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(resolved["path"], python.to_str().unwrap());
+    assert_eq!(resolved["version"], version.to_string());
+    assert!(resolved["source"].is_string());
+    assert!(resolved["venv"].is_null());
+}
+
+#[test]
+fn serve() {
+    let python = python_launcher::find_executable(RequestedVersion::Any)
+        .expect("no Python executable found");
+    let version = ExactVersion::from_path(&python).unwrap();
+    let output = py_executable()
+        .arg("--serve")
+        .write_stdin(format!(
+            "{{\"id\": 1, \"method\": \"resolve\", \"version\": \"{version}\"}}\n{{\"id\": 2, \"method\": \"list\"}}\n"
+        ))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let responses = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<serde_json::Value>>();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[0]["result"]["path"], python.to_str().unwrap());
+    assert_eq!(responses[1]["id"], 2);
+    assert!(responses[1]["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|executable| executable["path"] == python.to_str().unwrap()));
+}
+
+#[test]
+fn clear_cache() {
+    let cache_home = tempfile::tempdir().unwrap();
+    let cache_path = cache_home
+        .path()
+        .join("python-launcher")
+        .join("executables.toml");
+    fs::create_dir_all(cache_path.parent().unwrap()).unwrap();
+    fs::write(&cache_path, "stale").unwrap();
+
+    py_executable()
+        .env("XDG_CACHE_HOME", cache_home.path())
+        .arg("--clear-cache")
+        .assert()
+        .success()
+        .stdout(str::is_empty());
+    assert!(!cache_path.exists());
+}
+
+#[test]
+fn spawn_run_mode() {
+    let config_dir = tempfile::tempdir().unwrap();
+    let config_path = config_dir.path().join("py.toml");
+    fs::write(&config_path, "run-mode = \"spawn\"\n").unwrap();
+    let result = py_executable()
+        .env("PYENV_VERSION", "system")
+        .env("PYLAUNCH_CONFIG", &config_path)
+        .args(["-c", "import sys; print('spawned'); sys.exit(3)"])
+        .assert();
+
+    result.code(3).stdout(str::starts_with("spawned"));
+}
+
+#[test]
+fn dry_run() {
+    let result = py_executable()
+        .env("PYENV_VERSION", "system")
+        .args(["--dry-run", "-c", "import sys; sys.exit(3)"])
+        .assert();
+
+    result
+        .success()
+        .stdout(str::ends_with(" -c 'import sys; sys.exit(3)'\n"))
+        .stderr(str::is_empty());
+}
+
 #[test]
 fn logging_output() {
     let result = py_executable()
@@ -143,7 +260,36 @@ fn unexecutable() {
     let cwd_name = cwd.dir.path().as_os_str();
     let fake_python = PathBuf::from("python0.1");
     common::touch_file(fake_python);
-    let result = py_executable().env("PATH", cwd_name).assert();
+    let result = py_executable()
+        .env("PATH", cwd_name)
+        .env_remove("CONDA_PREFIX")
+        .env_remove("PY_PYTHON_PATH")
+        .env_remove("PY_FLAGS")
+        .env_remove("PY_DISABLE_SOURCES")
+        .env_remove("PYENV_ROOT")
+        .env_remove("PYENV_VERSION")
+        .env_remove("ASDF_DATA_DIR")
+        .env_remove("UV_PYTHON_INSTALL_DIR")
+        .env_remove("PYLAUNCH_INSTALL_DIR")
+        .env_remove("PYLAUNCH_RELEASE_URL")
+        .env_remove("PYLAUNCH_UPDATE_URL")
+        .env_remove("PYLAUNCHER_ALLOW_INSTALL")
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("RYE_HOME")
+        .env_remove("RUNNER_TOOL_CACHE")
+        .env_remove("PIPENV_CUSTOM_VENV_NAME")
+        .env_remove("WORKON_HOME")
+        .env_remove("XDG_CACHE_HOME")
+        .env_remove("POETRY_CACHE_DIR")
+        .env_remove("POETRY_VIRTUALENVS_PATH")
+        .env_remove("NIX_PROFILES")
+        .env_remove("WSL_DISTRO_NAME")
+        .env_remove("SHELL")
+        .env_remove("PYLAUNCH_CONFIG")
+        .env_remove("PYLAUNCH_NO_CONFIG")
+        .env_remove("HOME")
+        .assert();
 
     result.failure();
 }